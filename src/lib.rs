@@ -1,7 +1,181 @@
 //! AC-Garden - AtCoderの提出をアーカイブするためのライブラリ
 
+pub use error::{Error, Result};
+
+/// メッセージを整形して表示言語に訳す。[`i18n`]を参照
+///
+/// 訳は整形前のテンプレートで引くので、引数の中身で訳が変わることはない
+#[macro_export]
+macro_rules! tr {
+    ($template:literal $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::translate_marked(&format!($template $(, $crate::i18n::Arg(&$arg))*))
+    };
+}
+
+/// ライブラリ全体で使うエラー型
+pub mod error {
+    use std::path::PathBuf;
+    use thiserror::Error;
+
+    /// 原因として保持するエラー
+    pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+    /// AC-Gardenのエラー
+    ///
+    /// 種類ごとにmatchできるよう、失敗した段階ごとにバリアントを分けている。
+    /// 元になったエラーは`source()`でたどれる
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum Error {
+        /// 設定ファイルの読み書きや内容の誤り
+        #[error("config error: {message}")]
+        Config {
+            message: String,
+            #[source]
+            source: Option<BoxError>,
+        },
+        /// HTTP通信の失敗
+        #[error("network error")]
+        Network(#[from] reqwest::Error),
+        /// 429が続き、待っても制限が解けなかった
+        #[error("rate limited by {url} even after waiting")]
+        RateLimited { url: String },
+        /// AtCoderがメンテナンス中
+        #[error("AtCoder is under maintenance{}", .retry_after.as_ref().map(|r| format!(", retry after {}", r)).unwrap_or_default())]
+        Maintenance { retry_after: Option<String> },
+        /// ページやAPIの応答を解釈できない
+        #[error("failed to scrape {url}: {message}")]
+        Scrape {
+            url: String,
+            message: String,
+            #[source]
+            source: Option<BoxError>,
+        },
+        /// Gitの操作の失敗
+        #[error("git error")]
+        Git(#[from] git2::Error),
+        /// ファイルの読み書きや、保存したメタデータの解釈の失敗
+        #[error("storage error at {}", .path.display())]
+        Storage {
+            path: PathBuf,
+            #[source]
+            source: BoxError,
+        },
+        /// フックやフォーマッタのコマンドを起動できない
+        #[error("failed to run `{command}`")]
+        Command {
+            command: String,
+            #[source]
+            source: std::io::Error,
+        },
+        /// フックが失敗したため処理を中止した
+        #[error("{event} hook failed, aborting")]
+        HookFailed { event: String },
+        /// OSのキーチェーンや暗号化ファイルの資格情報を読み書きできない
+        #[error("credential error for {name}: {message}")]
+        Credential {
+            name: String,
+            message: String,
+            #[source]
+            source: Option<BoxError>,
+        },
+        /// GitHubなどのホスティングサービスが要求を受け付けない
+        #[error("{service} rejected the request: {message}")]
+        Hosting { service: String, message: String },
+        /// ブロッキングAPI用の非同期ランタイムを起動できない
+        #[error("failed to start the async runtime")]
+        Runtime(#[source] std::io::Error),
+        /// オフラインモードでネットワークが必要な処理をしようとした
+        #[error("{url} needs the network, but offline mode is on (drop --offline)")]
+        Offline { url: String },
+        /// 指定した提出IDがユーザーの提出にない
+        #[error("submission {id} is not among {user_id}'s submissions")]
+        SubmissionNotFound { id: i64, user_id: String },
+        /// アーカイブする提出を選ぶコールバックが失敗した (端末の入力エラーなど)
+        #[error("failed to select the submissions to archive")]
+        Select(#[source] BoxError),
+    }
+
+    impl Error {
+        pub fn config(message: impl Into<String>) -> Self {
+            Error::Config { message: message.into(), source: None }
+        }
+
+        pub fn config_with_source(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+            Error::Config { message: message.into(), source: Some(source.into()) }
+        }
+
+        pub fn scrape(url: impl Into<String>, message: impl Into<String>) -> Self {
+            Error::Scrape { url: url.into(), message: message.into(), source: None }
+        }
+
+        pub fn scrape_with_source(url: impl Into<String>, message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+            Error::Scrape { url: url.into(), message: message.into(), source: Some(source.into()) }
+        }
+
+        pub fn credential(name: impl Into<String>, message: impl Into<String>) -> Self {
+            Error::Credential { name: name.into(), message: message.into(), source: None }
+        }
+
+        pub fn credential_with_source(name: impl Into<String>, message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+            Error::Credential { name: name.into(), message: message.into(), source: Some(source.into()) }
+        }
+
+        pub fn storage(path: impl Into<PathBuf>, source: impl Into<BoxError>) -> Self {
+            Error::Storage { path: path.into(), source: source.into() }
+        }
+
+        pub fn command(command: impl Into<String>, source: std::io::Error) -> Self {
+            Error::Command { command: command.into(), source }
+        }
+
+        /// AtCoderのメンテナンスによる失敗か
+        pub fn is_maintenance(&self) -> bool {
+            matches!(self, Error::Maintenance { .. })
+        }
+
+        /// 続けても失敗するだけなので、実行を中断すべきか (メンテナンスやレート制限)
+        pub fn is_interruption(&self) -> bool {
+            matches!(self, Error::Maintenance { .. } | Error::RateLimited { .. })
+        }
+    }
+
+    pub type Result<T, E = Error> = std::result::Result<T, E>;
+}
+
+/// 処理を止めない警告の通知先
+///
+/// ライブラリは標準出力・標準エラー出力に直接書かない。警告は設定したハンドラに渡し、設定がなければ捨てる。
+/// [`archive::ArchiveClient`]の警告は、進捗のコールバックがあれば[`archive::Progress::Warning`]で届く
+pub mod warning {
+    use std::sync::OnceLock;
+
+    type Handler = Box<dyn Fn(&str) + Send + Sync>;
+
+    static HANDLER: OnceLock<Handler> = OnceLock::new();
+
+    /// 警告を受け取るハンドラを設定する。設定できるのは一度だけ
+    pub fn set_handler(handler: impl Fn(&str) + Send + Sync + 'static) {
+        let _ = HANDLER.set(Box::new(handler));
+    }
+
+    /// 訳したメッセージをハンドラに渡す
+    pub(crate) fn warn(message: &str) {
+        if let Some(handler) = HANDLER.get() {
+            handler(message);
+        }
+    }
+}
+
+/// アプリケーション名。設定ディレクトリやUser-Agentに使う
+pub const APP_NAME: &str = "ac-garden";
+pub const ATCODER_BASE_URL: &str = "https://atcoder.jp";
+/// アーカイブ内でのAtCoderのディレクトリ名。重複判定のキーの接頭辞にも使う
+pub const ATCODER_SERVICE_NAME: &str = "atcoder.jp";
+
 /// AtCoderの提出結果を表す構造体
 pub mod submission {
+    use crate::{ATCODER_BASE_URL, ATCODER_SERVICE_NAME};
     use serde::{Deserialize, Serialize};
 
     /// AtCoderの提出結果
@@ -11,74 +185,7647 @@ pub mod submission {
         pub epoch_second: i64,
         pub problem_id: String,
         pub contest_id: String,
+        /// コンテスト名 (例: "AtCoder Beginner Contest 300")
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub contest_title: Option<String>,
+        /// コンテスト内での問題の記号 (例: "A")
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub problem_index: Option<String>,
         pub user_id: String,
         pub language: String,
         pub point: f64,
         pub length: i64,
         pub result: String,
         pub execution_time: Option<i64>,
+        /// 提出ページから取得したメモリ使用量 (KB)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub memory: Option<i64>,
+        /// 提出ページから取得したテストケースごとの結果
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub test_cases: Vec<TestCaseResult>,
+        /// `tag`サブコマンドで付けたタグ
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub tags: Vec<String>,
+        /// 公式解説のURL
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub editorial_url: Option<String>,
+        /// 提出ページのURL
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub submission_url: Option<String>,
+    }
+
+    /// テストケースごとのジャッジ結果
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TestCaseResult {
+        pub name: String,
+        pub status: String,
+        pub execution_time: Option<i64>,
+        pub memory: Option<i64>,
+    }
+
+    /// 提出ページのURL
+    pub fn submission_url(submission: &Submission) -> String {
+        format!(
+            "{}/contests/{}/submissions/{}",
+            ATCODER_BASE_URL, submission.contest_id, submission.id
+        )
+    }
+
+    /// 問題ページのURL
+    pub fn task_url(submission: &Submission) -> String {
+        format!("{}/contests/{}/tasks/{}", ATCODER_BASE_URL, submission.contest_id, submission.problem_id)
+    }
+
+    /// 重複判定のキー。ジャッジ間で衝突しないようサービス名を含める
+    pub fn archive_key(submission: &Submission) -> String {
+        format!("{}/{}_{}", ATCODER_SERVICE_NAME, submission.contest_id, submission.problem_id)
     }
 }
 
 /// 設定ファイルの管理
 pub mod config {
+    use crate::archive::{DEFAULT_AHC_COMMIT_TEMPLATE, DEFAULT_COMMIT_TEMPLATE};
+    use crate::i18n::Lang;
+    use crate::store::{NOTES_MD, SUBMISSION_JSON};
+    use crate::submission::{archive_key, Submission};
+    use crate::languages::canonical_language;
+    use crate::utils::{glob_match, is_bare_repository, language_dir_name, problem_matches, sanitize_path_component, utc_offset_minutes};
+    use crate::{Error, Result, APP_NAME, ATCODER_SERVICE_NAME};
     use serde::{Deserialize, Serialize};
-    use std::path::PathBuf;
+    use serde_json::{Map, Value};
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    /// ベアリポジトリにアーカイブするとき、ファイルを書き出す作業用ディレクトリの名前 (リポジトリの中に作る)
+    ///
+    /// フォーマッタやフックがファイルを前提にしているので、blobから直接ではなくこのディレクトリを経由して書き込む
+    pub const BARE_WORK_DIR: &str = "ac-garden-worktree";
 
     /// サービス設定
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
     pub struct Service {
         pub repository_path: String,
         pub user_id: String,
         pub user_email: String,
+        /// 参加したコンテストの最終順位をresult.jsonとして保存するか
+        #[serde(default)]
+        pub archive_contest_results: bool,
+        /// `archive`の実行の記録をリポジトリのARCHIVE_LOG.mdに残すか
+        #[serde(default)]
+        pub archive_log: bool,
+        /// レーティング・最高レーティング・順位・参加回数をprofile.jsonとして保存するか
+        #[serde(default)]
+        pub archive_profile: bool,
+        /// atcoder.jpのログインセッション (REVEL_SESSIONクッキーの値)
+        #[serde(default)]
+        pub session: String,
+        /// リポジトリ内でこのサービスを置くサブディレクトリ。
+        /// 複数のジャッジで1つのリポジトリを共有するときに使う
+        #[serde(default)]
+        pub root: Option<String>,
+        /// アーカイブを置くディレクトリの名前。なければ`atcoder.jp`、空なら作らずにコンテストのディレクトリを直接置く
+        #[serde(default)]
+        pub service_dir_name: Option<String>,
+        /// ソースファイルの名前の付け方
+        #[serde(default)]
+        pub file_naming: FileNaming,
+        /// 問題のディレクトリの名前の付け方
+        #[serde(default)]
+        pub problem_dir_naming: ProblemDirNaming,
+        /// 問題のディレクトリの中に言語ごとのサブディレクトリ (例: abc300_a/rust) を作るか。
+        /// `policy`が`all`のとき、別の言語で解いた提出を上書きせずに残せる
+        #[serde(default)]
+        pub language_dirs: bool,
+        /// 改行コードの扱い
+        #[serde(default)]
+        pub line_ending: LineEnding,
+        /// コード先頭のBOMを取り除くか
+        #[serde(default)]
+        pub strip_bom: bool,
+        /// 拡張子か正規の言語名ごとのフォーマッタコマンド (例: {"rs": "rustfmt", "C++": "clang-format -i"})。
+        /// 言語名の方を優先する。ファイル名はコマンドの末尾に付けて渡す
+        #[serde(default)]
+        pub formatters: HashMap<String, String>,
+        /// フォーマット前のコードを`Main.orig`として残すか
+        #[serde(default)]
+        pub keep_original: bool,
+        /// 問題URLや提出日時などのヘッダをコメントとしてコードの先頭に入れるか
+        #[serde(default)]
+        pub header: bool,
+        /// 提出のメタデータの保存形式
+        #[serde(default)]
+        pub metadata_format: MetadataFormat,
+        /// どの提出をアーカイブするか
+        #[serde(default)]
+        pub policy: Policy,
+        /// コミットメッセージのテンプレート。`{contest_id}`などを提出の値に置き換える
+        #[serde(default)]
+        pub commit_template: Option<String>,
+        /// `commit_template`がないときに使うテンプレートのプリセット
+        #[serde(default)]
+        pub commit_style: CommitStyle,
+        /// コミットに使うGitの実装
+        #[serde(default)]
+        pub git_backend: GitBackend,
+        /// アーカイブ先のリポジトリを作るときのブランチ (例: "main")。未設定ならlibgit2の既定
+        #[serde(default)]
+        pub default_branch: Option<String>,
+        /// コミットの日時に付けるタイムゾーン。`local` (既定)、`UTC`、`+09:00`のようなずれ、`Asia/Tokyo`のような名前
+        #[serde(default)]
+        pub timezone: Option<String>,
+        /// チームでアーカイブするユーザーID。指定すると`atcoder.jp/<user>/<contest>/<problem>`に分けて置く
+        #[serde(default)]
+        pub users: Vec<String>,
+        /// ユーザーIDごとのコミットの作者
+        #[serde(default)]
+        pub authors: HashMap<String, Author>,
+        /// コミッター。指定すると作者 (提出日時) とは別に、この名前とアーカイブした日時をコミッターに記録する。
+        /// 名前やメールアドレスを省くと作者のものを使う
+        #[serde(default)]
+        pub committer: Option<Author>,
+        /// AHC (AtCoder Heuristic Contest) の提出を、スコアが伸びるたびに別のファイルとして残すか。
+        /// バージョン1までは`ahc`という名前だった
+        #[serde(default)]
+        pub ahc_scores: bool,
+        /// アーカイブするコンテストの種類。空ならすべて
+        #[serde(default)]
+        pub contests: Vec<ContestCategory>,
+        /// 公式解説を記録・保存するか
+        #[serde(default)]
+        pub editorial: EditorialMode,
+        /// 言語のバージョン (例: "gcc 12.2") をファイル名やlanguage.txtにも残すか
+        #[serde(default)]
+        pub language_version: LanguageVersion,
+        /// アーカイブしない言語のパターン (例: "Bash*")。提出の言語名か正規の言語名に一致すれば、提出ページを取得しない
+        #[serde(default)]
+        pub skip_languages: Vec<String>,
+        /// アーカイブを写す別のリポジトリ。アーカイブのたびに条件に合う問題を写してコミットする
+        #[serde(default)]
+        pub mirrors: Vec<Mirror>,
+        /// `archive`でコミットが増えたときにpushするリモートの名前 (例: "origin")。資格情報は`remotes`から決める
+        #[serde(default)]
+        pub push: Option<String>,
+    }
+
+    /// アーカイブを写すリポジトリ (公開用に一部の問題だけを置くものなど)
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct Mirror {
+        pub repository_path: String,
+        /// 写す問題のパターン (.ac-garden-ignoreと同じ書き方)。空ならすべて
+        #[serde(default)]
+        pub include: Vec<String>,
+        /// 写さない問題のパターン。`include`より優先する
+        #[serde(default)]
+        pub exclude: Vec<String>,
+        /// 写すコンテストの種類。空ならすべて
+        #[serde(default)]
+        pub contests: Vec<ContestCategory>,
+        /// コミットした後にpushするリモート
+        #[serde(default)]
+        pub push: Option<String>,
+    }
+
+    impl Mirror {
+        /// 問題をこのミラーに写すか
+        pub fn includes(&self, contest_id: &str, problem_id: &str, category: ContestCategory) -> bool {
+            (self.contests.is_empty() || self.contests.contains(&category))
+                && (self.include.is_empty() || self.include.iter().any(|pattern| problem_matches(pattern, contest_id, problem_id)))
+                && !self.exclude.iter().any(|pattern| problem_matches(pattern, contest_id, problem_id))
+        }
+
+        /// ミラーのリポジトリに問題を置くための設定。置き方はアーカイブ元と同じにする
+        pub fn service(&self, source: &Service) -> Service {
+            Service { repository_path: self.repository_path.clone(), mirrors: Vec::new(), ..source.clone() }
+        }
+    }
+
+    /// コミットの作者
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct Author {
+        /// 作者名。なければユーザーID
+        #[serde(default)]
+        pub name: Option<String>,
+        /// メールアドレス。なければ`user_email`
+        #[serde(default)]
+        pub email: Option<String>,
+    }
+
+    /// 言語のバージョンの残し方。メタデータには設定によらず言語の文字列をそのまま記録する
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum LanguageVersion {
+        /// メタデータだけに残す
+        #[default]
+        Off,
+        /// ファイル名に付ける (例: Main_gcc-12.2.cpp)
+        FileName,
+        /// 言語の文字列をlanguage.txtに書く
+        File,
+    }
+
+    /// 公式解説の扱い
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum EditorialMode {
+        /// 何もしない
+        #[default]
+        Off,
+        /// 解説のURLをメタデータに記録する
+        Link,
+        /// URLを記録し、ログインしていれば解説をEDITORIAL.md (古いコンテストはeditorial.pdf) として保存する
+        Download,
+    }
+
+    /// 提出のメタデータの保存形式
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum MetadataFormat {
+        /// 問題ごとのsubmission.json
+        #[default]
+        Json,
+        /// 問題ごとのNOTES.mdのフロントマター
+        FrontMatter,
+        /// アーカイブのコミットに付けるgit notes (refs/notes/ac-garden)。作業ツリーにファイルを置かない
+        GitNotes,
+    }
+
+    impl MetadataFormat {
+        /// メタデータを書き込むファイル名。git notesならNone
+        pub fn file_name(self) -> Option<&'static str> {
+            match self {
+                MetadataFormat::Json => Some(SUBMISSION_JSON),
+                MetadataFormat::FrontMatter => Some(NOTES_MD),
+                MetadataFormat::GitNotes => None,
+            }
+        }
+    }
+
+    /// どの提出をアーカイブするか
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Policy {
+        /// 問題ごとに最新のACだけ。アーカイブ済みの問題はスキップする
+        #[default]
+        Latest,
+        /// すべてのAC。古い順にコミットするので、過去のACはGitの履歴に残る
+        All,
+    }
+
+    /// コミットに使うGitの実装
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum GitBackend {
+        /// 組み込みのlibgit2
+        #[default]
+        Libgit2,
+        /// システムの`git`コマンド。認証ヘルパーやfsmonitor、部分クローン、smudgeフィルタをgitと同じように扱う
+        Cli,
     }
 
+    /// 保存するコードの改行コード
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum LineEnding {
+        /// 提出ページから取得したまま
+        #[default]
+        Keep,
+        /// CRLF/CRをLFにそろえる
+        Lf,
+    }
+
+    /// コンテストの種類
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ContestCategory {
+        /// AtCoder Beginner Contest (ABC相当の企業コンテストを含む)
+        Abc,
+        /// AtCoder Regular Contest
+        Arc,
+        /// AtCoder Grand Contest
+        Agc,
+        /// AtCoder Heuristic Contest
+        Ahc,
+        /// アルゴリズム実技検定
+        Past,
+        /// 日本情報オリンピックのミラー
+        Joi,
+        /// そのほかのコンテスト
+        Other,
+    }
+
+    impl std::str::FromStr for ContestCategory {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            serde_json::from_value(Value::String(s.to_lowercase()))
+                .map_err(|_| format!("unknown contest category `{}` (expected abc, arc, agc, ahc, past, joi or other)", s))
+        }
+    }
+
+    /// アーカイブするソースファイルの名前の付け方
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum FileNaming {
+        /// 言語ごとの`Main.*` (例: Main.cpp)
+        #[default]
+        Main,
+        /// 問題名から付ける (例: A_Welcome_to_AtCoder.cpp)
+        Title,
+    }
+
+    /// コミットメッセージのプリセット
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum CommitStyle {
+        /// `[AC] AtCoder Beginner Contest 300 abc300_a`
+        #[default]
+        Plain,
+        /// 難易度の色の絵文字を付ける (`🟢 [AC] AtCoder Beginner Contest 300 abc300_c`)
+        Emoji,
+        /// gitmoji (`✨ Solve abc300_a (AtCoder Beginner Contest 300)`)
+        Gitmoji,
+        /// Conventional Commits (`feat(abc300): solve abc300_a`)
+        Conventional,
+    }
+
+    impl CommitStyle {
+        /// (通常の提出, AHCモードの提出) のテンプレート
+        pub fn templates(self) -> (&'static str, &'static str) {
+            match self {
+                CommitStyle::Plain => (DEFAULT_COMMIT_TEMPLATE, DEFAULT_AHC_COMMIT_TEMPLATE),
+                CommitStyle::Emoji => (
+                    "{difficulty_emoji} [AC] {contest_title} {problem_id}",
+                    "📈 [AHC] {contest_title} {problem_id} score {point}",
+                ),
+                CommitStyle::Gitmoji => (
+                    "✨ Solve {problem_id} ({contest_title})",
+                    "⚡️ Improve {problem_id} to {point} ({contest_title})",
+                ),
+                CommitStyle::Conventional => (
+                    "feat({contest_id}): solve {problem_id}",
+                    "perf({contest_id}): improve {problem_id} score to {point}",
+                ),
+            }
+        }
+    }
+
+    /// 問題のディレクトリの名前の付け方
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ProblemDirNaming {
+        /// 問題ID (例: abc300/abc300_a)
+        #[default]
+        Id,
+        /// コンテストの問題一覧ページの記号 (例: abc300/A)
+        Index,
+    }
+
+    impl Service {
+        /// 空のままになっている必須の項目
+        pub fn missing_fields(&self) -> Vec<&'static str> {
+            [
+                ("repository_path", &self.repository_path),
+                ("user_id", &self.user_id),
+                ("user_email", &self.user_email),
+            ]
+            .into_iter()
+            .filter(|(_, value)| value.trim().is_empty())
+            .map(|(key, _)| key)
+            .collect()
+        }
+
+        /// リポジトリ内でのこのサービスのディレクトリ (相対パス)。チームの場合はユーザーごとのディレクトリ
+        pub fn relative_dir(&self) -> PathBuf {
+            let mut dir = PathBuf::new();
+            if let Some(root) = &self.root {
+                dir.push(root);
+            }
+            match self.service_dir_name.as_deref().unwrap_or(ATCODER_SERVICE_NAME) {
+                "" => {}
+                name => dir.push(sanitize_path_component(name)),
+            }
+            if self.users.is_empty() {
+                dir
+            } else {
+                dir.join(sanitize_path_component(&self.user_id))
+            }
+        }
+
+        /// アーカイブするユーザーごとの設定。チームでなければ自分だけ
+        pub fn user_services(&self) -> Vec<Service> {
+            if self.users.is_empty() {
+                return vec![self.clone()];
+            }
+            self.users
+                .iter()
+                .map(|user| Service { user_id: user.clone(), ..self.clone() })
+                .collect()
+        }
+
+        /// `skip_languages`のどれかに一致する言語か。大文字と小文字は区別しない
+        pub fn skips_language(&self, language: &str) -> bool {
+            if self.skip_languages.is_empty() {
+                return false;
+            }
+            let names = [language.to_lowercase(), canonical_language(language).to_lowercase()];
+            self.skip_languages.iter().any(|pattern| {
+                let pattern = pattern.to_lowercase();
+                names.iter().any(|name| glob_match(&pattern, name))
+            })
+        }
+
+        /// ユーザーのコミットの作者名とメールアドレス
+        pub fn author(&self, user_id: &str) -> (String, String) {
+            let author = self.authors.get(user_id);
+            let name = author.and_then(|author| author.name.clone()).unwrap_or_else(|| user_id.to_string());
+            let email = author.and_then(|author| author.email.clone()).unwrap_or_else(|| self.user_email.clone());
+            (name, email)
+        }
+
+        /// ファイルを置くディレクトリ。ベアリポジトリではその中の作業用ディレクトリ
+        pub fn work_dir(&self) -> PathBuf {
+            let repo_path = Path::new(&self.repository_path);
+            if is_bare_repository(repo_path) {
+                repo_path.join(BARE_WORK_DIR)
+            } else {
+                repo_path.to_path_buf()
+            }
+        }
+
+        /// このサービスのディレクトリ
+        pub fn service_dir(&self) -> PathBuf {
+            self.work_dir().join(self.relative_dir())
+        }
+
+        /// コンテストのディレクトリ (相対パス)
+        pub fn contest_dir(&self, contest_id: &str) -> PathBuf {
+            self.relative_dir().join(sanitize_path_component(contest_id))
+        }
+
+        /// 問題のディレクトリ (相対パス)
+        ///
+        /// `problem_dir_naming`が`index`で問題の記号が分かっていれば記号 (例: abc300/A) にする
+        pub fn problem_dir(&self, contest_id: &str, problem_id: &str, problem_index: Option<&str>) -> PathBuf {
+            let name = match (self.problem_dir_naming, problem_index) {
+                (ProblemDirNaming::Index, Some(index)) => index,
+                _ => problem_id,
+            };
+            self.contest_dir(contest_id).join(sanitize_path_component(name))
+        }
+
+        /// 提出を置くディレクトリ (相対パス)。`language_dirs`なら問題のディレクトリの中の言語のディレクトリ
+        pub fn submission_dir(&self, submission: &Submission) -> PathBuf {
+            let dir = self.problem_dir(&submission.contest_id, &submission.problem_id, submission.problem_index.as_deref());
+            if self.language_dirs {
+                dir.join(language_dir_name(&submission.language))
+            } else {
+                dir
+            }
+        }
+
+        /// 重複判定のキー。`language_dirs`なら言語ごとに分ける
+        pub fn archive_key(&self, submission: &Submission) -> String {
+            if self.language_dirs {
+                format!("{}/{}", archive_key(submission), language_dir_name(&submission.language))
+            } else {
+                archive_key(submission)
+            }
+        }
+    }
+
+    /// 現在の設定ファイルの形式のバージョン
+    pub const CONFIG_VERSION: u32 = 2;
+
     /// アプリケーション設定
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct Config {
+        /// 設定ファイルの形式のバージョン。ないものは0 (バージョン導入前) とみなす
+        #[serde(default)]
+        pub version: u32,
         pub atcoder: Service,
+        /// HTTPクライアントの設定
+        #[serde(default)]
+        pub http: HttpConfig,
+        /// アーカイブの各段階で実行するコマンド
+        #[serde(default)]
+        pub hooks: HooksConfig,
+        /// `edit`で使うエディタ。`$VISUAL`と`$EDITOR`がないときに使う
+        #[serde(default)]
+        pub editor: Option<String>,
+        /// メッセージの言語。なければ`LANG`などの環境変数から決める
+        #[serde(default)]
+        pub language: Option<Lang>,
+        /// pushするリモートのホスト名 (例: "gitlab.com") ごとの資格情報
+        #[serde(default)]
+        pub remotes: HashMap<String, RemoteAuth>,
+        /// `--profile`で選ぶ設定の組
+        #[serde(default)]
+        pub profiles: HashMap<String, ProfileConfig>,
+    }
+
+    /// `--profile`で切り替える設定の組。書かれた項目だけが`atcoder`の設定より優先される
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct ProfileConfig {
+        pub repository_path: Option<String>,
+        pub git_backend: Option<GitBackend>,
+        /// `archive`でコミットが増えたときにpushするリモート。空ならpushしない
+        pub push: Option<String>,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Config {
+                version: CONFIG_VERSION,
+                atcoder: Service::default(),
+                http: HttpConfig::default(),
+                hooks: HooksConfig::default(),
+                editor: None,
+                language: None,
+                remotes: HashMap::new(),
+                profiles: HashMap::new(),
+            }
+        }
+    }
+
+    impl Config {
+        /// 必須の項目がすべて埋まっているか確かめる。空の項目はまとめて1つのエラーで挙げる
+        pub fn validate(&self) -> Result<()> {
+            let missing = self.atcoder.missing_fields();
+            if !missing.is_empty() {
+                let fields: Vec<String> = missing.iter().map(|key| format!("atcoder.{}", key)).collect();
+                return Err(Error::config(format!(
+                    "Required config fields are empty: {} (fill them in with `ac-garden edit`, or recreate the config with `ac-garden init --force`)",
+                    fields.join(", ")
+                )));
+            }
+            if let Some(timezone) = &self.atcoder.timezone {
+                if utc_offset_minutes(timezone, 0).is_none() {
+                    return Err(Error::config(format!(
+                        "atcoder.timezone {} is not a timezone (use local, UTC, an offset like +09:00 or a name like Asia/Tokyo)",
+                        timezone
+                    )));
+                }
+            }
+            Ok(())
+        }
+
+        /// `profiles`にある設定の組を`atcoder`の設定に重ねる
+        pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+            let Some(profile) = self.profiles.get(name) else {
+                let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                names.sort();
+                return Err(Error::config(format!("Unknown profile {} (profiles in the config: {})", name, names.join(", "))));
+            };
+            if let Some(repository_path) = &profile.repository_path {
+                self.atcoder.repository_path = repository_path.clone();
+            }
+            if let Some(git_backend) = profile.git_backend {
+                self.atcoder.git_backend = git_backend;
+            }
+            if let Some(push) = &profile.push {
+                self.atcoder.push = Some(push.clone()).filter(|push| !push.is_empty());
+            }
+            Ok(())
+        }
+    }
+
+    /// アーカイブの各段階で実行するフックコマンド
+    ///
+    /// 提出の情報は`AC_GARDEN_*`環境変数と標準入力のJSONで渡す
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct HooksConfig {
+        /// アーカイブを始める前。失敗するとアーカイブを中止する
+        #[serde(default)]
+        pub pre_archive: Option<String>,
+        /// ファイルを書き込んだ後 (コミット前)
+        #[serde(default)]
+        pub post_file: Option<String>,
+        /// コミットした後
+        #[serde(default)]
+        pub post_commit: Option<String>,
+        /// アーカイブがすべて終わった後
+        #[serde(default)]
+        pub post_run: Option<String>,
+    }
+
+    /// リモートにpushするときの資格情報
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct RemoteAuth {
+        /// HTTPSで使うユーザー名。なければGitHubは`x-access-token`、ほかは`oauth2`
+        #[serde(default)]
+        pub username: Option<String>,
+        /// HTTPSで使うトークンを読む環境変数
+        #[serde(default)]
+        pub token_env: Option<String>,
+        /// SSHで使う秘密鍵。パスフレーズはssh-agentに登録しておく
+        #[serde(default)]
+        pub ssh_key: Option<String>,
+    }
+
+    /// HTTPクライアントの設定
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct HttpConfig {
+        /// User-Agentを丸ごと置き換える
+        #[serde(default)]
+        pub user_agent: Option<String>,
+        /// 既定のUser-Agentに含める連絡先 (メールアドレスやURL)
+        #[serde(default)]
+        pub contact: Option<String>,
+        /// http://, https://, socks5:// のプロキシURL
+        #[serde(default)]
+        pub proxy: Option<String>,
+        /// プロキシを経由しないホスト (カンマ区切り)
+        #[serde(default)]
+        pub no_proxy: Option<String>,
+        #[serde(default)]
+        pub connect_timeout_secs: Option<u64>,
+        /// リクエスト全体のタイムアウト
+        #[serde(default)]
+        pub timeout_secs: Option<u64>,
+        /// 追加で信頼するCA証明書 (PEM) のパス
+        #[serde(default)]
+        pub ca_certificate: Option<String>,
+        /// "1.0", "1.1", "1.2", "1.3"
+        #[serde(default)]
+        pub min_tls_version: Option<String>,
+        /// 証明書の検証を無効にする (自己署名の社内プロキシ向け)
+        #[serde(default)]
+        pub accept_invalid_certs: bool,
+        /// atcoder.jpへのリクエストの最小間隔 (ミリ秒)。1000未満にはできない
+        #[serde(default)]
+        pub request_interval_ms: Option<u64>,
+        /// 間隔を空けずに続けて送れるリクエスト数 (1〜3)
+        #[serde(default)]
+        pub burst: Option<u32>,
+        /// 待つときに足すランダムな時間の上限 (ミリ秒)
+        #[serde(default)]
+        pub jitter_ms: Option<u64>,
+        /// 送ったすべてのリクエストを時刻付きで追記するファイル
+        #[serde(default)]
+        pub audit_log: Option<String>,
+    }
+
+    /// リポジトリに置く設定ファイルの名前
+    pub const REPO_CONFIG_FILE: &str = ".ac-garden.toml";
+
+    /// リポジトリに置く設定 (.ac-garden.toml)
+    ///
+    /// リポジトリの構成をリポジトリ自身に残し、共同で使う人が同じ設定でアーカイブできるようにする。
+    /// 書かれた項目だけがユーザーの設定より優先される
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct RepoConfig {
+        #[serde(default)]
+        pub layout: LayoutConfig,
+        #[serde(default)]
+        pub commit: CommitConfig,
+        #[serde(default)]
+        pub retention: RetentionConfig,
+    }
+
+    /// ディレクトリ構成とファイルの形式
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct LayoutConfig {
+        pub root: Option<String>,
+        pub service_dir_name: Option<String>,
+        pub file_naming: Option<FileNaming>,
+        pub problem_dir_naming: Option<ProblemDirNaming>,
+        pub language_dirs: Option<bool>,
+        pub metadata_format: Option<MetadataFormat>,
+        pub line_ending: Option<LineEnding>,
+        pub header: Option<bool>,
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct CommitConfig {
+        /// コミットメッセージのテンプレート
+        pub template: Option<String>,
+        /// テンプレートのプリセット
+        pub style: Option<CommitStyle>,
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct RetentionConfig {
+        pub policy: Option<Policy>,
+    }
+
+    impl RepoConfig {
+        /// リポジトリの.ac-garden.tomlを読み込む。なければNone
+        pub fn load(repo_path: &Path) -> Result<Option<RepoConfig>> {
+            let path = repo_path.join(REPO_CONFIG_FILE);
+            if !path.is_file() {
+                return Ok(None);
+            }
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| Error::config_with_source(format!("Failed to read {}", path.display()), e))?;
+            toml::from_str(&content)
+                .map(Some)
+                .map_err(|e| Error::config_with_source(format!("Failed to parse {}", path.display()), e))
+        }
+
+        /// 書かれた項目でサービスの設定を上書きする
+        pub fn apply(&self, service: &mut Service) {
+            let layout = &self.layout;
+            if let Some(root) = &layout.root {
+                service.root = Some(root.clone()).filter(|root| !root.is_empty());
+            }
+            if let Some(name) = &layout.service_dir_name {
+                service.service_dir_name = Some(name.clone());
+            }
+            if let Some(file_naming) = layout.file_naming {
+                service.file_naming = file_naming;
+            }
+            if let Some(problem_dir_naming) = layout.problem_dir_naming {
+                service.problem_dir_naming = problem_dir_naming;
+            }
+            if let Some(language_dirs) = layout.language_dirs {
+                service.language_dirs = language_dirs;
+            }
+            if let Some(metadata_format) = layout.metadata_format {
+                service.metadata_format = metadata_format;
+            }
+            if let Some(line_ending) = layout.line_ending {
+                service.line_ending = line_ending;
+            }
+            if let Some(header) = layout.header {
+                service.header = header;
+            }
+            if let Some(template) = &self.commit.template {
+                service.commit_template = Some(template.clone());
+            }
+            if let Some(style) = self.commit.style {
+                service.commit_style = style;
+            }
+            if let Some(policy) = self.retention.policy {
+                service.policy = policy;
+            }
+        }
+    }
+
+    /// アーカイブ先のリポジトリに.ac-garden.tomlがあれば設定に反映する
+    pub fn apply_repo_config(config: &mut Config) -> Result<()> {
+        if config.atcoder.repository_path.is_empty() {
+            return Ok(());
+        }
+        if let Some(repo_config) = RepoConfig::load(&config.atcoder.work_dir())? {
+            repo_config.apply(&mut config.atcoder);
+        }
+        Ok(())
+    }
+
+    /// リポジトリに置く、アーカイブしない問題のリストの名前
+    pub const IGNORE_FILE: &str = ".ac-garden-ignore";
+
+    /// アーカイブしない問題やコンテストのパターン (.ac-garden-ignore)
+    ///
+    /// gitignoreと同じく1行に1つ、`contest_id/problem_id`に対するパターンを書く。
+    /// `/`を含まないパターンはコンテストIDと問題IDのどちらにも一致する。
+    /// `#`で始まる行はコメントで、`!`で始まるパターンは除外を取り消す。後に書いたパターンが優先される
+    #[derive(Debug, Clone, Default)]
+    pub struct IgnoreList {
+        /// (`!`で取り消すか, パターン)
+        patterns: Vec<(bool, String)>,
+    }
+
+    impl IgnoreList {
+        pub fn parse(content: &str) -> IgnoreList {
+            let patterns = content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| match line.strip_prefix('!') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, line),
+                })
+                .map(|(negated, pattern)| (negated, pattern.trim_matches('/').to_string()))
+                .filter(|(_, pattern)| !pattern.is_empty())
+                .collect();
+            IgnoreList { patterns }
+        }
+
+        /// ディレクトリの.ac-garden-ignoreを読み込む。なければ空
+        pub fn load(dir: &Path) -> Result<IgnoreList> {
+            let path = dir.join(IGNORE_FILE);
+            if !path.is_file() {
+                return Ok(IgnoreList::default());
+            }
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| Error::config_with_source(format!("Failed to read {}", path.display()), e))?;
+            Ok(IgnoreList::parse(&content))
+        }
+
+        /// 問題をアーカイブしないか
+        pub fn is_ignored(&self, contest_id: &str, problem_id: &str) -> bool {
+            let mut ignored = false;
+            for (negated, pattern) in &self.patterns {
+                if problem_matches(pattern, contest_id, problem_id) {
+                    ignored = !negated;
+                }
+            }
+            ignored
+        }
+    }
+
+    /// 設定ディレクトリ (~/.ac-garden)
+    pub fn get_config_dir() -> Result<PathBuf> {
+        let home = home::home_dir().ok_or_else(|| Error::config("Failed to get home directory"))?;
+        Ok(home.join(format!(".{}", APP_NAME)))
     }
 
     /// 設定ファイルのパスを取得
-    pub fn get_config_path() -> PathBuf {
-        let home = home::home_dir().expect("Failed to get home directory");
-        home.join(".ac-garden").join("config.json")
+    pub fn get_config_path() -> Result<PathBuf> {
+        Ok(get_config_dir()?.join("config.json"))
+    }
+
+    /// 設定ファイルを書き込む
+    pub fn save_config(path: &Path, config: &Config) -> Result<()> {
+        let json = serde_json::to_string_pretty(config)
+            .map_err(|e| Error::config_with_source("Failed to serialize config", e))?;
+        std::fs::write(path, json)
+            .map_err(|e| Error::config_with_source("Failed to write config file", e))
+    }
+
+    /// 設定ファイルを読み込む
+    ///
+    /// 古い形式であれば読み込んだ内容を現在の形式に変換する (ファイルは書き換えない)
+    pub fn load_config(path: &Path) -> Result<Config> {
+        read_config(path).map(|(config, _)| config)
+    }
+
+    /// 現在の形式への変換の結果
+    #[derive(Debug, Clone, Default)]
+    pub struct MigrationReport {
+        /// 読み込んだファイルのバージョン
+        pub from_version: u32,
+        /// 行った変更の説明
+        pub changes: Vec<String>,
+    }
+
+    impl MigrationReport {
+        /// 古いバージョンの形式だったか
+        pub fn is_outdated(&self) -> bool {
+            self.from_version < CONFIG_VERSION
+        }
+    }
+
+    /// 設定ファイルを読み込み、現在の形式への変換の結果と一緒に返す
+    pub fn read_config(path: &Path) -> Result<(Config, MigrationReport)> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::config_with_source("Failed to read config file", e))?;
+        let value = serde_json::from_str(&content)
+            .map_err(|e| Error::config_with_source("Failed to parse config", e))?;
+        migrate_config(value)
+    }
+
+    /// 設定ファイルの形式を1つ新しくする変換 (変換前のバージョン, 説明, 変換)
+    ///
+    /// フィールド名を変えたときはここに変換を足し、CONFIG_VERSIONを上げる
+    type Migration = (u32, &'static str, fn(&mut Map<String, Value>));
+
+    const MIGRATIONS: &[Migration] = &[
+        (0, "add the version field", |_| {}),
+        // `contests`の"ahc"と紛らわしいので、スコアごとにファイルを残す設定の名前を変えた
+        (1, "rename atcoder.ahc to atcoder.ahc_scores", |config| rename_key(config, "atcoder", "ahc", "ahc_scores")),
+    ];
+
+    /// `section`の中のキーの名前を変える。新しい名前がすでにあればそちらを残す
+    fn rename_key(config: &mut Map<String, Value>, section: &str, from: &str, to: &str) {
+        let Some(section) = config.get_mut(section).and_then(Value::as_object_mut) else {
+            return;
+        };
+        if let Some(value) = section.remove(from) {
+            section.entry(to).or_insert(value);
+        }
+    }
+
+    /// 古い形式の設定を現在の形式に変換する
+    ///
+    /// 新しく増えたキーは既定値で補う
+    pub fn migrate_config(mut value: Value) -> Result<(Config, MigrationReport)> {
+        let object = value.as_object_mut()
+            .ok_or_else(|| Error::config("Config must be a JSON object"))?;
+        let from_version = object.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        let mut version = from_version;
+        if version > CONFIG_VERSION {
+            return Err(Error::config(format!(
+                "Config version {} is newer than this version of {} supports ({}). Please upgrade {}",
+                version, APP_NAME, CONFIG_VERSION, APP_NAME
+            )));
+        }
+
+        let mut changes = Vec::new();
+        for (from, description, migrate) in MIGRATIONS {
+            if *from == version {
+                migrate(object);
+                version += 1;
+                changes.push(format!("v{} -> v{}: {}", from, version, description));
+            }
+        }
+        object.insert("version".to_string(), version.into());
+
+        let config: Config = serde_json::from_value(value.clone())
+            .map_err(|e| Error::config_with_source("Failed to parse config", e))?;
+
+        // 既定値で補われたキー
+        let filled = serde_json::to_value(&config)
+            .map_err(|e| Error::config_with_source("Failed to serialize config", e))?;
+        collect_added_keys("", &value, &filled, &mut changes);
+
+        Ok((config, MigrationReport { from_version, changes }))
+    }
+
+    /// `after`にだけあるキーを"add atcoder.header = false"の形で集める
+    fn collect_added_keys(prefix: &str, before: &Value, after: &Value, changes: &mut Vec<String>) {
+        let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+            return;
+        };
+        for (key, value) in after {
+            let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            match before.get(key) {
+                Some(old) => collect_added_keys(&path, old, value, changes),
+                None => changes.push(format!("add {} = {}", path, value)),
+            }
+        }
+    }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        fn version_0() -> Value {
+            json!({
+                "atcoder": {
+                    "repository_path": "/tmp/garden", "user_id": "me", "user_email": "me@example.com",
+                    "ahc": true, "contests": ["ahc"],
+                },
+            })
+        }
+
+        #[test]
+        fn migrates_old_configs_step_by_step() {
+            let (config, report) = migrate_config(version_0()).unwrap();
+            assert_eq!(report.from_version, 0);
+            assert!(report.is_outdated());
+            assert_eq!(config.version, CONFIG_VERSION);
+            assert!(config.atcoder.ahc_scores);
+            assert_eq!(config.atcoder.contests, vec![ContestCategory::Ahc]);
+            assert_eq!(report.changes[..2], [
+                "v0 -> v1: add the version field".to_string(),
+                "v1 -> v2: rename atcoder.ahc to atcoder.ahc_scores".to_string(),
+            ]);
+            // 既定値で補ったキーも報告する。名前を変えたキーは追加扱いにしない
+            assert!(report.changes.contains(&"add atcoder.header = false".to_string()));
+            assert!(!report.changes.iter().any(|c| c.starts_with("add atcoder.ahc_scores")));
+        }
+
+        #[test]
+        fn keeps_the_new_key_when_both_names_are_present() {
+            let mut value = version_0();
+            value["version"] = json!(1);
+            value["atcoder"]["ahc_scores"] = json!(false);
+            let (config, report) = migrate_config(value).unwrap();
+            assert_eq!(report.from_version, 1);
+            assert!(!config.atcoder.ahc_scores);
+        }
+
+        #[test]
+        fn round_trips_migrated_configs_without_changes() {
+            let (config, _) = migrate_config(version_0()).unwrap();
+            let saved = serde_json::to_value(&config).unwrap();
+            assert_eq!(saved["version"], json!(CONFIG_VERSION));
+            assert!(saved["atcoder"].get("ahc").is_none());
+
+            let (reloaded, report) = migrate_config(saved.clone()).unwrap();
+            assert_eq!(report.from_version, CONFIG_VERSION);
+            assert!(!report.is_outdated());
+            assert!(report.changes.is_empty());
+            assert_eq!(serde_json::to_value(&reloaded).unwrap(), saved);
+        }
+
+        #[test]
+        fn rejects_configs_from_newer_versions() {
+            let mut value = version_0();
+            value["version"] = json!(CONFIG_VERSION + 1);
+            assert!(matches!(migrate_config(value), Err(Error::Config { .. })));
+        }
     }
 }
 
-/// ファイル操作のユーティリティ
-pub mod utils {
-    use std::path::Path;
+/// セッションなどの秘密の値の保存
+///
+/// 設定ファイルに平文で書く代わりに、値を`"keyring"`にしておくとOSのキーチェーンから読み出す。
+/// キーチェーンが使えない環境ではパスフレーズで暗号化したファイルに保存する
+pub mod credentials {
+    use crate::config::get_config_dir;
+    use crate::{Error, Result, APP_NAME};
+    use argon2::Argon2;
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+    use chacha20poly1305::aead::rand_core::RngCore;
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
 
-    /// ディレクトリが存在するか確認
-    pub fn is_dir_exist<P: AsRef<Path>>(path: P) -> bool {
-        path.as_ref().is_dir()
+    /// 設定ファイルでキーチェーンの値を使うことを表す値
+    pub const KEYRING_REFERENCE: &str = "keyring";
+    /// 暗号化ファイルのパスフレーズを渡す環境変数
+    pub const PASSPHRASE_ENV: &str = "AC_GARDEN_PASSPHRASE";
+    const CREDENTIALS_FILE: &str = "credentials.enc.json";
+
+    /// 値を保存した場所
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum SecretStore {
+        /// OSのキーチェーン
+        Keyring,
+        /// パスフレーズで暗号化したファイル
+        EncryptedFile(PathBuf),
     }
 
-    /// ファイルが存在するか確認
-    pub fn is_file_exist<P: AsRef<Path>>(path: P) -> bool {
-        path.as_ref().is_file()
+    /// 暗号化ファイルの1項目。値はすべて16進数
+    #[derive(Debug, Serialize, Deserialize)]
+    struct EncryptedSecret {
+        salt: String,
+        nonce: String,
+        ciphertext: String,
+    }
+
+    /// 保存できる値の名前
+    pub const SECRET_NAMES: &[&str] = &["session", "github_token", "gitlab_token"];
+
+    /// 設定の値がキーチェーンの参照か
+    pub fn is_reference(value: &str) -> bool {
+        value == KEYRING_REFERENCE
     }
 
-    /// 言語に基づいて適切なファイル名を決定
-    pub fn language_to_file_name(language: &str) -> String {
-        // プログラミング言語名から拡張子を判断する
-        // 言語名には時にバージョン情報などが括弧付きで含まれる
-        let language = if let Some(idx) = language.find('(') {
-            &language[..idx].trim()
+    /// 設定の値が`"keyring"`なら保存した値を読み出し、そうでなければそのまま返す
+    pub fn resolve_secret(name: &str, value: &str, passphrase: impl FnOnce() -> Result<String>) -> Result<String> {
+        if is_reference(value) {
+            load_secret(name, passphrase)
         } else {
-            language
+            Ok(value.to_string())
+        }
+    }
+
+    /// 環境変数からパスフレーズを読む
+    pub fn passphrase_from_env() -> Result<String> {
+        std::env::var(PASSPHRASE_ENV).map_err(|_| {
+            Error::credential(
+                "credentials file",
+                format!("set {} to unlock the encrypted credentials file", PASSPHRASE_ENV),
+            )
+        })
+    }
+
+    /// 暗号化ファイルのパス
+    pub fn credentials_file() -> Result<PathBuf> {
+        Ok(get_config_dir()?.join(CREDENTIALS_FILE))
+    }
+
+    fn keyring_entry(name: &str) -> keyring::Result<keyring::Entry> {
+        keyring::Entry::new(APP_NAME, name)
+    }
+
+    /// 値を読み出す。キーチェーンになければ暗号化ファイルを探す
+    ///
+    /// パスフレーズは暗号化ファイルを読むときだけ求める
+    pub fn load_secret(name: &str, passphrase: impl FnOnce() -> Result<String>) -> Result<String> {
+        match keyring_entry(name).and_then(|entry| entry.get_password()) {
+            Ok(secret) => return Ok(secret),
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => crate::warning::warn(&crate::tr!("Warning: OS keyring is unavailable ({}), using the encrypted credentials file", e)),
+        }
+
+        let secrets = read_file()?;
+        let encrypted = secrets.get(name).ok_or_else(|| {
+            Error::credential(name, format!("not stored yet, run `{} credentials set {}`", APP_NAME, name))
+        })?;
+        decrypt(name, encrypted, &passphrase()?)
+    }
+
+    /// 値を保存する。キーチェーンが使えなければ暗号化ファイルに保存する
+    pub fn save_secret(name: &str, secret: &str, passphrase: impl FnOnce() -> Result<String>) -> Result<SecretStore> {
+        match keyring_entry(name).and_then(|entry| entry.set_password(secret)) {
+            Ok(()) => return Ok(SecretStore::Keyring),
+            Err(e) => crate::warning::warn(&crate::tr!("Warning: OS keyring is unavailable ({}), using the encrypted credentials file", e)),
+        }
+
+        let mut secrets = read_file()?;
+        secrets.insert(name.to_string(), encrypt(name, secret, &passphrase()?)?);
+        let path = write_file(&secrets)?;
+        Ok(SecretStore::EncryptedFile(path))
+    }
+
+    /// キーチェーンと暗号化ファイルの両方から値を消す。消したものがあればtrue
+    pub fn delete_secret(name: &str) -> Result<bool> {
+        let mut deleted = keyring_entry(name)
+            .and_then(|entry| entry.delete_credential())
+            .is_ok();
+
+        let mut secrets = read_file()?;
+        if secrets.remove(name).is_some() {
+            write_file(&secrets)?;
+            deleted = true;
+        }
+        Ok(deleted)
+    }
+
+    /// キーチェーンか暗号化ファイルに値が保存されているか
+    pub fn has_secret(name: &str) -> Result<bool> {
+        if keyring_entry(name).and_then(|entry| entry.get_password()).is_ok() {
+            return Ok(true);
+        }
+        Ok(read_file()?.contains_key(name))
+    }
+
+    /// 名前 → 値をまとめてパスフレーズで暗号化する。設定を別のマシンに移すときに使う
+    pub fn seal_secrets(secrets: &BTreeMap<String, String>, passphrase: &str) -> Result<String> {
+        let json = serde_json::to_string(secrets)
+            .map_err(|e| Error::credential_with_source("backup", "failed to encrypt", e))?;
+        let encrypted = encrypt("backup", &json, passphrase)?;
+        serde_json::to_string(&encrypted)
+            .map_err(|e| Error::credential_with_source("backup", "failed to encrypt", e))
+    }
+
+    /// `seal_secrets`で暗号化した値を戻す
+    pub fn unseal_secrets(sealed: &str, passphrase: &str) -> Result<BTreeMap<String, String>> {
+        let encrypted: EncryptedSecret = serde_json::from_str(sealed)
+            .map_err(|e| Error::credential_with_source("backup", "the credentials file is corrupted", e))?;
+        let json = decrypt("backup", &encrypted, passphrase)?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::credential_with_source("backup", "the credentials file is corrupted", e))
+    }
+
+    fn read_file() -> Result<BTreeMap<String, EncryptedSecret>> {
+        let path = credentials_file()?;
+        if !path.is_file() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path).map_err(|e| Error::storage(&path, e))?;
+        serde_json::from_str(&content).map_err(|e| Error::storage(&path, e))
+    }
+
+    fn write_file(secrets: &BTreeMap<String, EncryptedSecret>) -> Result<PathBuf> {
+        let path = credentials_file()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::storage(dir, e))?;
+        }
+        let json = serde_json::to_string_pretty(secrets).map_err(|e| Error::storage(&path, e))?;
+        fs::write(&path, json).map_err(|e| Error::storage(&path, e))?;
+
+        // 暗号化していても他のユーザーからは読めないようにしておく
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).map_err(|e| Error::storage(&path, e))?;
+        }
+        Ok(path)
+    }
+
+    /// パスフレーズとソルトからArgon2で鍵を導出する
+    fn derive_key(name: &str, passphrase: &str, salt: &[u8]) -> Result<Key> {
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| Error::credential(name, format!("failed to derive the key: {}", e)))?;
+        Ok(key)
+    }
+
+    fn encrypt(name: &str, secret: &str, passphrase: &str) -> Result<EncryptedSecret> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let cipher = ChaCha20Poly1305::new(&derive_key(name, passphrase, &salt)?);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher.encrypt(&nonce, secret.as_bytes())
+            .map_err(|_| Error::credential(name, "failed to encrypt"))?;
+
+        Ok(EncryptedSecret {
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    fn decrypt(name: &str, encrypted: &EncryptedSecret, passphrase: &str) -> Result<String> {
+        let decode = |value: &str| {
+            hex::decode(value).map_err(|e| Error::credential_with_source(name, "the credentials file is corrupted", e))
         };
+        let salt = decode(&encrypted.salt)?;
+        let nonce = decode(&encrypted.nonce)?;
+        if nonce.len() != 12 {
+            return Err(Error::credential(name, "the credentials file is corrupted"));
+        }
+        let ciphertext = decode(&encrypted.ciphertext)?;
 
-        match language {
-            "C++" | "C++14" | "C++17" | "C++20" => "Main.cpp",
-            "Bash" => "Main.sh",
-            "C" => "Main.c",
-            // 他の言語の対応も同様...
-            _ => {
-                eprintln!("Unknown language: {}", language);
-                "Main.txt"
+        let cipher = ChaCha20Poly1305::new(&derive_key(name, passphrase, &salt)?);
+        let plaintext = cipher.decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| Error::credential(name, "wrong passphrase"))?;
+        String::from_utf8(plaintext)
+            .map_err(|e| Error::credential_with_source(name, "the stored value is not valid UTF-8", e))
+    }
+}
+
+/// アーカイブ済みファイルの読み書き
+pub mod store {
+    use crate::config::{MetadataFormat, Service};
+    use crate::editorial::{EDITORIAL_MD, EDITORIAL_PDF};
+    use crate::metadata::{parse_front_matter, replace_front_matter};
+    use crate::submission::Submission;
+    use crate::utils::{is_dir_exist, is_file_exist, long_path};
+    use crate::{Error, Result};
+    use rayon::iter::{ParallelBridge, ParallelIterator};
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    pub const SUBMISSION_JSON: &str = "submission.json";
+    pub const NOTES_MD: &str = "NOTES.md";
+    /// `language_version`が`file`のときに言語の文字列を書くファイル
+    pub const LANGUAGE_TXT: &str = "language.txt";
+    /// メタデータを付けるgit notesの参照
+    pub const NOTES_REF: &str = "refs/notes/ac-garden";
+    /// アーカイブがソースファイルと一緒に書き込む、決まった名前のファイル
+    ///
+    /// ソースファイルを探すときは、これと整形前の`*.orig`を除く
+    pub const METADATA_FILES: &[&str] = &[SUBMISSION_JSON, NOTES_MD, LANGUAGE_TXT, EDITORIAL_MD, EDITORIAL_PDF, "result.json"];
+
+    /// コードとメタデータを問題ディレクトリに書き込む
+    pub fn archive_file(code: &str, file_name: &str, path: &Path, submission: &Submission, format: MetadataFormat) -> Result<()> {
+        write_source(code, file_name, path)?;
+        write_metadata(path, submission, format)
+    }
+
+    /// ソースコードだけをディレクトリに書き込む
+    pub fn write_source(code: &str, file_name: &str, path: &Path) -> Result<()> {
+        fs::create_dir_all(long_path(path)).map_err(|e| Error::storage(path, e))?;
+
+        let file_path = path.join(file_name);
+        fs::write(long_path(&file_path), code).map_err(|e| Error::storage(&file_path, e))
+    }
+
+    /// 提出のメタデータを設定された形式で保存する
+    ///
+    /// git notesはコミットした後に付けるので、ここでは何もしない
+    pub fn write_metadata(path: &Path, submission: &Submission, format: MetadataFormat) -> Result<()> {
+        let Some(file_name) = format.file_name() else {
+            return Ok(());
+        };
+        let metadata_path = path.join(file_name);
+        match format {
+            MetadataFormat::Json => {
+                // 提出JSONを保存
+                let json = serde_json::to_string_pretty(submission)
+                    .map_err(|e| Error::storage(&metadata_path, e))?;
+
+                fs::write(long_path(&metadata_path), json).map_err(|e| Error::storage(&metadata_path, e))?;
             }
-        }.to_string()
+            MetadataFormat::FrontMatter => {
+                let value = match serde_json::to_value(submission).map_err(|e| Error::storage(&metadata_path, e))? {
+                    serde_json::Value::Object(map) => map,
+                    _ => unreachable!("submission is serialized as an object"),
+                };
+
+                // 既存のNOTES.mdがあれば本文は残す
+                let notes = if is_file_exist(&metadata_path) {
+                    fs::read_to_string(long_path(&metadata_path)).map_err(|e| Error::storage(&metadata_path, e))?
+                } else {
+                    String::new()
+                };
+
+                fs::write(long_path(&metadata_path), replace_front_matter(&notes, &value))
+                    .map_err(|e| Error::storage(&metadata_path, e))?;
+            }
+            MetadataFormat::GitNotes => {}
+        }
+
+        Ok(())
+    }
+
+    /// submission.jsonまたはNOTES.mdのフロントマターから提出を読み込む
+    ///
+    /// NOTES.mdにフロントマターがなければNone
+    pub fn read_metadata(path: &Path) -> Result<Option<Submission>> {
+        let content = fs::read_to_string(long_path(path)).map_err(|e| Error::storage(path, e))?;
+
+        if path.file_name().is_some_and(|name| name == NOTES_MD) {
+            return match parse_front_matter(&content) {
+                Some((map, _)) => serde_json::from_value(serde_json::Value::Object(map))
+                    .map(Some)
+                    .map_err(|e| Error::storage(path, e)),
+                None => Ok(None),
+            };
+        }
+
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| Error::storage(path, e))
+    }
+
+    /// メタデータファイルの形式 (設定ではなく実際のファイル名で判断する)
+    pub fn metadata_format_of(metadata_path: &Path) -> MetadataFormat {
+        if metadata_path.file_name().is_some_and(|name| name == NOTES_MD) {
+            MetadataFormat::FrontMatter
+        } else {
+            MetadataFormat::Json
+        }
+    }
+
+    /// アーカイブのコミットに付けたgit notesから提出を読み込む
+    ///
+    /// このサービスのディレクトリに置かれる問題だけを、新しい提出が先になるように返す
+    pub fn read_notes(service: &Service) -> Vec<Submission> {
+        read_notes_by_commit(service).into_iter().map(|(_, submission)| submission).collect()
+    }
+
+    /// [`read_notes`]と同じ提出を、ノートを付けたコミットと一緒に返す
+    pub fn read_notes_by_commit(service: &Service) -> Vec<(git2::Oid, Submission)> {
+        let Ok(repo) = git2::Repository::open(&service.repository_path) else {
+            return Vec::new();
+        };
+        let Ok(notes) = repo.notes(Some(NOTES_REF)) else {
+            return Vec::new();
+        };
+
+        let user_id = service.user_id.to_lowercase();
+        let mut submissions: Vec<(git2::Oid, Submission)> = notes
+            .filter_map(|note| note.ok())
+            .filter_map(|(_, commit_id)| {
+                let note = repo.find_note(Some(NOTES_REF), commit_id).ok()?;
+                Some((commit_id, serde_json::from_str::<Submission>(note.message()?).ok()?))
+            })
+            // チームの場合は自分のディレクトリの提出だけ
+            .filter(|(_, submission)| service.users.is_empty() || submission.user_id.to_lowercase() == user_id)
+            .collect();
+        submissions.sort_by_key(|(_, s)| std::cmp::Reverse((s.epoch_second, s.id)));
+        submissions
+    }
+
+    /// コミットに提出のメタデータをgit notesとして付ける。すでにあれば置き換える
+    pub fn write_note(repo_path: &Path, commit_id: git2::Oid, author: &git2::Signature, committer: &git2::Signature, submission: &Submission) -> Result<()> {
+        let repo = git2::Repository::open(repo_path)?;
+        let json = serde_json::to_string_pretty(submission).map_err(|e| Error::storage(repo_path, e))?;
+        repo.note(author, committer, Some(NOTES_REF), commit_id, &json, true)?;
+        Ok(())
+    }
+
+    /// アーカイブ済みの提出を、メタデータファイルのパスと一緒にすべて読み込む
+    ///
+    /// 他のジャッジのファイルと混ざらないよう、このサービスのディレクトリだけを走査する。
+    /// git notesにだけある提出は、問題ディレクトリの`submission.json`のパスで返す (ファイルはない)
+    pub fn scan_archived_submissions(service: &Service) -> Vec<(PathBuf, Submission)> {
+        let mut archived = scan_metadata_files(service);
+
+        let mut seen: HashSet<String> = archived.iter().map(|(_, s)| service.archive_key(s)).collect();
+        for submission in read_notes(service) {
+            if seen.insert(service.archive_key(&submission)) {
+                let dir = service.work_dir().join(service.submission_dir(&submission));
+                archived.push((dir.join(SUBMISSION_JSON), submission));
+            }
+        }
+
+        archived
+    }
+
+    /// 問題ディレクトリのメタデータファイルから提出を読み込む
+    ///
+    /// 走査で見つけたファイルから順に並列で読み込む。結果は実行ごとに変わらないようパスの順にそろえる
+    fn scan_metadata_files(service: &Service) -> Vec<(PathBuf, Submission)> {
+        let service_dir = service.service_dir();
+        if !is_dir_exist(&service_dir) {
+            return Vec::new();
+        }
+
+        let mut archived: Vec<(PathBuf, Submission)> = walkdir::WalkDir::new(&service_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy();
+                e.file_type().is_file() && (name == SUBMISSION_JSON || name == NOTES_MD)
+            })
+            .par_bridge()
+            .filter_map(|entry| match read_metadata(entry.path()) {
+                Ok(Some(submission)) => Some((entry.into_path(), submission)),
+                Ok(None) => None,
+                Err(e) => {
+                    crate::warning::warn(&crate::tr!("Skipping {}: {:#}", entry.path().display(), anyhow::Error::from(e)));
+                    None
+                },
+            })
+            .collect();
+        archived.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        archived
+    }
+
+    /// アーカイブ済みのコンテストID一覧
+    pub fn archived_contest_ids(service: &Service) -> Result<Vec<String>> {
+        let service_dir = service.service_dir();
+        let mut contest_ids = Vec::new();
+        if !is_dir_exist(&service_dir) {
+            return Ok(contest_ids);
+        }
+
+        let entries = fs::read_dir(&service_dir).map_err(|e| Error::storage(&service_dir, e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| Error::storage(&service_dir, e))?;
+            if entry.file_type().map_err(|e| Error::storage(entry.path(), e))?.is_dir() {
+                contest_ids.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        contest_ids.sort();
+
+        Ok(contest_ids)
+    }
+
+    /// 見出しの下に新しい記録を先頭に足していくMarkdownに、記録を1つ足した内容
+    ///
+    /// 既存の見出しは1つにまとめ、見出しのないファイルは元の内容をそのまま記録の下に残す
+    pub fn prepend_log_entry(previous: &str, title: &str, entry: &str) -> String {
+        let previous = previous.trim_start();
+        let rest = match previous.split_once('\n') {
+            Some((first, rest)) if first.trim_end() == title => rest,
+            None if previous.trim_end() == title => "",
+            _ => previous,
+        };
+        let rest = rest.trim_start_matches(['\r', '\n']);
+
+        let mut content = format!("{}\n\n{}\n", title, entry.trim_end());
+        if !rest.is_empty() {
+            content.push('\n');
+            content.push_str(rest);
+        }
+        content
+    }
+
+    /// 問題ディレクトリのファイルが、提出をアーカイブしたときに書き込むものか
+    ///
+    /// メタデータなどの決まった名前と、`Main.*`や整形前の`*.orig`、提出の言語の拡張子のソースファイルだけを認め、
+    /// メモや入力例のようにユーザーが置いたファイルは含めない
+    pub fn is_archive_file(name: &str, submission: &Submission) -> bool {
+        if METADATA_FILES.contains(&name) {
+            return true;
+        }
+        if name.starts_with("Main.") || name.starts_with("Main_") || name.ends_with(".orig") {
+            return true;
+        }
+        let extension = crate::languages::table().extension(&submission.language);
+        extension.is_some_and(|extension| Path::new(name).extension().is_some_and(|ext| ext == extension))
+    }
+
+    /// 問題ディレクトリ内のソースファイル (メタデータや整形前のファイルを除く)
+    pub fn find_source_files(dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut sources = Vec::new();
+        for entry in fs::read_dir(dir).map_err(|e| Error::storage(dir, e))? {
+            let entry = entry.map_err(|e| Error::storage(dir, e))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !entry.file_type().map_err(|e| Error::storage(entry.path(), e))?.is_file()
+                || METADATA_FILES.contains(&name.as_str())
+                || name.ends_with(".orig")
+            {
+                continue;
+            }
+            sources.push(entry.path());
+        }
+        sources.sort();
+        Ok(sources)
+    }
+
+    /// `base`からの相対パスのファイルを1つのtar、tar.gzまたはzipにまとめる
+    ///
+    /// 外部の`tar`や`zip`コマンドがない環境 (Windowsなど) でも作れるよう、プロセス内で書き出す
+    pub fn write_export(base: &Path, files: &[PathBuf], format: &str, output: &Path) -> Result<()> {
+        let file = fs::File::create(long_path(output)).map_err(|e| Error::storage(output, e))?;
+        match format {
+            "zip" => {
+                let mut zip = zip::ZipWriter::new(file);
+                let options = zip::write::SimpleFileOptions::default();
+                for rel_path in files {
+                    // zipの中のパスは区切りを`/`にする
+                    let name = rel_path.components()
+                        .map(|component| component.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    let path = base.join(rel_path);
+                    let content = fs::read(long_path(&path)).map_err(|e| Error::storage(&path, e))?;
+                    zip.start_file(name, options).map_err(|e| Error::storage(output, e))?;
+                    std::io::Write::write_all(&mut zip, &content).map_err(|e| Error::storage(output, e))?;
+                }
+                zip.finish().map_err(|e| Error::storage(output, e))?;
+            }
+            "tar" => {
+                let mut tar = tar::Builder::new(file);
+                append_to_tar(&mut tar, base, files)?;
+                tar.into_inner().map_err(|e| Error::storage(output, e))?;
+            }
+            "tar.gz" => {
+                let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(file, flate2::Compression::default()));
+                append_to_tar(&mut tar, base, files)?;
+                tar.into_inner()
+                    .and_then(|gzip| gzip.finish())
+                    .map_err(|e| Error::storage(output, e))?;
+            }
+            _ => return Err(Error::config(format!("Unknown export format {}", format))),
+        }
+        Ok(())
+    }
+
+    fn append_to_tar<W: std::io::Write>(tar: &mut tar::Builder<W>, base: &Path, files: &[PathBuf]) -> Result<()> {
+        for rel_path in files {
+            let path = base.join(rel_path);
+            tar.append_path_with_name(long_path(&path), rel_path).map_err(|e| Error::storage(&path, e))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::Read;
+
+        #[test]
+        fn recognizes_only_the_files_an_archive_writes() {
+            let submission: Submission = serde_json::from_value(serde_json::json!({
+                "id": 1, "epoch_second": 1_700_000_000, "problem_id": "abc300_a", "contest_id": "abc300",
+                "user_id": "me", "language": "C++ 20 (gcc 12.2)", "point": 100.0, "length": 10, "result": "AC",
+                "execution_time": 1,
+            })).unwrap();
+            for name in ["Main.cpp", "Main.WA.cpp", "Main_gcc-12.2.cpp", "Main.orig", "A_Welcome_to_AtCoder.cpp", "submission.json", "NOTES.md"] {
+                assert!(is_archive_file(name, &submission), "{}", name);
+            }
+            for name in ["memo.txt", "input1.txt", "a.out", "solve.py"] {
+                assert!(!is_archive_file(name, &submission), "{}", name);
+            }
+        }
+
+        #[test]
+        fn finds_source_files_without_the_metadata() {
+            let dir = std::env::temp_dir().join(format!("ac-garden-sources-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            for name in METADATA_FILES.iter().copied().chain(["Main.orig", "Main.cpp", "memo.txt"]) {
+                fs::write(dir.join(name), "").unwrap();
+            }
+            assert_eq!(find_source_files(&dir).unwrap(), vec![dir.join("Main.cpp"), dir.join("memo.txt")]);
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn exports_files_into_each_archive_format() {
+            let dir = std::env::temp_dir().join(format!("ac-garden-export-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            let files = [PathBuf::from("atcoder.jp/abc300/abc300_a/Main.rs"), PathBuf::from("atcoder.jp/abc300/abc300_a/submission.json")];
+            for rel_path in &files {
+                fs::create_dir_all(dir.join(rel_path).parent().unwrap()).unwrap();
+                fs::write(dir.join(rel_path), format!("content of {}", rel_path.display())).unwrap();
+            }
+            let expected: Vec<(String, String)> = files.iter()
+                .map(|rel_path| (rel_path.to_string_lossy().to_string(), format!("content of {}", rel_path.display())))
+                .collect();
+
+            let read_tar = |reader: &mut dyn Read| -> Vec<(String, String)> {
+                let mut archive = tar::Archive::new(reader);
+                archive.entries().unwrap()
+                    .map(|entry| {
+                        let mut entry = entry.unwrap();
+                        let name = entry.path().unwrap().to_string_lossy().to_string();
+                        let mut content = String::new();
+                        entry.read_to_string(&mut content).unwrap();
+                        (name, content)
+                    })
+                    .collect()
+            };
+
+            let output = dir.join("export.tar");
+            write_export(&dir, &files, "tar", &output).unwrap();
+            assert_eq!(read_tar(&mut fs::File::open(&output).unwrap()), expected);
+
+            let output = dir.join("export.tar.gz");
+            write_export(&dir, &files, "tar.gz", &output).unwrap();
+            assert_eq!(read_tar(&mut flate2::read::GzDecoder::new(fs::File::open(&output).unwrap())), expected);
+
+            let output = dir.join("export.zip");
+            write_export(&dir, &files, "zip", &output).unwrap();
+            let mut zip = zip::ZipArchive::new(fs::File::open(&output).unwrap()).unwrap();
+            let entries: Vec<(String, String)> = (0..zip.len())
+                .map(|i| {
+                    let mut entry = zip.by_index(i).unwrap();
+                    let mut content = String::new();
+                    entry.read_to_string(&mut content).unwrap();
+                    (entry.name().to_string(), content)
+                })
+                .collect();
+            assert_eq!(entries, expected);
+
+            assert!(write_export(&dir, &files, "rar", &dir.join("export.rar")).is_err());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
+
+/// リクエストの間隔の制御と送信
+///
+/// atcoder.jpとAtCoder Problemsへのリクエストは、[`RateLimiter`](rate_limit::RateLimiter)を共有して[`send_get`](rate_limit::send_get)で送る
+pub mod rate_limit {
+    use crate::config::HttpConfig;
+    use crate::{Error, Result};
+    use reqwest::{Client, RequestBuilder, Response, StatusCode};
+    use std::fs::{File, OpenOptions};
+    use std::io::Write;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+
+    /// リクエストの最小間隔の既定値
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(1500);
+    /// 設定できる最小間隔の下限。AtCoderに負荷をかけないよう、設定でこれより短くしても使わない
+    pub const MIN_INTERVAL: Duration = Duration::from_millis(1000);
+    const _: () = assert!(DEFAULT_INTERVAL.as_millis() >= MIN_INTERVAL.as_millis());
+    /// 続けて送れるリクエスト数の上限
+    pub const MAX_BURST: u32 = 3;
+    /// ジッターの既定値
+    pub const DEFAULT_JITTER: Duration = Duration::from_millis(250);
+
+    /// トークンバケット方式のレートリミッター
+    ///
+    /// 複製しても同じバケットを共有するので、複数のクライアントで使い回せる
+    #[derive(Debug, Clone)]
+    pub struct RateLimiter {
+        interval: Duration,
+        burst: u32,
+        jitter: Duration,
+        /// 次のトークンが溜まる理論上の時刻 (GCRA)
+        next: Arc<Mutex<Option<Instant>>>,
+    }
+
+    impl Default for RateLimiter {
+        fn default() -> Self {
+            RateLimiter::new(DEFAULT_INTERVAL, 1, DEFAULT_JITTER)
+        }
+    }
+
+    impl RateLimiter {
+        /// 間隔は`MIN_INTERVAL`以上、バーストは1〜`MAX_BURST`に丸める
+        pub fn new(interval: Duration, burst: u32, jitter: Duration) -> Self {
+            RateLimiter {
+                interval: interval.max(MIN_INTERVAL),
+                burst: burst.clamp(1, MAX_BURST),
+                jitter,
+                next: Arc::new(Mutex::new(None)),
+            }
+        }
+
+        pub fn from_config(http: &HttpConfig) -> Self {
+            if let Some(interval) = http.request_interval_ms.filter(|&ms| u128::from(ms) < MIN_INTERVAL.as_millis()) {
+                crate::warning::warn(&crate::tr!(
+                    "request_interval_ms {} is below the minimum, using {} ms",
+                    interval,
+                    MIN_INTERVAL.as_millis()
+                ));
+            }
+            RateLimiter::new(
+                http.request_interval_ms.map(Duration::from_millis).unwrap_or(DEFAULT_INTERVAL),
+                http.burst.unwrap_or(1),
+                http.jitter_ms.map(Duration::from_millis).unwrap_or(DEFAULT_JITTER),
+            )
+        }
+
+        pub fn interval(&self) -> Duration {
+            self.interval
+        }
+
+        /// 少なくとも`duration`の間、このリミッターを使うすべてのリクエストを止める
+        pub fn pause(&self, duration: Duration) {
+            let mut next = self.next.lock().unwrap();
+            let resume = Instant::now() + duration + self.interval * (self.burst - 1);
+            *next = Some(next.map_or(resume, |tat| tat.max(resume)));
+        }
+
+        /// リクエストを送ってよくなるまで待つ
+        pub async fn acquire(&self) {
+            let wait = self.reserve(Instant::now());
+            if !wait.is_zero() {
+                tokio::time::sleep(wait + self.random_jitter()).await;
+            }
+        }
+
+        /// `now`にリクエストを1件予約し、送ってよくなるまでの時間を返す (ジッターは含まない)
+        fn reserve(&self, now: Instant) -> Duration {
+            let mut next = self.next.lock().unwrap();
+            let tat = next.map_or(now, |tat| tat.max(now));
+            // バーストの分だけ前倒しで送れる
+            let allowed_at = tat.checked_sub(self.interval * (self.burst - 1)).unwrap_or(now);
+            *next = Some(tat + self.interval);
+            allowed_at.saturating_duration_since(now)
+        }
+
+        fn random_jitter(&self) -> Duration {
+            let max = self.jitter.as_millis() as u64;
+            if max == 0 {
+                return Duration::ZERO;
+            }
+            Duration::from_millis(fastrand::u64(0..=max))
+        }
+    }
+
+    /// 監査ログのファイル。`open_audit_log`で開くまでは記録しない
+    static AUDIT_LOG: OnceLock<Mutex<File>> = OnceLock::new();
+
+    /// 送ったリクエストを`path`に追記するようにする。すでに開いていれば何もしない
+    pub fn open_audit_log(path: &Path) -> Result<()> {
+        if AUDIT_LOG.get().is_some() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| Error::storage(parent, e))?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| Error::storage(path, e))?;
+        let _ = AUDIT_LOG.set(Mutex::new(file));
+        Ok(())
+    }
+
+    /// オフラインモード。オンならリクエストを送らずにエラーにする
+    static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+    /// オフラインモードを切り替える。キャッシュや索引だけで済む処理はそのまま動く
+    pub fn set_offline(offline: bool) {
+        OFFLINE.store(offline, Ordering::Relaxed);
+    }
+
+    pub fn is_offline() -> bool {
+        OFFLINE.load(Ordering::Relaxed)
+    }
+
+    /// リクエストを送り、監査ログを開いていれば1行記録する
+    ///
+    /// 行は`<送信時刻 (RFC 3339)> <メソッド> <URL> <ステータスまたはerror> <かかった時間>ms`
+    pub async fn send(request: RequestBuilder) -> Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        if is_offline() {
+            return Err(Error::Offline { url: request.url().to_string() });
+        }
+        let Some(log) = AUDIT_LOG.get() else {
+            return Ok(client.execute(request).await?);
+        };
+
+        let (method, url) = (request.method().clone(), request.url().clone());
+        let sent_at = chrono::Local::now();
+        let started = Instant::now();
+        let result = client.execute(request).await;
+        let status = match &result {
+            Ok(response) => response.status().as_u16().to_string(),
+            Err(_) => "error".to_string(),
+        };
+        let line = format!(
+            "{} {} {} {} {}ms\n",
+            sent_at.to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+            method,
+            url,
+            status,
+            started.elapsed().as_millis()
+        );
+        // 記録に失敗してもリクエストは止めない
+        if let Ok(mut file) = log.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+        Ok(result?)
+    }
+
+    /// 429でRetry-Afterがないときに待つ時間
+    const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+    /// Retry-Afterが長すぎても待つのはここまで
+    const MAX_RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+    /// 429のあとに再試行する回数
+    const RATE_LIMIT_RETRIES: usize = 3;
+
+    /// Retry-Afterの値 (秒数またはHTTP日付) を待つ時間にする
+    fn parse_retry_after(value: &str) -> Option<Duration> {
+        if let Ok(secs) = value.trim().parse::<u64>() {
+            return Some(Duration::from_secs(secs));
+        }
+        let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+        let secs = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+        Some(Duration::from_secs(secs as u64))
+    }
+
+    /// リクエストの間隔を守ってGETする
+    ///
+    /// 429が返ってきたら、Retry-Afterの間 (なければ既定の時間) 同じリミッターを使うリクエストをすべて止めてから再試行する
+    pub async fn send_get(client: &Client, url: &str, rate_limiter: &RateLimiter) -> Result<Response> {
+        for _ in 0..=RATE_LIMIT_RETRIES {
+            rate_limiter.acquire().await;
+            let response = send(client.get(url)).await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+            let wait = response.headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after)
+                .unwrap_or(DEFAULT_RETRY_AFTER)
+                .min(MAX_RETRY_AFTER);
+            let host = response.url().host_str().unwrap_or_default().to_string();
+            crate::warning::warn(&crate::tr!("Rate limited by {} (429), pausing all requests for {} seconds", host, wait.as_secs()));
+            rate_limiter.pause(wait);
+        }
+        Err(Error::RateLimited { url: url.to_string() })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        #[test]
+        fn spaces_requests_by_the_interval_after_the_burst() {
+            let limiter = RateLimiter::new(Duration::from_millis(100), 1, Duration::ZERO);
+            let interval = limiter.interval();
+            assert_eq!(interval, MIN_INTERVAL);
+            let now = Instant::now();
+            assert_eq!(limiter.reserve(now), Duration::ZERO);
+            assert_eq!(limiter.reserve(now), interval);
+            assert_eq!(limiter.reserve(now), interval * 2);
+            assert_eq!(limiter.reserve(now + interval * 3), Duration::ZERO);
+
+            let limiter = RateLimiter::new(interval, MAX_BURST, Duration::ZERO);
+            for _ in 0..MAX_BURST {
+                assert_eq!(limiter.reserve(now), Duration::ZERO);
+            }
+            assert_eq!(limiter.reserve(now), interval);
+        }
+
+        #[test]
+        fn pause_holds_every_clone_of_the_limiter() {
+            let limiter = RateLimiter::new(MIN_INTERVAL, 1, Duration::ZERO);
+            let shared = limiter.clone();
+            limiter.pause(Duration::from_secs(30));
+            assert!(shared.reserve(Instant::now()) > Duration::from_secs(29));
+            assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+            assert_eq!(parse_retry_after("Thu, 01 Jan 1970 00:00:00 GMT"), Some(Duration::ZERO));
+        }
+
+        #[tokio::test]
+        async fn retries_after_the_retry_after_of_a_429() {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            let server = tokio::spawn(async move {
+                let mut received = Vec::new();
+                for response in ["429 Too Many Requests\r\nRetry-After: 2", "200 OK"] {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let mut buffer = [0; 4096];
+                    let _ = stream.read(&mut buffer).await.unwrap();
+                    received.push(Instant::now());
+                    let reply = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", response);
+                    stream.write_all(reply.as_bytes()).await.unwrap();
+                }
+                received
+            });
+
+            let client = Client::builder().no_proxy().build().unwrap();
+            let limiter = RateLimiter::new(MIN_INTERVAL, 1, Duration::ZERO);
+            let response = send_get(&client, &url, &limiter).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let received = server.await.unwrap();
+            assert!(received[1] - received[0] >= Duration::from_secs(2));
+        }
+    }
+}
+
+/// 提出の取得とアーカイブ
+///
+/// CLIの`archive`と同じ処理を[`ArchiveClient`]として提供する
+///
+/// ```no_run
+/// # async fn run() -> ac_garden::Result<()> {
+/// use ac_garden::archive::{ArchiveClient, Policy, Progress};
+///
+/// let mut client = ArchiveClient::builder()
+///     .user("tourist")
+///     .repository("/path/to/archive")
+///     .policy(Policy::Latest)
+///     .on_progress(|progress| {
+///         if let Progress::Archived { path, .. } = progress {
+///             println!("archived {}", path.display());
+///         }
+///     })
+///     .build()?;
+/// let summary = client.archive_all().await?;
+/// println!("{} of {} archived", summary.archived, summary.pending);
+/// # Ok(())
+/// # }
+/// ```
+pub mod archive {
+    pub use crate::config::Policy;
+
+    use crate::chart::{line_chart_svg, Series};
+    use crate::config::{
+        apply_repo_config, get_config_dir, Config, ContestCategory, EditorialMode, FileNaming, GitBackend, HttpConfig, IgnoreList, LanguageVersion, LineEnding,
+        MetadataFormat, ProblemDirNaming, Service, BARE_WORK_DIR,
+    };
+    use crate::contest::{contest_titles, fetch_contests, fetch_problem_models, Fetched};
+    use crate::credentials::{passphrase_from_env, resolve_secret};
+    use crate::editorial::{editorial_to_markdown, find_official_editorial, EDITORIAL_MD, EDITORIAL_PDF};
+    use crate::languages::canonical_language;
+    use crate::rate_limit::{open_audit_log, send_get, RateLimiter};
+    use crate::store::{
+        archive_file, archived_contest_ids, write_note, write_source, read_metadata, read_notes, scan_archived_submissions, LANGUAGE_TXT, METADATA_FILES, NOTES_REF,
+        SUBMISSION_JSON,
+    };
+    use crate::submission::{archive_key, submission_url, task_url, Submission, TestCaseResult};
+    use crate::utils::{
+        ace_mode_to_extension, decode_utf8_lossy, guess_extension_from_code, is_bare_repository, is_dir_exist, is_file_exist,
+        language_to_file_name, language_version, long_path,
+        normalize_line_endings, prepend_header, sanitize_path_component, strip_bom, title_to_file_stem, utc_offset_minutes,
+    };
+    use crate::error::BoxError;
+    use crate::{Error, Result, APP_NAME, ATCODER_BASE_URL};
+    use git2::{Index, IndexEntry, IndexTime, Oid, Repository, Signature};
+    use reqwest::{Client, StatusCode};
+    use scraper::{Html, Selector};
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::time::Duration;
+
+    const ATCODER_API_SUBMISSION_URL: &str = "https://kenkoooo.com/atcoder/atcoder-api/v3/user/submissions";
+    /// AtCoder Problemsの提出APIが1回に返す最大の件数
+    const ATCODER_API_PAGE_SIZE: usize = 500;
+    /// 提出APIで、アーカイブ済みの最新の提出よりさかのぼって取得する秒数。
+    /// AtCoder Problemsが提出を遅れて取り込んでも落とさないようにする
+    const API_FETCH_MARGIN: i64 = 24 * 60 * 60;
+    /// AtCoder Problemsの問題モデル (難易度など)
+    pub const PROBLEM_MODELS_URL: &str = "https://kenkoooo.com/atcoder/resources/problem-models.json";
+    /// 既定のコミットメッセージのテンプレート
+    pub const DEFAULT_COMMIT_TEMPLATE: &str = "[AC] {contest_title} {problem_id}";
+    /// AHCモードのコミットメッセージの既定のテンプレート
+    pub const DEFAULT_AHC_COMMIT_TEMPLATE: &str = "[AHC] {contest_title} {problem_id} score {point}";
+    /// ACでない提出 (`archive --id`で指定したWAなど) のコミットメッセージのテンプレート
+    const NON_AC_COMMIT_TEMPLATE: &str = "[{result}] {contest_title} {problem_id}";
+    /// AtCoderの難易度の色。最後は難易度のない問題
+    pub const DIFFICULTY_COLORS: [&str; 9] = ["gray", "brown", "green", "cyan", "blue", "yellow", "orange", "red", "unknown"];
+    /// DIFFICULTY_COLORSと同じ順の絵文字
+    const DIFFICULTY_EMOJIS: [&str; 9] = ["⚪", "🟤", "🟢", "🩵", "🔵", "🟡", "🟠", "🔴", "❔"];
+    /// プロフィールのスナップショットを保存するファイル (サービスのディレクトリに置く)
+    pub const PROFILE_JSON: &str = "profile.json";
+    /// AHCモードでスコアの推移を記録するファイル (コンテストのディレクトリに置く)
+    pub const SCORES_JSON: &str = "scores.json";
+    /// AHCモードでスコアの推移を描いたグラフ
+    pub const SCORES_SVG: &str = "scores.svg";
+
+    /// アーカイブの進み具合
+    #[derive(Debug, Clone)]
+    pub enum Progress {
+        /// アーカイブする提出が決まった。取得したAC提出の数と、そのうちアーカイブ済みの数も添える
+        Started { fetched: usize, already_archived: usize, total: usize },
+        /// 提出をアーカイブした
+        Archived { submission: Submission, path: PathBuf },
+        /// 提出ページからコードを取り出せなかった
+        Skipped { submission: Submission, reason: String },
+        /// 取得やコミットに失敗した。残りの提出はそのまま続ける
+        Failed { submission: Submission, error: String },
+        /// コンテストの最終順位を保存した
+        ContestResult { contest_id: String, rank: i64 },
+        /// 前回の実行で書き込み中に止まった提出を、コミットまで済ませたか書き込む前に戻した
+        Recovered(Recovery),
+        /// プロフィールが前回から変わったのでprofile.jsonを更新した
+        Profile { rating: Option<i64> },
+        /// メンテナンスやレート制限で途中で中断した
+        Interrupted { archived: usize, total: usize },
+        /// 処理は続けたが知らせておくこと (取得の失敗で代わりのものを使った、フックが失敗したなど)。訳したメッセージ
+        Warning { message: String },
+    }
+
+    /// アーカイブした提出
+    #[derive(Debug, Clone)]
+    pub struct Archived {
+        pub submission: Submission,
+        /// 書き込んだソースファイル
+        pub path: PathBuf,
+        /// 言語が拡張子の対応表になく、拡張子を推測したか
+        pub unknown_language: bool,
+    }
+
+    /// `archive_all`の結果
+    #[derive(Debug, Clone, Default)]
+    pub struct ArchiveSummary {
+        /// 取得したAC提出の数
+        pub fetched: usize,
+        /// 取得したAC提出のうち、アーカイブ済みだった数
+        pub already_archived: usize,
+        /// アーカイブ対象だった提出の数
+        pub pending: usize,
+        /// 上限を超えたので次の実行に回した提出の数
+        pub deferred: usize,
+        /// アーカイブした提出の数
+        pub archived: usize,
+        /// 失敗した提出とエラー
+        pub failed: Vec<(Submission, String)>,
+        /// 対応表にない言語 → (件数, 保存した拡張子)
+        pub unknown_languages: BTreeMap<String, (usize, String)>,
+    }
+
+    type ProgressCallback = Box<dyn FnMut(&Progress) + Send>;
+
+    /// 進捗を知らせる。コールバックがなければ、警告だけは[`crate::warning`]のハンドラに渡す
+    fn report(callback: &mut Option<ProgressCallback>, progress: Progress) {
+        match (callback, progress) {
+            (Some(callback), progress) => callback(&progress),
+            (None, Progress::Warning { message }) => crate::warning::warn(&message),
+            (None, _) => {}
+        }
+    }
+    type SelectCallback = Box<dyn FnMut(Vec<Submission>) -> std::result::Result<Vec<Submission>, BoxError> + Send>;
+
+    /// [`ArchiveClient`]のビルダー
+    #[derive(Default)]
+    pub struct ArchiveClientBuilder {
+        config: Option<Config>,
+        user: Option<String>,
+        email: Option<String>,
+        repository: Option<PathBuf>,
+        policy: Option<Policy>,
+        contests: Option<Vec<ContestCategory>>,
+        submission_ids: Option<HashSet<i64>>,
+        force: bool,
+        progress: Option<ProgressCallback>,
+        select: Option<SelectCallback>,
+        limit: Option<usize>,
+        full_history: bool,
+        rate_limiter: Option<RateLimiter>,
+    }
+
+    impl ArchiveClientBuilder {
+        /// 設定ファイルの内容をまとめて使う。個別の設定はこれより優先される
+        pub fn config(mut self, config: Config) -> Self {
+            self.config = Some(config);
+            self
+        }
+
+        /// AtCoderのユーザーID
+        pub fn user(mut self, user: impl Into<String>) -> Self {
+            self.user = Some(user.into());
+            self
+        }
+
+        /// コミットに使うメールアドレス
+        pub fn email(mut self, email: impl Into<String>) -> Self {
+            self.email = Some(email.into());
+            self
+        }
+
+        /// アーカイブ先のリポジトリ
+        pub fn repository(mut self, path: impl Into<PathBuf>) -> Self {
+            self.repository = Some(path.into());
+            self
+        }
+
+        /// 設定やリポジトリの.ac-garden.tomlより優先される
+        pub fn policy(mut self, policy: Policy) -> Self {
+            self.policy = Some(policy);
+            self
+        }
+
+        /// アーカイブするコンテストの種類。設定より優先される
+        pub fn contests(mut self, contests: Vec<ContestCategory>) -> Self {
+            self.contests = Some(contests);
+            self
+        }
+
+        /// この提出IDのものだけをアーカイブする
+        pub fn submission_ids(mut self, ids: impl IntoIterator<Item = i64>) -> Self {
+            self.submission_ids = Some(ids.into_iter().collect());
+            self
+        }
+
+        /// コミットしていない変更があるファイルも上書きする
+        pub fn force(mut self, force: bool) -> Self {
+            self.force = force;
+            self
+        }
+
+        /// 進み具合を受け取るコールバック
+        pub fn on_progress(mut self, callback: impl FnMut(&Progress) + Send + 'static) -> Self {
+            self.progress = Some(Box::new(callback));
+            self
+        }
+
+        /// `archive_all`でアーカイブする提出を、まだアーカイブしていない提出の中から選ぶコールバック
+        ///
+        /// 返した提出だけをアーカイブする。選ばなかった提出は、同じ問題のより新しい提出をアーカイブしなければ次の実行でまた候補になる。
+        /// 問題ごとにアーカイブ済みの最新の提出より古いものは候補にしないので、`Policy::All`で新しい方だけを選ぶと古い方は候補から外れる。
+        /// エラーを返すと`archive_all`は何もアーカイブせずに[`Error::Select`]で失敗する
+        pub fn select(
+            mut self,
+            callback: impl FnMut(Vec<Submission>) -> std::result::Result<Vec<Submission>, BoxError> + Send + 'static,
+        ) -> Self {
+            self.select = Some(Box::new(callback));
+            self
+        }
+
+        /// `archive_all`で1回にアーカイブする提出の上限。古い提出から順にアーカイブし、残りは次の実行に回す
+        pub fn limit(mut self, limit: usize) -> Self {
+            self.limit = Some(limit);
+            self
+        }
+
+        /// アーカイブ済みの最新の提出以降だけでなく、提出の履歴をすべて取得して調べる
+        ///
+        /// 失敗やスキップのまま残った古い提出を拾い直すのに使う。提出IDを指定したときと`select`では常にすべて取得する
+        pub fn full_history(mut self, full_history: bool) -> Self {
+            self.full_history = full_history;
+            self
+        }
+
+        /// ほかのクライアントとリクエストの間隔を共有する。指定しなければ`http`の設定から作る
+        pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+            self.rate_limiter = Some(rate_limiter);
+            self
+        }
+
+        pub fn build(self) -> Result<ArchiveClient> {
+            let mut config = self.config.unwrap_or_default();
+            if let Some(user) = self.user {
+                config.atcoder.user_id = user;
+            }
+            if let Some(email) = self.email {
+                config.atcoder.user_email = email;
+            }
+            if let Some(repository) = self.repository {
+                config.atcoder.repository_path = repository.to_string_lossy().into_owned();
+            }
+
+            if config.atcoder.user_id.trim().is_empty() {
+                return Err(Error::config("user id is not set"));
+            }
+            if config.atcoder.repository_path.trim().is_empty() {
+                return Err(Error::config("repository path is not set"));
+            }
+
+            prepare_work_dir(&config.atcoder)?;
+            apply_repo_config(&mut config)?;
+            if let Some(policy) = self.policy {
+                config.atcoder.policy = policy;
+            }
+            if let Some(contests) = self.contests {
+                config.atcoder.contests = contests;
+            }
+
+            let http = build_client(&config)?;
+            let rate_limiter = self.rate_limiter.unwrap_or_else(|| RateLimiter::from_config(&config.http));
+            Ok(ArchiveClient {
+                config,
+                http,
+                rate_limiter,
+                submission_ids: self.submission_ids,
+                force: self.force,
+                progress: self.progress,
+                select: self.select,
+                limit: self.limit,
+                full_history: self.full_history,
+                notes: None,
+                contest_titles: None,
+                contests_refreshed: false,
+                task_indexes: HashMap::new(),
+                difficulties: None,
+            })
+        }
+    }
+
+    /// AtCoderから提出を取得してリポジトリにアーカイブするクライアント
+    pub struct ArchiveClient {
+        config: Config,
+        http: Client,
+        rate_limiter: RateLimiter,
+        submission_ids: Option<HashSet<i64>>,
+        force: bool,
+        progress: Option<ProgressCallback>,
+        select: Option<SelectCallback>,
+        limit: Option<usize>,
+        full_history: bool,
+        /// git notesにある問題ごとの最新の提出。タグの引き継ぎに使い、必要になったときに一度だけ読み込む
+        notes: Option<HashMap<String, Submission>>,
+        /// コンテストID → コンテスト名。必要になるまで読み込まない
+        contest_titles: Option<HashMap<String, String>>,
+        contests_refreshed: bool,
+        /// コンテストID → (問題ID → 問題の記号)
+        task_indexes: HashMap<String, HashMap<String, String>>,
+        /// 問題ID → 難易度。コミットメッセージに使うときだけ読み込む
+        difficulties: Option<HashMap<String, f64>>,
+    }
+
+    impl ArchiveClient {
+        pub fn builder() -> ArchiveClientBuilder {
+            ArchiveClientBuilder::default()
+        }
+
+        pub fn config(&self) -> &Config {
+            &self.config
+        }
+
+        /// 設定を反映したHTTPクライアント
+        pub fn http_client(&self) -> &Client {
+            &self.http
+        }
+
+        fn report(&mut self, progress: Progress) {
+            report(&mut self.progress, progress);
+        }
+
+        fn warn(&mut self, message: String) {
+            report(&mut self.progress, Progress::Warning { message });
+        }
+
+        /// atcoder.jpのページを取得する。メンテナンス中であればError::Maintenanceを返す
+        pub async fn fetch_page(&mut self, url: &str) -> Result<(StatusCode, String)> {
+            fetch_atcoder(&self.http, url, &self.rate_limiter).await
+        }
+
+        /// 提出一覧を取得する。APIが使えない場合はatcoder.jpから直接取得する
+        pub async fn fetch_submissions(&mut self) -> Result<Vec<Submission>> {
+            self.fetch_submissions_since(0).await
+        }
+
+        /// `from_second`以降の提出一覧を取得する。APIが使えなければatcoder.jpからすべて取得する
+        async fn fetch_submissions_since(&mut self, from_second: i64) -> Result<Vec<Submission>> {
+            match fetch_submissions_from_api(&self.http, &self.config.atcoder.user_id, from_second, &self.rate_limiter).await {
+                Ok(submissions) => Ok(submissions),
+                Err(e @ Error::Offline { .. }) => Err(e),
+                Err(e) => {
+                    self.warn(crate::tr!("Failed to fetch submissions from the API: {:#}", anyhow::Error::from(e)));
+                    self.warn(crate::tr!("Falling back to scraping atcoder.jp..."));
+                    let (submissions, warnings) = scrape_submissions(&self.http, &self.config, &self.rate_limiter).await?;
+                    for warning in warnings {
+                        self.warn(warning);
+                    }
+                    Ok(submissions)
+                }
+            }
+        }
+
+        /// コンテストID → コンテスト名を読み込む。取得できなければ空 (IDだけで扱う)
+        ///
+        /// 一覧にないコンテストがあれば、始まったばかりかもしれないのでキャッシュを一度だけ取り直す
+        async fn load_contest_titles<'a>(&mut self, contest_ids: impl IntoIterator<Item = &'a str>) {
+            if self.contest_titles.is_none() {
+                self.contest_titles = Some(self.fetch_contest_titles(false).await);
+            }
+            let missing = {
+                let titles = self.contest_titles.as_ref().unwrap();
+                contest_ids.into_iter().any(|id| !titles.contains_key(id))
+            };
+            if missing && !self.contests_refreshed {
+                self.contests_refreshed = true;
+                let titles = self.fetch_contest_titles(true).await;
+                if !titles.is_empty() {
+                    self.contest_titles = Some(titles);
+                }
+            }
+        }
+
+        async fn fetch_contest_titles(&mut self, refresh: bool) -> HashMap<String, String> {
+            match fetch_contests(&self.http, refresh, &self.rate_limiter).await {
+                Ok(Fetched::Fresh(contests)) => contest_titles(&contests),
+                Ok(Fetched::Stale { value, error }) => {
+                    self.warn(crate::tr!("Failed to refresh the contest list, using the cached one: {:#}", anyhow::Error::from(error)));
+                    contest_titles(&value)
+                }
+                Err(e) => {
+                    self.warn(crate::tr!("Failed to fetch contest titles, using contest ids instead: {:#}", anyhow::Error::from(e)));
+                    HashMap::new()
+                }
+            }
+        }
+
+        /// コンテスト名。分からなければNone
+        pub async fn contest_title(&mut self, contest_id: &str) -> Option<String> {
+            self.load_contest_titles([contest_id]).await;
+            self.contest_titles.as_ref()?.get(contest_id).cloned()
+        }
+
+        /// 問題の記号 (例: "A")。コンテストの問題一覧ページから取得し、コンテストごとに覚えておく
+        ///
+        /// 取得できなければNone (問題IDのディレクトリを使う)
+        pub async fn problem_index(&mut self, contest_id: &str, problem_id: &str) -> Result<Option<String>> {
+            if !self.task_indexes.contains_key(contest_id) {
+                let url = format!("{}/contests/{}/tasks?lang=en", ATCODER_BASE_URL, contest_id);
+                let indexes = match fetch_atcoder(&self.http, &url, &self.rate_limiter).await {
+                    Ok((status, html)) if status.is_success() => parse_task_indexes(&Html::parse_document(&html)),
+                    Ok((status, _)) => {
+                        self.warn(crate::tr!("Failed to fetch the task list of {}: HTTP {}", contest_id, status));
+                        HashMap::new()
+                    }
+                    Err(e) if e.is_interruption() => return Err(e),
+                    Err(e) => {
+                        self.warn(crate::tr!("Failed to fetch the task list of {}: {:#}", contest_id, anyhow::Error::from(e)));
+                        HashMap::new()
+                    }
+                };
+                self.task_indexes.insert(contest_id.to_string(), indexes);
+            }
+            Ok(self.task_indexes.get(contest_id).and_then(|indexes| indexes.get(problem_id)).cloned())
+        }
+
+        /// 問題の難易度。取得に失敗したら以後は問い合わせない
+        async fn difficulty(&mut self, problem_id: &str) -> Option<f64> {
+            if self.difficulties.is_none() {
+                let difficulties = match fetch_difficulties(&self.http, &self.rate_limiter).await {
+                    Ok(Fetched::Fresh(difficulties)) => difficulties,
+                    Ok(Fetched::Stale { value, error }) => {
+                        self.warn(crate::tr!("Failed to refresh the problem models, using the cached ones: {:#}", anyhow::Error::from(error)));
+                        value
+                    }
+                    Err(e) => {
+                        self.warn(crate::tr!("Failed to fetch difficulties: {:#}", anyhow::Error::from(e)));
+                        HashMap::new()
+                    }
+                };
+                self.difficulties = Some(difficulties);
+            }
+            self.difficulties.as_ref()?.get(problem_id).copied()
+        }
+
+        /// 提出ページから、アーカイブ時と同じ加工をしたコードを取得する
+        pub async fn fetch_code(&mut self, submission: &Submission) -> Result<String> {
+            let url = submission_url(submission);
+            let (_, html) = self.fetch_page(&url).await?;
+            let document = Html::parse_document(&html);
+            let code = extract_code(&document)
+                .ok_or_else(|| missing_code_error(&url, &document, &html, submission.id))?;
+            Ok(normalize_code(&code, &self.config.atcoder))
+        }
+
+        /// まだアーカイブしていないAC提出を、アーカイブする順に返す
+        pub async fn fetch_pending(&mut self) -> Result<Vec<Submission>> {
+            Ok(self.find_pending().await?.0)
+        }
+
+        /// まだアーカイブしていない提出と、取得したAC提出の数、そのうちアーカイブ済みの数
+        async fn find_pending(&mut self) -> Result<(Vec<Submission>, usize, usize)> {
+            // 途中で止まった書き込みを片付けてから、アーカイブ済みの提出を調べる
+            self.recover_journal()?;
+            // 選んだ提出だけを扱うときは、アーカイブ済みより古い提出も候補になるのですべて取得する
+            let incremental = !self.full_history && self.submission_ids.is_none() && self.select.is_none();
+            let from_second = match incremental {
+                true => newest_archived_epoch(&self.config.atcoder).map_or(0, |epoch| (epoch - API_FETCH_MARGIN).max(0)),
+                false => 0,
+            };
+            let mut submissions = self.fetch_submissions_since(from_second).await?;
+
+            // .ac-garden-ignoreに書いた問題は取得もしない
+            let ignore = IgnoreList::load(&self.config.atcoder.work_dir())?;
+            submissions.retain(|s| !ignore.is_ignored(&s.contest_id, &s.problem_id));
+
+            // skip_languagesの言語の提出も取得しない
+            submissions.retain(|s| !self.config.atcoder.skips_language(&s.language));
+
+            // 設定した種類のコンテストだけを残す
+            let categories = self.config.atcoder.contests.clone();
+            if !categories.is_empty() {
+                self.load_contest_titles(submissions.iter().map(|s| s.contest_id.as_str())).await;
+                let titles = self.contest_titles.clone().unwrap_or_default();
+                submissions.retain(|s| {
+                    let title = titles.get(&s.contest_id).map(String::as_str);
+                    categories.contains(&contest_category(&s.contest_id, title))
+                });
+            }
+
+            let (mut pending, fetched, already_archived) = select_pending(&self.config.atcoder, submissions);
+            if let Some(ids) = &self.submission_ids {
+                pending.retain(|s| ids.contains(&s.id));
+            }
+            Ok((pending, fetched, already_archived))
+        }
+
+        /// 提出を1件アーカイブしてコミットする
+        ///
+        /// 提出ページにコードがなければNone
+        pub async fn archive_one(&mut self, mut submission: Submission) -> Result<Option<Archived>> {
+            self.recover_journal()?;
+            submission.contest_title = self.contest_title(&submission.contest_id).await;
+            submission.submission_url = Some(submission_url(&submission));
+            // ACでない提出は結果を付けた別のファイルに置き、問題のメタデータはACの提出のまま残す
+            let accepted = submission.result == "AC";
+            let heuristic = accepted && self.config.atcoder.ahc_scores && is_heuristic_contest(&submission.contest_id);
+            let difficulty = match commit_template(&self.config.atcoder, heuristic).contains("{difficulty") {
+                true => self.difficulty(&submission.problem_id).await,
+                false => None,
+            };
+            if self.config.atcoder.problem_dir_naming == ProblemDirNaming::Index {
+                submission.problem_index = self.problem_index(&submission.contest_id, &submission.problem_id).await?;
+            }
+
+            let service = &self.config.atcoder;
+            let mut url = submission_url(&submission);
+            if service.file_naming == FileNaming::Title {
+                // 英語の問題名があればそちらを使う
+                url.push_str("?lang=en");
+            }
+
+            let (_, html) = fetch_atcoder(&self.http, &url, &self.rate_limiter).await?;
+            let document = Html::parse_document(&html);
+
+            let service = &self.config.atcoder;
+            let code = match extract_code(&document) {
+                Some(code) => normalize_code(&code, service),
+                None => return Err(missing_code_error(&url, &document, &html, submission.id)),
+            };
+            if code.is_empty() {
+                self.report(Progress::Skipped { submission, reason: "empty code".to_string() });
+                return Ok(None);
+            }
+
+            let details = parse_judge_details(&document);
+            if let Some(code_size) = details.code_size {
+                submission.length = code_size;
+            }
+            submission.memory = details.memory.or(submission.memory);
+            submission.test_cases = details.test_cases;
+            // APIの言語にバージョンがなければ、提出ページの表記を使う
+            if let Some(language) = details.language {
+                if language_version(&submission.language).is_none() && language_version(&language).is_some() {
+                    submission.language = language;
+                }
+            }
+
+            let mut unknown_language = false;
+            let mut file_name = match language_to_file_name(&submission.language) {
+                Some(file_name) => file_name,
+                None => {
+                    // 提出ページのエディタのモード、なければコードの内容から拡張子を推測する
+                    let ext = find_ace_mode(&document)
+                        .and_then(|mode| ace_mode_to_extension(&mode))
+                        .or_else(|| guess_extension_from_code(&code));
+                    unknown_language = true;
+                    format!("Main.{}", ext.unwrap_or("txt"))
+                }
+            };
+            if service.file_naming == FileNaming::Title {
+                if let Some(title) = find_task_title(&document, &submission.problem_id) {
+                    file_name = titled_file_name(&title, &file_name);
+                }
+            }
+            if service.language_version == LanguageVersion::FileName {
+                if let Some(version) = language_version(&submission.language) {
+                    file_name = versioned_file_name(&file_name, version);
+                }
+            }
+            // AHCモードではスコアごとに別のファイルにする
+            if heuristic {
+                file_name = scored_file_name(&file_name, submission.point);
+            }
+            if !accepted {
+                file_name = verdict_file_name(&file_name, &submission.result);
+            }
+            let file_name = sanitize_path_component(&file_name);
+            let repo_path = Path::new(&service.repository_path);
+            let problem_dir = service.submission_dir(&submission);
+            let archive_dir_path = service.work_dir().join(&problem_dir);
+
+            // git notesはGitリポジトリでなければ付けられないので、submission.jsonに書く
+            let is_git_repo = is_git_repository(repo_path);
+            let metadata_format = match service.metadata_format {
+                MetadataFormat::GitNotes if !is_git_repo => MetadataFormat::Json,
+                format => format,
+            };
+            let metadata_file = metadata_format.file_name().filter(|_| accepted).map(|file_name| problem_dir.join(file_name));
+            let metadata_path = archive_dir_path.join(metadata_format.file_name().unwrap_or(SUBMISSION_JSON));
+            let notes = metadata_format == MetadataFormat::GitNotes && accepted;
+
+            // 再アーカイブでも付けたタグは引き継ぐ
+            let existing = match metadata_format {
+                _ if !accepted => None,
+                MetadataFormat::GitNotes => self.notes
+                    .get_or_insert_with(|| {
+                        // 新しい提出が先に並ぶので、問題ごとに最初のものを残す
+                        let mut latest = HashMap::new();
+                        for note in read_notes(service) {
+                            latest.entry(service.archive_key(&note)).or_insert(note);
+                        }
+                        latest
+                    })
+                    .get(&service.archive_key(&submission))
+                    .cloned(),
+                _ => read_metadata(&metadata_path).ok().flatten(),
+            };
+            if let Some(existing) = existing {
+                submission.tags = existing.tags;
+            }
+
+            // 手で編集してまだコミットしていないファイルは上書きしない
+            if is_git_repo && !is_bare_repository(repo_path) && !self.force {
+                let mut rel_paths = vec![problem_dir.join(&file_name)];
+                rel_paths.extend(metadata_file.clone());
+                let modified = locally_modified(repo_path, &rel_paths)?;
+                if !modified.is_empty() {
+                    let paths: Vec<String> = modified.iter().map(|path| path.display().to_string()).collect();
+                    let reason = format!("uncommitted changes in {} (use --force to overwrite)", paths.join(", "));
+                    self.report(Progress::Skipped { submission, reason });
+                    return Ok(None);
+                }
+            }
+
+            // 書き込むかもしれないファイルをジャーナルに残してから書き始める
+            let mut journal = None;
+            if is_git_repo {
+                let mut paths = vec![problem_dir.join(&file_name), problem_dir.join(original_file_name(&file_name))];
+                paths.extend(metadata_file.clone());
+                paths.extend([LANGUAGE_TXT, EDITORIAL_MD, EDITORIAL_PDF].map(|name| problem_dir.join(name)));
+                if heuristic {
+                    let contest_dir = service.contest_dir(&submission.contest_id);
+                    paths.extend([SCORES_JSON, SCORES_SVG].map(|name| contest_dir.join(name)));
+                }
+                let started = Journal::start(repo_path, &service.work_dir(), &submission, paths, notes)?;
+                write_journal(repo_path, &started)?;
+                journal = Some(started);
+            }
+
+            // 公式解説のURLを記録し、ログインしていれば本文も保存する
+            let mut editorial_file = None;
+            if service.editorial != EditorialMode::Off {
+                let download = service.editorial == EditorialMode::Download && !service.session.is_empty();
+                match fetch_editorial(&self.http, &submission, &archive_dir_path, download, &self.rate_limiter).await {
+                    Ok((url, file)) => {
+                        submission.editorial_url = url;
+                        editorial_file = file;
+                    }
+                    Err(e) if e.is_interruption() => return Err(e),
+                    Err(e) => report(
+                        &mut self.progress,
+                        Progress::Warning {
+                            message: crate::tr!("Failed to fetch the editorial of {}: {:#}", submission.problem_id, anyhow::Error::from(e)),
+                        },
+                    ),
+                }
+            }
+
+            let code = if service.header {
+                with_header(&code, &file_name, &submission)
+            } else {
+                code
+            };
+
+            if accepted {
+                archive_file(&code, &file_name, &archive_dir_path, &submission, metadata_format)?;
+            } else {
+                write_source(&code, &file_name, &archive_dir_path)?;
+            }
+            let language_file = if service.language_version == LanguageVersion::File && accepted {
+                let path = archive_dir_path.join(LANGUAGE_TXT);
+                fs::write(long_path(&path), format!("{}\n", submission.language)).map_err(|e| Error::storage(&path, e))?;
+                Some(problem_dir.join(LANGUAGE_TXT))
+            } else {
+                None
+            };
+
+            let original_file_name = format_source(&archive_dir_path, &file_name, &submission.language, service, &mut self.progress)?;
+            let score_paths = if heuristic { record_score(service, &submission, &file_name)? } else { Vec::new() };
+
+            let file_path = archive_dir_path.join(&file_name);
+            let hook_env = submission_hook_env(&submission, &file_path);
+            let hook_input = serde_json::to_value(&submission).map_err(|e| Error::storage(&metadata_path, e))?;
+            let hooks = &self.config.hooks;
+            run_hook("post_file", hooks.post_file.as_deref(), repo_path, &hook_env, &hook_input, &mut self.progress)?;
+
+            // Gitリポジトリである場合、gitのaddとcommit
+            if is_git_repo {
+                // ソースファイルとメタデータをadd
+                let mut rel_paths = vec![problem_dir.join(&file_name)];
+                rel_paths.extend(metadata_file);
+                if let Some(original_file_name) = &original_file_name {
+                    rel_paths.push(problem_dir.join(original_file_name));
+                }
+                rel_paths.extend(score_paths);
+                rel_paths.extend(editorial_file.map(|file_name| problem_dir.join(file_name)));
+                rel_paths.extend(language_file);
+
+                let (author, committer) = commit_signatures(service, &submission.user_id, submission.epoch_second)?;
+
+                let template = if accepted { commit_template(service, heuristic) } else { NON_AC_COMMIT_TEMPLATE };
+                let message = with_trailers(&render_commit_message(template, &submission, difficulty), &submission);
+
+                // 書き終えたので、ここからはコミットするファイルとメッセージがあればやり直せる
+                if let Some(mut journal) = journal {
+                    journal.submission = submission.clone();
+                    journal.paths = rel_paths.clone();
+                    journal.message = Some(message.clone());
+                    write_journal(repo_path, &journal)?;
+                }
+
+                let commit_id = commit_files(service.git_backend, repo_path, &rel_paths, &author, &committer, &message)?;
+                if notes {
+                    write_note(repo_path, commit_id, &author, &committer, &submission)?;
+                    if let Some(latest) = &mut self.notes {
+                        latest.insert(service.archive_key(&submission), submission.clone());
+                    }
+                }
+                remove_journal(repo_path)?;
+
+                run_hook("post_commit", hooks.post_commit.as_deref(), repo_path, &hook_env, &hook_input, &mut self.progress)?;
+            }
+
+            let archived = Archived { submission, path: file_path, unknown_language };
+            self.report(Progress::Archived { submission: archived.submission.clone(), path: archived.path.clone() });
+            Ok(Some(archived))
+        }
+
+        /// 提出IDを指定して1件だけアーカイブする
+        ///
+        /// 結果 (WAなど) やアーカイブ済みかどうかに関わらず、提出ページから取得し直してコミットする
+        pub async fn archive_by_id(&mut self, submission_id: i64) -> Result<Option<Archived>> {
+            self.recover_journal()?;
+            let submission = self.fetch_submissions().await?
+                .into_iter()
+                .find(|s| s.id == submission_id)
+                .ok_or_else(|| Error::SubmissionNotFound { id: submission_id, user_id: self.config.atcoder.user_id.clone() })?;
+            self.report(Progress::Started { fetched: 1, already_archived: 0, total: 1 });
+            self.archive_one(submission).await
+        }
+
+        /// まだアーカイブしていない提出をすべてアーカイブする
+        ///
+        /// メンテナンスで中断しても、それまでの提出はコミット済みなので次回はその続きから再開される
+        pub async fn archive_all(&mut self) -> Result<ArchiveSummary> {
+            let (mut pending, fetched, already_archived) = self.find_pending().await?;
+            if let Some(select) = self.select.as_mut().filter(|_| !pending.is_empty()) {
+                pending = select(pending).map_err(Error::Select)?;
+            }
+            // 上限があれば古い提出から順に、残りは次の実行に回す
+            let mut deferred = 0;
+            if let Some(limit) = self.limit.filter(|&limit| pending.len() > limit) {
+                pending.sort_by_key(|s| (s.epoch_second, s.id));
+                deferred = pending.len() - limit;
+                pending.truncate(limit);
+            }
+            let total = pending.len();
+            let repo_path = PathBuf::from(&self.config.atcoder.repository_path);
+
+            let input = serde_json::json!({ "pending": total });
+            if !run_hook("pre_archive", self.config.hooks.pre_archive.as_deref(), &repo_path, &[], &input, &mut self.progress)? {
+                return Err(Error::HookFailed { event: "pre_archive".to_string() });
+            }
+
+            self.report(Progress::Started { fetched, already_archived, total });
+            let mut summary = ArchiveSummary { fetched, already_archived, pending: total, deferred, ..Default::default() };
+
+            for (index, submission) in pending.into_iter().enumerate() {
+                let archived = match self.archive_one(submission.clone()).await {
+                    Ok(Some(archived)) => archived,
+                    Ok(None) => continue,
+                    Err(e) if e.is_interruption() => {
+                        self.report(Progress::Interrupted { archived: index, total });
+                        return Err(e);
+                    }
+                    Err(e) => {
+                        // 1件の失敗で全体を止めず、書きかけのファイルを戻して記録し、次に進む
+                        let error = format!("{:#}", anyhow::Error::from(e));
+                        self.recover_journal()?;
+                        self.report(Progress::Failed { submission: submission.clone(), error: error.clone() });
+                        summary.failed.push((submission, error));
+                        continue;
+                    }
+                };
+                summary.archived += 1;
+                if archived.unknown_language {
+                    let ext = archived.path.extension().unwrap_or_default().to_string_lossy().to_string();
+                    summary.unknown_languages.entry(archived.submission.language.clone()).or_insert((0, ext)).0 += 1;
+                }
+            }
+
+            if self.config.atcoder.archive_contest_results {
+                self.archive_contest_results().await?;
+            }
+            if self.config.atcoder.archive_profile {
+                self.archive_profile().await?;
+            }
+
+            let summary_json = serde_json::json!({ "pending": total, "archived": summary.archived, "failed": summary.failed.len() });
+            run_hook("post_run", self.config.hooks.post_run.as_deref(), &repo_path, &[], &summary_json, &mut self.progress)?;
+
+            Ok(summary)
+        }
+
+        /// 参加したコンテストの最終順位を取得してresult.jsonに保存する
+        pub async fn archive_contest_results(&mut self) -> Result<()> {
+            let service = self.config.atcoder.clone();
+            let repo_path = Path::new(&service.repository_path);
+            let service_dir = service.service_dir();
+            if !is_dir_exist(&service_dir) {
+                return Ok(());
+            }
+
+            // アーカイブ済みでresult.jsonがまだないコンテスト
+            let pending_contests: Vec<String> = archived_contest_ids(&service)?
+                .into_iter()
+                .filter(|contest_id| !is_file_exist(service_dir.join(contest_id).join("result.json")))
+                .collect();
+            if pending_contests.is_empty() {
+                return Ok(());
+            }
+
+            // 参加履歴にあるコンテストだけ順位表を取得する
+            // 順位はおまけなので、取得できなくてもアーカイブの実行は失敗にしない
+            let history = match fetch_contest_history(&self.http, &service.user_id, &self.rate_limiter).await {
+                Ok(history) => history,
+                Err(e) => {
+                    self.warn(crate::tr!("Skipped saving contest results: {}", e));
+                    return Ok(());
+                }
+            };
+
+            let is_git_repo = is_git_repository(repo_path);
+
+            for contest in history {
+                let contest_id = contest.contest_id().to_string();
+                if !pending_contests.contains(&sanitize_path_component(&contest_id)) {
+                    continue;
+                }
+
+                let url = format!("{}/contests/{}/standings/json", ATCODER_BASE_URL, contest_id);
+                let body = match self.fetch_page(&url).await {
+                    Ok((status, body)) if status.is_success() => body,
+                    Ok((status, _)) => {
+                        self.warn(crate::tr!("Failed to fetch standings of {}: {}", contest_id, status));
+                        continue;
+                    }
+                    Err(e) => {
+                        self.warn(crate::tr!("Failed to fetch standings of {}: {}", contest_id, e));
+                        continue;
+                    }
+                };
+                let standings: Standings = match serde_json::from_str(&body) {
+                    Ok(standings) => standings,
+                    Err(e) => {
+                        self.warn(crate::tr!("Failed to decode standings of {}: {}", contest_id, e));
+                        continue;
+                    }
+                };
+
+                let row = match standings.standings_data.into_iter()
+                    .find(|row| row.user_screen_name.eq_ignore_ascii_case(&service.user_id))
+                {
+                    Some(row) => row,
+                    None => continue,
+                };
+
+                // 順位表のScoreは100倍、Elapsedはナノ秒で格納されている
+                let result = ContestResult {
+                    contest_id: contest_id.clone(),
+                    user_id: service.user_id.clone(),
+                    rank: row.rank,
+                    score: row.total_result.score as f64 / 100.0,
+                    penalty: row.total_result.penalty,
+                    accepted: row.total_result.accepted,
+                    elapsed_seconds: row.total_result.elapsed / 1_000_000_000,
+                    is_rated: contest.is_rated,
+                };
+
+                let rel_path = service.contest_dir(&contest_id).join("result.json");
+                let path = service.work_dir().join(&rel_path);
+                let json = serde_json::to_string_pretty(&result).map_err(|e| Error::storage(&path, e))?;
+                fs::write(&path, json).map_err(|e| Error::storage(&path, e))?;
+
+                self.report(Progress::ContestResult { contest_id: contest_id.clone(), rank: result.rank });
+
+                if is_git_repo {
+                    let (author, committer) = commit_signatures(&service, &service.user_id, chrono::Utc::now().timestamp())?;
+                    let message = format!("[Result] {}", contest_id);
+                    commit_files(service.git_backend, repo_path, &[rel_path], &author, &committer, &message)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        /// 前回の実行が提出の書き込み中に止まっていれば、ジャーナルをもとにコミットを済ませるか書き込む前に戻す
+        pub fn recover_journal(&mut self) -> Result<()> {
+            let service = self.config.atcoder.clone();
+            if let Some(recovery) = recover_journal(&service)? {
+                self.report(Progress::Recovered(recovery));
+            }
+            Ok(())
+        }
+
+        /// ユーザーページからレーティングなどを読み取ってprofile.jsonに保存する
+        ///
+        /// 前回の内容から変わっていなければ書き込まないので、コミットの履歴がそのまま成績の推移になる
+        pub async fn archive_profile(&mut self) -> Result<()> {
+            let service = self.config.atcoder.clone();
+            let repo_path = Path::new(&service.repository_path);
+            let url = format!("{}/users/{}", ATCODER_BASE_URL, service.user_id);
+            let html = match self.fetch_page(&url).await {
+                Ok((status, html)) if status.is_success() => html,
+                Ok((status, _)) => {
+                    self.warn(crate::tr!("Failed to fetch the profile of {}: {}", service.user_id, status));
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.warn(crate::tr!("Failed to fetch the profile of {}: {}", service.user_id, e));
+                    return Ok(());
+                }
+            };
+            let profile = parse_profile(&Html::parse_document(&html), &service.user_id);
+
+            let rel_path = service.relative_dir().join(PROFILE_JSON);
+            let path = service.work_dir().join(&rel_path);
+            let previous: Option<Profile> = fs::read_to_string(&path).ok().and_then(|json| serde_json::from_str(&json).ok());
+            if previous.is_some_and(|previous| previous.same_except_rank(&profile)) {
+                return Ok(());
+            }
+            let json = serde_json::to_string_pretty(&profile).map_err(|e| Error::storage(&path, e))?;
+            fs::create_dir_all(service.service_dir())
+                .and_then(|_| fs::write(&path, json))
+                .map_err(|e| Error::storage(&path, e))?;
+
+            self.report(Progress::Profile { rating: profile.rating });
+
+            if is_git_repository(repo_path) {
+                let (author, committer) = commit_signatures(&service, &service.user_id, chrono::Utc::now().timestamp())?;
+                let message = match profile.rating {
+                    Some(rating) => format!("[Profile] {} (rating {})", service.user_id, rating),
+                    None => format!("[Profile] {}", service.user_id),
+                };
+                commit_files(service.git_backend, repo_path, &[rel_path], &author, &committer, &message)?;
+            }
+
+            Ok(())
+        }
+
+        /// ユーザーがコンテストの順位表に載っているか (参加したか)
+        ///
+        /// 順位表を取得できなければfalse
+        pub async fn participated(&mut self, contest_id: &str, user_id: &str) -> Result<bool> {
+            let url = format!("{}/contests/{}/standings/json", ATCODER_BASE_URL, contest_id);
+            let (status, body) = self.fetch_page(&url).await?;
+            if !status.is_success() {
+                self.warn(crate::tr!("Failed to fetch standings of {}: {}", contest_id, status));
+                return Ok(false);
+            }
+            let standings: Standings = serde_json::from_str(&body)
+                .map_err(|e| Error::scrape_with_source(&url, "Failed to decode standings", e))?;
+            Ok(standings.standings_data.iter().any(|row| row.user_screen_name.eq_ignore_ascii_case(user_id)))
+        }
+    }
+
+    /// 提出ページのジャッジ詳細
+    #[derive(Debug, Default)]
+    struct JudgeDetails {
+        /// バージョンまで含んだ言語 (例: "C++ 20 (gcc 12.2)")
+        language: Option<String>,
+        code_size: Option<i64>,
+        memory: Option<i64>,
+        test_cases: Vec<TestCaseResult>,
+    }
+
+    /// コンテスト参加履歴 (https://atcoder.jp/users/<user>/history/json)
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct ContestHistory {
+        is_rated: bool,
+        contest_screen_name: String,
+    }
+
+    impl ContestHistory {
+        /// ContestScreenNameは"abc300.contest.atcoder.jp"の形式
+        fn contest_id(&self) -> &str {
+            self.contest_screen_name.split('.').next().unwrap_or_default()
+        }
+    }
+
+    /// 順位表JSONのうち必要な部分だけ
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Standings {
+        standings_data: Vec<StandingsRow>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct StandingsRow {
+        rank: i64,
+        user_screen_name: String,
+        total_result: TotalResult,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct TotalResult {
+        accepted: i64,
+        penalty: i64,
+        score: i64,
+        elapsed: i64,
+    }
+
+    /// result.jsonとして保存するコンテスト成績
+    #[derive(Debug, Serialize, Deserialize)]
+    struct ContestResult {
+        contest_id: String,
+        user_id: String,
+        rank: i64,
+        score: f64,
+        penalty: i64,
+        accepted: i64,
+        elapsed_seconds: i64,
+        is_rated: bool,
+    }
+
+    /// profile.jsonとして保存するユーザー情報
+    ///
+    /// 生年や所属のような個人情報は含めず、成績に関わる項目だけを残す。未参加なら各項目はnull
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Profile {
+        user_id: String,
+        rating: Option<i64>,
+        highest_rating: Option<i64>,
+        rank: Option<i64>,
+        rated_matches: Option<i64>,
+        last_competed: Option<String>,
+    }
+
+    impl Profile {
+        /// 順位を除いて同じか。順位はほかのユーザーの成績でも変わるので、それだけではprofile.jsonを更新しない
+        fn same_except_rank(&self, other: &Profile) -> bool {
+            Profile { rank: None, ..self.clone() } == Profile { rank: None, ..other.clone() }
+        }
+    }
+
+    /// 設定に応じたHTTPクライアントを作成
+    pub fn build_client(config: &Config) -> Result<Client> {
+        let http = &config.http;
+        if let Some(path) = &http.audit_log {
+            open_audit_log(Path::new(path))?;
+        }
+        let mut builder = Client::builder().user_agent(user_agent(http));
+
+        if let Some(proxy_url) = &http.proxy {
+            let mut proxy = reqwest::Proxy::all(proxy_url)
+                .map_err(|e| Error::config_with_source(format!("Invalid proxy {}", proxy_url), e))?;
+            if let Some(no_proxy) = &http.no_proxy {
+                proxy = proxy.no_proxy(reqwest::NoProxy::from_string(no_proxy));
+            }
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(secs) = http.connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = http.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+
+        if let Some(path) = &http.ca_certificate {
+            let pem = fs::read(path).map_err(|e| Error::storage(path, e))?;
+            let certificate = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| Error::config_with_source(format!("Invalid CA certificate {}", path), e))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        if let Some(version) = &http.min_tls_version {
+            let version = match version.as_str() {
+                "1.0" => reqwest::tls::Version::TLS_1_0,
+                "1.1" => reqwest::tls::Version::TLS_1_1,
+                "1.2" => reqwest::tls::Version::TLS_1_2,
+                "1.3" => reqwest::tls::Version::TLS_1_3,
+                _ => return Err(Error::config(format!("Unknown TLS version: {}", version))),
+            };
+            builder = builder.min_tls_version(version);
+        }
+
+        if http.accept_invalid_certs {
+            crate::warning::warn(&crate::tr!("Warning: TLS certificate verification is disabled"));
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        // セッションが設定されていればログイン状態でatcoder.jpにアクセスする
+        let session = resolve_secret("session", &config.atcoder.session, passphrase_from_env)?;
+        if !session.is_empty() {
+            let mut headers = reqwest::header::HeaderMap::new();
+            let cookie = format!("REVEL_SESSION={}", session);
+            headers.insert(
+                reqwest::header::COOKIE,
+                reqwest::header::HeaderValue::from_str(&cookie)
+                    .map_err(|e| Error::config_with_source("Invalid session value", e))?,
+            );
+            builder = builder.default_headers(headers);
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// AtCoderのユーザーIDとして使える文字列か (3〜16文字の英数字とアンダースコア)
+    pub fn is_valid_user_id(user_id: &str) -> bool {
+        (3..=16).contains(&user_id.len()) && user_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// atcoder.jpにそのユーザーがいるか
+    pub async fn user_exists(client: &Client, user_id: &str, rate_limiter: &RateLimiter) -> Result<bool> {
+        let url = format!("{}/users/{}", ATCODER_BASE_URL, user_id);
+        let status = send_get(client, &url, rate_limiter).await?.status();
+        match status {
+            StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(Error::scrape(&url, format!("unexpected status {}", status))),
+        }
+    }
+
+    /// リクエストに付けるUser-Agent
+    fn user_agent(http: &HttpConfig) -> String {
+        if let Some(user_agent) = &http.user_agent {
+            return user_agent.clone();
+        }
+
+        let mut user_agent = format!(
+            "{}/{} (+https://github.com/Mamo1031/AC-Garden",
+            APP_NAME,
+            env!("CARGO_PKG_VERSION")
+        );
+        if let Some(contact) = &http.contact {
+            user_agent.push_str("; ");
+            user_agent.push_str(contact);
+        }
+        user_agent.push(')');
+        user_agent
+    }
+
+    /// atcoder.jpのページを取得する。メンテナンス中であればError::Maintenanceを返す
+    async fn fetch_atcoder(client: &Client, url: &str, rate_limiter: &RateLimiter) -> Result<(StatusCode, String)> {
+        let response = send_get(client, url, rate_limiter).await?;
+        let status = response.status();
+        let retry_after = response.headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| match v.parse::<u64>() {
+                Ok(secs) => format!("{} seconds", secs),
+                Err(_) => v.to_string(),
+            });
+        let bytes = response.bytes().await?;
+        let (body, replaced) = decode_utf8_lossy(&bytes);
+        if replaced > 0 {
+            crate::warning::warn(&crate::tr!("Warning: replaced {} invalid UTF-8 sequence(s) in {}", replaced, url));
+        }
+
+        if status == StatusCode::SERVICE_UNAVAILABLE || is_maintenance_page(&body) {
+            return Err(Error::Maintenance { retry_after });
+        }
+
+        Ok((status, body))
+    }
+
+    /// 公式解説を探し、downloadなら問題のディレクトリに保存する。(解説のURL, 保存したファイル名)
+    async fn fetch_editorial(
+        client: &Client,
+        submission: &Submission,
+        dir: &Path,
+        download: bool,
+        rate_limiter: &RateLimiter,
+    ) -> Result<(Option<String>, Option<&'static str>)> {
+        let (status, html) = fetch_atcoder(client, &format!("{}/editorial", task_url(submission)), rate_limiter).await?;
+        if !status.is_success() {
+            return Ok((None, None));
+        }
+        let Some(url) = find_official_editorial(&html, &submission.contest_id) else {
+            return Ok((None, None));
+        };
+        if !download {
+            return Ok((Some(url), None));
+        }
+
+        let (file_name, content) = if url.ends_with(".pdf") {
+            let response = send_get(client, &url, rate_limiter).await?;
+            if !response.status().is_success() {
+                return Ok((Some(url), None));
+            }
+            (EDITORIAL_PDF, response.bytes().await?.to_vec())
+        } else {
+            let (status, html) = fetch_atcoder(client, &url, rate_limiter).await?;
+            match editorial_to_markdown(&html, &url) {
+                Some(markdown) if status.is_success() => (EDITORIAL_MD, markdown.into_bytes()),
+                _ => return Ok((Some(url), None)),
+            }
+        };
+        let path = dir.join(file_name);
+        std::fs::create_dir_all(long_path(dir))
+            .and_then(|_| std::fs::write(long_path(&path), content))
+            .map_err(|e| Error::storage(&path, e))?;
+        Ok((Some(url), Some(file_name)))
+    }
+
+    /// メンテナンス中に表示されるページかどうか
+    fn is_maintenance_page(html: &str) -> bool {
+        let document = Html::parse_document(html);
+        let selector = Selector::parse("title").unwrap();
+        document.select(&selector).next()
+            .map(|title| {
+                let title = title.text().collect::<String>();
+                title.contains("メンテナンス") || title.to_lowercase().contains("maintenance")
+            })
+            .unwrap_or(false)
+    }
+
+    /// ユーザーのコンテスト参加履歴を取得
+    async fn fetch_contest_history(client: &Client, user_id: &str, rate_limiter: &RateLimiter) -> Result<Vec<ContestHistory>> {
+        let url = format!("{}/users/{}/history/json", ATCODER_BASE_URL, user_id);
+        let (status, body) = fetch_atcoder(client, &url, rate_limiter).await?;
+        if !status.is_success() {
+            return Err(Error::scrape(&url, format!("Failed to fetch contest history of {}: {}", user_id, status)));
+        }
+        serde_json::from_str(&body)
+            .map_err(|e| Error::scrape_with_source(&url, "Failed to decode contest history", e))
+    }
+
+    /// AtCoder Problemsの提出APIから、`from_second`以降の提出をページごとにたどってすべて取得する
+    async fn fetch_submissions_from_api(client: &Client, user_id: &str, from_second: i64, rate_limiter: &RateLimiter) -> Result<Vec<Submission>> {
+        fetch_pages(from_second, |from_second| async move {
+            let url = format!("{}?user={}&from_second={}", ATCODER_API_SUBMISSION_URL, user_id, from_second);
+            let response = send_get(client, &url, rate_limiter).await?.error_for_status()?;
+            let text = response.text().await?;
+            serde_json::from_str(&text).map_err(|e| Error::scrape_with_source(&url, "Failed to decode response as an array", e))
+        })
+        .await
+    }
+
+    /// `fetch_page`で`from_second`からのページを順に取得して、重複を除いてつなげる
+    ///
+    /// 1ページは提出日時の古い順に最大500件。同じ秒の提出がページをまたいでも落とさないよう、
+    /// 次のページは最後の提出と同じ秒から取得し、取得済みの提出は除く
+    async fn fetch_pages<F, Fut>(mut from_second: i64, mut fetch_page: F) -> Result<Vec<Submission>>
+    where
+        F: FnMut(i64) -> Fut,
+        Fut: std::future::Future<Output = Result<Vec<Submission>>>,
+    {
+        let mut submissions: Vec<Submission> = Vec::new();
+        let mut seen = HashSet::new();
+        loop {
+            let page = fetch_page(from_second).await?;
+            let full = page.len() >= ATCODER_API_PAGE_SIZE;
+            let before = submissions.len();
+            submissions.extend(page.into_iter().filter(|submission| seen.insert(submission.id)));
+            // 同じ秒の提出だけで1ページが埋まった場合も、進まなくなったら打ち切る
+            if !full || submissions.len() == before {
+                break;
+            }
+            from_second = submissions.iter().map(|submission| submission.epoch_second).max().unwrap_or(from_second);
+        }
+        Ok(submissions)
+    }
+
+    /// アーカイブ済みの提出のうち最も新しい提出日時。ソースファイルのないものは数えない
+    fn newest_archived_epoch(service: &Service) -> Option<i64> {
+        scan_archived_submissions(service)
+            .into_iter()
+            .filter(|(path, _)| has_source_file(path))
+            .map(|(_, submission)| submission.epoch_second)
+            .max()
+    }
+
+    /// atcoder.jpの提出一覧ページを巡回してAC提出を集める
+    ///
+    /// 対象は参加履歴のあるコンテストとアーカイブ済みのコンテスト。
+    /// セッションが設定されていれば`submissions/me`を、なければユーザーで絞り込んだ提出一覧を使う。
+    /// 取得できなかったコンテストは飛ばし、その警告を提出と一緒に返す
+    async fn scrape_submissions(client: &Client, config: &Config, rate_limiter: &RateLimiter) -> Result<(Vec<Submission>, Vec<String>)> {
+        let user_id = &config.atcoder.user_id;
+
+        let mut contest_ids = archived_contest_ids(&config.atcoder)?;
+        for contest in fetch_contest_history(client, user_id, rate_limiter).await? {
+            let contest_id = contest.contest_id().to_string();
+            if !contest_ids.contains(&contest_id) {
+                contest_ids.push(contest_id);
+            }
+        }
+
+        let mut submissions = Vec::new();
+        let mut warnings = Vec::new();
+
+        for contest_id in &contest_ids {
+            let mut page = 1;
+            loop {
+                let url = if config.atcoder.session.is_empty() {
+                    format!(
+                        "{}/contests/{}/submissions?f.User={}&f.Status=AC&page={}",
+                        ATCODER_BASE_URL, contest_id, user_id, page
+                    )
+                } else {
+                    format!(
+                        "{}/contests/{}/submissions/me?f.Status=AC&page={}",
+                        ATCODER_BASE_URL, contest_id, page
+                    )
+                };
+
+                let (status, html) = fetch_atcoder(client, &url, rate_limiter).await?;
+                if !status.is_success() {
+                    warnings.push(crate::tr!("Failed to fetch submissions of {}: {}", contest_id, status));
+                    break;
+                }
+
+                let (rows, last_page) = parse_submission_list(&html, &url, contest_id, user_id)?;
+                submissions.extend(rows);
+
+                if page >= last_page {
+                    break;
+                }
+                page += 1;
+            }
+        }
+
+        Ok((submissions, warnings))
+    }
+
+    /// 提出一覧ページのテーブルを解析し、提出と最終ページ番号を返す
+    fn parse_submission_list(html: &str, url: &str, contest_id: &str, user_id: &str) -> Result<(Vec<Submission>, usize)> {
+        let document = Html::parse_document(html);
+        let row_selector = Selector::parse("table tbody tr").unwrap();
+        let cell_selector = Selector::parse("td").unwrap();
+        let link_selector = Selector::parse("a").unwrap();
+        let page_selector = Selector::parse("ul.pagination li a").unwrap();
+
+        let mut submissions = Vec::new();
+
+        for row in document.select(&row_selector) {
+            let cells: Vec<_> = row.select(&cell_selector).collect();
+            // 時刻・問題・ユーザー・言語・得点・コード長・結果・実行時間・メモリ・詳細
+            if cells.len() < 10 {
+                continue;
+            }
+            let text = |i: usize| cells[i].text().collect::<String>().trim().to_string();
+            let href = |i: usize| {
+                cells[i].select(&link_selector)
+                    .filter_map(|a| a.value().attr("href"))
+                    .last()
+                    .map(|href| href.to_string())
+            };
+
+            let epoch_second = chrono::DateTime::parse_from_str(&text(0), "%Y-%m-%d %H:%M:%S%z")
+                .map_err(|e| Error::scrape_with_source(url, "Failed to parse submission time", e))?
+                .timestamp();
+            let problem_id = href(1)
+                .and_then(|href| href.rsplit('/').next().map(|s| s.to_string()))
+                .ok_or_else(|| Error::scrape(url, "Failed to find the task link"))?;
+            let id = href(cells.len() - 1)
+                .and_then(|href| href.rsplit('/').next().and_then(|s| s.parse().ok()))
+                .ok_or_else(|| Error::scrape(url, "Failed to find the submission link"))?;
+
+            submissions.push(Submission {
+                id,
+                epoch_second,
+                problem_id,
+                contest_id: contest_id.to_string(),
+                contest_title: None,
+                problem_index: None,
+                user_id: user_id.to_string(),
+                language: text(3),
+                point: text(4).parse().unwrap_or(0.0),
+                length: parse_leading_number(&text(5)).unwrap_or(0),
+                result: text(6),
+                execution_time: parse_leading_number(&text(7)),
+                memory: parse_leading_number(&text(8)),
+                test_cases: Vec::new(),
+                tags: Vec::new(),
+                editorial_url: None,
+                submission_url: None,
+            });
+        }
+
+        let last_page = document.select(&page_selector)
+            .filter_map(|a| a.text().collect::<String>().trim().parse().ok())
+            .max()
+            .unwrap_or(1);
+
+        Ok((submissions, last_page))
+    }
+
+    /// "3852 KB"や"1 ms"のような文字列の先頭の数値
+    fn parse_leading_number(text: &str) -> Option<i64> {
+        text.split_whitespace().next()?.parse().ok()
+    }
+
+    /// 提出ページからコード長・メモリ・テストケースごとの結果を読み取る
+    fn parse_judge_details(document: &Html) -> JudgeDetails {
+        let table_selector = Selector::parse("table").unwrap();
+        let row_selector = Selector::parse("tr").unwrap();
+        let header_selector = Selector::parse("th").unwrap();
+        let cell_selector = Selector::parse("td").unwrap();
+
+        let mut details = JudgeDetails::default();
+
+        for table in document.select(&table_selector) {
+            let headers: Vec<String> = table.select(&header_selector)
+                .map(|th| th.text().collect::<String>().trim().to_string())
+                .collect();
+
+            if headers.iter().any(|h| h == "Case Name" || h == "ケース名") {
+                // テストケースの表: ケース名・結果・実行時間・メモリ
+                for row in table.select(&row_selector) {
+                    let cells: Vec<String> = row.select(&cell_selector)
+                        .map(|td| td.text().collect::<String>().trim().to_string())
+                        .collect();
+                    if cells.len() < 2 {
+                        continue;
+                    }
+                    details.test_cases.push(TestCaseResult {
+                        name: cells[0].clone(),
+                        status: cells[1].clone(),
+                        execution_time: cells.get(2).and_then(|c| parse_leading_number(c)),
+                        memory: cells.get(3).and_then(|c| parse_leading_number(c)),
+                    });
+                }
+                continue;
+            }
+
+            // 提出情報の表: 見出しと値が1行ずつ並ぶ
+            for row in table.select(&row_selector) {
+                let header = match row.select(&header_selector).next() {
+                    Some(th) => th.text().collect::<String>().trim().to_string(),
+                    None => continue,
+                };
+                let value = match row.select(&cell_selector).next() {
+                    Some(td) => td.text().collect::<String>(),
+                    None => continue,
+                };
+                match header.as_str() {
+                    "Language" | "言語" => details.language = Some(value.trim().to_string()).filter(|v| !v.is_empty()),
+                    "Code Size" | "コード長" => details.code_size = parse_leading_number(value.trim()),
+                    "Memory" | "メモリ" => details.memory = parse_leading_number(value.trim()),
+                    _ => {}
+                }
+            }
+        }
+
+        details
+    }
+
+    /// ユーザーページの表からレーティング・順位・参加回数を読み取る
+    ///
+    /// 値は"1234th"や"1500 ― 3 Kyu"のように後ろに飾りが付くので、先頭の数字だけを使う
+    fn parse_profile(document: &Html, user_id: &str) -> Profile {
+        let row_selector = Selector::parse("table.dl-table tr").unwrap();
+        let header_selector = Selector::parse("th").unwrap();
+        let cell_selector = Selector::parse("td").unwrap();
+        let leading_digits = |text: &str| -> Option<i64> {
+            text.trim().chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().ok()
+        };
+
+        let mut profile = Profile { user_id: user_id.to_string(), ..Default::default() };
+        for row in document.select(&row_selector) {
+            let (Some(th), Some(td)) = (row.select(&header_selector).next(), row.select(&cell_selector).next()) else {
+                continue;
+            };
+            let header = th.text().collect::<String>();
+            let value = td.text().collect::<String>();
+            match header.trim() {
+                "Rank" | "順位" => profile.rank = leading_digits(&value),
+                "Rating" => profile.rating = leading_digits(&value),
+                "Highest Rating" | "最高Rating" => profile.highest_rating = leading_digits(&value),
+                "Rated Matches" | "コンテスト参加回数" => profile.rated_matches = leading_digits(&value),
+                "Last Competed" | "最後に参加した日" => {
+                    profile.last_competed = Some(value.trim().to_string()).filter(|v| !v.is_empty())
+                }
+                _ => {}
+            }
+        }
+        profile
+    }
+
+    /// 提出ページのコード欄に付いているエディタのモード名 (例: "c_cpp")
+    fn find_ace_mode(document: &Html) -> Option<String> {
+        let selector = Selector::parse("#submission-code").unwrap();
+        document.select(&selector).next()
+            .and_then(|element| element.value().attr("data-ace-mode"))
+            .map(|mode| mode.to_string())
+    }
+
+    /// 提出ページからコードを取り出す
+    ///
+    /// ページの構成が変わっても取り出せるよう、コードブロックの中の`<pre>`、
+    /// コードブロックそのもの、ページに埋め込まれたJavaScriptの変数の順に試す
+    fn extract_code(document: &Html) -> Option<String> {
+        code_from_selectors(document, &["#submission-code pre", "#submission-code", "#submission-code-textarea", "pre.prettyprint"])
+            .or_else(|| code_from_script(document))
+    }
+
+    /// 最初に見つかった要素のテキスト。prettifyで行ごとの`<li>`に分かれていればつなぎ直す
+    fn code_from_selectors(document: &Html, selectors: &[&str]) -> Option<String> {
+        let line_selector = Selector::parse("ol.linenums > li").unwrap();
+        selectors.iter().find_map(|selector| {
+            let selector = Selector::parse(selector).unwrap();
+            let element = document.select(&selector).next()?;
+            let lines: Vec<String> = element.select(&line_selector).map(|li| li.text().collect()).collect();
+            if lines.is_empty() {
+                Some(element.text().collect())
+            } else {
+                Some(lines.join("\n"))
+            }
+        })
+    }
+
+    /// コードを持つJavaScriptの変数の名前
+    const CODE_VARIABLES: &[&str] = &["submissionCode", "sourceCode"];
+
+    /// `var submissionCode = "...";`のように埋め込まれたコード
+    fn code_from_script(document: &Html) -> Option<String> {
+        let selector = Selector::parse("script").unwrap();
+        document.select(&selector).find_map(|script| {
+            let script = script.text().collect::<String>();
+            CODE_VARIABLES.iter().find_map(|name| {
+                let (_, rest) = script.split_once(name)?;
+                let literal = rest.trim_start().strip_prefix('=')?.trim_start();
+                parse_js_string(literal)
+            })
+        })
+    }
+
+    /// 先頭にあるJavaScriptの文字列リテラルを読む
+    fn parse_js_string(source: &str) -> Option<String> {
+        let mut chars = source.chars();
+        let quote = chars.next().filter(|c| *c == '"' || *c == '\'')?;
+        let hex = |chars: &mut std::str::Chars, len: usize| -> Option<u32> {
+            let digits: String = chars.take(len).collect();
+            (digits.len() == len).then(|| u32::from_str_radix(&digits, 16).ok()).flatten()
+        };
+        let mut value = String::new();
+        let mut high_surrogate = None;
+        while let Some(c) = chars.next() {
+            let c = match c {
+                '\\' => match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'v' => '\u{b}',
+                    '0' => '\0',
+                    'x' => char::from_u32(hex(&mut chars, 2)?)?,
+                    'u' => {
+                        // サロゲートペアは2つ目と合わせて1文字にする
+                        let unit = hex(&mut chars, 4)?;
+                        match (high_surrogate.take(), unit) {
+                            (None, 0xD800..=0xDBFF) => {
+                                high_surrogate = Some(unit);
+                                continue;
+                            }
+                            (Some(high), 0xDC00..=0xDFFF) => char::from_u32(0x10000 + ((high - 0xD800) << 10) + (unit - 0xDC00))?,
+                            (_, unit) => char::from_u32(unit)?,
+                        }
+                    }
+                    // 行末の\は行の継続
+                    '\n' => continue,
+                    other => other,
+                },
+                c if c == quote => return Some(value),
+                c => c,
+            };
+            value.push(c);
+        }
+        None
+    }
+
+    /// コードが見つからなかった提出ページを保存するディレクトリ (~/.ac-garden/debug)
+    pub fn get_debug_dir() -> Result<PathBuf> {
+        Ok(get_config_dir()?.join("debug"))
+    }
+
+    /// コードが見つからなかった理由をページの内容から推測する
+    fn describe_missing_code(document: &Html, html: &str) -> &'static str {
+        let title = Selector::parse("title").unwrap();
+        let title = document.select(&title).next().map(|t| t.text().collect::<String>().to_lowercase()).unwrap_or_default();
+        let login_form = Selector::parse("form[action*='/login']").unwrap();
+        let lower = html.to_lowercase();
+        if lower.contains("captcha") || lower.contains("cf-challenge") || title.contains("just a moment") {
+            "the page is a CAPTCHA or bot check"
+        } else if document.select(&login_form).next().is_some() || title.contains("sign in") || title.contains("ログイン") {
+            "the page asks to sign in (the session may have expired)"
+        } else {
+            "#submission-code was not found (the page layout may have changed)"
+        }
+    }
+
+    /// コードが見つからなかった提出ページを保存し、理由と保存先を示すエラーにする
+    fn missing_code_error(url: &str, document: &Html, html: &str, submission_id: i64) -> Error {
+        let reason = describe_missing_code(document, html);
+        let saved = get_debug_dir().and_then(|dir| {
+            fs::create_dir_all(&dir).map_err(|e| Error::storage(&dir, e))?;
+            let path = dir.join(format!("{}-{}.html", submission_id, chrono::Utc::now().format("%Y%m%d%H%M%S")));
+            fs::write(&path, html).map_err(|e| Error::storage(&path, e))?;
+            Ok(path)
+        });
+        match saved {
+            Ok(path) => Error::scrape(url, format!("Failed to find the code: {}; saved the page to {}", reason, path.display())),
+            Err(e) => Error::scrape_with_source(url, format!("Failed to find the code: {}", reason), e),
+        }
+    }
+
+    /// 設定に応じて改行コードとBOMを整える
+    fn normalize_code(code: &str, service: &Service) -> String {
+        let code = if service.strip_bom { strip_bom(code) } else { code };
+        match service.line_ending {
+            LineEnding::Keep => code.to_string(),
+            LineEnding::Lf => normalize_line_endings(code),
+        }
+    }
+
+    /// 提出のメタデータをヘッダコメントとして付けたコード
+    pub fn with_header(code: &str, file_name: &str, submission: &Submission) -> String {
+        let ext = Path::new(file_name).extension().unwrap_or_default().to_string_lossy();
+
+        // 日時はAtCoderに合わせて日本時間で表示する
+        let jst = chrono::FixedOffset::east_opt(9 * 3600).unwrap();
+        let date = chrono::DateTime::from_timestamp(submission.epoch_second, 0)
+            .map(|dt| dt.with_timezone(&jst).format("%Y-%m-%d %H:%M:%S %:z").to_string())
+            .unwrap_or_default();
+
+        let mut lines = Vec::new();
+        if let Some(title) = &submission.contest_title {
+            lines.push(format!("Contest: {}", title));
+        }
+        lines.extend([
+            format!("Problem: {}", task_url(submission)),
+            format!("Submission: {}", submission_url(submission)),
+            format!("Date: {}", date),
+            format!("Verdict: {}", submission.result),
+        ]);
+        if let Some(execution_time) = submission.execution_time {
+            lines.push(format!("Execution Time: {} ms", execution_time));
+        }
+        lines.push(format!("Points: {}", submission.point));
+
+        prepend_header(code, &ext, &lines).unwrap_or_else(|| code.to_string())
+    }
+
+    /// コンテストの問題一覧ページから、問題ID → 問題の記号 (例: "abc300_a" → "A") の対応を作る
+    fn parse_task_indexes(document: &Html) -> HashMap<String, String> {
+        let row_selector = Selector::parse("table tbody tr").unwrap();
+        let link_selector = Selector::parse("td a[href*='/tasks/']").unwrap();
+        document.select(&row_selector)
+            .filter_map(|row| {
+                let link = row.select(&link_selector).next()?;
+                let problem_id = link.value().attr("href")?.rsplit('/').next()?.to_string();
+                let index = link.text().collect::<String>().trim().to_string();
+                (!index.is_empty() && !problem_id.is_empty()).then_some((problem_id, index))
+            })
+            .collect()
+    }
+
+    /// 提出ページから問題名 (例: "A - Welcome to AtCoder") を探す
+    fn find_task_title(document: &Html, problem_id: &str) -> Option<String> {
+        let selector = Selector::parse("a[href*='/tasks/']").unwrap();
+        let suffix = format!("/tasks/{}", problem_id);
+        document.select(&selector)
+            .find(|a| a.value().attr("href").is_some_and(|href| href.ends_with(&suffix)))
+            .map(|a| a.text().collect::<String>().trim().to_string())
+            .filter(|title| !title.is_empty())
+    }
+
+    /// `Main.cpp`の`Main`を問題名に置き換えたファイル名
+    fn titled_file_name(title: &str, default_file_name: &str) -> String {
+        let stem = title_to_file_stem(title);
+        if stem.is_empty() {
+            return default_file_name.to_string();
+        }
+        match Path::new(default_file_name).extension() {
+            Some(ext) => format!("{}.{}", stem, ext.to_string_lossy()),
+            None => stem,
+        }
+    }
+
+    /// コンテストの種類。IDで判別できなければコンテスト名 (企業コンテストの「ABC 123相当」など) から判別する
+    pub fn contest_category(contest_id: &str, title: Option<&str>) -> ContestCategory {
+        const ID_PREFIXES: [(&str, ContestCategory); 6] = [
+            ("abc", ContestCategory::Abc),
+            ("arc", ContestCategory::Arc),
+            ("agc", ContestCategory::Agc),
+            ("ahc", ContestCategory::Ahc),
+            ("past", ContestCategory::Past),
+            ("joi", ContestCategory::Joi),
+        ];
+        const TITLE_PATTERNS: [(&str, ContestCategory); 6] = [
+            ("beginner contest", ContestCategory::Abc),
+            ("regular contest", ContestCategory::Arc),
+            ("grand contest", ContestCategory::Agc),
+            ("heuristic", ContestCategory::Ahc),
+            ("アルゴリズム実技検定", ContestCategory::Past),
+            ("情報オリンピック", ContestCategory::Joi),
+        ];
+
+        let id = contest_id.to_lowercase();
+        if let Some((_, category)) = ID_PREFIXES.iter().find(|(prefix, _)| id.starts_with(prefix)) {
+            return *category;
+        }
+        let title = title.unwrap_or_default().to_lowercase();
+        if let Some((_, category)) = TITLE_PATTERNS.iter().find(|(pattern, _)| title.contains(pattern)) {
+            return *category;
+        }
+        // "（ABC 123相当）"や"ABC123"のような略称。"Research"などの単語の一部には一致させない
+        let is_abbreviation = |word: &str, prefix: &str| {
+            word.strip_prefix(prefix).is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()))
+        };
+        title
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .find_map(|word| ID_PREFIXES.iter().find(|(prefix, _)| is_abbreviation(word, prefix)))
+            .map_or(ContestCategory::Other, |(_, category)| *category)
+    }
+
+    /// AHC (AtCoder Heuristic Contest) のコンテストか
+    pub fn is_heuristic_contest(contest_id: &str) -> bool {
+        contest_id.starts_with("ahc")
+    }
+
+    /// 取得した提出から、アーカイブする提出を選ぶ。(アーカイブする提出, 取得したAC提出の数, そのうちアーカイブ済みの数)
+    ///
+    /// ACの提出のうち、アーカイブ済みの提出IDでなく、その問題のアーカイブ済みの提出より新しいものを`policy`に従って残す
+    fn select_pending(service: &Service, submissions: Vec<Submission>) -> (Vec<Submission>, usize, usize) {
+        // AC提出だけをフィルタリング。AHCモードではAHCの提出を分けて扱う
+        let ahc = service.ahc_scores;
+        let (heuristic, ac_submissions): (Vec<Submission>, Vec<Submission>) = submissions
+            .into_iter()
+            .filter(|s| s.result == "AC")
+            .partition(|s| ahc && is_heuristic_contest(&s.contest_id));
+        let fetched = heuristic.len() + ac_submissions.len();
+
+        // アーカイブ済みの提出IDと、問題ごとのアーカイブ済みの最新の提出時刻・得点。
+        // メタデータだけあってソースファイルがない提出は、途中で止まったものとしてアーカイブし直す
+        let mut archived_ids: HashSet<i64> = HashSet::new();
+        let mut archived: HashMap<String, i64> = HashMap::new();
+        let mut archived_scores: HashMap<String, (i64, f64)> = HashMap::new();
+        for (path, submission) in scan_archived_submissions(service) {
+            if !has_source_file(&path) {
+                continue;
+            }
+            archived_ids.insert(submission.id);
+            let epoch_second = archived.entry(service.archive_key(&submission)).or_insert(i64::MIN);
+            *epoch_second = (*epoch_second).max(submission.epoch_second);
+            archived_scores.insert(archive_key(&submission), (submission.epoch_second, submission.point));
+        }
+        // アーカイブ済みの提出ではなく、その問題のアーカイブ済みの提出より新しいか
+        let is_new = |s: &Submission| {
+            !archived_ids.contains(&s.id) && archived.get(&service.archive_key(s)).is_none_or(|&epoch| s.epoch_second > epoch)
+        };
+        let already_archived = heuristic.iter().chain(&ac_submissions).filter(|s| archived_ids.contains(&s.id)).count();
+        let improved = improved_submissions(heuristic, &archived_scores);
+        let ac_submissions = ac_submissions.into_iter();
+
+        let mut pending = match service.policy {
+            Policy::Latest => {
+                // 提出時間で逆順ソートし、各問題の最新提出だけを残す
+                let mut filtered: Vec<Submission> = ac_submissions.collect();
+                filtered.sort_by_key(|s| std::cmp::Reverse(s.epoch_second));
+                let mut seen = HashSet::new();
+                filtered.retain(|s| seen.insert(service.archive_key(s)));
+
+                // アーカイブ済みの提出より新しいACがあれば置き換える
+                filtered.retain(|s| is_new(s));
+                filtered
+            }
+            Policy::All => {
+                // アーカイブ済みより新しいACを古い順に
+                let mut filtered: Vec<Submission> = ac_submissions.filter(|s| is_new(s)).collect();
+                filtered.sort_by_key(|s| (s.epoch_second, s.id));
+                filtered
+            }
+        };
+        pending.extend(improved);
+        (pending, fetched, already_archived)
+    }
+
+    /// アーカイブ済みの得点より高い提出だけを古い順に残す。得点が伸びるたびに1件ずつ残る
+    fn improved_submissions(mut submissions: Vec<Submission>, archived: &HashMap<String, (i64, f64)>) -> Vec<Submission> {
+        submissions.sort_by_key(|s| (s.epoch_second, s.id));
+        let mut best = archived.clone();
+        let mut improved = Vec::new();
+        for submission in submissions {
+            let key = archive_key(&submission);
+            if let Some(&(epoch_second, point)) = best.get(&key) {
+                if submission.epoch_second <= epoch_second || submission.point <= point {
+                    continue;
+                }
+            }
+            best.insert(key, (submission.epoch_second, submission.point));
+            improved.push(submission);
+        }
+        improved
+    }
+
+    /// ファイル名に言語のバージョンを付ける (Main.cpp → Main_gcc-12.2.cpp)
+    fn versioned_file_name(file_name: &str, version: &str) -> String {
+        let version: String = version.split_whitespace().collect::<Vec<_>>().join("-");
+        match file_name.rsplit_once('.') {
+            Some((stem, ext)) => format!("{}_{}.{}", stem, version, ext),
+            None => format!("{}_{}", file_name, version),
+        }
+    }
+
+    /// ACでない提出のファイル名。結果を拡張子の前に入れる (例: Main.WA.cpp)
+    fn verdict_file_name(file_name: &str, result: &str) -> String {
+        match file_name.rsplit_once('.') {
+            Some((stem, ext)) => format!("{}.{}.{}", stem, result, ext),
+            None => format!("{}.{}", file_name, result),
+        }
+    }
+
+    /// ファイル名に得点を入れる (例: `Main.cpp` → `Main_1234567.cpp`)
+    fn scored_file_name(file_name: &str, point: f64) -> String {
+        match file_name.rsplit_once('.') {
+            Some((stem, ext)) => format!("{}_{}.{}", stem, point, ext),
+            None => format!("{}_{}", file_name, point),
+        }
+    }
+
+    /// AHCモードで記録するスコアの推移
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct ScoreRecord {
+        problem_id: String,
+        submission_id: i64,
+        epoch_second: i64,
+        score: f64,
+        file: String,
+    }
+
+    /// コンテストのscores.jsonに得点を追記し、scores.svgのグラフを描き直す
+    ///
+    /// 書き込んだファイルのリポジトリ内の相対パスを返す
+    fn record_score(service: &Service, submission: &Submission, file_name: &str) -> Result<Vec<PathBuf>> {
+        let contest_dir = service.contest_dir(&submission.contest_id);
+        let json_path = service.work_dir().join(&contest_dir).join(SCORES_JSON);
+
+        let mut records: Vec<ScoreRecord> = match fs::read_to_string(&json_path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| Error::storage(&json_path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(Error::storage(&json_path, e)),
+        };
+        records.retain(|record| record.submission_id != submission.id);
+        records.push(ScoreRecord {
+            problem_id: submission.problem_id.clone(),
+            submission_id: submission.id,
+            epoch_second: submission.epoch_second,
+            score: submission.point,
+            file: file_name.to_string(),
+        });
+        records.sort_by_key(|record| (record.epoch_second, record.submission_id));
+
+        let json = serde_json::to_string_pretty(&records).map_err(|e| Error::storage(&json_path, e))?;
+        fs::write(&json_path, json).map_err(|e| Error::storage(&json_path, e))?;
+
+        let mut series: Vec<Series> = Vec::new();
+        for record in &records {
+            let point = (record.epoch_second as f64, record.score);
+            match series.iter_mut().find(|s| s.name == record.problem_id) {
+                Some(s) => s.points.push(point),
+                None => series.push(Series { name: record.problem_id.clone(), points: vec![point] }),
+            }
+        }
+        let svg = line_chart_svg(&format!("{} score", submission.contest_id), &series, |x| {
+            chrono::DateTime::from_timestamp(x as i64, 0)
+                .map(|dt| dt.format("%m-%d %H:%M").to_string())
+                .unwrap_or_default()
+        });
+        let svg_path = service.work_dir().join(&contest_dir).join(SCORES_SVG);
+        fs::write(&svg_path, svg).map_err(|e| Error::storage(&svg_path, e))?;
+
+        Ok(vec![contest_dir.join(SCORES_JSON), contest_dir.join(SCORES_SVG)])
+    }
+
+    /// Gitリポジトリか確認する。`.git`がファイルのリンクされたワークツリーやベアリポジトリも含む
+    pub fn is_git_repository(repo_path: &Path) -> bool {
+        Repository::open(repo_path).is_ok()
+    }
+
+    /// アーカイブ先のリポジトリを用意する。なければ`default_branch`をHEADにして作り、作ったらtrueを返す
+    ///
+    /// まだコミットのないリポジトリでも、最初のコミットが`default_branch`に入るようにHEADを向け直す。
+    /// HEADがほかのブランチにあればエラーにする
+    pub fn prepare_repository(service: &Service) -> Result<bool> {
+        let repo_path = Path::new(&service.repository_path);
+        let branch = service.default_branch.as_deref().filter(|branch| !branch.is_empty());
+        if let Some(branch) = branch {
+            if !git2::Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+                return Err(Error::config(format!("default_branch {} is not a valid branch name", branch)));
+            }
+        }
+
+        if !repo_path.exists() {
+            let mut options = git2::RepositoryInitOptions::new();
+            options.mkpath(true);
+            if let Some(branch) = branch {
+                options.initial_head(branch);
+            }
+            Repository::init_opts(repo_path, &options)?;
+            return Ok(true);
+        }
+
+        let (Some(branch), Ok(repo)) = (branch, Repository::open(repo_path)) else {
+            return Ok(false);
+        };
+        let head = match repo.head() {
+            Ok(head) => head,
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => {
+                repo.set_head(&format!("refs/heads/{}", branch))?;
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // コミットはHEADに積むので、ほかのブランチや切り離されたHEADには積まない
+        if head.name() != Some(format!("refs/heads/{}", branch).as_str()) {
+            return Err(Error::config(format!(
+                "The repository is on {} but default_branch is {}; check out {} before archiving",
+                head.shorthand().unwrap_or("HEAD"),
+                branch,
+                branch
+            )));
+        }
+        Ok(false)
+    }
+
+    /// ベアリポジトリなら、HEADを作業用ディレクトリに書き出してファイルを読み書きできるようにする
+    ///
+    /// ほかから押し込まれたコミットも反映するため、毎回HEADの内容で上書きする
+    pub fn prepare_work_dir(service: &Service) -> Result<()> {
+        let repo_path = Path::new(&service.repository_path);
+        if !is_bare_repository(repo_path) {
+            return Ok(());
+        }
+        let work_dir = service.work_dir();
+        fs::create_dir_all(&work_dir).map_err(|e| Error::storage(&work_dir, e))?;
+
+        let repo = Repository::open_bare(repo_path)?;
+        let tree = match repo.head() {
+            Ok(head) => head.peel_to_tree()?,
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.target_dir(&work_dir).force().update_index(false);
+        repo.checkout_tree(tree.as_object(), Some(&mut checkout))?;
+        Ok(())
+    }
+
+    /// 設定したタイムゾーンで日時を付けたコミットの署名
+    pub fn commit_signature(service: &Service, name: &str, email: &str, epoch_second: i64) -> Result<Signature<'static>> {
+        let timezone = service.timezone.as_deref().unwrap_or_default();
+        let offset = utc_offset_minutes(timezone, epoch_second)
+            .ok_or_else(|| Error::config(format!("atcoder.timezone {} is not a timezone", timezone)))?;
+        Ok(Signature::new(name, email, &git2::Time::new(epoch_second, offset))?)
+    }
+
+    /// ユーザーの`epoch_second`の作業を記録するコミットの作者とコミッター
+    ///
+    /// `committer`を設定していなければ、コミッターは作者と同じにする
+    pub fn commit_signatures(service: &Service, user_id: &str, epoch_second: i64) -> Result<(Signature<'static>, Signature<'static>)> {
+        let (name, email) = service.author(user_id);
+        let author = commit_signature(service, &name, &email, epoch_second)?;
+        let Some(committer) = &service.committer else {
+            return Ok((author.clone(), author));
+        };
+        let name = committer.name.as_deref().unwrap_or(&name);
+        let email = committer.email.as_deref().unwrap_or(&email);
+        let committer = commit_signature(service, name, email, chrono::Utc::now().timestamp())?;
+        Ok((author, committer))
+    }
+
+    /// リポジトリ内の相対パスのファイル群をcommitする
+    ///
+    /// HEADのツリーにファイル群だけを重ねてコミットするので、ユーザーがステージしている変更は含めない。
+    /// コミット後、ユーザーのインデックスはコミットしたファイルだけを更新する
+    pub fn commit_files(
+        backend: GitBackend,
+        repo_path: &Path,
+        rel_paths: &[PathBuf],
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+    ) -> Result<Oid> {
+        if backend == GitBackend::Cli {
+            commit_files_with_cli(repo_path, rel_paths, author, committer, message)?;
+            return Ok(Repository::open(repo_path)?.head()?.peel_to_commit()?.id());
+        }
+
+        let repo = Repository::open(repo_path)?;
+        let work_dir = repo.workdir().map(Path::to_path_buf).unwrap_or_else(|| repo_path.join(BARE_WORK_DIR));
+        let parent_commit = match repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        // HEADのツリーから作ったメモリ上のインデックスにファイルを追加する
+        let mut tree_index = Index::new()?;
+        if let Some(parent_commit) = &parent_commit {
+            tree_index.read_tree(&parent_commit.tree()?)?;
+        }
+        for rel_path in rel_paths {
+            let path = work_dir.join(rel_path);
+            if !path.exists() {
+                tree_index.remove_path(rel_path)?;
+                continue;
+            }
+            tree_index.add(&index_entry(&repo, &path, rel_path)?)?;
+        }
+
+        let tree_id = tree_index.write_tree_to(&repo)?;
+        let tree = repo.find_tree(tree_id)?;
+        let parents: Vec<&git2::Commit> = parent_commit.iter().collect();
+
+        let commit_id = repo.commit(
+            Some("HEAD"),
+            author,
+            committer,
+            message,
+            &tree,
+            &parents,
+        )?;
+
+        // 新しいHEADとずれないように、コミットしたファイルだけユーザーのインデックスに反映する
+        if repo.is_bare() {
+            return Ok(commit_id);
+        }
+        let mut index = repo.index()?;
+        for rel_path in rel_paths {
+            if work_dir.join(rel_path).exists() {
+                index.add_path(rel_path)?;
+            } else {
+                index.remove_path(rel_path)?;
+            }
+        }
+        index.write()?;
+
+        Ok(commit_id)
+    }
+
+    /// メタデータのある問題のディレクトリに、ソースファイルがあるか
+    ///
+    /// メタデータや解説などアーカイブが一緒に書くファイル以外があれば、ソースファイルとみなす
+    fn has_source_file(metadata_path: &Path) -> bool {
+        let Some(dir) = metadata_path.parent() else {
+            return false;
+        };
+        let Ok(entries) = fs::read_dir(long_path(dir)) else {
+            return false;
+        };
+        entries.filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+            .any(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                !METADATA_FILES.contains(&name.as_str()) && !name.ends_with(".orig")
+            })
+    }
+
+    /// 書き込み中の提出を記録するジャーナルのファイル名 (gitディレクトリに置く)
+    pub const JOURNAL_FILE: &str = "ac-garden-journal.json";
+
+    /// 1件の提出の書き込みとコミットのジャーナル
+    ///
+    /// ファイルを書く前に書き込むかもしれないパスとその時点の内容を、書き終えたらコミットするパスとメッセージを記録し、コミットしたら消す。
+    /// ソースファイルだけがコミットされてメタデータがない、という状態を残さないために使う
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Journal {
+        submission: Submission,
+        /// 書き始めたときのHEAD
+        head: Option<String>,
+        /// 書き終える前は書き込むかもしれないパス、書き終えた後はコミットするパス (作業ディレクトリからの相対パス)
+        paths: Vec<PathBuf>,
+        /// 書き始める前の各パスの内容をblobとして保存したID。なかったファイルはNone
+        #[serde(default)]
+        before: Vec<(PathBuf, Option<String>)>,
+        /// 書き終えていればコミットメッセージ
+        message: Option<String>,
+        /// メタデータをgit notesに書くか
+        notes: bool,
+    }
+
+    impl Journal {
+        /// 書き始める前のジャーナル。書き込むかもしれないファイルの今の内容 (コミットしていない編集を含む) をblobに残す
+        fn start(repo_path: &Path, work_dir: &Path, submission: &Submission, paths: Vec<PathBuf>, notes: bool) -> Result<Journal> {
+            let repo = Repository::open(repo_path)?;
+            let mut before = Vec::new();
+            for rel_path in &paths {
+                let path = work_dir.join(rel_path);
+                let id = match path.is_file() {
+                    true => Some(repo.blob_path(&long_path(&path))?.to_string()),
+                    false => None,
+                };
+                before.push((rel_path.clone(), id));
+            }
+            Ok(Journal { submission: submission.clone(), head: head_id(repo_path), paths, before, message: None, notes })
+        }
+    }
+
+    fn journal_path(repo_path: &Path) -> Result<PathBuf> {
+        Ok(Repository::open(repo_path)?.path().join(JOURNAL_FILE))
+    }
+
+    /// ジャーナルを書き換える。途中で止まっても前の内容か新しい内容のどちらかが残るよう、一時ファイルから置き換える
+    fn write_journal(repo_path: &Path, journal: &Journal) -> Result<()> {
+        let path = journal_path(repo_path)?;
+        let temp_path = path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(journal).map_err(|e| Error::storage(&path, e))?;
+        fs::write(&temp_path, json)
+            .and_then(|_| fs::rename(&temp_path, &path))
+            .map_err(|e| Error::storage(&path, e))
+    }
+
+    fn remove_journal(repo_path: &Path) -> Result<()> {
+        let path = journal_path(repo_path)?;
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::storage(&path, e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// HEADのコミットID。まだコミットがなければNone
+    fn head_id(repo_path: &Path) -> Option<String> {
+        let repo = Repository::open(repo_path).ok()?;
+        let commit = repo.head().ok()?.peel_to_commit().ok()?;
+        Some(commit.id().to_string())
+    }
+
+    /// ジャーナルを片付けた結果
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Recovery {
+        /// 途中で止まった提出のコミットを済ませた
+        Completed { submission_id: i64 },
+        /// 書き込む前の状態に戻した。コミットをやり直して失敗したときはそのエラー
+        RolledBack { submission_id: i64, error: Option<String> },
+        /// 読めないか片付けられなかったジャーナルを、次の実行を止めないように脇へ移した
+        SetAside { path: PathBuf, error: String },
+    }
+
+    /// ジャーナルが残っていれば、途中で止まった提出を片付ける
+    ///
+    /// - 書き始めた後のHEADがその提出のコミットなら、コミットは済んでいるので足りないgit notesだけ付ける
+    /// - ファイルを書き終えていれば、記録したメッセージでコミットする。失敗すれば書き込む前に戻す
+    /// - 書き終えていなければ、書き始めてから内容が変わったファイルだけを書き込む前の内容に戻す (なかったファイルは消す)
+    ///
+    /// ジャーナルが壊れている、または戻すこともできないときは、`.broken`を付けて脇へ移す
+    fn recover_journal(service: &Service) -> Result<Option<Recovery>> {
+        let repo_path = Path::new(&service.repository_path);
+        if !is_git_repository(repo_path) {
+            return Ok(None);
+        }
+        let path = journal_path(repo_path)?;
+        let Ok(json) = fs::read_to_string(&path) else {
+            return Ok(None);
+        };
+        let journal: Journal = match serde_json::from_str(&json) {
+            Ok(journal) => journal,
+            Err(e) => return set_journal_aside(&path, e.to_string()).map(Some),
+        };
+        let submission_id = journal.submission.id;
+
+        let recovery = match finish_journal(service, &journal) {
+            Ok(true) => Recovery::Completed { submission_id },
+            Ok(false) => Recovery::RolledBack { submission_id, error: None },
+            Err(e) => {
+                let error = format!("{:#}", anyhow::Error::from(e));
+                match rollback_journal(service, &journal, true) {
+                    Ok(()) => Recovery::RolledBack { submission_id, error: Some(error) },
+                    Err(e) => return set_journal_aside(&path, format!("{}; {:#}", error, anyhow::Error::from(e))).map(Some),
+                }
+            }
+        };
+        remove_journal(repo_path)?;
+        Ok(Some(recovery))
+    }
+
+    /// コミットを済ませられればtrue。書き終えていなければ書き込む前に戻してfalse
+    fn finish_journal(service: &Service, journal: &Journal) -> Result<bool> {
+        let repo_path = Path::new(&service.repository_path);
+        let submission = &journal.submission;
+        let repo = Repository::open(repo_path)?;
+        let submission_id = submission.id.to_string();
+        let committed = repo.head().ok()
+            .and_then(|head| head.peel_to_commit().ok())
+            .filter(|commit| journal.head.as_deref() != Some(commit.id().to_string().as_str()))
+            .filter(|commit| parse_trailers(commit.message().unwrap_or_default()).contains(&("Submission-Id", submission_id.as_str())))
+            .map(|commit| commit.id());
+
+        match (committed, &journal.message) {
+            (Some(commit_id), _) => {
+                if journal.notes && repo.find_note(Some(NOTES_REF), commit_id).is_err() {
+                    let (author, committer) = commit_signatures(service, &submission.user_id, submission.epoch_second)?;
+                    write_note(repo_path, commit_id, &author, &committer, submission)?;
+                }
+                Ok(true)
+            }
+            (None, Some(message)) => {
+                let (author, committer) = commit_signatures(service, &submission.user_id, submission.epoch_second)?;
+                let commit_id = commit_files(service.git_backend, repo_path, &journal.paths, &author, &committer, message)?;
+                if journal.notes {
+                    write_note(repo_path, commit_id, &author, &committer, submission)?;
+                }
+                Ok(true)
+            }
+            (None, None) => {
+                rollback_journal(service, journal, false)?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// ジャーナルを`.broken`を付けた名前に移し、そのパスを返す
+    fn set_journal_aside(path: &Path, error: String) -> Result<Recovery> {
+        let aside = path.with_extension("json.broken");
+        fs::rename(path, &aside).map_err(|e| Error::storage(path, e))?;
+        Ok(Recovery::SetAside { path: aside, error })
+    }
+
+    /// 書き始めてから内容が変わったファイルを、書き込む前の内容に戻す。なかったファイルは消す
+    ///
+    /// 変わっていないファイルは書き込んでいないので触らない。
+    /// `reset_index`なら、コミットに失敗して残ったかもしれないインデックスのエントリもHEADに戻す
+    fn rollback_journal(service: &Service, journal: &Journal, reset_index: bool) -> Result<()> {
+        let repo = Repository::open(&service.repository_path)?;
+        let work_dir = service.work_dir();
+        for (rel_path, before) in &journal.before {
+            let path = work_dir.join(rel_path);
+            let current = match path.is_file() {
+                true => Some(Oid::hash_file(git2::ObjectType::Blob, long_path(&path))?.to_string()),
+                false => None,
+            };
+            if current == *before {
+                continue;
+            }
+            match before {
+                Some(id) => {
+                    let blob = repo.find_blob(Oid::from_str(id)?)?;
+                    fs::write(long_path(&path), blob.content()).map_err(|e| Error::storage(&path, e))?;
+                }
+                None => {
+                    fs::remove_file(long_path(&path)).map_err(|e| Error::storage(&path, e))?;
+                    // 空になった問題やコンテストのディレクトリも消す
+                    for dir in path.ancestors().skip(1).take_while(|dir| *dir != work_dir) {
+                        if fs::remove_dir(dir).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        if reset_index && !repo.is_bare() {
+            let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+            let paths: Vec<&PathBuf> = journal.before.iter().map(|(rel_path, _)| rel_path).collect();
+            repo.reset_default(head.as_ref().map(|commit| commit.as_object()), paths)?;
+        }
+        Ok(())
+    }
+
+    /// 相対パスのうち、HEADからの変更をコミットしていないもの (追跡していない既存のファイルを含む)
+    fn locally_modified(repo_path: &Path, rel_paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let repo = Repository::open(repo_path)?;
+        let mut modified = Vec::new();
+        for rel_path in rel_paths {
+            if !repo_path.join(rel_path).exists() {
+                continue;
+            }
+            let status = repo.status_file(rel_path)?;
+            if !status.is_empty() && !status.contains(git2::Status::IGNORED) {
+                modified.push(rel_path.clone());
+            }
+        }
+        Ok(modified)
+    }
+
+    /// ファイルをblobとして書き込み、インデックスのエントリを作る
+    fn index_entry(repo: &Repository, path: &Path, rel_path: &Path) -> Result<IndexEntry> {
+        let id = repo.blob_path(path)?;
+        let metadata = fs::metadata(path).map_err(|e| Error::storage(path, e))?;
+
+        #[cfg(unix)]
+        let executable = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode() & 0o111 != 0
+        };
+        #[cfg(not(unix))]
+        let executable = false;
+
+        // Gitのパスは常に'/'区切り
+        let git_path = rel_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Ok(IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: if executable { 0o100755 } else { 0o100644 },
+            uid: 0,
+            gid: 0,
+            file_size: metadata.len() as u32,
+            id,
+            flags: git_path.len().min(0xfff) as u16,
+            flags_extended: 0,
+            path: git_path.into_bytes(),
+        })
+    }
+
+    /// システムの`git`コマンドでaddしてcommitする。作者とコミッターは環境変数で渡す
+    fn commit_files_with_cli(repo_path: &Path, rel_paths: &[PathBuf], author: &Signature, committer: &Signature, message: &str) -> Result<()> {
+        let mut add = git_command(repo_path);
+        add.args(["add", "--"]).args(rel_paths);
+        run_git(add, "add")?;
+
+        // パスを指定してコミットし、ほかにステージされている変更は含めない
+        let mut commit = git_command(repo_path);
+        commit.args(["commit", "--quiet", "-m", message, "--"]).args(rel_paths);
+        for (role, signature) in [("AUTHOR", author), ("COMMITTER", committer)] {
+            commit
+                .env(format!("GIT_{}_NAME", role), signature.name().unwrap_or_default())
+                .env(format!("GIT_{}_EMAIL", role), signature.email().unwrap_or_default())
+                .env(format!("GIT_{}_DATE", role), git_date(&signature.when()));
+        }
+        run_git(commit, "commit")
+    }
+
+    /// gitの環境変数に渡す日時 (`@<epoch> +0900`)。`@`がないと小さいエポック秒を日付として読めない
+    fn git_date(when: &git2::Time) -> String {
+        let offset = when.offset_minutes();
+        format!(
+            "@{} {}{:02}{:02}",
+            when.seconds(),
+            if offset < 0 { '-' } else { '+' },
+            offset.abs() / 60,
+            offset.abs() % 60
+        )
+    }
+
+    /// リポジトリを対象にするgitコマンド。ベアリポジトリでは作業用ディレクトリを作業ツリーにする
+    fn git_command(repo_path: &Path) -> Command {
+        let mut command = Command::new("git");
+        if is_bare_repository(repo_path) {
+            command
+                .arg("--git-dir")
+                .arg(repo_path)
+                .arg("--work-tree")
+                .arg(repo_path.join(BARE_WORK_DIR));
+        } else {
+            command.arg("-C").arg(repo_path);
+        }
+        command
+    }
+
+    /// gitコマンドを実行し、失敗したら標準エラー出力をエラーにする
+    fn run_git(mut command: Command, subcommand: &str) -> Result<()> {
+        let output = command
+            .output()
+            .map_err(|e| Error::command(format!("git {}", subcommand), e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(git2::Error::from_str(&format!("`git {}` failed: {}", subcommand, stderr.trim())).into());
+        }
+        Ok(())
+    }
+
+    /// コミットメッセージのテンプレート。`commit_template`、なければ`commit_style`のプリセット
+    pub fn commit_template(service: &Service, heuristic: bool) -> &str {
+        match &service.commit_template {
+            Some(template) => template,
+            None => {
+                let (template, ahc_template) = service.commit_style.templates();
+                if heuristic { ahc_template } else { template }
+            }
+        }
+    }
+
+    /// AtCoder Problemsと同じく、400未満の難易度は正の値に補正する
+    pub fn clip_difficulty(difficulty: f64) -> f64 {
+        if difficulty >= 400.0 {
+            difficulty.round()
+        } else {
+            (400.0 / (1.0 - difficulty / 400.0).exp()).round()
+        }
+    }
+
+    /// 難易度の色の番号 (DIFFICULTY_COLORSの添字)
+    pub fn difficulty_color(difficulty: Option<f64>) -> usize {
+        match difficulty {
+            Some(d) => ((d / 400.0) as usize).min(DIFFICULTY_COLORS.len() - 2),
+            None => DIFFICULTY_COLORS.len() - 1,
+        }
+    }
+
+    /// 難易度の色の絵文字
+    pub fn difficulty_emoji(difficulty: Option<f64>) -> &'static str {
+        DIFFICULTY_EMOJIS[difficulty_color(difficulty)]
+    }
+
+    /// ジャッジ結果の絵文字
+    pub fn verdict_emoji(result: &str) -> &'static str {
+        match result {
+            "AC" => "✅",
+            "WA" => "❌",
+            "TLE" => "⏱️",
+            "MLE" => "💾",
+            "RE" => "💥",
+            "CE" => "🔧",
+            _ => "❔",
+        }
+    }
+
+    /// AtCoder Problemsの難易度 (補正後)。問題ID → 難易度
+    pub async fn fetch_difficulties(http: &Client, rate_limiter: &RateLimiter) -> Result<Fetched<HashMap<String, f64>>> {
+        #[derive(Deserialize)]
+        struct Model {
+            difficulty: Option<f64>,
+        }
+        let models: Fetched<HashMap<String, Model>> = fetch_problem_models(http, false, rate_limiter).await?;
+        Ok(models.map(|models| {
+            models
+                .into_iter()
+                .filter_map(|(problem_id, model)| Some((problem_id, clip_difficulty(model.difficulty?))))
+                .collect()
+        }))
+    }
+
+    /// テンプレートの`{contest_id}`・`{contest_title}`・`{problem_id}`・`{submission_id}`・`{submission_url}`・`{language}`・`{user_id}`・`{result}`・`{point}`を提出の値に、
+    /// `{difficulty}`・`{difficulty_emoji}`を難易度に、`{verdict_emoji}`をジャッジ結果の絵文字に置き換える
+    pub fn render_commit_message(template: &str, submission: &Submission, difficulty: Option<f64>) -> String {
+        let difficulty_text = difficulty.map(|d| d.to_string()).unwrap_or_else(|| "?".to_string());
+        template
+            .replace("{difficulty_emoji}", difficulty_emoji(difficulty))
+            .replace("{difficulty}", &difficulty_text)
+            .replace("{verdict_emoji}", verdict_emoji(&submission.result))
+            .replace("{contest_id}", &submission.contest_id)
+            .replace("{contest_title}", submission.contest_title.as_deref().unwrap_or(&submission.contest_id))
+            .replace("{problem_id}", &submission.problem_id)
+            .replace("{submission_id}", &submission.id.to_string())
+            .replace("{submission_url}", &submission_url(submission))
+            .replace("{language}", &submission.language)
+            .replace("{user_id}", &submission.user_id)
+            .replace("{result}", &submission.result)
+            .replace("{point}", &submission.point.to_string())
+    }
+
+    /// コミットメッセージの末尾に、提出の情報をGitのトレーラーとして付ける
+    ///
+    /// `git log --format=%(trailers)`だけで履歴からアーカイブの索引を作り直せるようにする
+    pub fn with_trailers(message: &str, submission: &Submission) -> String {
+        let submitted_at = chrono::DateTime::from_timestamp(submission.epoch_second, 0)
+            .map(|dt| dt.to_rfc3339())
+            .unwrap_or_default();
+        let mut trailers = vec![
+            ("Submission-Id", submission.id.to_string()),
+            ("Contest", submission.contest_id.clone()),
+        ];
+        if let Some(title) = &submission.contest_title {
+            trailers.push(("Contest-Title", title.clone()));
+        }
+        trailers.extend([
+            ("Problem", submission.problem_id.clone()),
+            ("Language", submission.language.clone()),
+            ("Result", submission.result.clone()),
+            ("Point", submission.point.to_string()),
+        ]);
+        if let Some(execution_time) = submission.execution_time {
+            trailers.push(("Execution-Time", format!("{} ms", execution_time)));
+        }
+        trailers.push(("Code-Length", format!("{} B", submission.length)));
+        trailers.push(("Submitted-At", submitted_at));
+
+        let trailers: Vec<String> = trailers
+            .into_iter()
+            .map(|(key, value)| format!("{}: {}", key, value.replace('\n', " ")))
+            .collect();
+        format!("{}\n\n{}\n", message.trim_end(), trailers.join("\n"))
+    }
+
+    /// コミットメッセージの最後の段落からトレーラーを読む ([`with_trailers`]の逆)
+    pub fn parse_trailers(message: &str) -> Vec<(&str, &str)> {
+        let last = message.trim_end().rsplit("\n\n").next().unwrap_or_default();
+        last.lines().filter_map(|line| line.split_once(": ")).collect()
+    }
+
+    /// 提出ごとのフックに渡す環境変数
+    fn submission_hook_env(submission: &Submission, file_path: &Path) -> Vec<(&'static str, String)> {
+        vec![
+            ("AC_GARDEN_SUBMISSION_ID", submission.id.to_string()),
+            ("AC_GARDEN_CONTEST_ID", submission.contest_id.clone()),
+            ("AC_GARDEN_PROBLEM_ID", submission.problem_id.clone()),
+            ("AC_GARDEN_LANGUAGE", submission.language.clone()),
+            ("AC_GARDEN_RESULT", submission.result.clone()),
+            ("AC_GARDEN_EPOCH_SECOND", submission.epoch_second.to_string()),
+            ("AC_GARDEN_SUBMISSION_URL", submission_url(submission)),
+            ("AC_GARDEN_FILE", file_path.display().to_string()),
+        ]
+    }
+
+    /// フックコマンドを実行する。設定されていなければ何もしない
+    ///
+    /// 成功したか (未設定ならtrue) を返す。失敗は警告として知らせ、pre_archive以外はそのまま続ける
+    fn run_hook(
+        event: &str,
+        command: Option<&str>,
+        repo_path: &Path,
+        env: &[(&str, String)],
+        input: &serde_json::Value,
+        progress: &mut Option<ProgressCallback>,
+    ) -> Result<bool> {
+        let command = match command {
+            Some(command) if !command.trim().is_empty() => command,
+            _ => return Ok(true),
+        };
+
+        let mut child = shell_command(command)
+            .current_dir(repo_path)
+            .env("AC_GARDEN_EVENT", event)
+            .env("AC_GARDEN_REPOSITORY", repo_path)
+            .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::command(command, e))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // フックが標準入力を読まずに終わることもあるので書き込みエラーは無視する
+            let _ = stdin.write_all(input.to_string().as_bytes());
+        }
+
+        let status = child.wait().map_err(|e| Error::command(command, e))?;
+        if !status.success() {
+            let message = crate::tr!("{} hook `{}` failed: {}", event, command, status);
+            report(progress, Progress::Warning { message });
+        }
+        Ok(status.success())
+    }
+
+    /// シェル経由でコマンドを実行するためのCommand
+    pub fn shell_command(command: &str) -> Command {
+        if cfg!(target_os = "windows") {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", command]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", command]);
+            cmd
+        }
+    }
+
+    /// フォーマッタのコマンドの末尾にファイル名を付けたCommand
+    ///
+    /// ファイル名は問題名から作るので、シェルに解釈させずに`$1`として渡す
+    fn formatter_command(formatter: &str, file_name: &str) -> Command {
+        if cfg!(target_os = "windows") {
+            // Windowsのファイル名には`"`を使えない
+            shell_command(&format!("{} \"{}\"", formatter, file_name))
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", &format!("{} \"$1\"", formatter), "sh", file_name]);
+            cmd
+        }
+    }
+
+    /// フォーマット前のコードを残すファイルの名前 (`Main.rs` → `Main.orig`)
+    fn original_file_name(file_name: &str) -> String {
+        format!("{}.orig", Path::new(file_name).file_stem().unwrap_or_default().to_string_lossy())
+    }
+
+    /// 設定された拡張子のフォーマッタでソースファイルを整形する
+    ///
+    /// 元のコードを残した場合はそのファイル名 (例: `Main.orig`) を返す。
+    /// フォーマッタが失敗したときは警告を知らせ、提出したままのコードに戻す
+    fn format_source(
+        dir: &Path,
+        file_name: &str,
+        language: &str,
+        service: &Service,
+        progress: &mut Option<ProgressCallback>,
+    ) -> Result<Option<String>> {
+        let ext = match Path::new(file_name).extension() {
+            Some(ext) => ext.to_string_lossy().to_string(),
+            None => return Ok(None),
+        };
+        let formatter = match service.formatters.get(&canonical_language(language)).or_else(|| service.formatters.get(&ext)) {
+            Some(formatter) => formatter,
+            None => return Ok(None),
+        };
+
+        let file_path = dir.join(file_name);
+        let original = fs::read(long_path(&file_path)).map_err(|e| Error::storage(&file_path, e))?;
+
+        let status = formatter_command(formatter, file_name)
+            .current_dir(dir)
+            .status()
+            .map_err(|e| Error::command(formatter.as_str(), e))?;
+
+        if !status.success() {
+            let message = crate::tr!("Formatter `{}` failed ({}), keeping the code as submitted", formatter, status);
+            report(progress, Progress::Warning { message });
+            fs::write(long_path(&file_path), &original).map_err(|e| Error::storage(&file_path, e))?;
+            return Ok(None);
+        }
+
+        if !service.keep_original || fs::read(long_path(&file_path)).map_err(|e| Error::storage(&file_path, e))? == original {
+            return Ok(None);
+        }
+
+        let original_file_name = original_file_name(file_name);
+        let original_path = dir.join(&original_file_name);
+        fs::write(long_path(&original_path), &original).map_err(|e| Error::storage(&original_path, e))?;
+
+        Ok(Some(original_file_name))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::config::CommitStyle;
+        use crate::store::NOTES_MD;
+
+        const EXPECTED: &str = "#include <iostream>\nint main() {\n    std::cout << \"Hello\" << std::endl;\n}";
+
+        fn extract(html: &str) -> Option<String> {
+            extract_code(&Html::parse_document(html)).map(|code| code.trim_end().to_string())
+        }
+
+        #[test]
+        fn extracts_code_from_the_submission_code_block() {
+            assert_eq!(extract(include_str!("../tests/fixtures/submission_pre.html")).as_deref(), Some(EXPECTED));
+        }
+
+        #[test]
+        fn extracts_code_from_prettified_lines() {
+            assert_eq!(extract(include_str!("../tests/fixtures/submission_linenums.html")).as_deref(), Some(EXPECTED));
+        }
+
+        #[test]
+        fn extracts_code_from_the_pre_inside_the_code_block() {
+            assert_eq!(extract(include_str!("../tests/fixtures/submission_nested_pre.html")).as_deref(), Some(EXPECTED));
+        }
+
+        #[test]
+        fn extracts_code_from_the_embedded_variable() {
+            assert_eq!(extract(include_str!("../tests/fixtures/submission_js_variable.html")).as_deref(), Some(EXPECTED));
+        }
+
+        #[test]
+        fn finds_no_code_on_the_login_page() {
+            let html = include_str!("../tests/fixtures/login.html");
+            assert_eq!(extract(html), None);
+            assert_eq!(
+                describe_missing_code(&Html::parse_document(html), html),
+                "the page asks to sign in (the session may have expired)"
+            );
+        }
+
+        #[test]
+        fn parses_task_indexes_from_the_task_list() {
+            let indexes = parse_task_indexes(&Html::parse_document(include_str!("../tests/fixtures/tasks.html")));
+            assert_eq!(indexes.len(), 3);
+            assert_eq!(indexes.get("abc300_a").map(String::as_str), Some("A"));
+            assert_eq!(indexes.get("abc300_h").map(String::as_str), Some("Ex"));
+        }
+
+        #[test]
+        fn parses_the_profile_without_personal_fields() {
+            let profile = parse_profile(&Html::parse_document(include_str!("../tests/fixtures/user.html")), "me");
+            assert_eq!(profile.rating, Some(1024));
+            assert_eq!(profile.highest_rating, Some(1203));
+            assert_eq!(profile.rank, Some(12345));
+            assert_eq!(profile.rated_matches, Some(42));
+            assert_eq!(profile.last_competed.as_deref(), Some("2024/05/04"));
+            assert!(!serde_json::to_string(&profile).unwrap().contains("1998"));
+
+            assert!(profile.same_except_rank(&Profile { rank: Some(12000), ..profile.clone() }));
+            assert!(!profile.same_except_rank(&Profile { rating: Some(1100), ..profile.clone() }));
+        }
+
+        #[test]
+        fn renders_commit_style_presets() {
+            let submission: Submission = serde_json::from_value(serde_json::json!({
+                "id": 1, "epoch_second": 0, "problem_id": "abc300_c", "contest_id": "abc300",
+                "contest_title": "AtCoder Beginner Contest 300", "user_id": "me", "language": "Rust",
+                "point": 300.0, "length": 100, "result": "AC", "execution_time": 1,
+            })).unwrap();
+            let render = |style: CommitStyle| render_commit_message(style.templates().0, &submission, Some(1000.0));
+            assert_eq!(render(CommitStyle::Emoji), "🟢 [AC] AtCoder Beginner Contest 300 abc300_c");
+            assert_eq!(render(CommitStyle::Gitmoji), "✨ Solve abc300_c (AtCoder Beginner Contest 300)");
+            assert_eq!(render(CommitStyle::Conventional), "feat(abc300): solve abc300_c");
+            assert_eq!(render_commit_message("{verdict_emoji} {difficulty}", &submission, None), "✅ ?");
+            assert_eq!(
+                render_commit_message("{problem_id} {submission_url}", &submission, None),
+                "abc300_c https://atcoder.jp/contests/abc300/submissions/1",
+            );
+        }
+
+        #[test]
+        fn parses_trailers_written_by_with_trailers() {
+            let submission: Submission = serde_json::from_value(serde_json::json!({
+                "id": 42, "epoch_second": 0, "problem_id": "abc300_a", "contest_id": "abc300",
+                "user_id": "me", "language": "C++ 20 (gcc 12.2)", "point": 100.0, "length": 512, "result": "AC",
+                "execution_time": null,
+            })).unwrap();
+            let message = with_trailers("[AC] abc300_a\n\nSolved: twice", &submission);
+            let trailers = parse_trailers(&message);
+            assert_eq!(trailers.first(), Some(&("Submission-Id", "42")));
+            assert!(trailers.contains(&("Language", "C++ 20 (gcc 12.2)")));
+            assert!(trailers.contains(&("Code-Length", "512 B")));
+            assert!(!trailers.iter().any(|(key, _)| *key == "Solved"));
+        }
+
+        #[test]
+        fn parses_javascript_string_escapes() {
+            assert_eq!(parse_js_string(r#""a\"b\\c\n\x41あ""#).as_deref(), Some("a\"b\\c\nA\u{3042}"));
+            assert_eq!(parse_js_string(r#"'it\'s'; rest"#).as_deref(), Some("it's"));
+            assert_eq!(parse_js_string(r#""\ud83d\ude00""#).as_deref(), Some("\u{1F600}"));
+            assert_eq!(parse_js_string(r#""unterminated"#), None);
+        }
+
+        #[test]
+        fn skips_languages_by_submitted_or_canonical_name() {
+            let service = Service { skip_languages: vec!["bash*".to_string(), "Python".to_string()], ..Service::default() };
+            assert!(service.skips_language("Bash (5.2.2)"));
+            assert!(service.skips_language("Bash (GNU bash v4.3.11)"));
+            assert!(service.skips_language("PyPy3 (7.3.0)"));
+            assert!(!service.skips_language("C++ 20 (gcc 12.2)"));
+            assert!(!Service::default().skips_language("Bash (5.2.2)"));
+        }
+
+        fn test_submission(id: i64) -> Submission {
+            serde_json::from_value(serde_json::json!({
+                "id": id, "epoch_second": 1_700_000_000, "problem_id": "abc300_a", "contest_id": "abc300",
+                "user_id": "me", "language": "C++ 20 (gcc 12.2)", "point": 100.0, "length": 10, "result": "AC",
+                "execution_time": 1,
+            })).unwrap()
+        }
+
+        /// 一時ディレクトリにファイルを1つずつコミットしたリポジトリを作る
+        fn test_repository(name: &str, files: &[(&str, &str)]) -> Service {
+            let dir = std::env::temp_dir().join(format!("ac-garden-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Repository::init(&dir).unwrap();
+            let service = Service {
+                repository_path: dir.to_string_lossy().to_string(),
+                user_id: "me".to_string(),
+                user_email: "me@example.com".to_string(),
+                ..Service::default()
+            };
+            let (author, committer) = commit_signatures(&service, "me", 1_600_000_000).unwrap();
+            for (rel_path, content) in files {
+                let path = dir.join(rel_path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, content).unwrap();
+                commit_files(GitBackend::Libgit2, &dir, &[PathBuf::from(rel_path)], &author, &committer, rel_path).unwrap();
+            }
+            service
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn passes_the_file_name_to_the_formatter_without_shell_expansion() {
+            let dir = std::env::temp_dir().join(format!("ac-garden-formatter-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let file_name = "$(touch pwned)`touch pwned2`.rs";
+            fs::write(dir.join(file_name), "fn main() {}").unwrap();
+            let service = Service {
+                formatters: HashMap::from([("rs".to_string(), "printf '%s' >args.txt".to_string())]),
+                ..Service::default()
+            };
+
+            format_source(&dir, file_name, "Rust", &service, &mut None).unwrap();
+            assert_eq!(fs::read_to_string(dir.join("args.txt")).unwrap(), file_name);
+            assert!(!dir.join("pwned").exists() && !dir.join("pwned2").exists());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[tokio::test]
+        async fn pages_through_submissions_until_a_short_or_empty_page() {
+            let page = |from: i64, count: i64| -> Vec<Submission> {
+                (0..count).map(|i| Submission { epoch_second: from + i, ..test_submission(from + i) }).collect()
+            };
+            let size = ATCODER_API_PAGE_SIZE as i64;
+
+            // 埋まったページの次に短いページが来たら終わる。次のページは最後の提出と同じ秒から取得する
+            let mut requested = Vec::new();
+            let submissions = fetch_pages(100, |from| {
+                requested.push(from);
+                let result = Ok(if from == 100 { page(100, size) } else { page(from, 3) });
+                async move { result }
+            })
+            .await
+            .unwrap();
+            assert_eq!(requested, vec![100, 100 + size - 1]);
+            assert_eq!(submissions.len(), size as usize + 2);
+
+            // 空のページで終わる
+            let submissions = fetch_pages(0, |_| async { Ok(Vec::new()) }).await.unwrap();
+            assert!(submissions.is_empty());
+
+            // 同じ秒の提出だけで埋まったページが繰り返されても、進まなくなったら終わる
+            let mut calls = 0;
+            let same_second: Vec<Submission> = (0..size).map(|id| Submission { epoch_second: 5, ..test_submission(id) }).collect();
+            let submissions = fetch_pages(5, |_| {
+                calls += 1;
+                let result = Ok(same_second.clone());
+                async move { result }
+            })
+            .await
+            .unwrap();
+            assert_eq!(calls, 2);
+            assert_eq!(submissions.len(), size as usize);
+        }
+
+        #[test]
+        fn prepends_log_entries_under_a_single_title() {
+            use crate::store::prepend_log_entry;
+
+            let first = prepend_log_entry("", "# Log", "## 2\n\n- Runtime: 1.0s\n");
+            assert_eq!(first, "# Log\n\n## 2\n\n- Runtime: 1.0s\n");
+            let second = prepend_log_entry(&first, "# Log", "## 3\n");
+            assert_eq!(second, "# Log\n\n## 3\n\n## 2\n\n- Runtime: 1.0s\n");
+            // 見出しの後の空行がなくても、見出しだけのファイルでも見出しは増えない
+            assert_eq!(prepend_log_entry("# Log\r\n## 1\n", "# Log", "## 2"), "# Log\n\n## 2\n\n## 1\n");
+            assert_eq!(prepend_log_entry("# Log", "# Log", "## 2"), "# Log\n\n## 2\n");
+            // 手で書いた見出しのないファイルは下に残す
+            assert_eq!(prepend_log_entry("notes\n", "# Log", "## 2"), "# Log\n\n## 2\n\nnotes\n");
+        }
+
+        #[test]
+        fn refuses_to_commit_onto_a_branch_other_than_the_default() {
+            let service = Service { default_branch: Some("main".to_string()), ..test_repository("branch", &[]) };
+            let repo = Repository::open(&service.repository_path).unwrap();
+            repo.set_head("refs/heads/main").unwrap();
+            prepare_repository(&service).unwrap();
+            let (author, committer) = commit_signatures(&service, "me", 1_600_000_000).unwrap();
+            fs::write(Path::new(&service.repository_path).join("a.txt"), "a").unwrap();
+            commit_files(GitBackend::Libgit2, Path::new(&service.repository_path), &[PathBuf::from("a.txt")], &author, &committer, "a").unwrap();
+            assert!(prepare_repository(&service).is_ok());
+
+            let head = repo.head().unwrap().peel_to_commit().unwrap();
+            repo.branch("topic", &head, false).unwrap();
+            repo.set_head("refs/heads/topic").unwrap();
+            assert!(matches!(prepare_repository(&service), Err(Error::Config { .. })));
+            repo.set_head_detached(head.id()).unwrap();
+            assert!(matches!(prepare_repository(&service), Err(Error::Config { .. })));
+        }
+
+        #[test]
+        fn a_rejected_submission_archived_by_id_does_not_hide_later_accepted_ones() {
+            let dir = std::env::temp_dir().join(format!("ac-garden-verdict-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            let service = Service { repository_path: dir.to_string_lossy().to_string(), ..Service::default() };
+            let at = |id: i64, epoch_second: i64, result: &str| Submission { epoch_second, result: result.to_string(), ..test_submission(id) };
+            let problem_dir = service.work_dir().join(service.submission_dir(&at(1, 100, "AC")));
+            archive_file("ac", "Main.cpp", &problem_dir, &at(1, 100, "AC"), MetadataFormat::Json).unwrap();
+            write_source("wa", &verdict_file_name("Main.cpp", "WA"), &problem_dir).unwrap();
+            assert!(problem_dir.join("Main.WA.cpp").exists());
+
+            let (pending, fetched, already_archived) = select_pending(&service, vec![at(1, 100, "AC"), at(2, 200, "AC"), at(3, 300, "WA")]);
+            assert_eq!(pending.iter().map(|s| s.id).collect::<Vec<_>>(), vec![2]);
+            assert_eq!((fetched, already_archived), (2, 1));
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn recovers_a_submission_interrupted_between_writing_and_committing() {
+            let service = test_repository("journal-crash", &[("abc300/abc300_a/Main.cpp", "old")]);
+            let repo_path = Path::new(&service.repository_path);
+            let problem_dir = PathBuf::from("abc300/abc300_a");
+            let paths = vec![problem_dir.join("Main.cpp"), problem_dir.join(SUBMISSION_JSON)];
+            let write = || {
+                fs::write(repo_path.join(&paths[0]), "new").unwrap();
+                fs::write(repo_path.join(&paths[1]), "{}").unwrap();
+            };
+
+            // メッセージを記録する前に止まれば、書き込む前に戻す
+            let journal = Journal::start(repo_path, repo_path, &test_submission(1), paths.clone(), false).unwrap();
+            write_journal(repo_path, &journal).unwrap();
+            write();
+            assert_eq!(recover_journal(&service).unwrap(), Some(Recovery::RolledBack { submission_id: 1, error: None }));
+            assert_eq!(fs::read_to_string(repo_path.join(&paths[0])).unwrap(), "old");
+            assert!(!repo_path.join(&paths[1]).exists());
+            assert_eq!(recover_journal(&service).unwrap(), None);
+
+            // メッセージまで記録していれば、コミットを済ませる
+            let mut journal = Journal::start(repo_path, repo_path, &test_submission(2), paths.clone(), false).unwrap();
+            write();
+            journal.message = Some(with_trailers("[AC] abc300_a", &test_submission(2)));
+            write_journal(repo_path, &journal).unwrap();
+            assert_eq!(recover_journal(&service).unwrap(), Some(Recovery::Completed { submission_id: 2 }));
+            let repo = Repository::open(repo_path).unwrap();
+            let head = repo.head().unwrap().peel_to_commit().unwrap();
+            assert!(head.message().unwrap().contains("Submission-Id: 2"));
+            assert!(head.tree().unwrap().get_path(&paths[1]).is_ok());
+            fs::remove_dir_all(repo_path).unwrap();
+        }
+
+        #[test]
+        fn rollback_keeps_uncommitted_edits_made_before_the_run() {
+            let service = test_repository("journal-notes", &[("abc300/abc300_a/NOTES.md", "committed"), ("abc300/abc300_a/language.txt", "C++")]);
+            let repo_path = Path::new(&service.repository_path);
+            let notes = PathBuf::from("abc300/abc300_a").join(NOTES_MD);
+            let language = PathBuf::from("abc300/abc300_a").join(LANGUAGE_TXT);
+            fs::write(repo_path.join(&notes), "my own notes").unwrap();
+            fs::write(repo_path.join(&language), "edited").unwrap();
+
+            let journal = Journal::start(repo_path, repo_path, &test_submission(1), vec![notes.clone(), language.clone()], false).unwrap();
+            write_journal(repo_path, &journal).unwrap();
+            fs::write(repo_path.join(&notes), "generated").unwrap();
+
+            assert_eq!(recover_journal(&service).unwrap(), Some(Recovery::RolledBack { submission_id: 1, error: None }));
+            assert_eq!(fs::read_to_string(repo_path.join(&notes)).unwrap(), "my own notes");
+            assert_eq!(fs::read_to_string(repo_path.join(&language)).unwrap(), "edited");
+            fs::remove_dir_all(repo_path).unwrap();
+        }
+
+        #[test]
+        fn sets_a_corrupt_journal_aside() {
+            let service = test_repository("journal-corrupt", &[]);
+            let repo_path = Path::new(&service.repository_path);
+            let path = journal_path(repo_path).unwrap();
+            fs::write(&path, "{ not json").unwrap();
+
+            match recover_journal(&service).unwrap() {
+                Some(Recovery::SetAside { path: aside, .. }) => assert_eq!(fs::read_to_string(aside).unwrap(), "{ not json"),
+                other => panic!("unexpected {:?}", other),
+            }
+            assert!(!path.exists());
+            assert_eq!(recover_journal(&service).unwrap(), None);
+            fs::remove_dir_all(repo_path).unwrap();
+        }
+    }
+}
+
+/// 非同期ランタイムを使わずに呼べるAPI (`blocking`フィーチャー)
+///
+/// 内部で専用のランタイムを持ち、[`archive::ArchiveClient`]と同じ処理を同期的に実行する。
+/// 非同期のコンテキストの中からは呼ばないこと
+///
+/// ```no_run
+/// # fn run() -> ac_garden::Result<()> {
+/// use ac_garden::blocking::ArchiveClient;
+///
+/// let mut client = ArchiveClient::builder()
+///     .user("tourist")
+///     .repository("/path/to/archive")
+///     .build()?;
+/// let summary = client.archive_all()?;
+/// println!("{} of {} archived", summary.archived, summary.pending);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use crate::archive::{self, ArchiveSummary, Archived, Policy, Progress};
+    use crate::config::{Config, ContestCategory};
+    use crate::error::BoxError;
+    use crate::rate_limit::RateLimiter;
+    use crate::submission::Submission;
+    use crate::{Error, Result};
+    use std::path::PathBuf;
+    use tokio::runtime::Runtime;
+
+    /// [`ArchiveClient`]のビルダー
+    #[derive(Default)]
+    pub struct ArchiveClientBuilder {
+        inner: archive::ArchiveClientBuilder,
+    }
+
+    impl ArchiveClientBuilder {
+        pub fn config(self, config: Config) -> Self {
+            ArchiveClientBuilder { inner: self.inner.config(config) }
+        }
+
+        pub fn user(self, user: impl Into<String>) -> Self {
+            ArchiveClientBuilder { inner: self.inner.user(user) }
+        }
+
+        pub fn email(self, email: impl Into<String>) -> Self {
+            ArchiveClientBuilder { inner: self.inner.email(email) }
+        }
+
+        pub fn repository(self, path: impl Into<PathBuf>) -> Self {
+            ArchiveClientBuilder { inner: self.inner.repository(path) }
+        }
+
+        pub fn policy(self, policy: Policy) -> Self {
+            ArchiveClientBuilder { inner: self.inner.policy(policy) }
+        }
+
+        pub fn contests(self, contests: Vec<ContestCategory>) -> Self {
+            ArchiveClientBuilder { inner: self.inner.contests(contests) }
+        }
+
+        pub fn submission_ids(self, ids: impl IntoIterator<Item = i64>) -> Self {
+            ArchiveClientBuilder { inner: self.inner.submission_ids(ids) }
+        }
+
+        pub fn force(self, force: bool) -> Self {
+            ArchiveClientBuilder { inner: self.inner.force(force) }
+        }
+
+        pub fn on_progress(self, callback: impl FnMut(&Progress) + Send + 'static) -> Self {
+            ArchiveClientBuilder { inner: self.inner.on_progress(callback) }
+        }
+
+        pub fn select(self, callback: impl FnMut(Vec<Submission>) -> std::result::Result<Vec<Submission>, BoxError> + Send + 'static) -> Self {
+            ArchiveClientBuilder { inner: self.inner.select(callback) }
+        }
+
+        pub fn limit(self, limit: usize) -> Self {
+            ArchiveClientBuilder { inner: self.inner.limit(limit) }
+        }
+
+        pub fn rate_limiter(self, rate_limiter: RateLimiter) -> Self {
+            ArchiveClientBuilder { inner: self.inner.rate_limiter(rate_limiter) }
+        }
+
+        pub fn build(self) -> Result<ArchiveClient> {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(Error::Runtime)?;
+            // HTTPクライアントはランタイムの中で作る
+            let inner = {
+                let _guard = runtime.enter();
+                self.inner.build()?
+            };
+            Ok(ArchiveClient { inner, runtime })
+        }
+    }
+
+    /// [`archive::ArchiveClient`]の同期版
+    pub struct ArchiveClient {
+        inner: archive::ArchiveClient,
+        runtime: Runtime,
+    }
+
+    impl ArchiveClient {
+        pub fn builder() -> ArchiveClientBuilder {
+            ArchiveClientBuilder::default()
+        }
+
+        pub fn config(&self) -> &Config {
+            self.inner.config()
+        }
+
+        pub fn fetch_submissions(&mut self) -> Result<Vec<Submission>> {
+            self.runtime.block_on(self.inner.fetch_submissions())
+        }
+
+        pub fn fetch_code(&mut self, submission: &Submission) -> Result<String> {
+            self.runtime.block_on(self.inner.fetch_code(submission))
+        }
+
+        pub fn fetch_pending(&mut self) -> Result<Vec<Submission>> {
+            self.runtime.block_on(self.inner.fetch_pending())
+        }
+
+        pub fn archive_one(&mut self, submission: Submission) -> Result<Option<Archived>> {
+            self.runtime.block_on(self.inner.archive_one(submission))
+        }
+
+        pub fn archive_all(&mut self) -> Result<ArchiveSummary> {
+            self.runtime.block_on(self.inner.archive_all())
+        }
+
+        pub fn archive_by_id(&mut self, submission_id: i64) -> Result<Option<Archived>> {
+            self.runtime.block_on(self.inner.archive_by_id(submission_id))
+        }
+
+        pub fn archive_contest_results(&mut self) -> Result<()> {
+            self.runtime.block_on(self.inner.archive_contest_results())
+        }
+
+        pub fn archive_profile(&mut self) -> Result<()> {
+            self.runtime.block_on(self.inner.archive_profile())
+        }
+
+        pub fn participated(&mut self, contest_id: &str, user_id: &str) -> Result<bool> {
+            self.runtime.block_on(self.inner.participated(contest_id, user_id))
+        }
+    }
+}
+
+/// メタデータのフロントマター形式
+///
+/// 値は1行のJSONとして書く。JSONはYAMLのフロースタイルとしても正しいので、
+/// 一般的なYAMLパーサでもそのまま読める
+pub mod metadata {
+    use serde_json::{Map, Value};
+
+    const DELIMITER: &str = "---";
+
+    /// JSONオブジェクトをフロントマターにする
+    pub fn to_front_matter(value: &Map<String, Value>) -> String {
+        let mut front_matter = String::from(DELIMITER);
+        front_matter.push('\n');
+        for (key, value) in value {
+            front_matter.push_str(&format!("{}: {}\n", key, value));
+        }
+        front_matter.push_str(DELIMITER);
+        front_matter.push('\n');
+        front_matter
+    }
+
+    /// テキスト先頭のフロントマターを読み取り、その内容と残りの本文を返す
+    ///
+    /// JSONとして読めない値は文字列として扱う
+    pub fn parse_front_matter(text: &str) -> Option<(Map<String, Value>, &str)> {
+        let rest = text.strip_prefix(DELIMITER)?;
+        let rest = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))?;
+
+        let mut map = Map::new();
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            offset += line.len();
+            let line = line.trim_end();
+            if line == DELIMITER {
+                return Some((map, &rest[offset..]));
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(':')?;
+            let value = value.trim();
+            let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+            map.insert(key.trim().to_string(), value);
+        }
+
+        // 閉じる区切りがない
+        None
+    }
+
+    /// 本文を残したままフロントマターだけを差し替える
+    pub fn replace_front_matter(text: &str, value: &Map<String, Value>) -> String {
+        let body = parse_front_matter(text).map(|(_, body)| body).unwrap_or(text);
+        format!("{}{}", to_front_matter(value), body)
+    }
+}
+
+/// コードの類似度の計算
+pub mod similarity {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of<T: Hash>(value: T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// 空白だけの違いを無視するよう、各行の前後の空白を除き空行を取り除く
+    pub fn normalize_lines(code: &str) -> Vec<String> {
+        code.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    /// 行の並びのハッシュ
+    pub fn hash_lines(lines: &[String]) -> u64 {
+        hash_of(lines)
+    }
+
+    /// 識別子・数値のまとまりと記号1文字ずつに分けたトークン列
+    pub fn tokenize(code: &str) -> Vec<&str> {
+        let mut tokens = Vec::new();
+        let mut start = None;
+        for (i, c) in code.char_indices() {
+            if c.is_alphanumeric() || c == '_' {
+                start.get_or_insert(i);
+                continue;
+            }
+            if let Some(s) = start.take() {
+                tokens.push(&code[s..i]);
+            }
+            if !c.is_whitespace() {
+                tokens.push(&code[i..i + c.len_utf8()]);
+            }
+        }
+        if let Some(s) = start {
+            tokens.push(&code[s..]);
+        }
+        tokens
+    }
+
+    /// 連続するk個のトークンをまとめたシングルのハッシュ集合
+    pub fn shingles(code: &str, k: usize) -> HashSet<u64> {
+        let tokens = tokenize(code);
+        if tokens.len() < k {
+            return std::iter::once(hash_of(&tokens)).collect();
+        }
+        tokens.windows(k).map(hash_of).collect()
+    }
+
+    /// Jaccard係数
+    pub fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+        let union = a.union(b).count();
+        if union == 0 {
+            return 0.0;
+        }
+        a.intersection(b).count() as f64 / union as f64
+    }
+
+    /// MinHashシグネチャ。似た集合ほど同じ値が並ぶ
+    pub fn minhash(shingles: &HashSet<u64>, num_hashes: usize) -> Vec<u64> {
+        (0..num_hashes)
+            .map(|i| shingles.iter().map(|x| hash_of((i, x))).min().unwrap_or(u64::MAX))
+            .collect()
+    }
+}
+
+/// 解き直しのスケジューリング (SM-2方式)
+pub mod review {
+    use serde::{Deserialize, Serialize};
+
+    const DAY: i64 = 24 * 60 * 60;
+    const INITIAL_EASE: f64 = 2.5;
+    const MIN_EASE: f64 = 1.3;
+
+    /// 問題ごとの復習状態
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ReviewItem {
+        pub problem_id: String,
+        /// 易しさの係数。正解を重ねるほど間隔が伸びる
+        pub ease: f64,
+        /// 前回の間隔 (日)
+        pub interval_days: i64,
+        /// 連続して解けた回数
+        pub repetitions: u32,
+        /// 次に解き直す日時 (UNIX時間)
+        pub due: i64,
+        /// 最後に解き直した日時
+        pub last_reviewed: Option<i64>,
+    }
+
+    impl ReviewItem {
+        /// まだ一度も復習していない問題。難しい問題ほど早めに解き直す
+        pub fn new(problem_id: &str, solved_at: i64, difficulty: Option<f64>) -> Self {
+            let first_interval = match difficulty {
+                Some(d) if d >= 1600.0 => 7,
+                Some(d) if d >= 800.0 => 14,
+                _ => 30,
+            };
+            ReviewItem {
+                problem_id: problem_id.to_string(),
+                ease: INITIAL_EASE,
+                interval_days: 0,
+                repetitions: 0,
+                due: solved_at + first_interval * DAY,
+                last_reviewed: None,
+            }
+        }
+
+        /// 解き直した結果を記録する。qualityは0 (全く解けない) から5 (すぐ解けた)
+        pub fn record(&mut self, quality: u8, now: i64) {
+            let quality = quality.min(5);
+            if quality < 3 {
+                self.repetitions = 0;
+                self.interval_days = 1;
+            } else {
+                self.repetitions += 1;
+                self.interval_days = match self.repetitions {
+                    1 => 1,
+                    2 => 6,
+                    _ => (self.interval_days as f64 * self.ease).round() as i64,
+                };
+            }
+
+            let miss = (5 - quality) as f64;
+            self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+            self.due = now + self.interval_days * DAY;
+            self.last_reviewed = Some(now);
+        }
+
+        /// 期限を何日過ぎているか (負なら期限前)
+        pub fn overdue_days(&self, now: i64) -> f64 {
+            (now - self.due) as f64 / DAY as f64
+        }
+    }
+}
+
+/// 毎日ACを続けている日数 (ストリーク) の計算
+pub mod streak {
+    use chrono::NaiveDate;
+    use std::collections::BTreeSet;
+
+    /// ストリークの集計
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Streaks {
+        /// 今続いているストリーク (昨日までACしていれば今日まだでも途切れていない)
+        pub current: usize,
+        /// これまでで最長のストリーク
+        pub longest: usize,
+        /// 今日すでにACしているか
+        pub solved_today: bool,
+    }
+
+    /// ACした日付の集合からストリークを求める
+    pub fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> Streaks {
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for &day in days {
+            run = match previous {
+                Some(prev) if prev.succ_opt() == Some(day) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            previous = Some(day);
+        }
+
+        let solved_today = days.contains(&today);
+        let mut current = 0;
+        let mut day = if solved_today { Some(today) } else { today.pred_opt() };
+        while let Some(d) = day.filter(|d| days.contains(d)) {
+            current += 1;
+            day = d.pred_opt();
+        }
+
+        Streaks { current, longest, solved_today }
+    }
+}
+
+/// 解き直した問題の実行時間とコード長の推移
+pub mod trend {
+    use crate::submission::Submission;
+
+    /// アーカイブ済みの提出と、同じ問題のAC提出の履歴
+    #[derive(Debug, Clone)]
+    pub struct Trend {
+        /// アーカイブ済みの提出
+        pub archived: Submission,
+        /// 同じ問題のAC提出 (古い順)
+        pub history: Vec<Submission>,
+    }
+
+    impl Trend {
+        /// 2回以上ACした問題だけ推移を作る。履歴は取得した順によらず提出日時の順に並べる
+        pub fn new(archived: Submission, mut history: Vec<Submission>) -> Option<Self> {
+            if history.len() < 2 {
+                return None;
+            }
+            history.sort_by_key(|s| (s.epoch_second, s.id));
+            Some(Trend { archived, history })
+        }
+
+        /// アーカイブ済みより新しい最新のAC
+        pub fn latest(&self) -> Option<&Submission> {
+            self.history
+                .iter()
+                .max_by_key(|s| (s.epoch_second, s.id))
+                .filter(|s| s.epoch_second > self.archived.epoch_second)
+        }
+
+        pub fn is_slower(&self) -> bool {
+            self.latest().is_some_and(|s| s.execution_time > self.archived.execution_time)
+        }
+
+        pub fn is_longer(&self) -> bool {
+            self.latest().is_some_and(|s| s.length > self.archived.length)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn ac(id: i64, epoch_second: i64, execution_time: i64, length: i64) -> Submission {
+            serde_json::from_value(serde_json::json!({
+                "id": id, "epoch_second": epoch_second, "problem_id": "abc300_a", "contest_id": "abc300",
+                "user_id": "me", "language": "C++ 20 (gcc 12.2)", "point": 100.0, "length": length, "result": "AC",
+                "execution_time": execution_time,
+            })).unwrap()
+        }
+
+        #[test]
+        fn compares_the_newest_submission_regardless_of_fetch_order() {
+            let archived = ac(1, 100, 10, 500);
+            // APIから新しい順に返ってきても、比べるのは最も新しいAC
+            let trend = Trend::new(archived.clone(), vec![ac(3, 300, 20, 400), ac(2, 200, 5, 900), archived]).unwrap();
+            assert_eq!(trend.history.iter().map(|s| s.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+            assert_eq!(trend.latest().map(|s| s.id), Some(3));
+            assert!(trend.is_slower());
+            assert!(!trend.is_longer());
+        }
+
+        #[test]
+        fn ignores_submissions_not_newer_than_the_archived_one() {
+            let archived = ac(3, 300, 10, 500);
+            let trend = Trend::new(archived.clone(), vec![archived, ac(1, 100, 50, 900)]).unwrap();
+            assert!(trend.latest().is_none());
+            assert!(!trend.is_slower() && !trend.is_longer());
+            assert!(Trend::new(ac(1, 100, 10, 500), vec![ac(1, 100, 10, 500)]).is_none());
+        }
+    }
+}
+
+/// 成長の推移を描くSVGの折れ線グラフ
+pub mod chart {
+    /// 折れ線グラフの系列
+    #[derive(Debug, Clone)]
+    pub struct Series {
+        pub name: String,
+        /// (x, y) の点。xの昇順
+        pub points: Vec<(f64, f64)>,
+    }
+
+    const WIDTH: f64 = 640.0;
+    const HEIGHT: f64 = 320.0;
+    const MARGIN: f64 = 48.0;
+    const COLORS: [&str; 6] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b"];
+
+    /// 折れ線グラフのSVG。x軸の目盛りは`x_label`で文字列にする
+    pub fn line_chart_svg(title: &str, series: &[Series], x_label: impl Fn(f64) -> String) -> String {
+        let points = series.iter().flat_map(|s| s.points.iter());
+        let (mut x_min, mut x_max, mut y_min, mut y_max) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+        for &(x, y) in points {
+            x_min = x_min.min(x);
+            x_max = x_max.max(x);
+            y_min = y_min.min(y);
+            y_max = y_max.max(y);
+        }
+        if x_min > x_max {
+            (x_min, x_max, y_min, y_max) = (0.0, 1.0, 0.0, 1.0);
+        }
+        // 点が1つだけでも描けるように幅を持たせる
+        let x_span = if x_max > x_min { x_max - x_min } else { 1.0 };
+        let y_span = if y_max > y_min { y_max - y_min } else { 1.0 };
+        let to_x = |x: f64| MARGIN + (x - x_min) / x_span * (WIDTH - 2.0 * MARGIN);
+        let to_y = |y: f64| HEIGHT - MARGIN - (y - y_min) / y_span * (HEIGHT - 2.0 * MARGIN);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+            w = WIDTH,
+            h = HEIGHT
+        );
+        svg.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", WIDTH, HEIGHT));
+        svg.push_str(&format!("<text x=\"{}\" y=\"20\" text-anchor=\"middle\" font-size=\"14\">{}</text>\n", WIDTH / 2.0, escape(title)));
+        // 軸と目盛り
+        svg.push_str(&format!(
+            "<path d=\"M{l} {t} V{b} H{r}\" fill=\"none\" stroke=\"#888\"/>\n",
+            l = MARGIN,
+            t = MARGIN,
+            b = HEIGHT - MARGIN,
+            r = WIDTH - MARGIN
+        ));
+        svg.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n", MARGIN - 4.0, to_y(y_max) + 4.0, y_max));
+        svg.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n", MARGIN - 4.0, to_y(y_min) + 4.0, y_min));
+        svg.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", MARGIN, HEIGHT - MARGIN + 16.0, escape(&x_label(x_min))));
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n",
+            WIDTH - MARGIN,
+            HEIGHT - MARGIN + 16.0,
+            escape(&x_label(x_max))
+        ));
+
+        for (i, s) in series.iter().enumerate() {
+            let color = COLORS[i % COLORS.len()];
+            let coords: Vec<String> = s.points.iter().map(|&(x, y)| format!("{:.1},{:.1}", to_x(x), to_y(y))).collect();
+            svg.push_str(&format!("<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n", coords.join(" "), color));
+            for &(x, y) in &s.points {
+                svg.push_str(&format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"/>\n", to_x(x), to_y(y), color));
+            }
+            // 凡例
+            svg.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" fill=\"{}\" text-anchor=\"end\">{}</text>\n",
+                WIDTH - MARGIN,
+                MARGIN + 14.0 * i as f64,
+                color,
+                escape(&s.name)
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// 複数のグラフのSVGを縦に並べて1つのSVGにする
+    pub fn stack_svg(charts: &[String]) -> String {
+        let height = HEIGHT * charts.len() as f64;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = WIDTH,
+            h = height
+        );
+        for (i, chart) in charts.iter().enumerate() {
+            svg.push_str(&format!("<g transform=\"translate(0 {})\">\n{}</g>\n", HEIGHT * i as f64, chart));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    }
+}
+
+/// コンテストと問題の一覧の取得とキャッシュ
+pub mod contest {
+    use crate::archive::PROBLEM_MODELS_URL;
+    use crate::config::get_config_dir;
+    use crate::rate_limit::{is_offline, send_get, RateLimiter};
+    use crate::utils::is_file_exist;
+    use crate::{Error, Result, ATCODER_BASE_URL};
+    use reqwest::Client;
+    use scraper::{Html, Selector};
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    /// AtCoder Problemsのコンテスト一覧
+    pub const CONTESTS_URL: &str = "https://kenkoooo.com/atcoder/resources/contests.json";
+    /// コンテスト一覧のキャッシュのファイル名
+    pub const CONTESTS_CACHE: &str = "contests.json";
+    /// 問題モデルのキャッシュのファイル名。AtCoder Problemsの応答をそのまま保存する
+    pub const PROBLEM_MODELS_CACHE: &str = "problem-models.json";
+    /// キャッシュを使い回す期間。これより古ければ取り直す
+    const CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// AtCoder Problemsのコンテスト情報
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Contest {
+        pub id: String,
+        pub start_epoch_second: i64,
+        pub duration_second: i64,
+        /// コンテスト名 (例: "AtCoder Beginner Contest 300")
+        pub title: String,
+        /// レーティング変動の対象。"-"ならunrated
+        pub rate_change: String,
+    }
+
+    /// キャッシュを置くディレクトリ (~/.ac-garden/cache)
+    pub fn get_cache_dir() -> Result<PathBuf> {
+        Ok(get_config_dir()?.join("cache"))
+    }
+
+    /// キャッシュしたコンテスト一覧と、それが期限内かどうか。なければNone
+    pub fn cached_contests() -> Option<(Vec<Contest>, bool)> {
+        let path = get_cache_dir().ok()?.join(CONTESTS_CACHE);
+        if !is_file_exist(&path) {
+            return None;
+        }
+        let contests = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+        Some((contests, is_fresh(&path)))
+    }
+
+    /// キャッシュのファイルが期限内か
+    fn is_fresh(path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age < CACHE_MAX_AGE)
+    }
+
+    /// キャッシュを使う取得の結果
+    #[derive(Debug)]
+    pub enum Fetched<T> {
+        /// 取得した値か、期限内のキャッシュ (オフラインなら期限切れのキャッシュも含む)
+        Fresh(T),
+        /// 取り直しに失敗したので、古いキャッシュを使った
+        Stale { value: T, error: Error },
+    }
+
+    impl<T> Fetched<T> {
+        /// 古いキャッシュかどうかによらず値を取り出す
+        pub fn into_value(self) -> T {
+            match self {
+                Fetched::Fresh(value) | Fetched::Stale { value, .. } => value,
+            }
+        }
+
+        pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Fetched<U> {
+            match self {
+                Fetched::Fresh(value) => Fetched::Fresh(f(value)),
+                Fetched::Stale { value, error } => Fetched::Stale { value: f(value), error },
+            }
+        }
+    }
+
+    /// コンテスト一覧を取得してキャッシュする
+    ///
+    /// キャッシュが期限内で`refresh`でなければ通信しない。
+    /// 取得に失敗しても古いキャッシュがあれば、失敗の原因とともに[`Fetched::Stale`]として返す
+    pub async fn fetch_contests(http: &Client, refresh: bool, rate_limiter: &RateLimiter) -> Result<Fetched<Vec<Contest>>> {
+        let cached = cached_contests();
+        if let Some((contests, fresh)) = &cached {
+            // オフラインなら古くなったキャッシュでも使う
+            if (*fresh && !refresh) || is_offline() {
+                return Ok(Fetched::Fresh(contests.clone()));
+            }
+        }
+
+        let result: Result<Vec<Contest>> = async {
+            Ok(send_get(http, CONTESTS_URL, rate_limiter).await?.error_for_status()?.json().await?)
+        }
+        .await;
+        match (result, cached) {
+            (Ok(contests), _) => {
+                save_cache(&contests)?;
+                Ok(Fetched::Fresh(contests))
+            }
+            (Err(error), Some((contests, _))) => Ok(Fetched::Stale { value: contests, error }),
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    fn save_cache(contests: &[Contest]) -> Result<()> {
+        let dir = get_cache_dir()?;
+        fs::create_dir_all(&dir).map_err(|e| Error::storage(&dir, e))?;
+        let path = dir.join(CONTESTS_CACHE);
+        let json = serde_json::to_string(contests).map_err(|e| Error::storage(&path, e))?;
+        fs::write(&path, json).map_err(|e| Error::storage(&path, e))
+    }
+
+    /// AtCoder Problemsの問題モデル (問題ID → モデル) を取得してキャッシュする
+    ///
+    /// 呼び出し側が必要な項目だけを読めるよう、型は`T`で指定する。
+    /// キャッシュの期限や、取得に失敗したときとオフラインのときの扱いはコンテスト一覧と同じ
+    pub async fn fetch_problem_models<T: DeserializeOwned>(
+        http: &Client,
+        refresh: bool,
+        rate_limiter: &RateLimiter,
+    ) -> Result<Fetched<HashMap<String, T>>> {
+        let dir = get_cache_dir()?;
+        let path = dir.join(PROBLEM_MODELS_CACHE);
+        let decode = |json: &str| -> Result<HashMap<String, T>> {
+            serde_json::from_str(json).map_err(|e| Error::scrape_with_source(PROBLEM_MODELS_URL, "Failed to decode problem models", e))
+        };
+
+        let cached = fs::read_to_string(&path).ok();
+        if let Some(json) = &cached {
+            if (is_fresh(&path) && !refresh) || is_offline() {
+                return decode(json).map(Fetched::Fresh);
+            }
+        }
+
+        let result: Result<String> = async {
+            Ok(send_get(http, PROBLEM_MODELS_URL, rate_limiter).await?.error_for_status()?.text().await?)
+        }
+        .await;
+        match (result, cached) {
+            (Ok(json), _) => {
+                let models = decode(&json)?;
+                fs::create_dir_all(&dir).map_err(|e| Error::storage(&dir, e))?;
+                fs::write(&path, json).map_err(|e| Error::storage(&path, e))?;
+                Ok(Fetched::Fresh(models))
+            }
+            (Err(error), Some(json)) => Ok(Fetched::Stale { value: decode(&json)?, error }),
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    /// コンテストID → コンテスト名
+    pub fn contest_titles(contests: &[Contest]) -> HashMap<String, String> {
+        contests.iter().map(|c| (c.id.clone(), c.title.clone())).collect()
+    }
+
+    /// atcoder.jpのコンテスト一覧ページから、予定されているコンテストを取得する
+    ///
+    /// AtCoder Problemsの一覧には開催前のコンテストが載らないため
+    pub async fn fetch_upcoming_contests(http: &Client, rate_limiter: &RateLimiter) -> Result<Vec<Contest>> {
+        let url = format!("{}/contests/?lang=en", ATCODER_BASE_URL);
+        let html = send_get(http, &url, rate_limiter).await?.error_for_status()?.text().await?;
+        Ok(parse_upcoming_contests(&html))
+    }
+
+    /// コンテスト一覧ページの「予定されたコンテスト」の表を読む
+    pub fn parse_upcoming_contests(html: &str) -> Vec<Contest> {
+        let document = Html::parse_document(html);
+        let row_selector = Selector::parse("#contest-table-upcoming tbody tr").unwrap();
+        let cell_selector = Selector::parse("td").unwrap();
+        let time_selector = Selector::parse("time").unwrap();
+        let link_selector = Selector::parse("a[href^='/contests/']").unwrap();
+
+        let mut contests = Vec::new();
+        for row in document.select(&row_selector) {
+            let cells: Vec<_> = row.select(&cell_selector).collect();
+            let text = |i: usize| cells.get(i).map(|cell| cell.text().collect::<String>().trim().to_string()).unwrap_or_default();
+            let Some(start) = row.select(&time_selector).next()
+                .map(|time| time.text().collect::<String>())
+                .and_then(|time| chrono::DateTime::parse_from_str(time.trim(), "%Y-%m-%d %H:%M:%S%z").ok())
+            else {
+                continue;
+            };
+            let Some(link) = row.select(&link_selector).next() else {
+                continue;
+            };
+            let Some(id) = link.value().attr("href").and_then(|href| href.trim_start_matches("/contests/").split(['/', '?']).next()) else {
+                continue;
+            };
+            // 時間は"01:40"や"240:00"のような時:分
+            let duration = text(2);
+            let (hours, minutes) = duration.split_once(':').unwrap_or(("0", "0"));
+            let duration_second = hours.parse::<i64>().unwrap_or(0) * 3600 + minutes.parse::<i64>().unwrap_or(0) * 60;
+
+            contests.push(Contest {
+                id: id.to_string(),
+                start_epoch_second: start.timestamp(),
+                duration_second,
+                title: link.text().collect::<String>().trim().to_string(),
+                rate_change: text(3),
+            });
+        }
+        contests
+    }
+
+    /// コンテストをiCalendar形式のカレンダーにする
+    pub fn contests_to_ics(contests: &[Contest]) -> String {
+        let format = |epoch_second: i64| {
+            chrono::DateTime::from_timestamp(epoch_second, 0)
+                .map(|dt| dt.format("%Y%m%dT%H%M%SZ").to_string())
+                .unwrap_or_default()
+        };
+        let escape = |text: &str| {
+            text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+        };
+        let now = format(chrono::Utc::now().timestamp());
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            format!("PRODID:-//{}//contests//EN", crate::APP_NAME),
+        ];
+        for contest in contests {
+            let url = format!("{}/contests/{}", ATCODER_BASE_URL, contest.id);
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}@atcoder.jp", contest.id),
+                format!("DTSTAMP:{}", now),
+                format!("DTSTART:{}", format(contest.start_epoch_second)),
+                format!("DTEND:{}", format(contest.start_epoch_second + contest.duration_second)),
+                format!("SUMMARY:{}", escape(&contest.title)),
+                format!("URL:{}", url),
+                format!("DESCRIPTION:{}", escape(&format!("Rated: {}\n{}", contest.rate_change, url))),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+        lines.push("END:VCALENDAR".to_string());
+        lines.iter().map(|line| format!("{}\r\n", line)).collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parses_only_upcoming_contests_from_the_contest_list() {
+            // 開催中・終了したコンテストと、開始時刻のない行は含めない
+            let contests = parse_upcoming_contests(include_str!("../tests/fixtures/contests.html"));
+            let summary: Vec<_> = contests.iter()
+                .map(|c| (c.id.as_str(), c.start_epoch_second, c.duration_second, c.title.as_str(), c.rate_change.as_str()))
+                .collect();
+            assert_eq!(summary, vec![
+                ("abc354", 1715428800, 100 * 60, "AtCoder Beginner Contest 354", "- 1999"),
+                ("ahc034", 1716616800, 240 * 3600, "AtCoder Heuristic Contest 034", "-"),
+            ]);
+        }
+    }
+}
+
+/// 公式解説の取り出し
+pub mod editorial {
+    use crate::ATCODER_BASE_URL;
+    use scraper::{ElementRef, Html, Node, Selector};
+
+    /// 解説ページを変換して保存するファイル名
+    pub const EDITORIAL_MD: &str = "EDITORIAL.md";
+    /// PDFの解説を保存するファイル名
+    pub const EDITORIAL_PDF: &str = "editorial.pdf";
+
+    /// 問題の解説一覧ページから公式解説のURLを探す
+    ///
+    /// 公式解説は一覧の先頭にある。個別の解説ページがなければ、古いコンテストのPDFを使う
+    pub fn find_official_editorial(html: &str, contest_id: &str) -> Option<String> {
+        let document = Html::parse_document(html);
+        let selector = Selector::parse("#main-container a[href]").unwrap();
+        let prefix = format!("/contests/{}/editorial/", contest_id);
+        let hrefs: Vec<&str> = document.select(&selector)
+            .filter_map(|a| a.value().attr("href"))
+            .collect();
+        hrefs.iter()
+            .find(|href| href.starts_with(&prefix))
+            .or_else(|| hrefs.iter().find(|href| href.ends_with(".pdf")))
+            .map(|href| absolute_url(href))
+    }
+
+    /// 解説ページの本文をMarkdownにする
+    pub fn editorial_to_markdown(html: &str, url: &str) -> Option<String> {
+        let document = Html::parse_document(html);
+        let selector = Selector::parse("#main-container .col-sm-12").unwrap();
+        // 本文はいちばん長い欄にある
+        let body = document.select(&selector)
+            .max_by_key(|element| element.text().map(str::len).sum::<usize>())?;
+        let markdown = html_to_markdown(body);
+        if markdown.is_empty() {
+            return None;
+        }
+        Some(format!("<!-- {} -->\n\n{}\n", url, markdown))
+    }
+
+    fn absolute_url(href: &str) -> String {
+        if href.starts_with('/') {
+            format!("{}{}", ATCODER_BASE_URL, href)
+        } else {
+            href.to_string()
+        }
+    }
+
+    /// HTMLの要素をMarkdownに変換する。数式はKaTeXの記法のまま残る
+    pub fn html_to_markdown(element: ElementRef) -> String {
+        let mut out = String::new();
+        write_children(element, &mut out);
+
+        // 行末の空白と3行以上の空行をまとめる
+        let mut result = String::new();
+        let mut blank_lines = 0;
+        for line in out.lines().map(str::trim_end) {
+            if line.trim().is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            if !result.is_empty() {
+                result.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+            }
+            blank_lines = 0;
+            result.push_str(line);
+        }
+        result
+    }
+
+    fn write_children(element: ElementRef, out: &mut String) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => {
+                    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if text.starts_with(char::is_whitespace) && !out.ends_with([' ', '\n']) {
+                        out.push(' ');
+                    }
+                    out.push_str(&collapsed);
+                    if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+                        out.push(' ');
+                    }
+                }
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        write_element(child, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// ブロックの前後は空行にする
+    fn block(out: &mut String) {
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+    }
+
+    fn inline(element: ElementRef) -> String {
+        let mut out = String::new();
+        write_children(element, &mut out);
+        out.trim().to_string()
+    }
+
+    fn write_element(element: ElementRef, out: &mut String) {
+        let name = element.value().name();
+        match name {
+            "script" | "style" | "noscript" | "button" | "form" => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                block(out);
+                let level = name[1..].parse().unwrap_or(1);
+                out.push_str(&format!("{} {}", "#".repeat(level), inline(element)));
+                block(out);
+            }
+            "p" | "div" | "section" | "article" | "blockquote" => {
+                block(out);
+                write_children(element, out);
+                block(out);
+            }
+            "br" => out.push('\n'),
+            "hr" => {
+                block(out);
+                out.push_str("---");
+                block(out);
+            }
+            "pre" => {
+                block(out);
+                let code = element.text().collect::<String>();
+                out.push_str(&format!("```\n{}\n```", code.trim_end()));
+                block(out);
+            }
+            "code" => out.push_str(&format!("`{}`", element.text().collect::<String>())),
+            "strong" | "b" => out.push_str(&format!("**{}**", inline(element))),
+            "em" | "i" => out.push_str(&format!("*{}*", inline(element))),
+            "a" => match element.value().attr("href") {
+                Some(href) => out.push_str(&format!("[{}]({})", inline(element), absolute_url(href))),
+                None => write_children(element, out),
+            },
+            "img" => {
+                if let Some(src) = element.value().attr("src") {
+                    let alt = element.value().attr("alt").unwrap_or_default();
+                    out.push_str(&format!("![{}]({})", alt, absolute_url(src)));
+                }
+            }
+            "ul" | "ol" => {
+                block(out);
+                let items = element.children().filter_map(ElementRef::wrap).filter(|li| li.value().name() == "li");
+                for (i, item) in items.enumerate() {
+                    let marker = if name == "ol" { format!("{}.", i + 1) } else { "-".to_string() };
+                    out.push_str(&format!("{} {}\n", marker, inline(item)));
+                }
+                block(out);
+            }
+            "table" => {
+                block(out);
+                let row_selector = Selector::parse("tr").unwrap();
+                for (i, row) in element.select(&row_selector).enumerate() {
+                    let cells: Vec<String> = row.children()
+                        .filter_map(ElementRef::wrap)
+                        .map(|cell| inline(cell).replace('|', "\\|"))
+                        .collect();
+                    out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                    // 1行目を見出しにする
+                    if i == 0 {
+                        out.push_str(&format!("|{}\n", " --- |".repeat(cells.len())));
+                    }
+                }
+                block(out);
+            }
+            _ => write_children(element, out),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn finds_the_official_editorial_in_the_main_container() {
+            assert_eq!(
+                find_official_editorial(include_str!("../tests/fixtures/editorial_list.html"), "abc300").as_deref(),
+                Some("https://atcoder.jp/contests/abc300/editorial/6213")
+            );
+            // 個別の解説ページがない古いコンテストはPDF
+            let html = include_str!("../tests/fixtures/editorial_pdf_list.html");
+            assert_eq!(find_official_editorial(html, "arc001").as_deref(), Some("https://img.atcoder.jp/arc001/editorial.pdf"));
+            assert_eq!(find_official_editorial(include_str!("../tests/fixtures/tasks.html"), "abc300"), None);
+        }
+
+        #[test]
+        fn converts_the_editorial_body_of_a_signed_in_page() {
+            let url = "https://atcoder.jp/contests/abc300/editorial/6213";
+            let markdown = editorial_to_markdown(include_str!("../tests/fixtures/editorial.html"), url).unwrap();
+            // ナビゲーションや投票のフォーム、スクリプトは含めない
+            assert_eq!(markdown, concat!(
+                "<!-- https://atcoder.jp/contests/abc300/editorial/6213 -->\n\n",
+                "## Overall Editorial\n\n",
+                "Find the index \\(i\\) such that \\(C_i = A + B\\).\n\n",
+                "- Read `N`, `A` and `B`.\n",
+                "- Compare each **choice** with the sum.\n\n",
+                "```\nfor i in range(n):\n    if c[i] == a + b:\n        print(i + 1)\n```\n\n",
+                "See also [the statement](https://atcoder.jp/contests/abc300/tasks/abc300_a).\n",
+            ));
+        }
+    }
+}
+
+/// AtCoderの言語と拡張子の対応表
+///
+/// 表は`data/languages.toml`に置き、設定ディレクトリの`languages.toml`で追加・上書きできる
+pub mod languages {
+    use crate::config::get_config_dir;
+    use crate::{Error, Result};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+    use std::path::Path;
+    use std::sync::OnceLock;
+
+    /// 組み込みの対応表
+    const BUILTIN: &str = include_str!("../data/languages.toml");
+    /// 対応表の形式のバージョン
+    pub const LANGUAGE_TABLE_VERSION: u32 = 1;
+    /// 設定ディレクトリに置く、ユーザーの対応表のファイル名
+    pub const LANGUAGES_FILE: &str = "languages.toml";
+
+    /// 提出の言語
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+    pub struct Language {
+        /// 提出の言語ID (2023年の言語アップデート以降の言語だけにある)
+        #[serde(default)]
+        pub id: Option<u32>,
+        /// 正式な言語名 (例: "Rust (rustc 1.70.0)")
+        pub name: String,
+        /// ソースファイルの拡張子 (ドットなし)
+        pub extension: String,
+    }
+
+    /// 言語と拡張子の対応表
+    #[derive(Debug, Clone, Default, Deserialize)]
+    pub struct LanguageTable {
+        pub version: u32,
+        /// 言語名の括弧より前の部分 → 拡張子
+        #[serde(default)]
+        pub names: BTreeMap<String, String>,
+        /// 改名された言語の名前 → 正規の言語名 (例: "Python3" → "Python")
+        #[serde(default)]
+        pub aliases: BTreeMap<String, String>,
+        /// 提出ページのエディタ (Ace) のモード名 → 言語名
+        #[serde(default)]
+        pub ace_modes: BTreeMap<String, String>,
+        /// 正規の言語名 → 言語ごとのディレクトリの名前
+        #[serde(default)]
+        pub dir_names: BTreeMap<String, String>,
+        #[serde(default)]
+        pub languages: Vec<Language>,
+    }
+
+    /// 言語名の括弧より前の部分 ("C++ 20 (gcc 12.2)" → "C++ 20")
+    fn base_name(language: &str) -> &str {
+        language.split('(').next().unwrap_or_default().trim()
+    }
+
+    /// 末尾のバージョン番号を落とした名前 ("C++ 20" → "C++", "C# 11.0" → "C#")
+    fn without_version(name: &str) -> &str {
+        let mut name = name.trim_end();
+        while let Some((head, last)) = name.rsplit_once(char::is_whitespace) {
+            if !last.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                break;
+            }
+            name = head.trim_end();
+        }
+        name
+    }
+
+    impl LanguageTable {
+        /// TOMLの対応表を読み込む
+        pub fn parse(content: &str) -> Result<LanguageTable> {
+            let table: LanguageTable = toml::from_str(content)
+                .map_err(|e| Error::config_with_source("Failed to parse the language table", e))?;
+            if table.version > LANGUAGE_TABLE_VERSION {
+                return Err(Error::config(format!("Unsupported language table version {}", table.version)));
+            }
+            Ok(table)
+        }
+
+        /// 組み込みの対応表
+        pub fn builtin() -> LanguageTable {
+            LanguageTable::parse(BUILTIN).expect("the builtin language table is valid")
+        }
+
+        /// ファイルの対応表を読み込む
+        pub fn load(path: &Path) -> Result<LanguageTable> {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| Error::config_with_source(format!("Failed to read {}", path.display()), e))?;
+            LanguageTable::parse(&content)
+        }
+
+        /// 別の対応表の項目で追加・上書きする
+        pub fn merge(&mut self, other: LanguageTable) {
+            self.names.extend(other.names);
+            self.aliases.extend(other.aliases);
+            self.ace_modes.extend(other.ace_modes);
+            self.dir_names.extend(other.dir_names);
+            for language in other.languages {
+                self.languages.retain(|l| l.name != language.name && (language.id.is_none() || l.id != language.id));
+                self.languages.push(language);
+            }
+        }
+
+        /// 言語IDから引く
+        pub fn by_id(&self, id: u32) -> Option<&Language> {
+            self.languages.iter().find(|language| language.id == Some(id))
+        }
+
+        /// 言語名に対応する拡張子
+        ///
+        /// 正式な言語名で見つからなければ、括弧より前の部分で`names`と言語の一覧の順に探す
+        pub fn extension(&self, language: &str) -> Option<&str> {
+            let language = language.trim();
+            if let Some(found) = self.languages.iter().find(|l| l.name == language) {
+                return Some(&found.extension);
+            }
+            let base = base_name(language);
+            self.names.get(base)
+                .or_else(|| self.languages.iter().find(|l| base_name(&l.name) == base).map(|l| &l.extension))
+                .map(String::as_str)
+        }
+
+        /// エディタのモード名 (例: "ace/mode/c_cpp") の言語の拡張子
+        pub fn ace_mode_extension(&self, mode: &str) -> Option<&str> {
+            let language = self.ace_modes.get(mode.trim_start_matches("ace/mode/"))?;
+            self.extension(language)
+        }
+
+        /// 言語ごとのディレクトリの名前。`dir_names`になければ正規の言語名を小文字にして空白を`-`にする
+        pub fn dir_name(&self, language: &str) -> String {
+            let name = self.canonical_name(language);
+            self.dir_names.get(&name)
+                .cloned()
+                .unwrap_or_else(|| name.split_whitespace().collect::<Vec<_>>().join("-").to_lowercase())
+        }
+
+        /// 版や改名の違いをまとめた正規の言語名
+        ///
+        /// 括弧より前の部分、末尾のバージョン番号を落とした部分の順に`aliases`を引き、なければ後者をそのまま使う。
+        /// "Python3 (3.4.3)"も"Python (CPython 3.11.4)"も"Python"になる
+        pub fn canonical_name(&self, language: &str) -> String {
+            let base = base_name(language);
+            let name = without_version(base);
+            self.aliases.get(base)
+                .or_else(|| self.aliases.get(name))
+                .cloned()
+                .unwrap_or_else(|| name.to_string())
+        }
+    }
+
+    /// [`table`]で引いた正規の言語名。言語ごとのディレクトリや集計、フォーマッタの選択に使う
+    pub fn canonical_language(language: &str) -> String {
+        table().canonical_name(language)
+    }
+
+    static TABLE: OnceLock<LanguageTable> = OnceLock::new();
+
+    /// 組み込みの対応表に、設定ディレクトリの`languages.toml`を重ねたもの。最初に呼ばれたときに読み込む
+    pub fn table() -> &'static LanguageTable {
+        TABLE.get_or_init(|| {
+            let mut table = LanguageTable::builtin();
+            let Ok(path) = get_config_dir().map(|dir| dir.join(LANGUAGES_FILE)) else {
+                return table;
+            };
+            if path.is_file() {
+                match LanguageTable::load(&path) {
+                    Ok(user) => table.merge(user),
+                    Err(e) => crate::warning::warn(&crate::tr!("Ignoring {}: {:#}", path.display(), anyhow::Error::from(e))),
+                }
+            }
+            table
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// AtCoderの言語一覧 (言語IDと正式な言語名)
+        const OFFICIAL: &str = include_str!("../tests/fixtures/languages.tsv");
+
+        #[test]
+        fn covers_official_languages() {
+            let table = LanguageTable::builtin();
+            for line in OFFICIAL.lines() {
+                let (id, name) = line.split_once('\t').unwrap();
+                let language = table.by_id(id.parse().unwrap()).unwrap_or_else(|| panic!("missing language id {}", id));
+                assert_eq!(language.name, name);
+                assert!(table.extension(name).is_some(), "missing extension for {}", name);
+            }
+        }
+
+        #[test]
+        fn ace_modes_map_to_the_extensions_of_their_languages() {
+            let table = LanguageTable::builtin();
+            for (mode, language) in &table.ace_modes {
+                let extension = table.extension(language);
+                assert!(extension.is_some(), "no extension for {} ({})", language, mode);
+                assert_eq!(table.ace_mode_extension(&format!("ace/mode/{}", mode)), extension);
+            }
+            assert_eq!(table.ace_mode_extension("ace/mode/prolog"), Some("pro"));
+            assert_eq!(table.ace_mode_extension("ace/mode/c_cpp"), Some("cpp"));
+            assert_eq!(table.ace_mode_extension("ace/mode/text"), None);
+        }
+
+        #[test]
+        fn resolves_legacy_and_unlisted_versions() {
+            let table = LanguageTable::builtin();
+            assert_eq!(table.extension("C++14 (GCC 5.4.1)"), Some("cpp"));
+            assert_eq!(table.extension("PyPy3 (2.4.0)"), Some("py"));
+            assert_eq!(table.extension("C++ 20 (gcc 12.1)"), Some("cpp"));
+            assert_eq!(table.extension("Rust"), Some("rs"));
+            assert_eq!(table.extension("Unknown Language (1.0)"), None);
+        }
+
+        #[test]
+        fn canonical_names_merge_renamed_languages() {
+            let table = LanguageTable::builtin();
+            assert_eq!(table.canonical_name("Python3 (3.4.3)"), "Python");
+            assert_eq!(table.canonical_name("Python (3.8.2)"), "Python");
+            assert_eq!(table.canonical_name("Python (CPython 3.11.4)"), "Python");
+            assert_eq!(table.canonical_name("C++14 (GCC 5.4.1)"), "C++");
+            assert_eq!(table.canonical_name("C++ 20 (gcc 12.2)"), "C++");
+            assert_eq!(table.canonical_name("C# 11.0 AOT (.NET 7.0.7)"), "C#");
+            assert_eq!(table.canonical_name("Common Lisp (SBCL 2.3.6)"), "Common Lisp");
+
+            let mut table = table;
+            table.merge(LanguageTable::parse("version = 1\n[aliases]\n\"Cython\" = \"Python\"").unwrap());
+            assert_eq!(table.canonical_name("Cython (0.29.34)"), "Python");
+        }
+
+        #[test]
+        fn user_table_overrides_builtin() {
+            let mut table = LanguageTable::builtin();
+            let user = LanguageTable::parse(r#"
+                version = 1
+                [names]
+                "Octave" = "octave"
+
+                [[languages]]
+                id = 5044
+                name = "Prolog (SWI-Prolog 9.0.4)"
+                extension = "prolog"
+            "#).unwrap();
+            table.merge(user);
+            assert_eq!(table.extension("Octave (4.0.2)"), Some("octave"));
+            assert_eq!(table.by_id(5044).map(|l| l.extension.as_str()), Some("prolog"));
+            assert!(LanguageTable::parse("version = 2").is_err());
+        }
+    }
+}
+
+/// アーカイブを公開するリモートリポジトリの作成とpush
+pub mod remote {
+    use crate::config::Config;
+    use crate::credentials::{has_secret, load_secret};
+    use crate::rate_limit::send;
+    use crate::{Error, Result};
+    use git2::Repository;
+    use reqwest::{Client, RequestBuilder, StatusCode};
+    use serde::Deserialize;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    pub const GITHUB_API_URL: &str = "https://api.github.com";
+    /// GitHubのトークンを渡す環境変数。なければ`credentials set github_token`で保存した値を使う
+    pub const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+    /// HTTPSでGitHubにトークンで認証するときのユーザー名
+    pub const GITHUB_USERNAME: &str = "x-access-token";
+    /// pushするgitにトークンを渡す環境変数。コマンドラインに載せないようにする
+    const GIT_TOKEN_ENV: &str = "AC_GARDEN_GIT_TOKEN";
+
+    /// 設定がなくてもトークンを探すホスト。(ホスト名, 環境変数, 保存した値の名前)
+    const KNOWN_HOSTS: [(&str, &str, &str); 2] = [
+        ("github.com", GITHUB_TOKEN_ENV, "github_token"),
+        ("gitlab.com", "GITLAB_TOKEN", "gitlab_token"),
+    ];
+
+    /// pushに使う資格情報
+    #[derive(Debug, Clone, Default)]
+    pub struct PushAuth {
+        /// HTTPSのユーザー名
+        pub username: String,
+        /// HTTPSのトークン。なければgitの資格情報ヘルパーに任せる
+        pub token: Option<String>,
+        /// SSHの秘密鍵。なければsshの設定とssh-agentに任せる
+        pub ssh_key: Option<PathBuf>,
+    }
+
+    /// リモートのURLのホスト名。`git@host:path`の形にも対応する
+    pub fn remote_host(url: &str) -> Option<String> {
+        if let Ok(parsed) = url::Url::parse(url) {
+            return parsed.host_str().map(str::to_lowercase);
+        }
+        // scp形式 ([user@]host:path)
+        let (authority, _) = url.split_once(':')?;
+        let host = authority.rsplit('@').next()?;
+        (!host.is_empty() && !host.contains('/')).then(|| host.to_lowercase())
+    }
+
+    /// リモートのURLと設定から、pushに使う資格情報を決める
+    ///
+    /// HTTPSでは設定の`token_env`、なければGitHubとGitLabの既定の環境変数と保存した値の順にトークンを探す。
+    /// パスフレーズは保存した値を暗号化ファイルから読むときだけ求める
+    pub fn push_auth(config: &Config, repo_path: &Path, remote: &str, passphrase: impl FnOnce() -> Result<String>) -> Result<PushAuth> {
+        let repo = Repository::open(repo_path)?;
+        let remote = repo.find_remote(remote)?;
+        let url = remote.pushurl().or(remote.url()).unwrap_or_default().to_string();
+        let Some(host) = remote_host(&url) else {
+            return Ok(PushAuth::default());
+        };
+        let settings = config.remotes.get(&host).cloned().unwrap_or_default();
+        let known = KNOWN_HOSTS.iter().find(|(known_host, _, _)| *known_host == host);
+
+        let username = settings.username.clone().unwrap_or_else(|| {
+            if host == "github.com" { GITHUB_USERNAME } else { "oauth2" }.to_string()
+        });
+        let ssh_key = settings.ssh_key.as_ref().map(PathBuf::from);
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Ok(PushAuth { username, token: None, ssh_key });
+        }
+
+        let env_token = |name: &str| std::env::var(name).ok().map(|token| token.trim().to_string()).filter(|token| !token.is_empty());
+        let mut token = settings.token_env.as_deref().and_then(env_token);
+        if token.is_none() {
+            if let Some((_, env, secret)) = known {
+                token = env_token(env);
+                if token.is_none() && has_secret(secret)? {
+                    token = Some(load_secret(secret, passphrase)?);
+                }
+            }
+        }
+        Ok(PushAuth { username, token, ssh_key })
+    }
+
+    /// GitHubのリポジトリ
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct GithubRepository {
+        /// `owner/name`
+        pub full_name: String,
+        pub html_url: String,
+        pub clone_url: String,
+        pub ssh_url: String,
+    }
+
+    fn github_request(builder: RequestBuilder, token: &str) -> RequestBuilder {
+        builder
+            .bearer_auth(token)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+    }
+
+    /// 失敗した応答をAPIのメッセージ付きのエラーにする
+    async fn github_error(response: reqwest::Response) -> Error {
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        let mut message = body["message"].as_str().unwrap_or("unexpected response").to_string();
+        if let Some(detail) = body["errors"].get(0).and_then(|error| error["message"].as_str()) {
+            message = format!("{} ({})", message, detail);
+        }
+        if status == StatusCode::UNAUTHORIZED {
+            message = format!("{} (check the token)", message);
+        }
+        Error::Hosting { service: "GitHub".to_string(), message: format!("HTTP {}: {}", status.as_u16(), message) }
+    }
+
+    /// トークンの持ち主のアカウントにリポジトリを作る
+    pub async fn create_github_repository(http: &Client, token: &str, name: &str, private: bool) -> Result<GithubRepository> {
+        let body = serde_json::json!({
+            "name": name,
+            "private": private,
+            "description": "AtCoder AC submissions archived by ac-garden",
+        });
+        let response = send(github_request(http.post(format!("{}/user/repos", GITHUB_API_URL)), token).json(&body)).await?;
+        if !response.status().is_success() {
+            return Err(github_error(response).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// リポジトリの既定のブランチを変える。ブランチはpush済みである必要がある
+    pub async fn set_github_default_branch(http: &Client, token: &str, full_name: &str, branch: &str) -> Result<()> {
+        let body = serde_json::json!({ "default_branch": branch });
+        let response = send(github_request(http.patch(format!("{}/repos/{}", GITHUB_API_URL, full_name)), token).json(&body)).await?;
+        if !response.status().is_success() {
+            return Err(github_error(response).await);
+        }
+        Ok(())
+    }
+
+    /// リモートにpushする
+    ///
+    /// ブランチを指定すると、ローカルのブランチの名前は変えずにHEADをリモートのそのブランチとしてpushし、
+    /// HEADがブランチにあれば上流にも設定する
+    pub fn push(repo_path: &Path, remote: &str, branch: Option<&str>, auth: &PushAuth) -> Result<()> {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(repo_path);
+        if let Some(token) = &auth.token {
+            // 保存済みの資格情報より先に、環境変数のトークンを返すヘルパーを使う
+            command
+                .args(["-c", "credential.helper="])
+                .args(["-c", &format!("credential.helper=!{}", credential_helper(&auth.username))])
+                .env(GIT_TOKEN_ENV, token);
+        }
+        if let Some(key) = &auth.ssh_key {
+            command.env("GIT_SSH_COMMAND", ssh_command(key));
+        }
+        command.args(["push", "--quiet"]);
+        if let Some(branch) = branch {
+            if !Repository::open(repo_path)?.head_detached()? {
+                command.arg("--set-upstream");
+            }
+            command.args([remote, &format!("HEAD:refs/heads/{}", branch)]);
+        } else {
+            command.arg(remote);
+        }
+        let output = command.output().map_err(|e| Error::command("git push", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(git2::Error::from_str(&format!("`git push` failed: {}", stderr.trim())).into());
+        }
+        Ok(())
+    }
+
+    /// シェルに1つの引数として渡すために`'`で囲む
+    fn shell_quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+
+    /// `$1`が`get`のときに、ユーザー名と環境変数のトークンを返す資格情報ヘルパーのシェル関数
+    fn credential_helper(username: &str) -> String {
+        format!(
+            "f() {{ test \"$1\" = get && echo {} && echo \"password=${}\"; }}; f",
+            shell_quote(&format!("username={}", username)),
+            GIT_TOKEN_ENV
+        )
+    }
+
+    /// 鍵を指定するsshのコマンド。パスフレーズ付きの鍵はssh-agentから使い、IdentitiesOnlyでほかの鍵を試さないようにする
+    fn ssh_command(key: &Path) -> String {
+        format!("ssh -i {} -o IdentitiesOnly=yes", shell_quote(&key.to_string_lossy()))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn quotes_the_username_and_key_path_for_the_shell() {
+            let username = "o'neil \"$(touch x)\"";
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(format!("{} get", credential_helper(username)))
+                .env(GIT_TOKEN_ENV, "t0ken")
+                .output()
+                .unwrap();
+            let expected = format!("username={}\npassword=t0ken\n", username);
+            assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+            assert_eq!(
+                ssh_command(Path::new("/home/o'neil/.ssh/id_ed25519")),
+                "ssh -i '/home/o'\\''neil/.ssh/id_ed25519' -o IdentitiesOnly=yes"
+            );
+        }
+
+        #[test]
+        fn pushes_head_as_the_branch_without_renaming_it() {
+            let dir = std::env::temp_dir().join(format!("ac-garden-push-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            let remote = Repository::init_bare(dir.join("remote.git")).unwrap();
+            let repo = Repository::init(dir.join("local")).unwrap();
+            let signature = git2::Signature::now("me", "me@example.com").unwrap();
+            let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+            repo.commit(Some("refs/heads/work"), &signature, &signature, "init", &tree, &[]).unwrap();
+            repo.set_head("refs/heads/work").unwrap();
+            repo.remote("origin", &dir.join("remote.git").to_string_lossy()).unwrap();
+
+            push(&dir.join("local"), "origin", Some("main"), &PushAuth::default()).unwrap();
+            assert!(remote.find_reference("refs/heads/main").is_ok());
+            assert_eq!(repo.head().unwrap().shorthand(), Some("work"));
+            let upstream = repo.find_branch("work", git2::BranchType::Local).unwrap().upstream().unwrap();
+            assert_eq!(upstream.name().unwrap(), Some("origin/main"));
+
+            // 切り離されたHEADでもpushできる
+            let commit = repo.head().unwrap().peel_to_commit().unwrap();
+            repo.set_head_detached(commit.id()).unwrap();
+            push(&dir.join("local"), "origin", Some("archive"), &PushAuth::default()).unwrap();
+            assert!(remote.find_reference("refs/heads/archive").is_ok());
+        }
+
+        #[test]
+        fn finds_the_host_of_remote_urls() {
+            assert_eq!(remote_host("https://gitlab.com/me/garden.git").as_deref(), Some("gitlab.com"));
+            assert_eq!(remote_host("ssh://git@git.example.com:2222/me/garden.git").as_deref(), Some("git.example.com"));
+            assert_eq!(remote_host("git@GitHub.com:me/garden.git").as_deref(), Some("github.com"));
+            assert_eq!(remote_host("/srv/git/garden.git"), None);
+        }
+    }
+}
+
+/// CLIのメッセージの翻訳
+///
+/// メッセージは英語で書き、表示するときに[`tr!`](crate::tr)で訳す。
+/// 訳は英語のメッセージをキーにした表で持ち、表にないものは英語のまま表示する
+pub mod i18n {
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use std::fmt::{self, Write};
+    use std::sync::atomic::{AtomicU8, Ordering};
+    use std::sync::OnceLock;
+
+    /// 表示言語
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Lang {
+        En,
+        Ja,
+    }
+
+    /// 設定で選んだ言語。0なら環境変数から決める
+    static SELECTED: AtomicU8 = AtomicU8::new(0);
+
+    /// 表示言語を設定で上書きする
+    pub fn set_lang(lang: Lang) {
+        SELECTED.store(lang as u8 + 1, Ordering::Relaxed);
+    }
+
+    /// `LC_ALL`、`LC_MESSAGES`、`LANG`の順に見て言語を決める
+    pub fn detect_lang() -> Lang {
+        for key in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+            match std::env::var(key) {
+                Ok(value) if !value.is_empty() => {
+                    return if value.starts_with("ja") { Lang::Ja } else { Lang::En };
+                },
+                _ => {},
+            }
+        }
+        Lang::En
+    }
+
+    /// 今の表示言語
+    pub fn lang() -> Lang {
+        match SELECTED.load(Ordering::Relaxed) {
+            1 => Lang::En,
+            2 => Lang::Ja,
+            _ => detect_lang(),
+        }
+    }
+
+    /// 英語のメッセージ → 日本語
+    ///
+    /// `{}`は任意の文字列に一致し、訳では`{0}`、`{1}`...で順に参照する。
+    /// 引数も訳すので、エラーの原因のように入れ子になったメッセージも訳せる
+    const JA: &[(&str, &str)] = &[
+        // エラー
+        ("Error", "エラー"),
+        ("Caused by:", "原因:"),
+        ("config error: {}", "設定エラー: {0}"),
+        ("network error", "通信エラー"),
+        ("AtCoder is under maintenance", "AtCoderはメンテナンス中です"),
+        ("AtCoder is under maintenance, retry after {}", "AtCoderはメンテナンス中です。{0}後に再試行してください"),
+        ("{} seconds", "{0}秒"),
+        ("failed to scrape {}: {}", "{0}を解析できません: {1}"),
+        ("git error", "Gitのエラー"),
+        ("storage error at {}", "{0}を読み書きできません"),
+        ("failed to run `{}`", "`{0}`を実行できません"),
+        ("{} hook failed, aborting", "{0}フックが失敗したため中止します"),
+        ("credential error for {}: {}", "{0}の資格情報のエラー: {1}"),
+        ("failed to start the async runtime", "非同期ランタイムを起動できません"),
+        ("Failed to get home directory", "ホームディレクトリを取得できません"),
+        ("Failed to read config file", "設定ファイルを読み込めません"),
+        ("Failed to parse config", "設定ファイルを解析できません"),
+        ("Failed to serialize config", "設定をシリアライズできません"),
+        ("Failed to write config file", "設定ファイルを書き込めません"),
+        ("Failed to create config file", "設定ファイルを作成できません"),
+        ("Failed to create config directory", "設定ディレクトリを作成できません"),
+        ("Config must be a JSON object", "設定はJSONのオブジェクトである必要があります"),
+        ("user id is not set", "user_idが設定されていません"),
+        ("repository path is not set", "repository_pathが設定されていません"),
+        ("No config found at {}. Set it up now?", "{0}に設定ファイルがありません。今から作りますか?"),
+        ("No config found at {}. Run `ac-garden init` to create one, or pass --config", "{0}に設定ファイルがありません。`ac-garden init`で作るか、--configで指定してください"),
+        ("The repository is on {} but default_branch is {}; check out {} before archiving", "リポジトリは{0}にありますが、default_branchは{1}です。アーカイブする前に{2}をチェックアウトしてください"),
+        ("default_branch {} is not a valid branch name", "default_branch {0}はブランチ名として正しくありません"),
+        ("Created the archive repository {} on the branch {}", "アーカイブ先のリポジトリ{0}をブランチ{1}で作成しました"),
+        ("Created the archive repository {}", "アーカイブ先のリポジトリ{0}を作成しました"),
+        ("Branch to push and make the default (defaults to default_branch, then main)", "pushして既定にするブランチ (既定はdefault_branch、なければmain)"),
+        ("atcoder.timezone {} is not a timezone (use local, UTC, an offset like +09:00 or a name like Asia/Tokyo)", "atcoder.timezone {0}はタイムゾーンとして読めません (local、UTC、+09:00のようなずれ、Asia/Tokyoのような名前を指定してください)"),
+        ("atcoder.timezone {} is not a timezone", "atcoder.timezone {0}はタイムゾーンとして読めません"),
+        ("Check index.json against the archived files and the git history", "index.jsonとアーカイブしたファイル・gitの履歴を突き合わせる"),
+        ("Restore deleted files from git, commit untracked archives and rebuild the index", "消えたファイルをgitから戻し、コミットしていないアーカイブをコミットして索引を作り直す"),
+        ("No source file for {} (submission {})", "{0}のソースファイルがありません (提出{1})"),
+        ("Missing file: {} ({}, still in HEAD)", "ファイルがありません: {0} ({1}、HEADにはあります)"),
+        ("Missing file: {} ({})", "ファイルがありません: {0} ({1})"),
+        ("No metadata for the indexed directory {}", "索引にあるディレクトリ{0}にメタデータがありません"),
+        ("Submission id mismatch in {}: index.json has {}, the metadata has {}", "{0}の提出IDが食い違っています: index.jsonは{1}、メタデータは{2}"),
+        ("Not in {}: {}", "{0}にありません: {1}"),
+        ("Not committed: {}", "コミットされていません: {0}"),
+        ("Uncommitted changes: {}", "コミットしていない変更があります: {0}"),
+        ("{} and the archive are consistent", "{0}とアーカイブは一致しています"),
+        ("Found {} problem(s). Run `ac-garden fsck --repair` to fix the ones it can", "{0}件の問題が見つかりました。`ac-garden fsck --repair`で直せるものを直せます"),
+        ("Restored {} file(s) from HEAD", "{0}個のファイルをHEADから戻しました"),
+        ("Committed {} file(s)", "{0}個のファイルをコミットしました"),
+        ("Rebuilt {}", "{0}を作り直しました"),
+        ("{} needs the network, but offline mode is on (drop --offline)", "{0}にはネットワークが必要ですが、オフラインモードです (--offlineを外してください)"),
+        ("Work only from the local archive, index and caches; fail commands that need the network", "ローカルのアーカイブ・索引・キャッシュだけで動かし、ネットワークが必要なコマンドはエラーにする"),
+        ("Failed to refresh the problem models, using the cached ones: {}", "問題モデルを取得し直せないため、キャッシュを使います: {0}"),
+        ("Re-download the contest list and problem difficulties into the local cache", "コンテスト一覧と問題の難易度をローカルのキャッシュに取得し直す"),
+        ("Cached {} contests and {} problem models", "{0}件のコンテストと{1}件の問題モデルをキャッシュしました"),
+        ("Failed to fetch the profile of {}: {}", "{0}のプロフィールを取得できませんでした: {1}"),
+        ("archived the profile (rating {})", "プロフィールをアーカイブしました (レーティング {0})"),
+        ("archived the profile", "プロフィールをアーカイブしました"),
+        ("Use this archive repository instead of the repository_path in the config", "設定のrepository_pathの代わりにこのアーカイブ先のリポジトリを使う"),
+        ("Use the settings of this profile from the profiles in the config", "設定のprofilesにあるこのプロファイルの設定を使う"),
+        ("Invalid repository path {}", "リポジトリのパス{0}が不正です"),
+        ("Completed the interrupted archive of submission {}", "中断していた提出{0}のアーカイブをコミットしました"),
+        ("Rolled back the interrupted archive of submission {}", "中断していた提出{0}のアーカイブを書き込む前に戻しました"),
+        ("Pick the submissions to archive from a checklist of the pending ones", "まだアーカイブしていない提出の一覧から、アーカイブする提出を選ぶ"),
+        ("Submissions to archive (space to toggle, enter to confirm)", "アーカイブする提出 (スペースで選択、Enterで決定)"),
+        ("--interactive needs a terminal", "--interactiveは端末で実行してください"),
+        ("Archive at most this many submissions, oldest first; the rest wait for the next run", "アーカイブする提出の上限。古い提出から順にアーカイブし、残りは次の実行に回す"),
+        ("{} pending submission(s) left for the next run", "{0}件の提出を次の実行に回しました"),
+        ("submission {} is not among {}'s submissions", "提出{0}は{1}の提出にありません"),
+        ("Archive only this submission, whatever its verdict and even if it is already archived. A non-AC submission goes to Main.<verdict>.<ext> next to the AC code; in a team, each member's submission list is fetched until the id is found", "この提出だけを、結果やアーカイブ済みかどうかに関わらずアーカイブする。ACでない提出はACのコードの隣のMain.<結果>.<拡張子>に置く。チームでは見つかるまでメンバーごとに提出一覧を取得する"),
+        ("Could not commit the interrupted archive of submission {}, rolled it back: {}", "中断した提出{0}のアーカイブをコミットできなかったので、書き込む前に戻しました: {1}"),
+        ("Warning: moved the unreadable journal to {}: {}", "警告: 読めないジャーナルを{0}に移しました: {1}"),
+        ("Note: once a newer submission of a problem is archived, older unticked ones of that problem are no longer offered", "注意: 問題の新しい提出をアーカイブすると、その問題の選ばなかった古い提出は候補に出なくなります"),
+        ("failed to select the submissions to archive", "アーカイブする提出を選べませんでした"),
+        ("Required config fields are empty: {} (fill them in with `ac-garden edit`, or recreate the config with `ac-garden init --force`)", "設定の必須の項目が空です: {0} (`ac-garden edit`で埋めるか、`ac-garden init --force`で設定を作り直してください)"),
+        ("Invalid proxy {}", "プロキシ{0}が不正です"),
+        ("Invalid CA certificate {}", "CA証明書{0}が不正です"),
+        ("Unknown TLS version: {}", "不明なTLSのバージョン: {0}"),
+        ("Invalid session value", "セッションの値が不正です"),
+        ("Invalid config path {}", "設定ファイルのパス{0}が不正です"),
+        ("unexpected status {}", "予期しないステータス{0}"),
+        ("set {} to unlock the encrypted credentials file", "暗号化した資格情報ファイルを開くには{0}を設定してください"),
+        ("not stored yet, run `{}`", "まだ保存されていません。`{0}`を実行してください"),
+        ("failed to derive the key: {}", "鍵を導出できません: {0}"),
+        ("failed to encrypt", "暗号化できません"),
+        ("failed to read the passphrase", "パスフレーズを読み込めません"),
+        ("the credentials file is corrupted", "資格情報ファイルが壊れています"),
+        ("wrong passphrase", "パスフレーズが違います"),
+        ("the stored value is not valid UTF-8", "保存された値が不正なUTF-8です"),
+        ("Failed to find the code", "コードが見つかりません"),
+        ("Failed to find the code: {}; saved the page to {}", "コードが見つかりません: {0}。ページを{1}に保存しました"),
+        ("Failed to find the code: {}", "コードが見つかりません: {0}"),
+        ("the page is a CAPTCHA or bot check", "CAPTCHAかボット対策のページです"),
+        ("the page asks to sign in (the session may have expired)", "ログインを求めるページです (セッションが切れている可能性があります)"),
+        ("#submission-code was not found (the page layout may have changed)", "#submission-codeがありません (ページの構成が変わった可能性があります)"),
+        ("Failed to find the task link", "問題へのリンクが見つかりません"),
+        ("Failed to find the submission link", "提出へのリンクが見つかりません"),
+        ("Failed to parse submission time", "提出時刻を解析できません"),
+        ("Failed to decode standings", "順位表を解析できません"),
+        ("Failed to decode contest history", "コンテストの参加履歴を解析できません"),
+        ("Failed to decode response as an array", "応答を配列として解析できません"),
+        ("Failed to fetch contest history of {}: {}", "{0}のコンテストの参加履歴を取得できません: {1}"),
+        ("Failed to decode contests", "コンテストの一覧を解析できません"),
+        ("Failed to decode problem models", "問題の難易度を解析できません"),
+        ("Failed to decode contest problems", "コンテストの問題の一覧を解析できません"),
+        ("Failed to read {}", "{0}を読み込めません"),
+        ("Failed to parse {}", "{0}を解析できません"),
+        ("Failed to parse the language table", "言語の対応表を解析できません"),
+        ("Unsupported language table version {}", "言語の対応表のバージョン{0}には対応していません"),
+        ("Ignoring {}: {}", "{0}を無視します: {1}"),
+        ("Failed to write {}", "{0}を書き込めません"),
+        ("Failed to create {}", "{0}を作成できません"),
+        ("Failed to remove {}", "{0}を削除できません"),
+        ("Failed to open {}", "{0}を開けません"),
+        ("Failed to create state directory", "状態ディレクトリを作成できません"),
+        ("Failed to serialize state", "状態をシリアライズできません"),
+        ("Failed to write the lock file", "ロックファイルを書き込めません"),
+        ("Failed to back up config to {}", "設定を{0}にバックアップできません"),
+        ("Failed to read the value", "値を読み込めません"),
+        ("Failed to run editor {}", "エディタ{0}を起動できません"),
+        ("Failed to fetch submissions of {}", "{0}の提出を取得できません"),
+        ("Failed to diff the last archive run", "直前のアーカイブとの差分を取れません"),
+        ("Failed to parse tag dataset (expected {\"problem_id\": [\"tag\", ...]})", "タグのデータセットを解析できません ({\"problem_id\": [\"tag\", ...]}の形式にしてください)"),
+        ("`git {}` failed: {}", "`git {0}`が失敗しました: {1}"),
+        ("Another ac-garden run is in progress{}. Remove {} if it is not running.", "ほかのac-gardenが実行中です{0}。実行中でなければ{1}を削除してください。"),
+        (" (pid {}, started at {})", " (pid {0}、{1}に開始)"),
+        ("{} is not a valid AtCoder user id", "{0}はAtCoderのユーザーIDとして正しくありません"),
+        ("AtCoder user {} does not exist", "AtCoderのユーザー{0}は存在しません"),
+        ("The value is empty", "値が空です"),
+        ("HEAD has moved since the last archive run; refusing to undo", "直前のアーカイブの後にHEADが動いているため取り消せません"),
+        ("HEAD is not a valid reference", "HEADが正しい参照ではありません"),
+        ("{} is not archived yet", "{0}はまだアーカイブされていません"),
+        ("No archived problems with a known difficulty yet", "難易度がわかるアーカイブ済みの問題がまだありません"),
+        ("No source file found in {}", "{0}にソースファイルがありません"),
+        ("No AC submission found for {}", "{0}のAC提出が見つかりません"),
+        // 警告
+        ("Warning: OS keyring is unavailable ({}), using the encrypted credentials file", "警告: OSのキーチェーンが使えないため ({0})、暗号化した資格情報ファイルを使います"),
+        ("Warning: TLS certificate verification is disabled", "警告: TLS証明書の検証が無効になっています"),
+        ("Warning: replaced {} invalid UTF-8 sequence(s) in {}", "警告: {1}の不正なUTF-8のバイト列を{0}か所置き換えました"),
+        ("Skipping {}: {}", "{0}をスキップします: {1}"),
+        ("Failed to fetch submissions from the API: {}", "APIから提出を取得できません: {0}"),
+        ("Falling back to scraping atcoder.jp...", "atcoder.jpから直接取得します..."),
+        ("Failed to fetch contest titles, using contest ids instead: {}", "コンテスト名を取得できないため、コンテストIDを使います: {0}"),
+        ("Failed to fetch upcoming contests", "予定されているコンテストを取得できません"),
+        ("Failed to fetch contests", "コンテストの一覧を取得できません"),
+        ("No contests to show", "表示するコンテストはありません"),
+        ("Watching for contests to end, checking every {} minute(s). Press Ctrl-C to stop.", "コンテストの終了を待っています ({0}分ごとに確認)。Ctrl-Cで終了します。"),
+        ("Rate limited by {} (429), pausing all requests for {} seconds", "{0}からレート制限 (429) を受けたため、すべてのリクエストを{1}秒止めます"),
+        ("rate limited by {} even after waiting", "待っても{0}のレート制限が解けません"),
+        ("Skipping {}: invalid file name", "{0}をスキップします: ファイル名が不正です"),
+        ("Failed to serialize the backup", "バックアップを書き出せません"),
+        ("Backed up the config, {} state file(s), {} cache file(s) and {} credential(s) to {}", "設定と状態ファイル{0}個・キャッシュ{1}個・資格情報{2}個を{3}に保存しました"),
+        ("{} was created by a newer version of ac-garden", "{0}は新しいバージョンのac-gardenで作られています"),
+        ("{} already exists. Use --force to overwrite it.", "{0}はすでにあります。上書きするには--forceを付けてください。"),
+        ("Restored {} from {} (created at {})", "{1}から{0}を戻しました ({2}に作成)"),
+        ("The repository {} does not exist on this machine. Clone it there or edit repository_path.", "リポジトリ{0}がこのマシンにありません。cloneするかrepository_pathを書き換えてください。"),
+        ("Failed to push to {}: {}", "{0}にpushできませんでした: {1}"),
+        ("Pushed the archive to {}", "アーカイブを{0}にpushしました"),
+        ("Failed to update the mirror {}: {}", "ミラー{0}を更新できません: {1}"),
+        ("{} does not exist", "{0}がありません"),
+        ("Mirrored {} problem(s) to {}", "{0}問を{1}に写しました"),
+        ("Failed to fetch the task list of {}: HTTP {}", "{0}の問題一覧を取得できません: HTTP {1}"),
+        ("Failed to fetch the task list of {}: {}", "{0}の問題一覧を取得できません: {1}"),
+        ("{} rejected the request: {}", "{0}が要求を受け付けませんでした: {1}"),
+        ("Create a remote repository for the archive, add it as a remote and push", "アーカイブのリモートリポジトリを作り、リモートに追加してpushする"),
+        ("Create the repository on GitHub (token from GITHUB_TOKEN or `credentials set github_token`)", "GitHubにリポジトリを作る (トークンはGITHUB_TOKENか`credentials set github_token`で保存した値)"),
+        ("Repository name (defaults to the name of the local repository directory)", "リポジトリ名 (既定値はローカルのリポジトリのディレクトリ名)"),
+        ("Create a private repository", "非公開のリポジトリにする"),
+        ("Name of the remote to add", "追加するリモートの名前"),
+        ("Use the SSH URL for the remote instead of HTTPS", "リモートにHTTPSではなくSSHのURLを使う"),
+        ("Set {} or run `ac-garden credentials set github_token` first", "先に{0}を設定するか`ac-garden credentials set github_token`を実行してください"),
+        ("{} is not a git repository", "{0}はGitリポジトリではありません"),
+        ("The remote {} already exists", "リモート{0}はすでにあります"),
+        ("Failed to decide the repository name. Pass --name", "リポジトリ名を決められません。--nameを指定してください"),
+        ("Created {}", "{0}を作成しました"),
+        ("Added the remote {} ({})", "リモート{0}を追加しました ({1})"),
+        ("Nothing to push yet. The archive will be committed to {}; push it after `ac-garden archive`.", "まだpushするものがありません。アーカイブは{0}にコミットされるので、`ac-garden archive`の後でpushしてください。"),
+        ("Pushed {} to {}", "{0}を{1}にpushしました"),
+        ("No archived problems match the filter", "条件に一致するアーカイブ済みの問題はありません"),
+        ("Exported {} file(s) from {} problem(s) to {}", "{1}問の{0}ファイルを{2}に書き出しました"),
+        ("Scaffolded {} into {}", "{0}を{1}に置きました"),
+        ("Opening {}", "{0}を開きます"),
+        ("Problem", "問題"),
+        ("Specify a problem id (choosing one interactively needs a terminal)", "問題IDを指定してください (一覧から選ぶには端末が必要です)"),
+        ("No archived problems", "アーカイブ済みの問題はありません"),
+        ("No problem selected", "問題が選ばれませんでした"),
+        ("{} already exists (use --force to overwrite)", "{0}はすでにあります (上書きするには--forceを付けてください)"),
+        ("cargo-compete workspaces are for Rust solutions, but {} is not a Rust file", "cargo-competeのワークスペースはRustの解答用ですが、{0}はRustのファイルではありません"),
+        ("Failed to serialize Cargo.toml", "Cargo.tomlを書き出せません"),
+        ("Failed to serialize contest.acc.json", "contest.acc.jsonを書き出せません"),
+        ("Invalid output path", "出力先のパスが正しくありません"),
+        ("Failed to pass the file list", "ファイルの一覧を渡せません"),
+        ("`{}` exited with {}", "`{0}`が{1}で終了しました"),
+        ("Failed to open stdin", "標準入力を開けません"),
+        ("request_interval_ms {} is below the minimum, using {} ms", "request_interval_msの{0}は下限より短いので{1}ミリ秒にします"),
+        ("Shortest solutions per problem (bytes):", "問題ごとの最短の解答 (バイト):"),
+        ("Shortest solutions by language:", "言語ごとの最短の解答:"),
+        ("  {} problem(s), {} B in total, {} shortened", "  {0}問、合計{1} B、{2}問で短縮"),
+        ("was {} B", "以前は{0} B"),
+        ("No AC submissions found in the archive history", "アーカイブの履歴にAC提出がありません"),
+        ("Wrote the leaderboard to {}", "表を{0}に書き出しました"),
+        ("Failed to run `rsvg-convert` (install librsvg to write PNG charts)", "`rsvg-convert`を実行できません (PNGのグラフを書き出すにはlibrsvgを入れてください)"),
+        ("Failed to pass the chart", "グラフを渡せません"),
+        ("Wrote the chart to {}", "グラフを{0}に書き出しました"),
+        ("Month     Solved  Problems  Lines of code", "年月        AC数    問題数         行数"),
+        ("Lines of code", "行数"),
+        ("Problems solved", "AC数"),
+        ("Cumulative lines of code", "ソースコードの行数 (累計)"),
+        ("Problems solved per month", "月ごとのAC数"),
+        ("Failed to update {}: {}", "{0}を更新できません: {1}"),
+        ("Failed to fetch difficulties, keeping the ones already in {}: {}", "難易度を取得できないため、{0}にある値を使います: {1}"),
+        ("Failed to serialize the index", "索引を書き出せません"),
+        ("Failed to fetch upcoming contests: {}", "予定されているコンテストを取得できません: {0}"),
+        ("{} ended, archiving new submissions", "{0}が終わったので、新しい提出をアーカイブします"),
+        ("Serving the archive API on http://{}", "アーカイブのAPIをhttp://{0}で公開しています"),
+        ("Invalid address {}:{}", "アドレス{0}:{1}が正しくありません"),
+        ("Failed to listen on {}", "{0}で待ち受けられません"),
+        ("API server failed", "APIサーバーが異常終了しました"),
+        ("Wrote {} contest(s) to {}", "{0}件のコンテストを{1}に書き出しました"),
+        ("Failed to refresh the contest list, using the cached one: {}", "コンテストの一覧を更新できないため、キャッシュを使います: {0}"),
+        ("Failed to fetch standings of {}: {}", "{0}の順位表を取得できません: {1}"),
+        ("Failed to fetch submissions of {}: {}", "{0}の提出を取得できません: {1}"),
+        ("{} hook `{}` failed: {}", "{0}フック`{1}`が失敗しました: {2}"),
+        ("Formatter `{}` failed ({}), keeping the code as submitted", "フォーマッタ`{0}`が失敗したため ({1})、提出したコードのまま保存します"),
+        ("Your config file is from an older version. Run `ac-garden config migrate` to update it.", "設定ファイルが古い形式です。`ac-garden config migrate`で更新してください。"),
+        ("Failed to fetch contests, skipping the time from contest start: {}", "コンテストの一覧を取得できないため、開始からの時間は省きます: {0}"),
+        ("Failed to fetch difficulties, scheduling without them: {}", "難易度を取得できないため、難易度なしで予定を立てます: {0}"),
+        ("Failed to fetch difficulties: {}", "難易度を取得できません: {0}"),
+        ("Could not check the AtCoder user {}: {}", "AtCoderのユーザー{0}を確認できません: {1}"),
+        // archive
+        ("Archiving {} code...", "{0}件のコードをアーカイブします..."),
+        ("archived the code at {}", "{0}にコードをアーカイブしました"),
+        ("Skipped {}: {}", "{0}をスキップしました: {1}"),
+        ("Failed to archive {}: {}", "{0}をアーカイブできません: {1}"),
+        ("Failed to fetch the editorial of {}: {}", "{0}の解説を取得できません: {1}"),
+        ("{} submission(s) failed. Run `ac-garden archive --retry-failed` to try them again.", "{0}件の提出が失敗しました。`ac-garden archive --retry-failed`でやり直せます。"),
+        ("No failed submissions to retry", "やり直す失敗した提出はありません"),
+        ("empty code", "コードが空です"),
+        ("uncommitted changes in {} (use --force to overwrite)", "{0}にコミットしていない変更があります (上書きするには--forceを付けてください)"),
+        ("archived the result of {} (rank {})", "{0}の結果をアーカイブしました ({1}位)"),
+        ("Archived {} of {} code before the interruption. Run archive again to continue.", "中断までに{1}件中{0}件をアーカイブしました。続きはもう一度archiveを実行してください。"),
+        ("Languages missing from the extension table:", "拡張子の対応表にない言語:"),
+        ("Summary:", "集計:"),
+        ("user", "ユーザー"),
+        ("archived", "アーカイブ"),
+        ("skipped", "スキップ"),
+        ("failed", "失敗"),
+        ("fetched", "取得"),
+        ("already archived", "アーカイブ済み"),
+        ("  Failed {} (submission {}): {}", "  {0}の失敗 (提出{1}): {2}"),
+        ("Finished in {}s", "{0}秒で終わりました"),
+        ("Created {} commit(s): {}", "{0}件のコミットを作りました: {1}"),
+        ("  {} ({} file(s), saved as .{})", "  {0} ({1}ファイル、.{2}で保存)"),
+        ("Removing a stale lock at {}", "{0}の古いロックを削除します"),
+        ("Nothing to undo", "取り消すものはありません"),
+        ("Reverted {} file(s) archived by the last run", "直前のアーカイブで書き込んだ{0}ファイルを元に戻しました"),
+        // init, config, credentials
+        ("Initialize your config...", "設定を初期化します..."),
+        ("Initialized your config at {}", "{0}に設定を作成しました"),
+        ("Config already exists at {} (use --force to recreate it)", "{0}に設定がすでにあります (作り直すには--forceを付けてください)"),
+        ("Found AtCoder user {}", "AtCoderのユーザー{0}が見つかりました"),
+        ("git user.name {} is not an AtCoder user, leaving user_id empty", "gitのuser.name {0}はAtCoderのユーザーではないため、user_idは空のままにします"),
+        ("Fill in {} with `ac-garden edit`", "`ac-garden edit`で{0}を設定してください"),
+        ("{} is up to date (version {})", "{0}は最新の形式です (バージョン{1})"),
+        ("Dry run: {} change(s), nothing written", "ドライラン: {0}件の変更 (書き込んでいません)"),
+        ("Migrated {} to version {} (backup: {})", "{0}をバージョン{1}に更新しました (バックアップ: {2})"),
+        ("Saved {} in the OS keyring", "{0}をOSのキーチェーンに保存しました"),
+        ("Saved {} in {}", "{0}を{1}に保存しました"),
+        ("Updated {} to read {} from the keyring", "{1}をキーチェーンから読むように{0}を更新しました"),
+        ("Deleted {}", "{0}を削除しました"),
+        ("{} is not stored", "{0}は保存されていません"),
+        ("Config looks good", "設定に問題はありません"),
+        ("atcoder.{} is empty", "atcoder.{0}が空です"),
+        ("unknown contest category `{}` (expected abc, arc, agc, ahc, past, joi or other)", "不明なコンテストの種類`{0}` (abc, arc, agc, ahc, past, joi, otherのいずれか)"),
+        ("Problems in {}:", "{0}の問題点:"),
+        ("Leaving the config as is. Fix it before running archive.", "設定はそのままにします。archiveを実行する前に直してください。"),
+        ("Re-open the editor?", "もう一度エディタで開きますか?"),
+        // list, status, stats
+        ("{} problem(s)", "{0}問"),
+        ("Archived problems: {}", "アーカイブ済みの問題: {0}"),
+        ("Latest AC: {} ({})", "最新のAC: {0} ({1})"),
+        ("Current streak: {} day(s) (longest: {})", "連続記録: {0}日 (最長: {1}日)"),
+        ("Current streak: {} day(s)", "連続記録: {0}日"),
+        ("Warning: no AC archived today yet. Solve one before midnight to keep your {}-day streak!", "警告: 今日はまだACをアーカイブしていません。{0}日の連続記録を続けるには今日中に1問解きましょう!"),
+        ("Longest streak: {} day(s)", "最長記録: {0}日"),
+        ("By language:", "言語別:"),
+        ("By tag:", "タグ別:"),
+        ("  (no tags yet, add some with `ac-garden tag`)", "  (タグはまだありません。`ac-garden tag`で付けられます)"),
+        ("By hour of day:", "時間帯別:"),
+        ("By weekday:", "曜日別:"),
+        ("No ACs during rated contests", "コンテスト中のACはありません"),
+        ("Average time from contest start to AC: {} min ({} AC(s) in rated contests)", "コンテスト開始からACまでの平均時間: {0}分 (コンテスト中のAC {1}件)"),
+        ("No re-solved problems yet", "解き直した問題はまだありません"),
+        ("Re-solved problems (execution time in ms / code length in bytes, oldest first):", "解き直した問題 (実行時間ms / コード長バイト、古い順):"),
+        // review, compare, recommend
+        ("Recorded {} (quality {}), next review on {}", "{0}を記録しました (評価{1})。次の復習は{2}です"),
+        ("Nothing to review today", "今日復習する問題はありません"),
+        ("Problems to re-attempt:", "解き直す問題:"),
+        ("After solving one again, record it with `ac-garden review --record <problem_id> --quality <0-5>`", "解き直したら`ac-garden review --record <problem_id> --quality <0-5>`で記録してください"),
+        ("{} solved {} problem(s) you haven't:", "{0}が解いていてあなたが解いていない問題 {1}問:"),
+        ("You solved {} problem(s) {} hasn't:", "あなたが解いていて{1}が解いていない問題 {0}問:"),
+        ("Both solved {} problem(s)", "両方が解いた問題: {0}問"),
+        ("No unsolved problems around difficulty {}", "難易度{0}付近に未解決の問題はありません"),
+        ("Your current level: around difficulty {}", "現在のレベル: 難易度{0}付近"),
+        ("Wrote {}", "{0}に書き込みました"),
+        // search, diff, dedupe-report, gap-report
+        ("{} matching line(s) in {} problem(s)", "{1}問で{0}行が一致しました"),
+        ("The archived code is already your latest AC (submission {})", "アーカイブ済みのコードがすでに最新のACです (提出{0})"),
+        ("Scanned {} source files (lines shared by {} or more files are treated as template)", "{0}個のソースファイルを調べました ({1}個以上のファイルにある行はテンプレートとして扱います)"),
+        ("Identical code: {} group(s)", "同一のコード: {0}グループ"),
+        ("Near-duplicates (similarity >= {}): {} pair(s)", "ほぼ同じコード (類似度{0}以上): {1}組"),
+        ("No partially solved contests", "途中まで解いたコンテストはありません"),
+        ("{}: solved {}, missing {}", "{0}: {1}を解いた、{2}が未解決"),
+        ("... and {} more contest(s)", "...ほか{0}コンテスト"),
+        // コマンドのヘルプ
+        ("Use this config file instead of ~/.ac-garden/config.json", "~/.ac-garden/config.jsonの代わりにこの設定ファイルを使う"),
+        ("Disable colored output (also disabled by NO_COLOR or when not writing to a terminal)", "色を付けずに出力する (NO_COLORの設定時や端末以外への出力でも色は付けない)"),
+        ("Archive your AC submissions", "AC提出をアーカイブする"),
+        ("Overwrite archived files that have uncommitted local changes", "コミットしていない変更があるファイルも上書きする"),
+        ("Only archive these contest categories: abc, arc, agc, ahc, past, joi, other (overrides the config)", "指定した種類のコンテストだけアーカイブする: abc, arc, agc, ahc, past, joi, other (設定より優先)"),
+        ("Only re-attempt the submissions that failed in earlier runs", "前回までに失敗した提出だけをやり直す"),
+        ("Initialize your config", "設定を初期化する"),
+        ("Force recreate config", "設定を作り直す"),
+        ("AtCoder user id (defaults to git's user.name if it is a valid AtCoder id)", "AtCoderのユーザーID (省略するとgitのuser.nameがAtCoderのIDとして正しければそれを使う)"),
+        ("Email for commits (defaults to git's user.email)", "コミットに使うメールアドレス (省略するとgitのuser.email)"),
+        ("Archive repository (defaults to the git repository of the current directory)", "アーカイブ先のリポジトリ (省略すると現在のディレクトリのgitリポジトリ)"),
+        ("Edit your config file", "設定ファイルを編集する"),
+        ("Revert the commits and files of the last archive run", "直前のアーカイブのコミットとファイルを取り消す"),
+        ("Show a diff between the archived code and your latest AC", "アーカイブしたコードと最新のAC提出の差分を表示する"),
+        ("Problem id (e.g. abc300_a)", "問題ID (例: abc300_a)"),
+        ("Add or remove tags on an archived problem", "アーカイブした問題にタグを付け外しする"),
+        ("Problem id (e.g. abc300_d)", "問題ID (例: abc300_d)"),
+        ("Tags to add (e.g. dp bitmask)", "付けるタグ (例: dp bitmask)"),
+        ("Remove the given tags instead of adding them", "タグを付ける代わりに外す"),
+        ("Seed tags from a JSON file mapping problem ids to tag lists", "問題IDからタグの一覧への対応を書いたJSONファイルからタグを付ける"),
+        ("List archived problems", "アーカイブした問題を一覧する"),
+        ("Only list problems with this tag", "このタグの問題だけ表示する"),
+        ("Only list submissions in this language", "この言語の提出だけ表示する"),
+        ("Show statistics of the archive", "アーカイブの統計を表示する"),
+        ("Show when ACs happen: hour of day, weekday and time from contest start", "ACした時間帯・曜日・コンテスト開始からの時間を表示する"),
+        ("Print the time breakdown as JSON (implies --time)", "時間の内訳をJSONで出力する (--timeを含む)"),
+        ("Show how execution time and code length changed for re-solved problems", "解き直した問題の実行時間とコード長の変化を表示する"),
+        ("Show the archive status and today's streak", "アーカイブの状況と連続記録を表示する"),
+        ("Suggest solved problems to re-attempt, or record a review result", "解き直す問題を提案する、または復習の結果を記録する"),
+        ("Number of problems to suggest", "提案する問題の数"),
+        ("Record a review result for this problem id (choose interactively if no id is given)", "この問題の復習の結果を記録する (IDを省略すると一覧から選ぶ)"),
+        ("Problem id (e.g. abc300_a); choose interactively if omitted", "問題ID (例: abc300_a)。省略すると一覧から選ぶ"),
+        ("How well you solved it, from 0 (could not solve) to 5 (solved right away)", "解けた度合い。0 (解けなかった) から5 (すぐ解けた)"),
+        ("Compare your solved problems with another user's", "解いた問題をほかのユーザーと比べる"),
+        ("AtCoder user id to compare with", "比べるAtCoderのユーザーID"),
+        ("Number of problems to show per difficulty color", "難易度の色ごとに表示する問題の数"),
+        ("Suggest unsolved problems slightly above your current level", "今のレベルより少し難しい未解決の問題を提案する"),
+        ("Also write the suggestions to todo.md in the repository", "提案をリポジトリのtodo.mdにも書き込む"),
+        ("Search archived code, notes and saved editorials", "アーカイブしたコード・メモ・保存した公式解説を検索する"),
+        ("Text to search for (case-insensitive)", "検索する文字列 (大文字小文字を区別しない)"),
+        ("Only search submissions in this language (e.g. C++, Python)", "この言語の提出だけ検索する (例: C++, Python)"),
+        ("Number of context lines to show around each match", "一致した行の前後に表示する行数"),
+        ("Manage the config file", "設定ファイルを管理する"),
+        ("Store secrets such as the session in the OS keyring", "セッションなどの秘密の値をOSのキーチェーンに保存する"),
+        ("Report problems solved with identical or near-identical code", "同じコードやほぼ同じコードで解いた問題を報告する"),
+        ("Minimum similarity (0.0-1.0) to report as a near-duplicate", "ほぼ同じとみなす類似度の下限 (0.0-1.0)"),
+        ("Lines appearing in at least this ratio of files are treated as template and ignored", "この割合以上のファイルにある行はテンプレートとして無視する"),
+        ("Report contests where some but not all problems are solved", "途中まで解いたコンテストを報告する"),
+        ("Number of contests to show", "表示するコンテストの数"),
+        ("List AtCoder contests with start times in your local timezone", "AtCoderのコンテストを開始時刻 (ローカル時間) とともに一覧する"),
+        ("List the contests scheduled on atcoder.jp instead of recently held ones", "最近開催されたコンテストではなく、atcoder.jpで予定されているコンテストを一覧する"),
+        ("Also write the listed contests to this iCalendar (.ics) file", "一覧したコンテストをこのiCalendar (.ics) ファイルにも書き出す"),
+        ("Keep running and archive automatically after contests you took part in end", "常駐して、参加したコンテストが終わったら自動でアーカイブする"),
+        ("Minutes to wait between checks", "確認する間隔 (分)"),
+        ("Minutes to wait after a contest ends before archiving", "コンテストの終了からアーカイブするまで待つ時間 (分)"),
+        ("Serve the archive over HTTP", "アーカイブをHTTPで公開する"),
+        ("Serve read-only JSON endpoints: /submissions, /problems, /stats and /heatmap", "読み取り専用のJSON API (/submissions, /problems, /stats, /heatmap) を公開する"),
+        ("Address to listen on", "待ち受けるアドレス"),
+        ("Port to listen on", "待ち受けるポート"),
+        ("Save the config, state, caches and credentials into one file to move them to another machine", "設定・状態・キャッシュ・資格情報を1つのファイルに保存して別のマシンに移せるようにする"),
+        ("Output file (defaults to ac-garden-backup-<date>.json in the current directory)", "出力するファイル (既定値はカレントディレクトリのac-garden-backup-<日付>.json)"),
+        ("Leave the stored credentials out of the backup", "保存した資格情報をバックアップに含めない"),
+        ("Restore the config, state, caches and credentials from a backup file", "バックアップファイルから設定・状態・キャッシュ・資格情報を戻す"),
+        ("Backup file created by `ac-garden backup`", "`ac-garden backup`で作ったバックアップファイル"),
+        ("Overwrite the existing config file", "既存の設定ファイルを上書きする"),
+        ("Bundle archived problems into a tar.gz, tar or zip file", "アーカイブした問題をtar.gz・tar・zipファイルにまとめる"),
+        ("Output file (defaults to ac-garden-export-<date>.<format> in the current directory)", "出力するファイル (既定値はカレントディレクトリのac-garden-export-<日付>.<形式>)"),
+        ("Archive format", "ファイルの形式"),
+        ("Only export problems whose contest or problem id matches this glob (e.g. \"abc*\", \"abc300/*\"); repeatable", "コンテストIDか問題IDがこのglobに一致する問題だけを書き出す (例: \"abc*\", \"abc300/*\")。複数指定できる"),
+        ("Also include metadata files such as submission.json and NOTES.md", "submission.jsonやNOTES.mdなどのメタデータも含める"),
+        ("Open an archived solution in your editor, or its problem or submission page in the browser", "アーカイブした解答をエディタで、問題や提出のページをブラウザで開く"),
+        ("Open the problem page in the browser", "問題のページをブラウザで開く"),
+        ("Open the archived submission's page in the browser", "アーカイブした提出のページをブラウザで開く"),
+        ("Show the shortest AC per problem and language, from every AC kept in the archive history", "アーカイブの履歴に残したすべてのACから、問題・言語ごとの最短の解答を表示する"),
+        ("Also write the tables as Markdown into this file (relative to the repository)", "表をMarkdownとしてこのファイルにも書き出す (リポジトリからの相対パス)"),
+        ("Show cumulative lines of code and problems solved per month", "月ごとのAC数と、累計の問題数・ソースコードの行数を表示する"),
+        ("Also draw the growth as a chart into this .svg or .png file (relative to the repository)", "月ごとの伸びをグラフにしてこの.svgか.pngのファイルにも書き出す (リポジトリからの相対パス)"),
+        ("Copy an archived solution into a fresh contest workspace to re-solve or benchmark it", "アーカイブした解答をコンテスト用のワークスペースに写し、解き直しや計測に使えるようにする"),
+        ("Workspace layout to create", "作るワークスペースの形式"),
+        ("Directory to create the contest workspace in (defaults to the current directory)", "コンテストのワークスペースを作るディレクトリ (既定値はカレントディレクトリ)"),
+        ("Overwrite an existing solution file in the workspace", "ワークスペースにある解答のファイルを上書きする"),
+        ("Save a secret (prompted) and reference it from the config as \"keyring\"", "秘密の値を (入力して) 保存し、設定から\"keyring\"として参照する"),
+        ("Delete a stored secret", "保存した秘密の値を削除する"),
+        ("Upgrade the config file to the current format", "設定ファイルを現在の形式に更新する"),
+        ("Look through the whole submission history instead of only submissions since the newest archived one", "アーカイブ済みの最新の提出以降だけでなく、提出の履歴をすべて調べる"),
+        ("Not committed and not written by ac-garden, left alone: {}", "コミットされていませんが、ac-gardenが書き込んだファイルではないのでそのままにします: {0}"),
+        ("Committing {} archived file(s):", "アーカイブのファイルを{0}個コミットします:"),
+        ("Failed to refresh {}, using the cached copy: {}", "{0}を取り直せなかったため、キャッシュを使います: {1}"),
+        ("Failed to refresh {}; the cache was left as it was", "{0}を取り直せなかったため、キャッシュはそのままです"),
+        ("the contest list", "コンテスト一覧"),
+        ("the problem models", "問題モデル"),
+        ("Unknown profile {} (profiles in the config: {})", "不明なプロファイル{0}です（設定にあるプロファイル: {1}）"),
+        ("Skipped saving contest results: {}", "コンテストの結果の保存を省きました: {0}"),
+        ("Failed to decode standings of {}: {}", "{0}の順位表を解析できません: {1}"),
+        ("Unknown export format {}", "不明な書き出し形式{0}"),
+        ("Show the changes without writing them", "書き込まずに変更を表示する"),
+    ];
+
+    /// 整形したメッセージの中で引数の始まりと終わりを示す文字 (私用領域)
+    const ARG_START: char = '\u{F8F0}';
+    const ARG_END: char = '\u{F8F1}';
+
+    /// [`tr!`](crate::tr)の引数。前後に目印を付けて整形し、整形後のメッセージからテンプレートと引数を取り出せるようにする
+    ///
+    /// `{:#}`で書いた引数 (エラーの原因の連なり) は、それ自体も[`translate`]で訳す
+    #[doc(hidden)]
+    pub struct Arg<'a, T: ?Sized>(pub &'a T);
+
+    impl<T: fmt::Display + ?Sized> fmt::Display for Arg<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_char(ARG_START)?;
+            if f.alternate() {
+                f.write_str(&translate(&format!("{:#}", self.0)))?;
+            } else {
+                self.0.fmt(f)?;
+            }
+            f.write_char(ARG_END)
+        }
+    }
+
+    impl<T: fmt::Debug + ?Sized> fmt::Debug for Arg<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_char(ARG_START)?;
+            self.0.fmt(f)?;
+            f.write_char(ARG_END)
+        }
+    }
+
+    /// 英語のテンプレート → 日本語の訳
+    fn ja_table() -> &'static HashMap<&'static str, &'static str> {
+        static TABLE: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+        TABLE.get_or_init(|| JA.iter().copied().collect())
+    }
+
+    /// [`tr!`](crate::tr)で整形したメッセージを表示言語に訳す
+    #[doc(hidden)]
+    pub fn translate_marked(message: &str) -> String {
+        localize(lang(), message)
+    }
+
+    /// 目印付きのメッセージを、引数を`{}`にしたテンプレートで引いて訳す。表にないものは目印を取り除いて返す
+    ///
+    /// テンプレートが`{}`だけのもの (`tr!("{}", error)`) は、引数を組み立て済みのメッセージとして[`translate`]で訳す
+    fn localize(lang: Lang, message: &str) -> String {
+        let mut template = String::new();
+        let mut args: Vec<String> = Vec::new();
+        let mut plain = String::new();
+        let mut depth = 0;
+        for c in message.chars() {
+            match c {
+                ARG_START => {
+                    if depth == 0 {
+                        template.push_str("{}");
+                        args.push(String::new());
+                    }
+                    depth += 1;
+                },
+                ARG_END => depth -= 1,
+                c if depth > 0 => {
+                    args.last_mut().unwrap().push(c);
+                    plain.push(c);
+                },
+                c => {
+                    template.push(c);
+                    plain.push(c);
+                },
+            }
+        }
+        if lang == Lang::En {
+            return plain;
+        }
+        if template == "{}" {
+            return translate(&args[0]);
+        }
+        match ja_table().get(template.as_str()) {
+            Some(translation) => fill(translation, &args),
+            None => plain,
+        }
+    }
+
+    /// ほかで組み立てたメッセージ (エラーやclapのヘルプ) を表示言語に訳す。表にないものはそのまま返す
+    ///
+    /// 整形後の文字列しかないので、表のテンプレートに当てはめて引数を取り出す。引数も訳すので、
+    /// エラーの原因のように入れ子になったメッセージも訳せる
+    pub fn translate(message: &str) -> String {
+        if lang() == Lang::En {
+            return message.to_string();
+        }
+        if let Some(translation) = ja_table().get(message) {
+            return translation.to_string();
+        }
+        for (template, translation) in JA {
+            if let Some(args) = match_template(template, message) {
+                let args: Vec<String> = args.into_iter().map(translate).collect();
+                return fill(translation, &args);
+            }
+        }
+        message.to_string()
+    }
+
+    /// テンプレートに一致すれば`{}`に当たる部分を返す
+    fn match_template<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+        let mut literals = template.split("{}");
+        let mut rest = message.strip_prefix(literals.next()?)?;
+        let literals: Vec<&str> = literals.collect();
+        let mut args = Vec::new();
+        for (i, literal) in literals.iter().enumerate() {
+            // 最後の引数は末尾の文字列を除いた残り全体
+            let end = if i + 1 == literals.len() {
+                rest.strip_suffix(literal)?.len()
+            } else {
+                rest.find(literal)?
+            };
+            args.push(&rest[..end]);
+            rest = &rest[end + literal.len()..];
+        }
+        rest.is_empty().then_some(args)
+    }
+
+    /// 訳の`{0}`、`{1}`...を引数で置き換える
+    fn fill(translation: &str, args: &[String]) -> String {
+        let mut result = String::new();
+        let mut rest = translation;
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let arg = rest.find('}')
+                .and_then(|end| rest[1..end].parse::<usize>().ok().map(|i| (i, end)))
+                .and_then(|(i, end)| args.get(i).map(|arg| (arg, end)));
+            match arg {
+                Some((arg, end)) => {
+                    result.push_str(arg);
+                    rest = &rest[end + 1..];
+                },
+                None => {
+                    result.push('{');
+                    rest = &rest[1..];
+                },
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// `tr!`と同じように目印付きで整形する
+        fn marked(template: &str, args: &[&str]) -> String {
+            let mut parts = template.split("{}");
+            let mut message = parts.next().unwrap().to_string();
+            for (part, arg) in parts.zip(args) {
+                message.push_str(&format!("{}{}", Arg(*arg), part));
+            }
+            message
+        }
+
+        #[test]
+        fn translates_by_the_template_not_by_the_arguments() {
+            // 引数にテンプレートの区切りと同じ": "が入っていても、引数の切れ目を取り違えない
+            let message = marked("Failed to push to {}: {}", &["origin: main", "rejected"]);
+            assert_eq!(localize(Lang::Ja, &message), "origin: mainにpushできませんでした: rejected");
+            // 引数が表のメッセージそのものでも訳さない
+            let message = marked("Pushed the archive to {}", &["Error"]);
+            assert_eq!(localize(Lang::Ja, &message), "アーカイブをErrorにpushしました");
+            // 引数に`{0}`があっても置き換えない
+            let message = marked("Pushed the archive to {}", &["{0}"]);
+            assert_eq!(localize(Lang::Ja, &message), "アーカイブを{0}にpushしました");
+            assert_eq!(localize(Lang::En, &message), "Pushed the archive to {0}");
+        }
+
+        #[test]
+        fn keeps_messages_missing_from_the_table() {
+            let message = marked("no such message {}", &["x"]);
+            assert_eq!(localize(Lang::Ja, &message), "no such message x");
+        }
+
+        #[test]
+        fn has_no_duplicate_keys() {
+            let mut seen = std::collections::HashSet::new();
+            let duplicates: Vec<&str> = JA.iter().map(|(key, _)| *key).filter(|key| !seen.insert(*key)).collect();
+            assert!(duplicates.is_empty(), "duplicate keys: {:?}", duplicates);
+        }
+
+        #[test]
+        fn formats_arguments_with_their_specs() {
+            assert_eq!(format!("{:.1}|{:>3}|{:?}", Arg(&1.25), Arg("a"), Arg("b")), "\u{F8F0}1.2\u{F8F1}|\u{F8F0}  a\u{F8F1}|\u{F8F0}\"b\"\u{F8F1}");
+        }
+    }
+}
+
+/// ファイル操作のユーティリティ
+pub mod utils {
+    use std::borrow::Cow;
+    use std::path::Path;
+
+    /// ディレクトリが存在するか確認
+    pub fn is_dir_exist<P: AsRef<Path>>(path: P) -> bool {
+        path.as_ref().is_dir()
+    }
+
+    /// ファイルが存在するか確認
+    pub fn is_file_exist<P: AsRef<Path>>(path: P) -> bool {
+        path.as_ref().is_file()
+    }
+
+    /// ベアリポジトリ (作業ディレクトリのないGitリポジトリ) か確認
+    pub fn is_bare_repository<P: AsRef<Path>>(path: P) -> bool {
+        let path = path.as_ref();
+        !path.join(".git").exists()
+            && path.join("HEAD").is_file()
+            && path.join("objects").is_dir()
+            && path.join("refs").is_dir()
+    }
+
+    /// タイムゾーンの指定から、その時刻でのUTCからのずれ (分) を求める。読めない指定はNone
+    ///
+    /// `local`か空なら実行しているマシンのタイムゾーン。名前で指定すると夏時間も反映する
+    pub fn utc_offset_minutes(timezone: &str, epoch_second: i64) -> Option<i32> {
+        use chrono::{Offset, TimeZone};
+
+        let timezone = timezone.trim();
+        let offset = match timezone {
+            "" | "local" => chrono::Local.timestamp_opt(epoch_second, 0).single()?.offset().fix(),
+            "UTC" | "utc" | "Z" => chrono::Utc.fix(),
+            _ if timezone.starts_with(['+', '-']) => timezone.parse::<chrono::FixedOffset>().ok()?,
+            _ => {
+                let tz: chrono_tz::Tz = timezone.parse().ok()?;
+                tz.timestamp_opt(epoch_second, 0).single()?.offset().fix()
+            },
+        };
+        Some(offset.local_minus_utc() / 60)
+    }
+
+    /// Windowsでディレクトリのパスに使える長さの上限 (MAX_PATHからファイル名の8.3形式の分を引いたもの)
+    #[cfg(windows)]
+    const WINDOWS_MAX_DIR_PATH: usize = 248;
+
+    /// Windowsの絶対パスを`\\?\`付きの拡張パスにする。相対パスやすでに拡張されたパスはNone
+    ///
+    /// 拡張パスでは`/`や`.`・`..`が解釈されないので、ここで区切り文字をそろえて畳み込む
+    pub fn extended_length_path(path: &str) -> Option<String> {
+        let path = path.replace('/', "\\");
+        if path.starts_with("\\\\?\\") || path.starts_with("\\\\.\\") {
+            return None;
+        }
+        let (prefix, rest) = if let Some(rest) = path.strip_prefix("\\\\") {
+            // UNCパス: \\server\share\... -> \\?\UNC\server\share\...
+            let mut parts = rest.splitn(3, '\\');
+            let (server, share) = (parts.next().filter(|s| !s.is_empty())?, parts.next().filter(|s| !s.is_empty())?);
+            (format!("\\\\?\\UNC\\{}\\{}", server, share), parts.next().unwrap_or_default().to_string())
+        } else {
+            let bytes = path.as_bytes();
+            if bytes.len() < 3 || !bytes[0].is_ascii_alphabetic() || bytes[1] != b':' || bytes[2] != b'\\' {
+                return None;
+            }
+            (format!("\\\\?\\{}", &path[..2]), path[3..].to_string())
+        };
+
+        let mut components: Vec<&str> = Vec::new();
+        for component in rest.split('\\') {
+            match component {
+                "" | "." => {},
+                ".." => {
+                    components.pop();
+                },
+                component => components.push(component),
+            }
+        }
+        let mut extended = prefix;
+        for component in components {
+            extended.push('\\');
+            extended.push_str(component);
+        }
+        if extended.ends_with(':') {
+            extended.push('\\');
+        }
+        Some(extended)
+    }
+
+    /// Windowsで長すぎるパスを拡張パスにして、MAX_PATHを超える深いディレクトリでも読み書きできるようにする
+    ///
+    /// ほかのOSや短いパスではそのまま返す
+    pub fn long_path(path: &Path) -> Cow<'_, Path> {
+        #[cfg(windows)]
+        if path.as_os_str().len() >= WINDOWS_MAX_DIR_PATH {
+            let extended = std::path::absolute(path).ok()
+                .and_then(|absolute| extended_length_path(&absolute.to_string_lossy()));
+            if let Some(extended) = extended {
+                return Cow::Owned(std::path::PathBuf::from(extended));
+            }
+        }
+        Cow::Borrowed(path)
+    }
+
+    /// バイト列をUTF-8として読み、不正なバイト列をU+FFFDに置き換える。置き換えた箇所の数も返す
+    pub fn decode_utf8_lossy(bytes: &[u8]) -> (String, usize) {
+        let mut decoded = String::with_capacity(bytes.len());
+        let mut replaced = 0;
+        let mut rest = bytes;
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    decoded.push_str(valid);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    // valid_up_toまでは正しいUTF-8であることが保証されている
+                    decoded.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                    decoded.push('\u{FFFD}');
+                    replaced += 1;
+                    match e.error_len() {
+                        Some(len) => rest = &after[len..],
+                        None => break,
+                    }
+                }
+            }
+        }
+        (decoded, replaced)
+    }
+
+    /// CRLFと単独のCRをLFにそろえる
+    pub fn normalize_line_endings(text: &str) -> String {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    }
+
+    /// 先頭のBOMを取り除く
+    pub fn strip_bom(text: &str) -> &str {
+        text.strip_prefix('\u{FEFF}').unwrap_or(text)
+    }
+
+    /// Windowsで予約されているファイル名 (拡張子付きでも使えない)
+    const RESERVED_NAMES: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL",
+        "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+        "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    /// 言語ごとのサブディレクトリの名前 ("Rust (rustc 1.70.0)" → "rust", "C++ 20 (gcc 12.2)" → "cpp")
+    ///
+    /// 正規の言語名から作るので、"C++14 (GCC 5.4.1)"のような改名前の言語も同じディレクトリになる
+    pub fn language_dir_name(language: &str) -> String {
+        sanitize_path_component(&crate::languages::table().dir_name(language))
+    }
+
+    /// パスの1要素をWindows/macOS/Linuxのどこでも使える名前にする
+    ///
+    /// - `<>:"/\|?*`と制御文字は`_`に置き換える
+    /// - 末尾のドットと空白は取り除く
+    /// - `CON`や`aux.txt`のような予約名には`_`を付ける
+    /// - 空文字列や`.`/`..`は`_`にする
+    pub fn sanitize_path_component(name: &str) -> String {
+        let mut sanitized: String = name.chars()
+            .map(|c| match c {
+                '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+
+        let trimmed_len = sanitized.trim_end_matches(['.', ' ']).len();
+        sanitized.truncate(trimmed_len);
+
+        if sanitized.is_empty() {
+            return "_".to_string();
+        }
+
+        let stem_len = sanitized.find('.').unwrap_or(sanitized.len());
+        if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(&sanitized[..stem_len])) {
+            sanitized.insert(stem_len, '_');
+        }
+
+        sanitized
+    }
+
+    /// ひらがなとローマ字の対応表 (拗音を先に照合する)
+    const KANA_TABLE: &[(&str, &str)] = &[
+        ("きゃ", "kya"), ("きゅ", "kyu"), ("きょ", "kyo"),
+        ("しゃ", "sha"), ("しゅ", "shu"), ("しょ", "sho"),
+        ("ちゃ", "cha"), ("ちゅ", "chu"), ("ちょ", "cho"),
+        ("にゃ", "nya"), ("にゅ", "nyu"), ("にょ", "nyo"),
+        ("ひゃ", "hya"), ("ひゅ", "hyu"), ("ひょ", "hyo"),
+        ("みゃ", "mya"), ("みゅ", "myu"), ("みょ", "myo"),
+        ("りゃ", "rya"), ("りゅ", "ryu"), ("りょ", "ryo"),
+        ("ぎゃ", "gya"), ("ぎゅ", "gyu"), ("ぎょ", "gyo"),
+        ("じゃ", "ja"), ("じゅ", "ju"), ("じょ", "jo"),
+        ("びゃ", "bya"), ("びゅ", "byu"), ("びょ", "byo"),
+        ("ぴゃ", "pya"), ("ぴゅ", "pyu"), ("ぴょ", "pyo"),
+        ("ふぁ", "fa"), ("ふぃ", "fi"), ("ふぇ", "fe"), ("ふぉ", "fo"),
+        ("てぃ", "ti"), ("でぃ", "di"), ("うぃ", "wi"), ("うぇ", "we"), ("うぉ", "wo"),
+        ("ゔぁ", "va"), ("ゔぃ", "vi"), ("ゔぇ", "ve"), ("ゔぉ", "vo"),
+        ("あ", "a"), ("い", "i"), ("う", "u"), ("え", "e"), ("お", "o"),
+        ("か", "ka"), ("き", "ki"), ("く", "ku"), ("け", "ke"), ("こ", "ko"),
+        ("さ", "sa"), ("し", "shi"), ("す", "su"), ("せ", "se"), ("そ", "so"),
+        ("た", "ta"), ("ち", "chi"), ("つ", "tsu"), ("て", "te"), ("と", "to"),
+        ("な", "na"), ("に", "ni"), ("ぬ", "nu"), ("ね", "ne"), ("の", "no"),
+        ("は", "ha"), ("ひ", "hi"), ("ふ", "fu"), ("へ", "he"), ("ほ", "ho"),
+        ("ま", "ma"), ("み", "mi"), ("む", "mu"), ("め", "me"), ("も", "mo"),
+        ("や", "ya"), ("ゆ", "yu"), ("よ", "yo"),
+        ("ら", "ra"), ("り", "ri"), ("る", "ru"), ("れ", "re"), ("ろ", "ro"),
+        ("わ", "wa"), ("を", "wo"), ("ん", "n"),
+        ("が", "ga"), ("ぎ", "gi"), ("ぐ", "gu"), ("げ", "ge"), ("ご", "go"),
+        ("ざ", "za"), ("じ", "ji"), ("ず", "zu"), ("ぜ", "ze"), ("ぞ", "zo"),
+        ("だ", "da"), ("ぢ", "ji"), ("づ", "zu"), ("で", "de"), ("ど", "do"),
+        ("ば", "ba"), ("び", "bi"), ("ぶ", "bu"), ("べ", "be"), ("ぼ", "bo"),
+        ("ぱ", "pa"), ("ぴ", "pi"), ("ぷ", "pu"), ("ぺ", "pe"), ("ぽ", "po"),
+        ("ゔ", "vu"),
+        ("ぁ", "a"), ("ぃ", "i"), ("ぅ", "u"), ("ぇ", "e"), ("ぉ", "o"),
+        ("ゃ", "ya"), ("ゅ", "yu"), ("ょ", "yo"),
+    ];
+
+    /// ひらがな・カタカナをローマ字に変換する。漢字などその他の文字はそのまま残す
+    pub fn transliterate_kana(text: &str) -> String {
+        // カタカナはひらがなに寄せてから変換する
+        let chars: Vec<char> = text.chars()
+            .map(|c| match c {
+                'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+                _ => c,
+            })
+            .collect();
+
+        let mut result = String::new();
+        let mut double_next = false;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c == 'っ' {
+                double_next = true;
+                i += 1;
+                continue;
+            }
+            if c == 'ー' {
+                // 長音は直前の母音を繰り返す
+                if let Some(vowel) = result.chars().last().filter(|v| "aiueo".contains(*v)) {
+                    result.push(vowel);
+                }
+                i += 1;
+                continue;
+            }
+
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let one = c.to_string();
+            let matched = KANA_TABLE.iter()
+                .find(|(kana, _)| *kana == two)
+                .map(|(_, romaji)| (*romaji, 2))
+                .or_else(|| KANA_TABLE.iter().find(|(kana, _)| *kana == one).map(|(_, romaji)| (*romaji, 1)));
+
+            match matched {
+                Some((romaji, len)) => {
+                    if double_next {
+                        result.push_str(&romaji[..1]);
+                    }
+                    result.push_str(romaji);
+                    i += len;
+                }
+                None => {
+                    result.push(c);
+                    i += 1;
+                }
+            }
+            double_next = false;
+        }
+        result
+    }
+
+    /// 問題名からファイル名の幹を作る (例: "A - Welcome to AtCoder" → "A_Welcome_to_AtCoder")
+    ///
+    /// かなはローマ字に変換し、英数字以外の文字の並びは1つの`_`にまとめる
+    pub fn title_to_file_stem(title: &str) -> String {
+        let title = transliterate_kana(title);
+        let mut stem = String::new();
+        for c in title.chars() {
+            if c.is_alphanumeric() {
+                stem.push(c);
+            } else if !stem.is_empty() && !stem.ends_with('_') {
+                stem.push('_');
+            }
+        }
+        stem.trim_end_matches('_').to_string()
+    }
+
+    /// 拡張子ごとのコメントの書き方
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum CommentStyle {
+        /// 行コメント (例: `//`)
+        Line(&'static str),
+        /// ブロックコメント (例: `(*`と`*)`)
+        Block(&'static str, &'static str),
+    }
+
+    /// 拡張子からコメントの書き方を決める。安全にコメントを書けない言語はNone
+    pub fn comment_style(ext: &str) -> Option<CommentStyle> {
+        use CommentStyle::*;
+        let style = match ext {
+            "c" | "cpp" | "cs" | "d" | "go" | "js" | "java" | "scala" | "swift" | "rs" | "kt" | "ts"
+            | "fs" | "ceylon" => Line("//"),
+            "sh" | "py" | "rb" | "pl" | "jl" | "cr" | "nim" | "awk" | "sed" | "p6" => Line("#"),
+            "hs" | "lua" | "moon" => Line("--"),
+            "lisp" | "clj" | "scm" => Line(";"),
+            "f08" => Line("!"),
+            "vb" => Line("'"),
+            "ml" | "sml" | "pas" => Block("(*", "*)"),
+            // .mはObjective-CとOctaveで共用、PHPはタグの外に書けない、
+            // Brainfuckなどは記号がそのまま命令になるので書かない
+            _ => return None,
+        };
+        Some(style)
+    }
+
+    /// コードの先頭にコメントとしてヘッダを差し込む。shebangがあればその次の行に入れる
+    ///
+    /// コメントを書けない拡張子の場合はNone
+    pub fn prepend_header(code: &str, ext: &str, lines: &[String]) -> Option<String> {
+        let mut header = String::new();
+        match comment_style(ext)? {
+            CommentStyle::Line(prefix) => {
+                for line in lines {
+                    header.push_str(&format!("{} {}\n", prefix, line));
+                }
+            }
+            CommentStyle::Block(start, end) => {
+                header.push_str(start);
+                header.push('\n');
+                for line in lines {
+                    header.push_str(&format!("   {}\n", line));
+                }
+                header.push_str(end);
+                header.push('\n');
+            }
+        }
+
+        if code.starts_with("#!") {
+            let (first_line, rest) = match code.find('\n') {
+                Some(idx) => code.split_at(idx + 1),
+                None => (code, ""),
+            };
+            let separator = if first_line.ends_with('\n') { "" } else { "\n" };
+            return Some(format!("{}{}{}{}", first_line, separator, header, rest));
+        }
+
+        Some(format!("{}{}", header, code))
+    }
+
+    /// 提出ページのエディタ (Ace) のモード名から拡張子を決める。対応は[`crate::languages::table`]の`ace_modes`
+    pub fn ace_mode_to_extension(mode: &str) -> Option<&'static str> {
+        crate::languages::table().ace_mode_extension(mode)
+    }
+
+    /// コードの内容から拡張子を推測する
+    ///
+    /// 各言語に特有の書き出しが行頭 (字下げは除く) にあるかで判断する。
+    /// コメントや文字列の中の`#include`などに引きずられないよう、行の途中は見ない
+    pub fn guess_extension_from_code(code: &str) -> Option<&'static str> {
+        let first_line = code.lines().next().unwrap_or_default();
+        if first_line.starts_with("#!") {
+            let interpreter = first_line.to_lowercase();
+            for (name, ext) in [("python", "py"), ("ruby", "rb"), ("perl", "pl"), ("node", "js"), ("bash", "sh"), ("/sh", "sh")] {
+                if interpreter.contains(name) {
+                    return Some(ext);
+                }
+            }
+        }
+
+        // 先にあるものほど優先する (Javaの`import`より`public static void main`など)
+        let patterns: &[(&str, &str)] = &[
+            ("#include", "cpp"),
+            ("fn main()", "rs"),
+            ("package main", "go"),
+            ("public static void main", "java"),
+            ("using System", "cs"),
+            ("fun main(", "kt"),
+            ("object Main", "scala"),
+            ("import std.", "d"),
+            ("main = ", "hs"),
+            ("console.log(", "js"),
+            ("def main", "py"),
+            ("print(", "py"),
+            ("puts ", "rb"),
+        ];
+        let lines: Vec<&str> = code.lines().map(str::trim_start).collect();
+        patterns.iter()
+            .find(|(pattern, _)| lines.iter().any(|line| line.starts_with(pattern)))
+            .map(|(_, ext)| *ext)
+    }
+
+    /// 言語の文字列の括弧内のバージョン ("C++ 20 (gcc 12.2)" → "gcc 12.2")
+    pub fn language_version(language: &str) -> Option<&str> {
+        let (_, rest) = language.rsplit_once('(')?;
+        let version = rest.strip_suffix(')')?.trim();
+        (!version.is_empty()).then_some(version)
+    }
+
+    /// 言語名からソースファイルの名前 (例: "Rust (rustc 1.70.0)" → "Main.rs") を決める。対応表は[`crate::languages::table`]
+    pub fn language_to_file_name(language: &str) -> Option<String> {
+        crate::languages::table().extension(language).map(|ext| format!("Main.{}", ext))
+    }
+
+    /// gitignoreのようなパターンに一致するか
+    ///
+    /// `*`は`/`以外の0文字以上、`**`は`/`も含む0文字以上、`?`は`/`以外の1文字に一致する
+    pub fn glob_match(pattern: &str, text: &str) -> bool {
+        fn matches(pattern: &[char], text: &[char]) -> bool {
+            match pattern {
+                [] => text.is_empty(),
+                ['*', '*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+                ['*', rest @ ..] => {
+                    let segment = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+                    (0..=segment).any(|i| matches(rest, &text[i..]))
+                }
+                ['?', rest @ ..] => text.first().is_some_and(|&c| c != '/') && matches(rest, &text[1..]),
+                [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+            }
+        }
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        matches(&pattern, &text)
+    }
+
+    /// 問題がパターンに一致するか
+    ///
+    /// `/`を含むパターンは`contest_id/problem_id`に、含まないパターンはコンテストIDと問題IDのどちらかに一致させる
+    pub fn problem_matches(pattern: &str, contest_id: &str, problem_id: &str) -> bool {
+        if pattern.contains('/') {
+            glob_match(pattern, &format!("{}/{}", contest_id, problem_id))
+        } else {
+            glob_match(pattern, contest_id) || glob_match(pattern, problem_id)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn sanitize_keeps_ordinary_ids() {
+            for id in ["abc300", "abc300_a", "ahc001", "rcl-contest-2021-long", "future-contest-2022-final", "A_Welcome_to_AtCoder.cpp"] {
+                assert_eq!(sanitize_path_component(id), id);
+            }
+        }
+
+        #[test]
+        fn sanitize_replaces_invalid_characters() {
+            assert_eq!(sanitize_path_component("marathon:2023?final*"), "marathon_2023_final_");
+            assert_eq!(sanitize_path_component("a<b>c|d\"e"), "a_b_c_d_e");
+            assert_eq!(sanitize_path_component("x/y\\z"), "x_y_z");
+            assert_eq!(sanitize_path_component("tab\tname"), "tab_name");
+        }
+
+        #[test]
+        fn sanitize_strips_trailing_dots_and_spaces() {
+            assert_eq!(sanitize_path_component("contest. . "), "contest");
+            assert_eq!(sanitize_path_component("."), "_");
+            assert_eq!(sanitize_path_component(".."), "_");
+            assert_eq!(sanitize_path_component(""), "_");
+        }
+
+        #[test]
+        fn sanitize_escapes_reserved_names() {
+            assert_eq!(sanitize_path_component("CON"), "CON_");
+            assert_eq!(sanitize_path_component("aux.cpp"), "aux_.cpp");
+            assert_eq!(sanitize_path_component("com1"), "com1_");
+            assert_eq!(sanitize_path_component("console"), "console");
+        }
+
+        #[test]
+        fn glob_matches_within_a_segment() {
+            assert!(glob_match("abc300", "abc300"));
+            assert!(glob_match("abc3??", "abc300"));
+            assert!(glob_match("abc*/abc*_a", "abc300/abc300_a"));
+            assert!(!glob_match("abc*", "abc300/abc300_a"));
+            assert!(!glob_match("abc300", "abc3000"));
+        }
+
+        #[test]
+        fn glob_double_star_crosses_segments() {
+            assert!(glob_match("**", "abc300/abc300_a"));
+            assert!(glob_match("**/abc300_a", "abc300/abc300_a"));
+            assert!(glob_match("abc**", "abc300/abc300_a"));
+        }
+
+        #[test]
+        fn problem_pattern_matches_either_id_or_path() {
+            assert!(problem_matches("abc*", "abc300", "abc300_a"));
+            assert!(problem_matches("*_a", "abc300", "abc300_a"));
+            assert!(problem_matches("abc300/*", "abc300", "abc300_a"));
+            assert!(!problem_matches("arc*", "abc300", "abc300_a"));
+            assert!(!problem_matches("abc300/*_b", "abc300", "abc300_a"));
+        }
+
+        #[test]
+        fn language_dir_names_drop_versions() {
+            assert_eq!(language_dir_name("Rust (rustc 1.70.0)"), "rust");
+            assert_eq!(language_dir_name("Python (CPython 3.11.4)"), "python");
+            assert_eq!(language_dir_name("C++ 20 (gcc 12.2)"), "cpp");
+            assert_eq!(language_dir_name("C# 11.0 (.NET 7.0.7)"), "csharp");
+            assert_eq!(language_dir_name("Common Lisp (SBCL 2.3.6)"), "common-lisp");
+            assert_eq!(language_dir_name("C++14 (GCC 5.4.1)"), "cpp");
+            assert_eq!(language_dir_name("Python3 (3.4.3)"), "python");
+        }
+
+        #[test]
+        fn guesses_extensions_from_line_starts() {
+            assert_eq!(guess_extension_from_code("#include <bits/stdc++.h>\nint main() {}\n"), Some("cpp"));
+            assert_eq!(guess_extension_from_code("use std::io;\n\nfn main() {\n}\n"), Some("rs"));
+            assert_eq!(guess_extension_from_code("import java.util.*;\nclass Main {\n    public static void main(String[] args) {}\n}\n"), Some("java"));
+            assert_eq!(guess_extension_from_code("n = int(input())\nfor i in range(n):\n    print(i)\n"), Some("py"));
+            assert_eq!(guess_extension_from_code("#!/usr/bin/env ruby\nputs 1\n"), Some("rb"));
+        }
+
+        #[test]
+        fn ignores_patterns_inside_comments_and_strings() {
+            // 行の途中やコメントの中の書き出しでは決めない
+            assert_eq!(guess_extension_from_code("# #include is not needed here\nprint(1)\n"), Some("py"));
+            assert_eq!(guess_extension_from_code("// package main\nconsole.log(1);\n"), Some("js"));
+            assert_eq!(guess_extension_from_code("puts \"fn main() {}\"\n"), Some("rb"));
+            assert_eq!(guess_extension_from_code("targets = [1, 2]\nprint(targets)\n"), Some("py"));
+            assert_eq!(guess_extension_from_code("x = input()  # fn main()\n"), None);
+            assert_eq!(guess_extension_from_code(""), None);
+        }
+
+        #[test]
+        fn utc_offsets_from_timezones() {
+            // 2023-04-29 12:00:00 UTC
+            let epoch_second = 1_682_769_600;
+            assert_eq!(utc_offset_minutes("UTC", epoch_second), Some(0));
+            assert_eq!(utc_offset_minutes("+09:00", epoch_second), Some(540));
+            assert_eq!(utc_offset_minutes("-05:30", epoch_second), Some(-330));
+            assert_eq!(utc_offset_minutes("Asia/Tokyo", epoch_second), Some(540));
+            // 夏時間
+            assert_eq!(utc_offset_minutes("America/New_York", epoch_second), Some(-240));
+            assert_eq!(utc_offset_minutes("America/New_York", 1_672_574_400), Some(-300));
+            assert!(utc_offset_minutes("local", epoch_second).is_some());
+            assert_eq!(utc_offset_minutes("Mars/Olympus", epoch_second), None);
+            assert_eq!(utc_offset_minutes("+25:00", epoch_second), None);
+        }
+
+        #[test]
+        fn extended_paths_for_drive_letters() {
+            assert_eq!(extended_length_path(r"C:\garden\atcoder.jp\abc300").as_deref(), Some(r"\\?\C:\garden\atcoder.jp\abc300"));
+            assert_eq!(extended_length_path("C:/garden/./atcoder.jp/x/../abc300/").as_deref(), Some(r"\\?\C:\garden\atcoder.jp\abc300"));
+            assert_eq!(extended_length_path(r"C:\").as_deref(), Some(r"\\?\C:\"));
+        }
+
+        #[test]
+        fn extended_paths_for_unc_repositories() {
+            assert_eq!(
+                extended_length_path(r"\\nas\share\garden\atcoder.jp\abc300\abc300_a").as_deref(),
+                Some(r"\\?\UNC\nas\share\garden\atcoder.jp\abc300\abc300_a"),
+            );
+            assert_eq!(extended_length_path("//nas/share/garden").as_deref(), Some(r"\\?\UNC\nas\share\garden"));
+            assert_eq!(extended_length_path(r"\\nas\share\..\..\garden").as_deref(), Some(r"\\?\UNC\nas\share\garden"));
+            assert_eq!(extended_length_path(r"\\nas"), None);
+        }
+
+        #[test]
+        fn extended_paths_leave_relative_and_extended_paths() {
+            assert_eq!(extended_length_path(r"garden\abc300"), None);
+            assert_eq!(extended_length_path(r"\garden"), None);
+            assert_eq!(extended_length_path(r"\\?\C:\garden"), None);
+            assert_eq!(extended_length_path(r"\\?\UNC\nas\share"), None);
+            assert_eq!(extended_length_path(r"\\.\pipe\garden"), None);
+        }
     }
 }
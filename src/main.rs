@@ -1,50 +1,127 @@
-use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use git2::{Repository, Signature};
-use home::home_dir;
+use ac_garden::archive::{
+    build_client, clip_difficulty, commit_files, commit_signatures, contest_category, difficulty_color, is_git_repository, is_valid_user_id, prepare_repository,
+    prepare_work_dir, user_exists, with_header, ArchiveClient, Progress, Recovery, DIFFICULTY_COLORS,
+};
+use ac_garden::config::{
+    apply_repo_config, get_config_path, read_config, save_config, Config, ContestCategory, Mirror, Service, CONFIG_VERSION,
+};
+use ac_garden::contest::{
+    contest_titles, contests_to_ics, fetch_contests, fetch_upcoming_contests, get_cache_dir, Contest, Fetched, CONTESTS_URL,
+};
+use ac_garden::credentials::{
+    delete_secret, has_secret, load_secret, passphrase_from_env, resolve_secret, save_secret, seal_secrets, unseal_secrets,
+    SecretStore, KEYRING_REFERENCE, SECRET_NAMES,
+};
+use ac_garden::editorial::EDITORIAL_MD;
+use ac_garden::languages::canonical_language;
+use ac_garden::rate_limit::{is_offline, send_get, set_offline, RateLimiter};
+use ac_garden::remote::{
+    create_github_repository, push, push_auth, set_github_default_branch, PushAuth, GITHUB_TOKEN_ENV, GITHUB_USERNAME,
+};
+use ac_garden::store::{
+    find_source_files, is_archive_file, metadata_format_of, prepend_log_entry, read_notes_by_commit, scan_archived_submissions, write_export, write_metadata, write_note, NOTES_MD, NOTES_REF,
+};
+use ac_garden::submission::{archive_key, submission_url, task_url, Submission};
+use ac_garden::trend::Trend;
+use ac_garden::utils::{is_dir_exist, is_file_exist, problem_matches, sanitize_path_component};
+use ac_garden::i18n::set_lang;
+use ac_garden::error::BoxError;
+use ac_garden::{tr, warning, Error, ATCODER_BASE_URL};
+use anyhow::{anyhow, Context, Result};
+use anstyle::{AnsiColor, Style};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use git2::Repository;
 use reqwest::Client;
-use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::{self, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
-use thiserror::Error;
 
-const APP_NAME: &str = "ac-garden";
-const ATCODER_API_SUBMISSION_URL: &str = "https://kenkoooo.com/atcoder/atcoder-api/results?user=";
+/// メッセージを表示言語に訳して標準出力に書く
+macro_rules! say {
+    ($($arg:tt)*) => {
+        anstream::println!("{}", tr!($($arg)*))
+    };
+}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AtCoderSubmission {
-    id: i64,
-    epoch_second: i64,
-    problem_id: String,
-    contest_id: String,
-    user_id: String,
-    language: String,
-    point: f64,
-    length: i64,
-    result: String,
-    execution_time: Option<i64>,
+/// メッセージを表示言語に訳して標準エラー出力に書く
+macro_rules! say_err {
+    ($($arg:tt)*) => {
+        anstream::eprintln!("{}", tr!($($arg)*))
+    };
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Service {
-    repository_path: String,
-    user_id: String,
-    user_email: String,
+/// アーカイブした提出の色
+const STYLE_ARCHIVED: Style = AnsiColor::Green.on_default();
+/// スキップした提出の色
+const STYLE_SKIPPED: Style = AnsiColor::Yellow.on_default();
+/// 失敗の色
+const STYLE_FAILED: Style = AnsiColor::Red.on_default().bold();
+
+/// 文字列に色を付ける。色を出せない出力先ではanstreamが取り除く
+fn paint(style: Style, text: impl std::fmt::Display) -> String {
+    format!("{}{}{:#}", style, text, style)
+}
+
+const CONTEST_PROBLEMS_URL: &str = "https://kenkoooo.com/atcoder/resources/contest-problem.json";
+// 終了コード。ラッパースクリプトやCIが結果で分岐できるようにする
+/// 正常終了（archiveでは新しい提出がなかった）
+const EXIT_NOTHING_NEW: i32 = 0;
+/// archiveで新しい提出をアーカイブした
+const EXIT_ARCHIVED: i32 = 1;
+/// 設定ファイルや資格情報の誤り。clapもコマンドライン引数の誤りに2を使う
+const EXIT_CONFIG: i32 = 2;
+/// HTTP通信の失敗
+const EXIT_NETWORK: i32 = 3;
+/// Gitの操作の失敗
+const EXIT_GIT: i32 = 4;
+/// ページやAPIの応答を解釈できない
+const EXIT_SCRAPE: i32 = 5;
+/// フックが失敗して中止した
+const EXIT_HOOK: i32 = 6;
+/// ほかの実行がロックを持っている
+const EXIT_LOCKED: i32 = 7;
+/// その他の失敗
+const EXIT_FAILURE: i32 = 8;
+/// AtCoderがメンテナンス中で中断した (EX_TEMPFAIL)
+const EXIT_MAINTENANCE: i32 = 75;
+
+/// AtCoder Problemsの問題モデルのうち必要な部分だけ
+#[derive(Debug, Deserialize)]
+struct ProblemModel {
+    difficulty: Option<f64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Config {
-    atcoder: Service,
+impl ProblemModel {
+    /// AtCoder Problemsと同じく、400未満の難易度は正の値に補正する
+    fn clipped_difficulty(&self) -> Option<f64> {
+        self.difficulty.map(clip_difficulty)
+    }
 }
 
 #[derive(Parser)]
 #[command(name = "ac-garden")]
 #[command(about = "Archive your AC submissions", long_about = None)]
 struct Cli {
+    /// Use this config file instead of ~/.ac-garden/config.json
+    #[arg(long, global = true, value_name = "FILE", env = "AC_GARDEN_CONFIG")]
+    config: Option<PathBuf>,
+    /// Use this archive repository instead of the repository_path in the config
+    #[arg(long, global = true, value_name = "DIR", env = "AC_GARDEN_REPO")]
+    repo: Option<PathBuf>,
+    /// Use the settings of this profile from the profiles in the config
+    #[arg(long, global = true, value_name = "NAME", env = "AC_GARDEN_PROFILE")]
+    profile: Option<String>,
+    /// Disable colored output (also disabled by NO_COLOR or when not writing to a terminal)
+    #[arg(long, global = true)]
+    no_color: bool,
+    /// Work only from the local archive, index and caches; fail commands that need the network
+    #[arg(long, global = true, env = "AC_GARDEN_OFFLINE")]
+    offline: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -52,106 +129,471 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Archive your AC submissions
-    Archive,
+    Archive {
+        /// Overwrite archived files that have uncommitted local changes
+        #[arg(short, long)]
+        force: bool,
+        /// Only archive these contest categories: abc, arc, agc, ahc, past, joi, other (overrides the config)
+        #[arg(long, value_delimiter = ',', value_name = "CATEGORIES")]
+        contests: Vec<ContestCategory>,
+        /// Only re-attempt the submissions that failed in earlier runs
+        #[arg(long)]
+        retry_failed: bool,
+        /// Pick the submissions to archive from a checklist of the pending ones
+        #[arg(short, long)]
+        interactive: bool,
+        /// Archive at most this many submissions, oldest first; the rest wait for the next run
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+        /// Archive only this submission, whatever its verdict and even if it is already archived. A non-AC submission goes to Main.<verdict>.<ext> next to the AC code; in a team, each member's submission list is fetched until the id is found
+        #[arg(long, value_name = "SUBMISSION_ID", conflicts_with_all = ["retry_failed", "interactive", "limit"])]
+        id: Option<i64>,
+        /// Look through the whole submission history instead of only submissions since the newest archived one
+        #[arg(long)]
+        full: bool,
+    },
     /// Initialize your config
     Init {
         /// Force recreate config
         #[arg(short, long)]
         force: bool,
+        /// AtCoder user id (defaults to git's user.name if it is a valid AtCoder id)
+        #[arg(long)]
+        user: Option<String>,
+        /// Email for commits (defaults to git's user.email)
+        #[arg(long)]
+        email: Option<String>,
+        /// Archive repository (defaults to the git repository of the current directory)
+        #[arg(long)]
+        repository: Option<PathBuf>,
     },
     /// Edit your config file
     Edit,
+    /// Revert the commits and files of the last archive run
+    Undo,
+    /// Show a diff between the archived code and your latest AC
+    Diff {
+        /// Problem id (e.g. abc300_a); choose interactively if omitted
+        problem_id: Option<String>,
+    },
+    /// Add or remove tags on an archived problem
+    Tag {
+        /// Problem id (e.g. abc300_d)
+        #[arg(required_unless_present = "import")]
+        problem_id: Option<String>,
+        /// Tags to add (e.g. dp bitmask)
+        tags: Vec<String>,
+        /// Remove the given tags instead of adding them
+        #[arg(long)]
+        remove: bool,
+        /// Seed tags from a JSON file mapping problem ids to tag lists
+        #[arg(long, value_name = "FILE")]
+        import: Option<PathBuf>,
+    },
+    /// List archived problems
+    List {
+        /// Only list problems with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+        /// Only list submissions in this language
+        #[arg(short, long)]
+        language: Option<String>,
+    },
+    /// Show statistics of the archive
+    Stats {
+        /// Show when ACs happen: hour of day, weekday and time from contest start
+        #[arg(long)]
+        time: bool,
+        /// Print the time breakdown as JSON (implies --time)
+        #[arg(long)]
+        json: bool,
+        /// Show how execution time and code length changed for re-solved problems
+        #[arg(long)]
+        trends: bool,
+        /// Show cumulative lines of code and problems solved per month
+        #[arg(long)]
+        growth: bool,
+        /// Also draw the growth as a chart into this .svg or .png file (relative to the repository)
+        #[arg(long, value_name = "FILE", requires = "growth")]
+        chart: Option<PathBuf>,
+    },
+    /// Show the archive status and today's streak
+    Status,
+    /// Suggest solved problems to re-attempt, or record a review result
+    Review {
+        /// Number of problems to suggest
+        #[arg(short = 'n', long, default_value_t = 5)]
+        count: usize,
+        /// Record a review result for this problem id (choose interactively if no id is given)
+        #[arg(long, value_name = "PROBLEM_ID", requires = "quality", num_args = 0..=1)]
+        record: Option<Option<String>>,
+        /// How well you solved it, from 0 (could not solve) to 5 (solved right away)
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=5))]
+        quality: Option<u8>,
+    },
+    /// Compare your solved problems with another user's
+    Compare {
+        /// AtCoder user id to compare with
+        user: String,
+        /// Number of problems to show per difficulty color
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Suggest unsolved problems slightly above your current level
+    Recommend {
+        /// Number of problems to suggest
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+        /// Also write the suggestions to todo.md in the repository
+        #[arg(long)]
+        todo: bool,
+    },
+    /// Search archived code, notes and saved editorials
+    Search {
+        /// Text to search for (case-insensitive)
+        query: String,
+        /// Only search submissions in this language (e.g. C++, Python)
+        #[arg(short, long)]
+        language: Option<String>,
+        /// Number of context lines to show around each match
+        #[arg(short = 'C', long, default_value_t = 2)]
+        context: usize,
+    },
+    /// Manage the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Store secrets such as the session in the OS keyring
+    Credentials {
+        #[command(subcommand)]
+        action: CredentialsAction,
+    },
+    /// Report problems solved with identical or near-identical code
+    DedupeReport {
+        /// Minimum similarity (0.0-1.0) to report as a near-duplicate
+        #[arg(long, default_value_t = 0.8)]
+        threshold: f64,
+        /// Lines appearing in at least this ratio of files are treated as template and ignored
+        #[arg(long, default_value_t = 0.3)]
+        template_ratio: f64,
+    },
+    /// Report contests where some but not all problems are solved
+    GapReport {
+        /// Number of contests to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// List AtCoder contests with start times in your local timezone
+    Contests {
+        /// List the contests scheduled on atcoder.jp instead of recently held ones
+        #[arg(long)]
+        upcoming: bool,
+        /// Also write the listed contests to this iCalendar (.ics) file
+        #[arg(long, value_name = "FILE")]
+        ics: Option<PathBuf>,
+        /// Number of contests to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Keep running and archive automatically after contests you took part in end
+    Watch {
+        /// Minutes to wait between checks
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+        /// Minutes to wait after a contest ends before archiving
+        #[arg(long, default_value_t = 30)]
+        delay: u64,
+    },
+    /// Serve the archive over HTTP
+    Serve {
+        /// Serve read-only JSON endpoints: /submissions, /problems, /stats and /heatmap
+        #[arg(long, required = true)]
+        api: bool,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Save the config, state, caches and credentials into one file to move them to another machine
+    Backup {
+        /// Output file (defaults to ac-garden-backup-<date>.json in the current directory)
+        output: Option<PathBuf>,
+        /// Leave the stored credentials out of the backup
+        #[arg(long)]
+        no_credentials: bool,
+    },
+    /// Restore the config, state, caches and credentials from a backup file
+    Restore {
+        /// Backup file created by `ac-garden backup`
+        input: PathBuf,
+        /// Overwrite the existing config file
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Create a remote repository for the archive, add it as a remote and push
+    SetupRemote {
+        /// Create the repository on GitHub (token from GITHUB_TOKEN or `credentials set github_token`)
+        #[arg(long, required = true)]
+        github: bool,
+        /// Repository name (defaults to the name of the local repository directory)
+        #[arg(long)]
+        name: Option<String>,
+        /// Create a private repository
+        #[arg(long)]
+        private: bool,
+        /// Branch to push and make the default (defaults to default_branch, then main)
+        #[arg(long)]
+        branch: Option<String>,
+        /// Name of the remote to add
+        #[arg(long, default_value = "origin")]
+        remote: String,
+        /// Use the SSH URL for the remote instead of HTTPS
+        #[arg(long)]
+        ssh: bool,
+    },
+    /// Bundle archived problems into a tar.gz, tar or zip file
+    Export {
+        /// Output file (defaults to ac-garden-export-<date>.<format> in the current directory)
+        output: Option<PathBuf>,
+        /// Archive format
+        #[arg(long, default_value = "tar.gz", value_parser = clap::builder::PossibleValuesParser::new(EXPORT_FORMATS))]
+        archive_format: String,
+        /// Only export problems whose contest or problem id matches this glob (e.g. "abc*", "abc300/*"); repeatable
+        #[arg(long)]
+        filter: Vec<String>,
+        /// Also include metadata files such as submission.json and NOTES.md
+        #[arg(long)]
+        metadata: bool,
+    },
+    /// Open an archived solution in your editor, or its problem or submission page in the browser
+    Open {
+        /// Problem id (e.g. abc300_a); choose interactively if omitted
+        problem_id: Option<String>,
+        /// Open the problem page in the browser
+        #[arg(long)]
+        web: bool,
+        /// Open the archived submission's page in the browser
+        #[arg(long)]
+        submission: bool,
+    },
+    /// Show the shortest AC per problem and language, from every AC kept in the archive history
+    Golf {
+        /// Only list submissions in this language
+        #[arg(long)]
+        language: Option<String>,
+        /// Also write the tables as Markdown into this file (relative to the repository)
+        #[arg(long, value_name = "FILE")]
+        markdown: Option<PathBuf>,
+    },
+    /// Copy an archived solution into a fresh contest workspace to re-solve or benchmark it
+    Scaffold {
+        /// Problem id (e.g. abc300_a); choose interactively if omitted
+        problem_id: Option<String>,
+        /// Workspace layout to create
+        #[arg(long, default_value = "cargo-compete", value_parser = clap::builder::PossibleValuesParser::new(SCAFFOLD_TEMPLATES))]
+        template: String,
+        /// Directory to create the contest workspace in (defaults to the current directory)
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Overwrite an existing solution file in the workspace
+        #[arg(long)]
+        force: bool,
+    },
+    /// Re-download the contest list and problem difficulties into the local cache
+    RefreshMetadata,
+    /// Check index.json against the archived files and the git history
+    Fsck {
+        /// Restore deleted files from git, commit untracked archives and rebuild the index
+        #[arg(long)]
+        repair: bool,
+    },
 }
 
-#[derive(Error, Debug)]
-enum AppError {
-    #[error("IO error: {0}")]
-    Io(#[from] io::Error),
-    #[error("HTTP error: {0}")]
-    Http(#[from] reqwest::Error),
-    #[error("JSON error: {0}")]
-    Json(#[from] serde_json::Error),
-    #[error("Git error: {0}")]
-    Git(#[from] git2::Error),
-    #[error("Config error: {0}")]
-    Config(String),
+#[derive(Subcommand)]
+enum CredentialsAction {
+    /// Save a secret (prompted) and reference it from the config as "keyring"
+    Set {
+        #[arg(value_parser = clap::builder::PossibleValuesParser::new(SECRET_NAMES))]
+        name: String,
+    },
+    /// Delete a stored secret
+    Delete {
+        #[arg(value_parser = clap::builder::PossibleValuesParser::new(SECRET_NAMES))]
+        name: String,
+    },
 }
 
-fn language_to_file_name(language: &str) -> String {
-    let language = if let Some(idx) = language.find('(') {
-        &language[..idx].trim()
-    } else {
-        language
-    };
-
-    match language {
-        "C++" | "C++14" | "C++17" | "C++20" => "Main.cpp",
-        "Bash" => "Main.sh",
-        "C" => "Main.c",
-        "C#" => "Main.cs",
-        "Clojure" => "Main.clj",
-        "Common Lisp" => "Main.lisp",
-        "D" => "Main.d",
-        "Fortran" => "Main.f08",
-        "Go" => "Main.go",
-        "Haskell" => "Main.hs",
-        "JavaScript" => "Main.js",
-        "Java" => "Main.java",
-        "OCaml" => "Main.ml",
-        "Pascal" => "Main.pas",
-        "Perl" => "Main.pl",
-        "PHP" => "Main.php",
-        "Python" | "Python3" | "PyPy2" | "PyPy3" => "Main.py",
-        "Ruby" => "Main.rb",
-        "Scala" => "Main.scala",
-        "Scheme" => "Main.scm",
-        "Visual Basic" => "Main.vb",
-        "Objective-C" => "Main.m",
-        "Swift" => "Main.swift",
-        "Rust" => "Main.rs",
-        "Sed" => "Main.sed",
-        "Awk" => "Main.awk",
-        "Brainfuck" => "Main.bf",
-        "Standard ML" => "Main.sml",
-        "Crystal" => "Main.cr",
-        "F#" => "Main.fs",
-        "Unlambda" => "Main.unl",
-        "Lua" | "LuaJIT" => "Main.lua",
-        "MoonScript" => "Main.moon",
-        "Ceylon" => "Main.ceylon",
-        "Julia" => "Main.jl",
-        "Octave" => "Main.m",
-        "Nim" => "Main.nim",
-        "TypeScript" => "Main.ts",
-        "Perl6" => "Main.p6",
-        "Kotlin" => "Main.kt",
-        "COBOL" => "Main.cob",
-        _ => {
-            eprintln!("Unknown language: {}", language);
-            "Main.txt"
-        }
-    }.to_string()
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Upgrade the config file to the current format
+    Migrate {
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
-fn is_dir_exist<P: AsRef<Path>>(path: P) -> bool {
-    path.as_ref().is_dir()
-}
+/// `--config`または`AC_GARDEN_CONFIG`で指定された設定ファイル
+static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// `--repo`または`AC_GARDEN_REPO`で指定されたアーカイブ先のリポジトリ。設定ファイルには書き込まない
+static REPOSITORY_PATH: OnceLock<PathBuf> = OnceLock::new();
 
-fn is_file_exist<P: AsRef<Path>>(path: P) -> bool {
-    path.as_ref().is_file()
+/// `--profile`または`AC_GARDEN_PROFILE`で選んだ設定の組
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// 使う設定ファイルのパス
+fn get_config_file() -> Result<PathBuf> {
+    match CONFIG_FILE.get() {
+        Some(path) => Ok(path.clone()),
+        None => Ok(get_config_path()?),
+    }
 }
 
+/// 設定ファイルを置くディレクトリ。状態ファイルもここに置く
 fn get_config_dir() -> Result<PathBuf> {
-    let home = home_dir().ok_or_else(|| AppError::Config("Failed to get home directory".into()))?;
-    Ok(home.join(format!(".{}", APP_NAME)))
+    let config_file = get_config_file()?;
+    Ok(config_file.parent().map(Path::to_path_buf).unwrap_or_default())
 }
 
-fn get_config_file() -> Result<PathBuf> {
-    Ok(get_config_dir()?.join("config.json"))
+/// 実行の間で引き継ぐ状態を置くディレクトリ
+fn get_state_dir() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join("state"))
+}
+
+/// 状態ファイルを読み込む。なければ既定値
+fn load_state<T: serde::de::DeserializeOwned + Default>(name: &str) -> Result<T> {
+    let path = get_state_dir()?.join(name);
+    if !is_file_exist(&path) {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// 状態ファイルを書き込む
+fn save_state<T: Serialize>(name: &str, state: &T) -> Result<()> {
+    let dir = get_state_dir()?;
+    fs::create_dir_all(&dir).context("Failed to create state directory")?;
+    let json = serde_json::to_string_pretty(state)
+        .context("Failed to serialize state")?;
+    fs::write(dir.join(name), json)
+        .with_context(|| format!("Failed to write {}", name))
+}
+
+/// 同時実行を防ぐロックファイル
+const LOCK_FILE: &str = "archive.lock";
+
+#[derive(Serialize, Deserialize)]
+struct LockInfo {
+    pid: u32,
+    /// 開始時刻（UNIX時間）
+    started_at: i64,
+}
+
+/// 取得したロック。dropで解放する
+struct RunLock {
+    path: PathBuf,
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+#[cfg(unix)]
+fn is_process_running(pid: u32) -> bool {
+    // procfsがあれば、ほかのユーザーのプロセスでも権限なしで確かめられる
+    if Path::new("/proc/self").exists() {
+        return Path::new("/proc").join(pid.to_string()).exists();
+    }
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(true)
+}
+
+#[cfg(windows)]
+fn is_process_running(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(true)
+}
+
+/// ロックを取った実行が終わっているか
+///
+/// 時間では判断しない。履歴が多い初回のarchiveは何時間もかかるので、プロセスが生きている限り有効とみなす
+fn is_stale_lock(path: &Path) -> bool {
+    let Ok(content) = fs::read_to_string(path) else {
+        return false;
+    };
+    // 読めないロックは書き込み途中かもしれないので、しばらくは有効とみなす
+    let Ok(info) = serde_json::from_str::<LockInfo>(&content) else {
+        return fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age.as_secs() > 60));
+    };
+    !is_process_running(info.pid)
+}
+
+/// ほかの実行がロックを持っている
+#[derive(Debug, thiserror::Error)]
+#[error("Another ac-garden run is in progress{holder}. Remove {} if it is not running.", .path.display())]
+struct LockHeld {
+    holder: String,
+    path: PathBuf,
+}
+
+/// archiveのロックを取る。ほかの実行中ならエラー
+fn acquire_lock() -> Result<RunLock> {
+    let dir = get_state_dir()?;
+    fs::create_dir_all(&dir).context("Failed to create state directory")?;
+    let path = dir.join(LOCK_FILE);
+
+    for _ in 0..2 {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                let info = LockInfo { pid: std::process::id(), started_at: chrono::Local::now().timestamp() };
+                let lock = RunLock { path };
+                file.write_all(serde_json::to_string(&info)?.as_bytes())
+                    .context("Failed to write the lock file")?;
+                return Ok(lock);
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                if !is_stale_lock(&path) {
+                    break;
+                }
+                say!("Removing a stale lock at {}", path.display());
+                fs::remove_file(&path).ok();
+            },
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", path.display()));
+            },
+        }
+    }
+
+    let holder = fs::read_to_string(&path).ok()
+        .and_then(|content| serde_json::from_str::<LockInfo>(&content).ok())
+        .and_then(|info| {
+            let started_at = chrono::DateTime::from_timestamp(info.started_at, 0)?.with_timezone(&chrono::Local);
+            Some(format!(" (pid {}, started at {})", info.pid, started_at.format("%Y-%m-%d %H:%M:%S")))
+        })
+        .unwrap_or_default();
+    Err(LockHeld { holder, path }.into())
 }
 
-fn init_config(force: bool) -> Result<()> {
-    println!("Initialize your config...");
+fn init_config(force: bool, atcoder: Service) -> Result<()> {
+    say!("Initialize your config...");
     
     let config_dir = get_config_dir()?;
     
@@ -164,13 +606,7 @@ fn init_config(force: bool) -> Result<()> {
     
     if force || !is_file_exist(&config_file) {
         // 初期設定
-        let atcoder = Service {
-            repository_path: String::new(),
-            user_id: String::new(),
-            user_email: String::new(),
-        };
-
-        let config = Config { atcoder };
+        let config = Config { atcoder, ..Config::default() };
 
         let json = serde_json::to_string_pretty(&config)
             .context("Failed to serialize config")?;
@@ -182,257 +618,3946 @@ fn init_config(force: bool) -> Result<()> {
             .context("Failed to write config file")?;
     }
 
-    println!("Initialized your config at {}", config_file.display());
+    say!("Initialized your config at {}", config_file.display());
+    Ok(())
+}
+
+/// 新しく作る設定ファイルの`default_branch`
+const DEFAULT_BRANCH: &str = "main";
+
+/// gitの設定とカレントディレクトリから、分かる項目を埋めたサービス設定
+fn prefill_service(user: Option<String>, email: Option<String>, repository: Option<PathBuf>) -> Service {
+    let git_config = git2::Config::open_default().ok();
+    let git_value = |key: &str| git_config.as_ref().and_then(|config| config.get_string(key).ok());
+
+    let user_id = user
+        .or_else(|| git_value("user.name").filter(|name| is_valid_user_id(name)))
+        .unwrap_or_default();
+    let user_email = email.or_else(|| git_value("user.email")).unwrap_or_default();
+    let repository_path = repository
+        .or_else(|| {
+            Repository::discover(".").ok()
+                .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+        })
+        .and_then(|path| std::path::absolute(path).ok())
+        // workdir()は末尾に区切り文字が付くのでそろえる
+        .map(|path| path.components().collect::<PathBuf>().display().to_string())
+        .unwrap_or_default();
+
+    Service { repository_path, user_id, user_email, default_branch: Some(DEFAULT_BRANCH.to_string()), ..Service::default() }
+}
+
+async fn init_cmd(force: bool, user: Option<String>, email: Option<String>, repository: Option<PathBuf>) -> Result<()> {
+    if !force && is_file_exist(get_config_file()?) {
+        say!("Config already exists at {} (use --force to recreate it)", get_config_file()?.display());
+        return Ok(());
+    }
+
+    let explicit_user = user.is_some();
+    let mut atcoder = prefill_service(user, email, repository);
+
+    if !atcoder.user_id.is_empty() {
+        if !is_valid_user_id(&atcoder.user_id) {
+            return Err(anyhow!("{} is not a valid AtCoder user id", atcoder.user_id));
+        }
+        let client = build_client(&Config::default())?;
+        match user_exists(&client, &atcoder.user_id, &RateLimiter::default()).await {
+            Ok(true) => say!("Found AtCoder user {}", atcoder.user_id),
+            Ok(false) if explicit_user => return Err(anyhow!("AtCoder user {} does not exist", atcoder.user_id)),
+            Ok(false) => {
+                // gitのuser.nameはAtCoderのIDとは限らない
+                say!("git user.name {} is not an AtCoder user, leaving user_id empty", atcoder.user_id);
+                atcoder.user_id.clear();
+            },
+            Err(e) => say_err!("Could not check the AtCoder user {}: {:#}", atcoder.user_id, anyhow::Error::from(e)),
+        }
+    }
+
+    let missing = atcoder.missing_fields();
+
+    // 指定されたリポジトリがまだなければ作っておく
+    let created = !atcoder.repository_path.is_empty() && prepare_repository(&atcoder)?;
+    let repository_path = atcoder.repository_path.clone();
+
+    init_config(force, atcoder)?;
+
+    if created {
+        say!("Created the archive repository {} on the branch {}", repository_path, DEFAULT_BRANCH);
+    }
+
+    if !missing.is_empty() {
+        say!("Fill in {} with `ac-garden edit`", missing.join(", "));
+    }
+    Ok(())
+}
+
+/// 設定ファイルがなければ、端末ではその場で`init`を実行し、そうでなければ作り方を案内して終了する
+async fn ensure_config() -> Result<()> {
+    let config_file = get_config_file()?;
+    if is_file_exist(&config_file) {
+        return Ok(());
+    }
+    if !confirm(&format!("No config found at {}. Set it up now?", config_file.display()))? {
+        let message = format!("No config found at {}. Run `ac-garden init` to create one, or pass --config", config_file.display());
+        return Err(Error::config(message).into());
+    }
+
+    init_cmd(false, None, None, None).await?;
+    // 自動で埋められなかった項目はエディタで埋めてもらう
+    let (config, _) = read_config(&config_file)?;
+    if !config.atcoder.missing_fields().is_empty() {
+        edit_cmd()?;
+    }
     Ok(())
 }
 
 fn load_config() -> Result<Config> {
     let config_file = get_config_file()?;
-    let config_str = fs::read_to_string(&config_file)
-        .context("Failed to read config file")?;
-    
-    let config: Config = serde_json::from_str(&config_str)
-        .context("Failed to parse config")?;
-    
+    let (mut config, report) = read_config(&config_file)?;
+    if let Some(language) = config.language {
+        set_lang(language);
+    }
+    if let Some(profile) = PROFILE.get() {
+        config.apply_profile(profile)?;
+    }
+    if let Some(repository) = REPOSITORY_PATH.get() {
+        config.atcoder.repository_path = repository.to_string_lossy().into_owned();
+    }
+    if report.is_outdated() {
+        say_err!("Your config file is from an older version. Run `ac-garden config migrate` to update it.");
+    }
+    // 空のuser_idでAPIを呼んで分かりにくいエラーになる前に止める
+    config.validate()?;
+    prepare_work_dir(&config.atcoder)?;
+    apply_repo_config(&mut config)?;
     Ok(config)
 }
 
-async fn archive_file(code: &str, file_name: &str, path: &Path, submission: &AtCoderSubmission) -> Result<()> {
-    fs::create_dir_all(path)
-        .context("Failed to create directory")?;
-        
-    let file_path = path.join(file_name);
-    
-    fs::write(&file_path, code)
-        .context("Failed to write source file")?;
-    
-    // 提出JSONを保存
-    let json = serde_json::to_string_pretty(submission)
-        .context("Failed to serialize submission")?;
-        
-    fs::write(path.join("submission.json"), json)
-        .context("Failed to write submission.json")?;
-    
+/// 設定ファイルを現在の形式に書き換える。元のファイルは`config.json.bak`として残す
+fn config_migrate_cmd(dry_run: bool) -> Result<()> {
+    let config_file = get_config_file()?;
+    let (config, report) = read_config(&config_file)?;
+    let changes = report.changes;
+
+    if changes.is_empty() {
+        say!("{} is up to date (version {})", config_file.display(), CONFIG_VERSION);
+        return Ok(());
+    }
+
+    for change in &changes {
+        println!("  {}", change);
+    }
+
+    if dry_run {
+        say!("Dry run: {} change(s), nothing written", changes.len());
+        return Ok(());
+    }
+
+    let backup = config_file.with_extension("json.bak");
+    fs::copy(&config_file, &backup)
+        .with_context(|| format!("Failed to back up config to {}", backup.display()))?;
+    save_config(&config_file, &config)?;
+
+    say!("Migrated {} to version {} (backup: {})", config_file.display(), CONFIG_VERSION, backup.display());
     Ok(())
 }
 
-async fn archive_cmd() -> Result<()> {
-    let config = load_config()?;
-    
-    let client = Client::new();
-    let url = format!("{}{}", ATCODER_API_SUBMISSION_URL, &config.atcoder.user_id);
-    
-    // APIからレスポンスを取得
-    let response = client.get(&url).send().await?;
-    let text = response.text().await?;
-    // 生のレスポンスを出力して内容を確認
-    println!("Raw response: {}", text);
-    
-    // ここでエラーになっているので、レスポンスの形式をまず確認する
-    let submissions: Vec<AtCoderSubmission> = serde_json::from_str(&text)
-        .context("Failed to decode response as an array")?;
-    
-    // AC提出だけをフィルタリング
-    let ac_submissions: Vec<AtCoderSubmission> = submissions.into_iter()
-        .filter(|s| s.result == "AC")
-        .collect();
-    
-    // すでにアーカイブされたコードをスキップ
-    let mut archived_keys = std::collections::HashSet::new();
-    
-    let repo_path = Path::new(&config.atcoder.repository_path);
-    
-    if is_dir_exist(repo_path) {
-        for entry in walkdir::WalkDir::new(repo_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file() && e.file_name().to_string_lossy().ends_with("submission.json"))
-        {
-            let content = fs::read_to_string(entry.path())?;
-            let submission: AtCoderSubmission = serde_json::from_str(&content)?;
-            let key = format!("{}_{}", submission.contest_id, submission.problem_id);
-            archived_keys.insert(key);
+/// 暗号化ファイルのパスフレーズ。環境変数になければ入力してもらう
+fn prompt_passphrase() -> ac_garden::Result<String> {
+    if let Ok(passphrase) = passphrase_from_env() {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password("Passphrase for the credentials file: ")
+        .map_err(|e| Error::credential_with_source("credentials file", "failed to read the passphrase", e))
+}
+
+/// `"keyring"`になっている設定をキーチェーンや暗号化ファイルの値に置き換える
+fn resolve_credentials(config: &mut Config) -> Result<()> {
+    config.atcoder.session = resolve_secret("session", &config.atcoder.session, prompt_passphrase)?;
+    Ok(())
+}
+
+fn credentials_set_cmd(name: &str) -> Result<()> {
+    let secret = rpassword::prompt_password(format!("Value for {}: ", name))
+        .context("Failed to read the value")?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(anyhow!("The value is empty"));
+    }
+
+    match save_secret(name, secret, prompt_passphrase)? {
+        SecretStore::Keyring => say!("Saved {} in the OS keyring", name),
+        SecretStore::EncryptedFile(path) => say!("Saved {} in {}", name, path.display()),
+    }
+
+    // 設定ファイルからは平文の値を消して参照に置き換える
+    let config_file = get_config_file()?;
+    if is_file_exist(&config_file) {
+        let (mut config, _) = read_config(&config_file)?;
+        if name == "session" && config.atcoder.session != KEYRING_REFERENCE {
+            config.atcoder.session = KEYRING_REFERENCE.to_string();
+            save_config(&config_file, &config)?;
+            say!("Updated {} to read {} from the keyring", config_file.display(), name);
         }
     }
-    
-    let mut filtered_submissions: Vec<AtCoderSubmission> = ac_submissions.into_iter()
-        .filter(|s| {
-            let key = format!("{}_{}", s.contest_id, s.problem_id);
-            !archived_keys.contains(&key)
-        })
-        .collect();
-    
-    // 提出時間で逆順ソート
-    filtered_submissions.sort_by(|a, b| b.epoch_second.cmp(&a.epoch_second));
-    
-    // 各問題の最新提出だけをフィルタリング
-    let mut seen = std::collections::HashSet::new();
-    let mut unique_submissions = Vec::new();
-    
-    for submission in filtered_submissions {
-        let key = format!("{}_{}", submission.contest_id, submission.problem_id);
-        if !seen.contains(&key) {
-            seen.insert(key);
-            unique_submissions.push(submission);
+
+    Ok(())
+}
+
+fn credentials_delete_cmd(name: &str) -> Result<()> {
+    if delete_secret(name)? {
+        say!("Deleted {}", name);
+    } else {
+        say!("{} is not stored", name);
+    }
+    Ok(())
+}
+
+/// バックアップファイルの形式のバージョン
+const BACKUP_VERSION: u32 = 1;
+
+/// `backup`で書き出すファイル
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    version: u32,
+    created_at: String,
+    /// 設定ファイルの中身。平文のセッションは`"keyring"`に置き換えてある
+    config: Config,
+    /// 状態ファイル (ファイル名 → 中身)
+    #[serde(default)]
+    state: BTreeMap<String, String>,
+    /// キャッシュ (ファイル名 → 中身)
+    #[serde(default)]
+    cache: BTreeMap<String, String>,
+    /// 資格情報。暗号化ファイルと同じパスフレーズで暗号化してある
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    credentials: Option<String>,
+}
+
+/// ディレクトリ直下のテキストファイルを読み込む。ロックファイルは含めない
+fn read_backup_dir(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    if !is_dir_exist(dir) {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !path.is_file() || name == LOCK_FILE {
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                files.insert(name.to_string(), content);
+            },
+            Err(e) => say_err!("Skipping {}: {}", path.display(), e),
         }
     }
-    
-    println!("Archiving {} code...", unique_submissions.len());
-    
-    let mut start_time = Instant::now();
-    
-    for submission in unique_submissions {
-        let url = format!(
-            "https://atcoder.jp/contests/{}/submissions/{}",
-            submission.contest_id, submission.id
-        );
-        
-        let elapsed = start_time.elapsed();
-        if elapsed < Duration::from_millis(1500) {
-            let sleep_time = Duration::from_millis(1500) - elapsed;
-            tokio::time::sleep(sleep_time).await;
-        }
-        
-        let response = client.get(&url).send().await?;
-        start_time = Instant::now();
-        
-        let html = response.text().await?;
-        let document = Html::parse_document(&html);
-        
-        let selector = Selector::parse("#submission-code").unwrap();
-        
-        if let Some(element) = document.select(&selector).next() {
-            let code = element.text().collect::<Vec<_>>().join("");
-            
-            if code.is_empty() {
-                println!("Empty string...");
-                continue;
-            }
-            
-            let file_name = language_to_file_name(&submission.language);
-            let archive_dir_path = repo_path
-                .join("atcoder.jp")
-                .join(&submission.contest_id)
-                .join(&submission.problem_id);
-            
-            archive_file(&code, &file_name, &archive_dir_path, &submission).await?;
-            
-            println!("archived the code at {}", archive_dir_path.join(&file_name).display());
-            
-            // Gitリポジトリである場合、gitのaddとcommit
-            let git_dir = repo_path.join(".git");
-            if is_dir_exist(&git_dir) {
-                let repo = Repository::open(repo_path)?;
-                let mut index = repo.index()?;
-                
-                // ソースファイルをadd
-                let rel_path = PathBuf::from("atcoder.jp")
-                    .join(&submission.contest_id)
-                    .join(&submission.problem_id)
-                    .join(&file_name);
-                    
-                index.add_path(&rel_path)?;
-                
-                // submission.jsonをadd
-                let json_path = PathBuf::from("atcoder.jp")
-                    .join(&submission.contest_id)
-                    .join(&submission.problem_id)
-                    .join("submission.json");
-                    
-                index.add_path(&json_path)?;
-                index.write()?;
-                
-                let tree_id = index.write_tree()?;
-                let tree = repo.find_tree(tree_id)?;
-                
-                let head = repo.head()?;
-                let parent_commit = repo.find_commit(head.target().unwrap())?;
-                
-                let user_id = &submission.user_id;
-                let user_email = &config.atcoder.user_email;
-                
-                // タイムスタンプの処理
-                // dt変数を削除または_dtにリネーム（未使用変数の警告を防ぐ）
-                // let dt = Utc.timestamp_opt(submission.epoch_second, 0).unwrap();
-                
-                let signature = Signature::new(
-                    user_id,
-                    user_email,
-                    &git2::Time::new(submission.epoch_second, 0),
-                )?;
-                
-                let message = format!("[AC] {} {}", submission.contest_id, submission.problem_id);
-                
-                repo.commit(
-                    Some("HEAD"),
-                    &signature,
-                    &signature,
-                    &message,
-                    &tree,
-                    &[&parent_commit],
-                )?;
-            }
+    Ok(files)
+}
+
+/// バックアップのファイルをディレクトリに書き戻す
+fn write_backup_dir(dir: &Path, files: &BTreeMap<String, String>) -> Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    for (name, content) in files {
+        // ほかの場所に書き込まないよう、ファイル名だけを受け付ける
+        if Path::new(name).file_name() != Some(std::ffi::OsStr::new(name)) {
+            say_err!("Skipping {}: invalid file name", name);
+            continue;
         }
+        let path = dir.join(name);
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
     }
-    
     Ok(())
 }
 
-fn edit_cmd() -> Result<()> {
-    let config_file = get_config_file()?;
-    
-    // 設定ファイルが存在しない場合は初期化
-    if !is_file_exist(&config_file) {
-        init_config(true)?;
+/// パスフレーズを一度だけ求める
+fn cached_passphrase(cache: &mut Option<String>) -> ac_garden::Result<String> {
+    if let Some(passphrase) = cache {
+        return Ok(passphrase.clone());
     }
-    
-    // 環境変数EDITORがあればそれを使う
-    if let Ok(editor) = std::env::var("EDITOR") {
-        Command::new(&editor)
-            .arg(&config_file)
-            .status()?;
+    let passphrase = prompt_passphrase()?;
+    *cache = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+/// 設定ファイルに平文で書かれたセッションを取り出し、設定には`"keyring"`を残す
+///
+/// 資格情報を含めないときは、平文のセッションを消すだけにする
+fn take_plain_session(config: &mut Config, secrets: &mut BTreeMap<String, String>, no_credentials: bool) {
+    let session = &mut config.atcoder.session;
+    if session.is_empty() || session == KEYRING_REFERENCE {
+        return;
+    }
+    if no_credentials {
+        session.clear();
     } else {
-        // ブラウザで開く (Windows/Mac/Linux)
-        #[cfg(target_os = "windows")]
-        {
-            Command::new("cmd")
-                .args(&["/c", "start", "", config_file.to_str().unwrap()])
-                .spawn()?;
-        }
-        
-        #[cfg(target_os = "macos")]
-        {
-            Command::new("open")
-                .arg(&config_file)
-                .spawn()?;
-        }
-        
-        #[cfg(target_os = "linux")]
-        {
-            Command::new("xdg-open")
-                .arg(&config_file)
-                .spawn()?;
+        secrets.insert("session".to_string(), std::mem::replace(session, KEYRING_REFERENCE.to_string()));
+    }
+}
+
+/// バックアップを書き出す。暗号化していても他のユーザーからは読めないようにしておく
+fn write_backup(output: &Path, backup: &Backup) -> Result<()> {
+    let json = serde_json::to_string_pretty(backup).context("Failed to serialize the backup")?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(output).with_context(|| format!("Failed to write {}", output.display()))?;
+    // 前からあったファイルは作成時のモードが使われないので、書き込む前に絞る
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+    }
+    file.write_all(json.as_bytes()).with_context(|| format!("Failed to write {}", output.display()))
+}
+
+fn backup_cmd(output: Option<PathBuf>, no_credentials: bool) -> Result<()> {
+    let config_file = get_config_file()?;
+    let (mut config, _) = read_config(&config_file)?;
+
+    let mut secrets = BTreeMap::new();
+    let mut passphrase = None;
+    if !no_credentials {
+        for name in SECRET_NAMES {
+            if has_secret(name)? {
+                let secret = load_secret(name, || cached_passphrase(&mut passphrase))?;
+                secrets.insert(name.to_string(), secret);
+            }
         }
     }
-    
+    take_plain_session(&mut config, &mut secrets, no_credentials);
+    let credentials = if secrets.is_empty() {
+        None
+    } else {
+        Some(seal_secrets(&secrets, &cached_passphrase(&mut passphrase)?)?)
+    };
+
+    let backup = Backup {
+        version: BACKUP_VERSION,
+        created_at: chrono::Local::now().to_rfc3339(),
+        config,
+        state: read_backup_dir(&get_state_dir()?)?,
+        cache: read_backup_dir(&get_cache_dir()?)?,
+        credentials,
+    };
+
+    let output = match output {
+        Some(output) => output,
+        None => PathBuf::from(format!("ac-garden-backup-{}.json", chrono::Local::now().format("%Y%m%d"))),
+    };
+    write_backup(&output, &backup)?;
+
+    say!(
+        "Backed up the config, {} state file(s), {} cache file(s) and {} credential(s) to {}",
+        backup.state.len(), backup.cache.len(), secrets.len(), output.display()
+    );
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn restore_cmd(input: &Path, force: bool) -> Result<()> {
+    let content = fs::read_to_string(input).with_context(|| format!("Failed to read {}", input.display()))?;
+    let backup: Backup = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", input.display()))?;
+    if backup.version > BACKUP_VERSION {
+        return Err(anyhow!("{} was created by a newer version of ac-garden", input.display()));
+    }
 
-    match cli.command {
-        Commands::Archive => {
-            archive_cmd().await?;
-        },
-        Commands::Init { force } => {
-            init_config(force)?;
-        },
-        Commands::Edit => {
-            edit_cmd()?;
+    let config_file = get_config_file()?;
+    if is_file_exist(&config_file) && !force {
+        return Err(anyhow!("{} already exists. Use --force to overwrite it.", config_file.display()));
+    }
+
+    // 資格情報を先に戻し、パスフレーズが違えば何も書き換えない
+    let secrets = match &backup.credentials {
+        Some(sealed) => unseal_secrets(sealed, &prompt_passphrase()?)?,
+        None => BTreeMap::new(),
+    };
+    for (name, secret) in &secrets {
+        match save_secret(name, secret, prompt_passphrase)? {
+            SecretStore::Keyring => say!("Saved {} in the OS keyring", name),
+            SecretStore::EncryptedFile(path) => say!("Saved {} in {}", name, path.display()),
         }
     }
 
+    if let Some(dir) = config_file.parent() {
+        fs::create_dir_all(dir).context("Failed to create config directory")?;
+    }
+    save_config(&config_file, &backup.config)?;
+    write_backup_dir(&get_state_dir()?, &backup.state)?;
+    write_backup_dir(&get_cache_dir()?, &backup.cache)?;
+
+    say!("Restored {} from {} (created at {})", config_file.display(), input.display(), backup.created_at);
+    let work_dir = backup.config.atcoder.work_dir();
+    if !is_dir_exist(&work_dir) {
+        say_err!("The repository {} does not exist on this machine. Clone it there or edit repository_path.", work_dir.display());
+    }
     Ok(())
 }
+
+/// `archive`のオプション
+#[derive(Default)]
+struct ArchiveOptions {
+    force: bool,
+    contests: Vec<ContestCategory>,
+    retry_failed: bool,
+    interactive: bool,
+    /// チームでもユーザーをまたいだ全体の上限
+    limit: Option<usize>,
+    /// この提出だけをアーカイブする
+    id: Option<i64>,
+    /// 提出の履歴をすべて取得する
+    full: bool,
+}
+
+/// アーカイブした提出の数を返す
+async fn archive_cmd(options: ArchiveOptions) -> Result<usize> {
+    use std::io::IsTerminal;
+
+    let started = Instant::now();
+    if options.interactive && !std::io::stdin().is_terminal() {
+        return Err(anyhow!("--interactive needs a terminal"));
+    }
+    // 設定や認証情報を読んでいる間に別の実行が始まらないよう、最初にロックを取る
+    let _lock = acquire_lock()?;
+    let mut config = load_config()?;
+    resolve_credentials(&mut config)?;
+
+    // チームの場合はユーザーごとにアーカイブする
+    let is_team = !config.atcoder.users.is_empty();
+    let previous_failures: Vec<FailedSubmission> = load_state(FAILED_STATE)?;
+    let mut targets: Vec<(Service, Option<Vec<i64>>)> = Vec::new();
+    for service in config.atcoder.user_services() {
+        if !options.retry_failed {
+            targets.push((service, None));
+            continue;
+        }
+        // 前回失敗した提出だけをやり直す
+        let ids: Vec<i64> = previous_failures.iter()
+            .filter(|failure| failure.user_id == service.user_id)
+            .map(|failure| failure.submission_id)
+            .collect();
+        if !ids.is_empty() {
+            targets.push((service, Some(ids)));
+        }
+    }
+    if options.retry_failed && targets.is_empty() {
+        say!("No failed submissions to retry");
+        return Ok(0);
+    }
+
+    if prepare_repository(&config.atcoder)? {
+        say!("Created the archive repository {}", config.atcoder.repository_path);
+    }
+
+    // undoで戻せるように、実行前のHEADを覚えておく
+    let repo_path = PathBuf::from(&config.atcoder.repository_path);
+    let before = is_git_repository(&repo_path).then(|| repository_heads(&repo_path));
+
+    let mut report = RunReport::default();
+    let result = archive_services(&config, targets, is_team, &options, &mut report).await;
+    let archived_any = result.as_ref().is_ok_and(|&archived| archived > 0);
+
+    // アーカイブが増えたら索引を作り直す。失敗してもアーカイブ自体は済んでいる
+    let index_missing = !is_file_exist(config.atcoder.work_dir().join(INDEX_JSON));
+    if archived_any || index_missing {
+        if let Err(e) = update_index(&config).await {
+            say_err!("Failed to update {}: {:#}", INDEX_JSON, e);
+        }
+    }
+
+    if result.is_ok() {
+        for mirror in &config.atcoder.mirrors {
+            if let Err(e) = sync_mirror(&config, mirror) {
+                say_err!("Failed to update the mirror {}: {:#}", mirror.repository_path, e);
+            }
+        }
+    }
+
+    let commits = before.as_ref().and_then(|(head_before, _)| commit_range(&repo_path, head_before.as_deref()));
+    let summary = RunSummary::new(&report, started.elapsed(), commits);
+    print_run_summary(&summary);
+    // 何もなかった実行まで残すとcronのたびにコミットが増えるので、アーカイブか失敗があったときだけ
+    if config.atcoder.archive_log && (archived_any || !report.failures.is_empty()) {
+        if let Err(e) = write_archive_log(&config, &summary) {
+            say_err!("Failed to update {}: {:#}", ARCHIVE_LOG_MD, e);
+        }
+    }
+
+    if let Some((head_before, notes_before)) = before {
+        let (head_after, notes_after) = repository_heads(&repo_path);
+        if let Some(remote) = config.atcoder.push.as_deref().filter(|_| head_after != head_before) {
+            match push_auth(&config, &repo_path, remote, prompt_passphrase).and_then(|auth| push(&repo_path, remote, None, &auth)) {
+                Ok(()) => say!("Pushed the archive to {}", remote),
+                Err(e) => say_err!("Failed to push to {}: {:#}", remote, e),
+            }
+        }
+        if head_after != head_before {
+            let last_run = LastRun {
+                repository: config.atcoder.repository_path.clone(),
+                head_before,
+                head_after,
+                notes_before,
+                notes_after,
+            };
+            save_state(LAST_RUN_STATE, &last_run)?;
+        }
+    }
+
+    // 最後まで処理したユーザーの失敗は今回の結果で置き換える
+    let mut failures: Vec<FailedSubmission> = previous_failures.into_iter()
+        .filter(|failure| !report.finished_users.contains(&failure.user_id))
+        .collect();
+    failures.append(&mut report.failures);
+    save_state(FAILED_STATE, &failures)?;
+    if !failures.is_empty() {
+        let message = tr!(
+            "{} submission(s) failed. Run `ac-garden archive --retry-failed` to try them again.",
+            failures.len()
+        );
+        anstream::println!("{}", paint(STYLE_FAILED, message));
+    }
+
+    let archived = result?;
+
+    if !report.unknown_languages.is_empty() {
+        say!("Languages missing from the extension table:");
+        for (language, (count, ext)) in &report.unknown_languages {
+            say!("  {} ({} file(s), saved as .{})", language, count, ext);
+        }
+    }
+
+    Ok(archived)
+}
+
+/// リポジトリのルートに置く、アーカイブ済みの問題の索引
+const INDEX_JSON: &str = "index.json";
+
+/// index.jsonの1件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexEntry {
+    contest_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    contest_title: Option<String>,
+    problem_id: String,
+    user_id: String,
+    /// リポジトリのルートからのソースファイルのパス
+    path: String,
+    submission_id: i64,
+    difficulty: Option<f64>,
+    language: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// ACした日時 (RFC 3339)
+    date: String,
+}
+
+impl IndexEntry {
+    /// ACした日時のUNIX時間。読めなければ0
+    fn epoch_second(&self) -> i64 {
+        chrono::DateTime::parse_from_rfc3339(&self.date).map_or(0, |dt| dt.timestamp())
+    }
+}
+
+/// アーカイブを走査して索引の項目を作る (コンテスト・問題・ユーザー・提出ID順)
+///
+/// 難易度は`models`になければ`previous`の値を使う
+fn index_entries(config: &Config, models: &HashMap<String, ProblemModel>, previous: &[IndexEntry]) -> Vec<IndexEntry> {
+    let work_dir = config.atcoder.work_dir();
+    let previous_difficulties: HashMap<&str, f64> = previous.iter()
+        .filter_map(|entry| Some((entry.problem_id.as_str(), entry.difficulty?)))
+        .collect();
+
+    let mut entries = Vec::new();
+    for service in config.atcoder.user_services() {
+        for (metadata_path, submission) in scan_archived_submissions(&service) {
+            let source = metadata_path.parent()
+                .and_then(|dir| find_source_files(dir).ok())
+                .and_then(|sources| sources.into_iter().next());
+            let path = source.as_deref()
+                .and_then(|source| source.strip_prefix(&work_dir).ok())
+                .map(|path| path.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            let difficulty = models.get(&submission.problem_id)
+                .and_then(ProblemModel::clipped_difficulty)
+                .or_else(|| previous_difficulties.get(submission.problem_id.as_str()).copied());
+            let date = chrono::DateTime::from_timestamp(submission.epoch_second, 0)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default();
+            entries.push(IndexEntry {
+                contest_id: submission.contest_id,
+                contest_title: submission.contest_title,
+                problem_id: submission.problem_id,
+                user_id: submission.user_id,
+                path,
+                submission_id: submission.id,
+                difficulty,
+                language: submission.language,
+                tags: submission.tags,
+                date,
+            });
+        }
+    }
+    entries.sort_by(|a, b| {
+        (&a.contest_id, &a.problem_id, &a.user_id, a.submission_id).cmp(&(&b.contest_id, &b.problem_id, &b.user_id, b.submission_id))
+    });
+    entries
+}
+
+/// index.jsonを作り直す。変わっていればtrue
+fn write_index(config: &Config, models: &HashMap<String, ProblemModel>) -> Result<bool> {
+    let index_path = config.atcoder.work_dir().join(INDEX_JSON);
+    let current = fs::read_to_string(&index_path).ok();
+    let previous: Vec<IndexEntry> = current.as_deref()
+        .and_then(|content| serde_json::from_str(content).ok())
+        .unwrap_or_default();
+
+    let entries = index_entries(config, models, &previous);
+    let json = format!("{}\n", serde_json::to_string_pretty(&entries).context("Failed to serialize the index")?);
+    if current.as_deref() == Some(json.as_str()) {
+        return Ok(false);
+    }
+    fs::write(&index_path, &json).with_context(|| format!("Failed to write {}", index_path.display()))?;
+    Ok(true)
+}
+
+/// index.jsonを作り直し、変わっていればコミットする
+async fn update_index(config: &Config) -> Result<()> {
+    // 難易度を取得できなければ、前回の索引の値を使う
+    let client = build_client(config)?;
+    let models = match fetch_problem_models(&client, &RateLimiter::from_config(&config.http)).await {
+        Ok(models) => models,
+        Err(e) => {
+            say_err!("Failed to fetch difficulties, keeping the ones already in {}: {:#}", INDEX_JSON, e);
+            HashMap::new()
+        }
+    };
+    if !write_index(config, &models)? {
+        return Ok(());
+    }
+
+    let repo_path = Path::new(&config.atcoder.repository_path);
+    if is_git_repository(repo_path) {
+        let (author, committer) = commit_signatures(&config.atcoder, &config.atcoder.user_id, chrono::Utc::now().timestamp())?;
+        let message = format!("[Index] Update {}", INDEX_JSON);
+        commit_files(config.atcoder.git_backend, repo_path, &[PathBuf::from(INDEX_JSON)], &author, &committer, &message)?;
+    }
+
+    Ok(())
+}
+
+/// 条件に合う問題のディレクトリをミラーに写してコミットし、設定されていればpushする
+///
+/// ミラーにだけあるファイルは消さない
+fn sync_mirror(config: &Config, mirror: &Mirror) -> Result<()> {
+    let mirror_service = mirror.service(&config.atcoder);
+    let repo_path = Path::new(&mirror.repository_path);
+    if !is_dir_exist(repo_path) {
+        return Err(anyhow!("{} does not exist", repo_path.display()));
+    }
+    prepare_work_dir(&mirror_service)?;
+    let source_dir = config.atcoder.work_dir();
+    let mirror_dir = mirror_service.work_dir();
+
+    // 途中で失敗してコミットしていないファイルが残らないよう、先にすべて読み込んでおく
+    let mut copies = Vec::new();
+    let mut problems = HashSet::new();
+    for service in config.atcoder.user_services() {
+        for (metadata_path, submission) in scan_archived_submissions(&service) {
+            let category = contest_category(&submission.contest_id, submission.contest_title.as_deref());
+            if !mirror.includes(&submission.contest_id, &submission.problem_id, category) {
+                continue;
+            }
+            let Some(dir) = metadata_path.parent().filter(|dir| is_dir_exist(dir)) else {
+                continue;
+            };
+            let Ok(rel_dir) = dir.strip_prefix(&source_dir) else {
+                continue;
+            };
+            for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+                let path = entry?.path();
+                if !path.is_file() {
+                    continue;
+                }
+                let rel_path = rel_dir.join(path.file_name().unwrap_or_default());
+                let content = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+                if fs::read(mirror_dir.join(&rel_path)).ok().as_deref() == Some(content.as_slice()) {
+                    continue;
+                }
+                copies.push((rel_path, content));
+                problems.insert(rel_dir.to_path_buf());
+            }
+        }
+    }
+    if copies.is_empty() {
+        return Ok(());
+    }
+
+    let mut changed = Vec::new();
+    for (rel_path, content) in copies {
+        let target = mirror_dir.join(&rel_path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&target, content).with_context(|| format!("Failed to write {}", target.display()))?;
+        changed.push(rel_path);
+    }
+
+    if is_git_repository(repo_path) {
+        let (author, committer) = commit_signatures(&config.atcoder, &config.atcoder.user_id, chrono::Utc::now().timestamp())?;
+        let message = format!("[Mirror] Update {} problem(s)", problems.len());
+        commit_files(config.atcoder.git_backend, repo_path, &changed, &author, &committer, &message)?;
+    }
+    say!("Mirrored {} problem(s) to {}", problems.len(), repo_path.display());
+
+    if let Some(remote) = &mirror.push {
+        let auth = push_auth(config, repo_path, remote, prompt_passphrase)?;
+        push(repo_path, remote, None, &auth)?;
+    }
+    Ok(())
+}
+
+/// 失敗した提出の記録
+const FAILED_STATE: &str = "failed.json";
+
+/// 取得やコミットに失敗した提出
+#[derive(Serialize, Deserialize)]
+struct FailedSubmission {
+    user_id: String,
+    submission_id: i64,
+    contest_id: String,
+    problem_id: String,
+    error: String,
+    failed_at: String,
+}
+
+/// archiveの実行中に集める結果
+#[derive(Default)]
+struct RunReport {
+    /// ユーザーごとの件数
+    counts: Vec<(String, Arc<Mutex<RunCounts>>)>,
+    /// 対応表にない言語 → (件数, 保存した拡張子)
+    unknown_languages: BTreeMap<String, (usize, String)>,
+    failures: Vec<FailedSubmission>,
+    /// 最後まで処理したユーザー
+    finished_users: HashSet<String>,
+}
+
+/// ユーザーごとにアーカイブする。提出IDを指定したユーザーはその提出だけを対象にする
+async fn archive_services(
+    config: &Config,
+    targets: Vec<(Service, Option<Vec<i64>>)>,
+    is_team: bool,
+    options: &ArchiveOptions,
+    report: &mut RunReport,
+) -> Result<usize> {
+    let mut archived = 0;
+    let mut attempted = 0;
+    // チームのユーザーを順にアーカイブしても、リクエストの間隔は全体で守る
+    let rate_limiter = RateLimiter::from_config(&config.http);
+    for (service, submission_ids) in targets {
+        let limit = options.limit.map(|limit| limit.saturating_sub(attempted));
+        if limit == Some(0) {
+            break;
+        }
+        if is_team {
+            println!("== {} ==", service.user_id);
+        }
+        let user_id = service.user_id.clone();
+        let user_counts = Arc::new(Mutex::new(RunCounts::default()));
+        report.counts.push((user_id.clone(), user_counts.clone()));
+        let config = Config { atcoder: service, ..config.clone() };
+        let mut builder = ArchiveClient::builder()
+            .config(config)
+            .force(options.force)
+            .rate_limiter(rate_limiter.clone());
+        if !options.contests.is_empty() {
+            builder = builder.contests(options.contests.clone());
+        }
+        if options.interactive {
+            builder = builder.select(select_submissions);
+        }
+        if let Some(limit) = limit {
+            builder = builder.limit(limit);
+        }
+        builder = builder.full_history(options.full);
+        if let Some(ids) = submission_ids {
+            // 失敗したときの書きかけのファイルはジャーナルで戻してあるので、手で直したファイルは普段どおり上書きしない
+            builder = builder.submission_ids(ids);
+        }
+        let mut client = builder
+            .on_progress(move |progress| {
+                let mut counts = user_counts.lock().unwrap();
+                match progress {
+                    Progress::Started { fetched, already_archived, total } => {
+                        counts.fetched = *fetched;
+                        counts.already_archived = *already_archived;
+                        counts.total = *total;
+                        say!("Archiving {} code...", total);
+                    },
+                    Progress::Archived { path, .. } => {
+                        counts.archived += 1;
+                        anstream::println!("{}", paint(STYLE_ARCHIVED, tr!("archived the code at {}", path.display())));
+                    },
+                    Progress::Skipped { submission, reason } => {
+                        counts.skipped += 1;
+                        anstream::println!("{}", paint(STYLE_SKIPPED, tr!("Skipped {}: {}", submission.problem_id, reason)));
+                    },
+                    Progress::Failed { submission, error } => {
+                        counts.failed += 1;
+                        anstream::println!("{}", paint(STYLE_FAILED, tr!("Failed to archive {}: {}", submission.problem_id, error)));
+                    },
+                    Progress::ContestResult { contest_id, rank } => say!("archived the result of {} (rank {})", contest_id, rank),
+                    Progress::Recovered(Recovery::Completed { submission_id }) => {
+                        say!("Completed the interrupted archive of submission {}", submission_id)
+                    },
+                    Progress::Recovered(Recovery::RolledBack { submission_id, error: None }) => {
+                        say!("Rolled back the interrupted archive of submission {}", submission_id)
+                    },
+                    Progress::Recovered(Recovery::RolledBack { submission_id, error: Some(error) }) => {
+                        say_err!("Could not commit the interrupted archive of submission {}, rolled it back: {}", submission_id, error)
+                    },
+                    Progress::Recovered(Recovery::SetAside { path, error }) => {
+                        say_err!("Warning: moved the unreadable journal to {}: {}", path.display(), error)
+                    },
+                    Progress::Profile { rating: Some(rating) } => say!("archived the profile (rating {})", rating),
+                    Progress::Profile { rating: None } => say!("archived the profile"),
+                    Progress::Interrupted { archived, total } => {
+                        counts.failed = total - counts.archived - counts.skipped;
+                        let message = tr!("Archived {} of {} code before the interruption. Run archive again to continue.", archived, total);
+                        anstream::println!("{}", paint(STYLE_FAILED, message));
+                    },
+                    Progress::Warning { message } => anstream::eprintln!("{}", message),
+                }
+            })
+            .build()?;
+
+        if let Some(id) = options.id {
+            // チームではどのユーザーの提出か分からないので、見つかるまで順に探す
+            match client.archive_by_id(id).await {
+                Ok(result) => {
+                    archived += usize::from(result.is_some());
+                    return Ok(archived);
+                },
+                Err(Error::SubmissionNotFound { .. }) if is_team => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let summary = client.archive_all().await?;
+        archived += summary.archived;
+        attempted += summary.pending;
+        if summary.deferred > 0 {
+            say!("{} pending submission(s) left for the next run", summary.deferred);
+        }
+        for (language, (count, ext)) in summary.unknown_languages {
+            report.unknown_languages.entry(language).or_insert((0, ext)).0 += count;
+        }
+        let failed_at = chrono::Local::now().to_rfc3339();
+        report.failures.extend(summary.failed.into_iter().map(|(submission, error)| FailedSubmission {
+            user_id: user_id.clone(),
+            submission_id: submission.id,
+            contest_id: submission.contest_id,
+            problem_id: submission.problem_id,
+            error,
+            failed_at: failed_at.clone(),
+        }));
+        report.finished_users.insert(user_id);
+    }
+    if let Some(id) = options.id {
+        return Err(Error::SubmissionNotFound { id, user_id: config.atcoder.users.join(", ") }.into());
+    }
+    Ok(archived)
+}
+
+/// 1回のarchiveでの提出の数
+#[derive(Default)]
+struct RunCounts {
+    /// 取得したAC提出
+    fetched: usize,
+    /// 取得したうち、アーカイブ済みだった提出
+    already_archived: usize,
+    total: usize,
+    archived: usize,
+    skipped: usize,
+    /// 失敗したか、エラーで中断してアーカイブできなかった
+    failed: usize,
+}
+
+/// アーカイブする提出を、まだアーカイブしていない提出のチェックボックスの一覧から選んでもらう
+///
+/// 選ばずに閉じたときは何もアーカイブしない。端末の入力エラーはそのまま返し、archiveを失敗させる
+fn select_submissions(pending: Vec<Submission>) -> Result<Vec<Submission>, BoxError> {
+    let items: Vec<String> = pending.iter()
+        .map(|s| {
+            let date = chrono::DateTime::from_timestamp(s.epoch_second, 0)
+                .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            format!("{}  {}  {}  #{}", s.problem_id, s.language, date, s.id)
+        })
+        .collect();
+    // 新しい方の提出をアーカイブすると、同じ問題の古い提出は次から候補に出ない
+    let mut problems = HashSet::new();
+    if !pending.iter().all(|s| problems.insert((s.contest_id.as_str(), s.problem_id.as_str()))) {
+        say!("Note: once a newer submission of a problem is archived, older unticked ones of that problem are no longer offered");
+    }
+    let selected: HashSet<usize> = dialoguer::MultiSelect::new()
+        .with_prompt(tr!("Submissions to archive (space to toggle, enter to confirm)"))
+        .items(&items)
+        .interact_opt()?
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    Ok(pending.into_iter()
+        .enumerate()
+        .filter(|(i, _)| selected.contains(i))
+        .map(|(_, submission)| submission)
+        .collect())
+}
+
+/// archiveが作ったコミットの範囲
+struct CommitRange {
+    /// 実行前のHEAD。リポジトリが空だった場合はNone
+    before: Option<String>,
+    after: String,
+    count: usize,
+}
+
+impl std::fmt::Display for CommitRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let short = |id: &str| id.chars().take(7).collect::<String>();
+        match &self.before {
+            Some(before) => write!(f, "{}..{}", short(before), short(&self.after)),
+            None => write!(f, "{}", short(&self.after)),
+        }
+    }
+}
+
+/// 実行前と今のHEADの間のコミット。HEADが動いていなければNone
+fn commit_range(repo_path: &Path, before: Option<&str>) -> Option<CommitRange> {
+    let repo = Repository::open(repo_path).ok()?;
+    let after = repo.head().ok()?.target()?;
+    if before == Some(after.to_string().as_str()) {
+        return None;
+    }
+    let mut walk = repo.revwalk().ok()?;
+    walk.push(after).ok()?;
+    if let Some(before) = before.and_then(|before| git2::Oid::from_str(before).ok()) {
+        walk.hide(before).ok()?;
+    }
+    Some(CommitRange { before: before.map(str::to_string), after: after.to_string(), count: walk.count() })
+}
+
+/// 実行の最後に表示し、ARCHIVE_LOG.mdにも残す集計
+struct RunSummary<'a> {
+    /// ユーザーごとの取得・アーカイブ済み・アーカイブ・スキップ・失敗の数
+    rows: Vec<(String, [usize; 5])>,
+    failures: &'a [FailedSubmission],
+    elapsed: Duration,
+    commits: Option<CommitRange>,
+}
+
+impl<'a> RunSummary<'a> {
+    fn new(report: &'a RunReport, elapsed: Duration, commits: Option<CommitRange>) -> Self {
+        let rows = report.counts.iter()
+            .map(|(user, counts)| {
+                let counts = counts.lock().unwrap();
+                (user.clone(), [counts.fetched, counts.already_archived, counts.archived, counts.skipped, counts.failed])
+            })
+            .collect();
+        RunSummary { rows, failures: &report.failures, elapsed, commits }
+    }
+
+    /// 取得した提出もアーカイブした提出もなかったか
+    fn is_empty(&self) -> bool {
+        self.rows.iter().all(|(_, values)| values.iter().all(|&count| count == 0))
+    }
+}
+
+/// 実行の最後に、ユーザーごとの件数の表と失敗の理由、かかった時間、作ったコミットの範囲を表示する
+fn print_run_summary(summary: &RunSummary) {
+    if summary.is_empty() {
+        return;
+    }
+
+    let headers = [tr!("fetched"), tr!("already archived"), tr!("archived"), tr!("skipped"), tr!("failed")];
+    let styles = [None, None, Some(STYLE_ARCHIVED), Some(STYLE_SKIPPED), Some(STYLE_FAILED)];
+    let widths: Vec<usize> = headers.iter().map(|header| header.chars().count().max(8)).collect();
+    let width = summary.rows.iter().map(|(user, _)| user.len()).max().unwrap_or(0).max(4);
+    println!();
+    say!("Summary:");
+    let header_cells: Vec<String> = headers.iter().zip(&widths).map(|(header, &w)| format!("{:>w$}", header)).collect();
+    println!("  {:<width$}  {}", tr!("user"), header_cells.join("  "), width = width);
+    for (user, values) in &summary.rows {
+        let cells: Vec<String> = values.iter().zip(&styles).zip(&widths)
+            .map(|((&count, &style), &w)| {
+                let text = format!("{:>w$}", count);
+                match style {
+                    Some(style) if count > 0 => paint(style, text),
+                    _ => text,
+                }
+            })
+            .collect();
+        anstream::println!("  {:<width$}  {}", user, cells.join("  "), width = width);
+    }
+
+    for failure in summary.failures {
+        say!("  Failed {} (submission {}): {}", failure.problem_id, failure.submission_id, failure.error);
+    }
+    say!("Finished in {:.1}s", summary.elapsed.as_secs_f64());
+    if let Some(commits) = &summary.commits {
+        say!("Created {} commit(s): {}", commits.count, commits);
+    }
+}
+
+/// 実行の記録を追記していくファイル (リポジトリのルートに置く)
+const ARCHIVE_LOG_MD: &str = "ARCHIVE_LOG.md";
+const ARCHIVE_LOG_TITLE: &str = "# Archive log";
+
+/// ARCHIVE_LOG.mdの先頭に今回の実行の記録を足してコミットする。新しい記録ほど上に並ぶ
+///
+/// 記録のコミットはアーカイブのコミットの後に別に作るので、記録したコミットの範囲には含まれない。undoではこのコミットも取り消す
+fn write_archive_log(config: &Config, summary: &RunSummary) -> Result<()> {
+    let mut section = format!("## {}\n\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S %:z"));
+    section.push_str("| User | Fetched | Already archived | Archived | Skipped | Failed |\n| --- | ---: | ---: | ---: | ---: | ---: |\n");
+    for (user, values) in &summary.rows {
+        let cells: Vec<String> = values.iter().map(|count| count.to_string()).collect();
+        section.push_str(&format!("| {} | {} |\n", user, cells.join(" | ")));
+    }
+    section.push('\n');
+    section.push_str(&format!("- Runtime: {:.1}s\n", summary.elapsed.as_secs_f64()));
+    if let Some(commits) = &summary.commits {
+        section.push_str(&format!("- Commits: `{}` ({}, followed by the commit of this entry)\n", commits, commits.count));
+    }
+    for failure in summary.failures {
+        let error = failure.error.replace('\n', " ");
+        section.push_str(&format!("- Failed {} (submission {}): {}\n", failure.problem_id, failure.submission_id, error));
+    }
+
+    let path = config.atcoder.work_dir().join(ARCHIVE_LOG_MD);
+    let previous = fs::read_to_string(&path).unwrap_or_default();
+    fs::write(&path, prepend_log_entry(&previous, ARCHIVE_LOG_TITLE, &section))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let repo_path = Path::new(&config.atcoder.repository_path);
+    if is_git_repository(repo_path) {
+        let (author, committer) = commit_signatures(&config.atcoder, &config.atcoder.user_id, chrono::Utc::now().timestamp())?;
+        let message = format!("[Log] Update {}", ARCHIVE_LOG_MD);
+        commit_files(config.atcoder.git_backend, repo_path, &[PathBuf::from(ARCHIVE_LOG_MD)], &author, &committer, &message)?;
+    }
+    Ok(())
+}
+
+/// 直前のarchiveの記録
+const LAST_RUN_STATE: &str = "last_run.json";
+
+/// archiveが動かしたコミットの範囲
+#[derive(Serialize, Deserialize, Default)]
+struct LastRun {
+    repository: String,
+    /// 実行前のHEAD。リポジトリが空だった場合はNone
+    head_before: Option<String>,
+    head_after: Option<String>,
+    #[serde(default)]
+    notes_before: Option<String>,
+    #[serde(default)]
+    notes_after: Option<String>,
+}
+
+/// HEADとノートの参照が指すコミット
+fn repository_heads(repo_path: &Path) -> (Option<String>, Option<String>) {
+    let Ok(repo) = Repository::open(repo_path) else {
+        return (None, None);
+    };
+    let head = repo.head().ok().and_then(|head| head.target()).map(|oid| oid.to_string());
+    let notes = repo.refname_to_id(NOTES_REF).ok().map(|oid| oid.to_string());
+    (head, notes)
+}
+
+/// 参照を指定したコミットに戻す。Noneなら参照を消す
+fn reset_reference(repo: &Repository, name: &str, target: Option<&str>) -> Result<()> {
+    match target {
+        Some(target) => {
+            let oid = git2::Oid::from_str(target)?;
+            repo.reference(name, oid, true, "ac-garden: undo")?;
+        },
+        None => {
+            if let Ok(mut reference) = repo.find_reference(name) {
+                reference.delete()?;
+            }
+        },
+    }
+    Ok(())
+}
+
+/// 直前のarchiveで作ったコミットを取り消し、書き込んだファイルを元に戻す
+fn undo_cmd() -> Result<()> {
+    let _lock = acquire_lock()?;
+    let config = load_config()?;
+    let last_run: LastRun = load_state(LAST_RUN_STATE)?;
+    if last_run.head_after.is_none() || last_run.repository != config.atcoder.repository_path {
+        say!("Nothing to undo");
+        return Ok(());
+    }
+
+    let repo_path = Path::new(&config.atcoder.repository_path);
+    let repo = Repository::open(repo_path)
+        .with_context(|| format!("Failed to open {}", repo_path.display()))?;
+    let (head, _) = repository_heads(repo_path);
+    if head != last_run.head_after {
+        return Err(anyhow!("HEAD has moved since the last archive run; refusing to undo"));
+    }
+
+    let tree_of = |id: &Option<String>| -> Result<Option<git2::Tree>> {
+        match id {
+            Some(id) => Ok(Some(repo.find_commit(git2::Oid::from_str(id)?)?.tree()?)),
+            None => Ok(None),
+        }
+    };
+    let before_tree = tree_of(&last_run.head_before)?;
+    let after_tree = tree_of(&last_run.head_after)?;
+    let diff = repo.diff_tree_to_tree(before_tree.as_ref(), after_tree.as_ref(), None)
+        .context("Failed to diff the last archive run")?;
+
+    // ファイルを実行前の状態に戻す
+    let work_dir = config.atcoder.work_dir();
+    let mut restored = Vec::new();
+    for delta in diff.deltas() {
+        let Some(rel_path) = delta.new_file().path().or(delta.old_file().path()) else {
+            continue;
+        };
+        let path = work_dir.join(rel_path);
+        if delta.old_file().id().is_zero() {
+            if is_file_exist(&path) {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+            // 空になったディレクトリも消す
+            let mut dir = path.parent();
+            while let Some(d) = dir.filter(|d| *d != work_dir) {
+                if fs::remove_dir(d).is_err() {
+                    break;
+                }
+                dir = d.parent();
+            }
+        } else {
+            let blob = repo.find_blob(delta.old_file().id())?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            fs::write(&path, blob.content())
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        restored.push(rel_path.to_path_buf());
+    }
+
+    // ブランチとノートを実行前に戻す
+    let branch = repo.head()?.name().map(str::to_string)
+        .ok_or_else(|| anyhow!("HEAD is not a valid reference"))?;
+    reset_reference(&repo, &branch, last_run.head_before.as_deref())?;
+    let (_, notes) = repository_heads(repo_path);
+    if notes == last_run.notes_after {
+        reset_reference(&repo, NOTES_REF, last_run.notes_before.as_deref())?;
+    }
+
+    // インデックスは戻したパスだけ更新する
+    if !repo.is_bare() {
+        let mut index = repo.index()?;
+        for rel_path in &restored {
+            if is_file_exist(work_dir.join(rel_path)) {
+                index.add_path(rel_path)?;
+            } else {
+                index.remove_path(rel_path)?;
+            }
+        }
+        index.write()?;
+    }
+
+    save_state(LAST_RUN_STATE, &LastRun::default())?;
+    say!("Reverted {} file(s) archived by the last run", restored.len());
+    Ok(())
+}
+
+fn is_maintenance_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Error>().is_some_and(Error::is_maintenance)
+}
+
+/// エラーの種類に対応する終了コード
+fn exit_code_of(error: &anyhow::Error) -> i32 {
+    for cause in error.chain() {
+        if let Some(error) = cause.downcast_ref::<Error>() {
+            return match error {
+                Error::Config { .. } | Error::Credential { .. } => EXIT_CONFIG,
+                Error::Network(_) | Error::RateLimited { .. } | Error::Offline { .. } => EXIT_NETWORK,
+                Error::Maintenance { .. } => EXIT_MAINTENANCE,
+                Error::Scrape { .. } => EXIT_SCRAPE,
+                Error::Git(_) => EXIT_GIT,
+                Error::HookFailed { .. } => EXIT_HOOK,
+                _ => EXIT_FAILURE,
+            };
+        }
+        if cause.is::<reqwest::Error>() {
+            return EXIT_NETWORK;
+        }
+        if cause.is::<git2::Error>() {
+            return EXIT_GIT;
+        }
+        if cause.is::<LockHeld>() {
+            return EXIT_LOCKED;
+        }
+    }
+    EXIT_FAILURE
+}
+
+/// 問題IDからアーカイブ済みの提出を探す
+fn find_archived(service: &Service, problem_id: &str) -> Result<(PathBuf, Submission)> {
+    scan_archived_submissions(service)
+        .into_iter()
+        .find(|(_, s)| s.problem_id == problem_id)
+        .ok_or_else(|| anyhow!("{} is not archived yet", problem_id))
+}
+
+/// タグを正規化して追加・削除する。変更があればtrue
+fn update_tags(submission: &mut Submission, tags: &[String], remove: bool) -> bool {
+    let before = submission.tags.clone();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
+        if remove {
+            submission.tags.retain(|t| *t != tag);
+        } else if !submission.tags.contains(&tag) {
+            submission.tags.push(tag);
+        }
+    }
+    submission.tags.sort();
+    submission.tags != before
+}
+
+fn tag_cmd(problem_id: Option<&str>, tags: &[String], remove: bool, import: Option<&Path>) -> Result<()> {
+    let config = load_config()?;
+    let repo_path = Path::new(&config.atcoder.repository_path);
+
+    // 問題ID → 追加するタグ
+    let mut updates: Vec<(String, Vec<String>)> = Vec::new();
+    if let Some(path) = import {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let dataset: HashMap<String, Vec<String>> = serde_json::from_str(&content)
+            .context("Failed to parse tag dataset (expected {\"problem_id\": [\"tag\", ...]})")?;
+        updates.extend(dataset);
+    }
+    if let Some(problem_id) = problem_id {
+        if tags.is_empty() && import.is_none() {
+            // タグを指定しなければ今のタグを表示する
+            let (_, submission) = find_archived(&config.atcoder, problem_id)?;
+            println!("{}: {}", problem_id, submission.tags.join(", "));
+            return Ok(());
+        }
+        updates.push((problem_id.to_string(), tags.to_vec()));
+    }
+
+    let archived = scan_archived_submissions(&config.atcoder);
+    let mut changed_paths = Vec::new();
+    let mut tagged = 0;
+
+    for (problem_id, tags) in updates {
+        let entry = archived.iter().find(|(_, s)| s.problem_id == problem_id);
+        let (metadata_path, submission) = match entry {
+            Some(entry) => entry,
+            None => {
+                // データセットには未アーカイブの問題も含まれる
+                if import.is_none() {
+                    return Err(anyhow!("{} is not archived yet", problem_id));
+                }
+                continue;
+            }
+        };
+
+        let mut submission = submission.clone();
+        if !update_tags(&mut submission, &tags, remove) {
+            continue;
+        }
+        tagged += 1;
+
+        if !metadata_path.exists() {
+            // git notesにだけある提出は、ノートを付けたコミットのノートを書き換える
+            let commit_id = read_notes_by_commit(&config.atcoder)
+                .into_iter()
+                .find(|(_, s)| s.id == submission.id)
+                .map(|(commit_id, _)| commit_id)
+                .ok_or_else(|| anyhow!("{} is not archived yet", problem_id))?;
+            let (author, committer) = commit_signatures(&config.atcoder, &config.atcoder.user_id, chrono::Utc::now().timestamp())?;
+            write_note(repo_path, commit_id, &author, &committer, &submission)?;
+            println!("{}: {}", problem_id, submission.tags.join(", "));
+            continue;
+        }
+
+        let dir = metadata_path.parent().unwrap_or(Path::new("."));
+        write_metadata(dir, &submission, metadata_format_of(metadata_path))?;
+        println!("{}: {}", problem_id, submission.tags.join(", "));
+
+        if let Ok(rel_path) = metadata_path.strip_prefix(config.atcoder.work_dir()) {
+            changed_paths.push(rel_path.to_path_buf());
+        }
+    }
+
+    // 索引のタグも合わせる。難易度は取得し直さずに前回の値を使う
+    if tagged > 0 && is_file_exist(config.atcoder.work_dir().join(INDEX_JSON)) && write_index(&config, &HashMap::new())? {
+        changed_paths.push(PathBuf::from(INDEX_JSON));
+    }
+
+    if !changed_paths.is_empty() && is_git_repository(repo_path) {
+        let (author, committer) = commit_signatures(&config.atcoder, &config.atcoder.user_id, chrono::Utc::now().timestamp())?;
+        let message = match problem_id {
+            Some(problem_id) if import.is_none() => format!("[Tag] {}", problem_id),
+            _ => format!("[Tag] Update tags of {} problems", tagged),
+        };
+        commit_files(config.atcoder.git_backend, repo_path, &changed_paths, &author, &committer, &message)?;
+    }
+
+    Ok(())
+}
+
+/// タグ・言語で絞り込んだアーカイブ済みの提出 (コンテスト・問題順)
+fn filter_archived(service: &Service, tag: Option<&str>, language: Option<&str>) -> Vec<Submission> {
+    let submissions = scan_archived_submissions(service).into_iter().map(|(_, submission)| submission);
+    filter_submissions(submissions, tag, language)
+}
+
+/// 提出をタグ・言語で絞り込み、コンテスト・問題順に並べる
+fn filter_submissions(submissions: impl IntoIterator<Item = Submission>, tag: Option<&str>, language: Option<&str>) -> Vec<Submission> {
+    let mut submissions: Vec<Submission> = submissions
+        .into_iter()
+        .filter(|s| tag.is_none_or(|tag| s.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+        .filter(|s| language.is_none_or(|filter| language_matches(&s.language, filter)))
+        .collect();
+    submissions.sort_by(|a, b| (&a.contest_id, &a.problem_id).cmp(&(&b.contest_id, &b.problem_id)));
+    submissions
+}
+
+async fn list_cmd(tag: Option<&str>, language: Option<&str>) -> Result<()> {
+    let config = load_config()?;
+    let submissions = filter_archived(&config.atcoder, tag, language);
+
+    // コンテスト名を記録していない以前の提出は、コンテスト一覧から名前を引く
+    let titles = if submissions.iter().any(|s| s.contest_title.is_none()) {
+        let client = build_client(&config)?;
+        fetch_contests(&client, false, &RateLimiter::from_config(&config.http)).await
+            .map(|contests| contest_titles(&value_or_warn(contests, tr!("the contest list"))))
+            .unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+    let contest_title = |submission: &Submission| -> String {
+        submission.contest_title.clone()
+            .or_else(|| titles.get(&submission.contest_id).cloned())
+            .unwrap_or_else(|| submission.contest_id.clone())
+    };
+    let width = submissions.iter().map(|s| contest_title(s).chars().count()).max().unwrap_or(0).max(12);
+
+    for submission in &submissions {
+        let date = chrono::DateTime::from_timestamp(submission.epoch_second, 0)
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let tags = if submission.tags.is_empty() {
+            String::new()
+        } else {
+            format!("  [{}]", submission.tags.join(", "))
+        };
+        println!(
+            "{:<width$} {:<16} {}  {}{}",
+            contest_title(submission), submission.problem_id, date, submission.language, tags,
+            width = width
+        );
+    }
+    say!("{} problem(s)", submissions.len());
+
+    Ok(())
+}
+
+/// 件数の多い順に並べた集計
+fn count_by<'a>(items: impl Iterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for item in items {
+        *counts.entry(item).or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+}
+
+/// アーカイブ済みの提出からストリークを求める (日付の区切りはローカル時間)
+fn archive_streaks(epoch_seconds: impl IntoIterator<Item = i64>) -> ac_garden::streak::Streaks {
+    use chrono::TimeZone;
+
+    let days: std::collections::BTreeSet<chrono::NaiveDate> = epoch_seconds.into_iter()
+        .filter_map(|epoch_second| chrono::Local.timestamp_opt(epoch_second, 0).single())
+        .map(|dt| dt.date_naive())
+        .collect();
+    ac_garden::streak::streaks(&days, chrono::Local::now().date_naive())
+}
+
+fn status_cmd() -> Result<()> {
+    let config = load_config()?;
+    let submissions = filter_archived(&config.atcoder, None, None);
+    let streaks = archive_streaks(submissions.iter().map(|s| s.epoch_second));
+
+    say!("Archived problems: {}", submissions.len());
+    if let Some(latest) = submissions.iter().max_by_key(|s| s.epoch_second) {
+        let date = chrono::DateTime::from_timestamp(latest.epoch_second, 0)
+            .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        say!("Latest AC: {} ({})", latest.problem_id, date);
+    }
+    say!("Current streak: {} day(s) (longest: {})", streaks.current, streaks.longest);
+
+    if !streaks.solved_today && streaks.current > 0 {
+        println!();
+        say!(
+            "Warning: no AC archived today yet. Solve one before midnight to keep your {}-day streak!",
+            streaks.current
+        );
+    }
+
+    Ok(())
+}
+
+async fn stats_cmd(time: bool, json: bool) -> Result<()> {
+    let config = load_config()?;
+    let submissions = filter_archived(&config.atcoder, None, None);
+    if time {
+        let client = build_client(&config)?;
+        let contests = match fetch_contests(&client, false, &RateLimiter::from_config(&config.http)).await {
+            Ok(contests) => value_or_warn(contests, tr!("the contest list")),
+            Err(e) => {
+                say_err!("Failed to fetch contests, skipping the time from contest start: {:#}", e);
+                Vec::new()
+            }
+        };
+        let stats = time_stats(&submissions, &contests);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+        } else {
+            print_time_stats(&stats);
+        }
+        return Ok(());
+    }
+    let streaks = archive_streaks(submissions.iter().map(|s| s.epoch_second));
+
+    say!("Archived problems: {}", submissions.len());
+    say!("Current streak: {} day(s)", streaks.current);
+    say!("Longest streak: {} day(s)", streaks.longest);
+
+    println!();
+    say!("By language:");
+    let languages: Vec<String> = submissions.iter().map(|s| canonical_language(&s.language)).collect();
+    for (language, count) in count_by(languages.iter().map(String::as_str)) {
+        println!("  {:<24} {}", language, count);
+    }
+
+    println!();
+    say!("By tag:");
+    let tags = count_by(submissions.iter().flat_map(|s| s.tags.iter().map(|t| t.as_str())));
+    if tags.is_empty() {
+        say!("  (no tags yet, add some with `ac-garden tag`)");
+    }
+    for (tag, count) in tags {
+        println!("  {:<24} {}", tag, count);
+    }
+
+    Ok(())
+}
+
+/// ACした時間帯の集計
+#[derive(Debug, Serialize)]
+struct TimeStats {
+    /// 時刻 (0〜23時、ローカル時間) ごとのAC数
+    by_hour: Vec<usize>,
+    /// 曜日 (月曜始まり) ごとのAC数
+    by_weekday: Vec<usize>,
+    /// レーティング対象のコンテスト中にACした数
+    rated_contest_acs: usize,
+    /// コンテスト開始からACまでの平均時間 (分)
+    average_minutes_from_start: Option<f64>,
+}
+
+fn time_stats(submissions: &[Submission], contests: &[Contest]) -> TimeStats {
+    use chrono::{Datelike, TimeZone, Timelike};
+
+    let mut by_hour = vec![0; 24];
+    let mut by_weekday = vec![0; 7];
+    for submission in submissions {
+        if let Some(dt) = chrono::Local.timestamp_opt(submission.epoch_second, 0).single() {
+            by_hour[dt.hour() as usize] += 1;
+            by_weekday[dt.weekday().num_days_from_monday() as usize] += 1;
+        }
+    }
+
+    // コンテスト中のACだけを、開始からの経過時間で数える
+    let rated: HashMap<&str, &Contest> = contests.iter()
+        .filter(|c| c.rate_change != "-")
+        .map(|c| (c.id.as_str(), c))
+        .collect();
+    let elapsed: Vec<i64> = submissions.iter()
+        .filter_map(|s| {
+            let contest = rated.get(s.contest_id.as_str())?;
+            let elapsed = s.epoch_second - contest.start_epoch_second;
+            (0..=contest.duration_second).contains(&elapsed).then_some(elapsed)
+        })
+        .collect();
+    let average_minutes_from_start = (!elapsed.is_empty())
+        .then(|| elapsed.iter().sum::<i64>() as f64 / elapsed.len() as f64 / 60.0);
+
+    TimeStats { by_hour, by_weekday, rated_contest_acs: elapsed.len(), average_minutes_from_start }
+}
+
+/// 件数を`#`の棒で表示する
+fn print_bars(labels: &[String], counts: &[usize]) {
+    const BAR_WIDTH: usize = 40;
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    for (label, &count) in labels.iter().zip(counts) {
+        println!("  {:<4} {:<width$} {}", label, "#".repeat(count * BAR_WIDTH / max), count, width = BAR_WIDTH);
+    }
+}
+
+fn print_time_stats(stats: &TimeStats) {
+    say!("By hour of day:");
+    let hours: Vec<String> = (0..24).map(|h| format!("{:02}", h)).collect();
+    print_bars(&hours, &stats.by_hour);
+
+    println!();
+    say!("By weekday:");
+    let weekdays: Vec<String> = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"].iter().map(|d| d.to_string()).collect();
+    print_bars(&weekdays, &stats.by_weekday);
+
+    println!();
+    match stats.average_minutes_from_start {
+        Some(minutes) => say!(
+            "Average time from contest start to AC: {:.1} min ({} AC(s) in rated contests)",
+            minutes, stats.rated_contest_acs
+        ),
+        None => say!("No ACs during rated contests"),
+    }
+}
+
+async fn trends_cmd() -> Result<()> {
+    let mut config = load_config()?;
+    resolve_credentials(&mut config)?;
+    let archived = filter_archived(&config.atcoder, None, None);
+
+    let mut client = ArchiveClient::builder().config(config).build()?;
+    let mut by_problem: HashMap<String, Vec<Submission>> = HashMap::new();
+    for submission in client.fetch_submissions().await? {
+        if submission.result == "AC" {
+            by_problem.entry(submission.problem_id.clone()).or_default().push(submission);
+        }
+    }
+
+    let mut trends: Vec<Trend> = archived.into_iter()
+        .filter_map(|archived| {
+            let history = by_problem.remove(&archived.problem_id)?;
+            Trend::new(archived, history)
+        })
+        .collect();
+    if trends.is_empty() {
+        say!("No re-solved problems yet");
+        return Ok(());
+    }
+    // 遅く・長くなった問題を先に
+    trends.sort_by_key(|t| (!(t.is_slower() || t.is_longer()), t.archived.problem_id.clone()));
+
+    let format_time = |s: &Submission| s.execution_time.map_or("-".to_string(), |t| t.to_string());
+    say!("Re-solved problems (execution time in ms / code length in bytes, oldest first):");
+    for trend in &trends {
+        let times: Vec<String> = trend.history.iter().map(format_time).collect();
+        let lengths: Vec<String> = trend.history.iter().map(|s| s.length.to_string()).collect();
+        let mut marks = Vec::new();
+        if trend.is_slower() {
+            marks.push("slower");
+        }
+        if trend.is_longer() {
+            marks.push("longer");
+        }
+        let marks = if marks.is_empty() {
+            String::new()
+        } else {
+            format!("  ! {} than archived", marks.join(" and "))
+        };
+        println!("  {:<16} time {}  length {}{}", trend.archived.problem_id, times.join("→"), lengths.join("→"), marks);
+    }
+
+    Ok(())
+}
+
+/// 月ごとのアーカイブの伸び
+struct MonthlyGrowth {
+    /// ローカル時間の年月 (例: 2024-05)
+    month: String,
+    /// その月にACした問題の数
+    solved: usize,
+    /// その月までの問題の数
+    total_problems: usize,
+    /// その月までのソースコードの行数
+    total_lines: usize,
+}
+
+/// アーカイブ済みの問題を、ACした月ごとに積み上げる。ACのない月も0件として入れる
+fn monthly_growth(service: &Service) -> Vec<MonthlyGrowth> {
+    use chrono::{Datelike, TimeZone};
+
+    // (ユーザー, 問題) → (最初にACした日時, メタデータのパス)。
+    // policyがallやAHCのスコアの記録で同じ問題の提出が複数あっても、最初にACした月の1問として数える
+    let mut first_acs: HashMap<(String, String), (i64, PathBuf)> = HashMap::new();
+    for service in service.user_services() {
+        for (metadata_path, submission) in scan_archived_submissions(&service) {
+            if submission.result != "AC" {
+                continue;
+            }
+            let first = first_acs.entry((service.user_id.clone(), archive_key(&submission)))
+                .or_insert((submission.epoch_second, metadata_path.clone()));
+            if submission.epoch_second < first.0 {
+                *first = (submission.epoch_second, metadata_path);
+            }
+        }
+    }
+
+    // (年, 月) → (AC数, 行数)
+    let mut months: BTreeMap<(i32, u32), (usize, usize)> = BTreeMap::new();
+    for (epoch_second, metadata_path) in first_acs.into_values() {
+        let Some(month) = chrono::Local.timestamp_opt(epoch_second, 0).single() else {
+            continue;
+        };
+        let dir = metadata_path.parent().unwrap_or(Path::new("."));
+        let lines = match find_source_files(dir) {
+            Ok(sources) => sources.first()
+                .and_then(|source| fs::read(source).ok())
+                .map_or(0, |code| String::from_utf8_lossy(&code).lines().count()),
+            Err(_) => 0,
+        };
+        let entry = months.entry((month.year(), month.month())).or_default();
+        entry.0 += 1;
+        entry.1 += lines;
+    }
+
+    let (Some(&first), Some(&last)) = (months.keys().next(), months.keys().next_back()) else {
+        return Vec::new();
+    };
+    let mut growth = Vec::new();
+    let (mut total_problems, mut total_lines) = (0, 0);
+    let (mut year, mut month) = first;
+    while (year, month) <= last {
+        let (solved, lines) = months.get(&(year, month)).copied().unwrap_or_default();
+        total_problems += solved;
+        total_lines += lines;
+        growth.push(MonthlyGrowth { month: format!("{}-{:02}", year, month), solved, total_problems, total_lines });
+        (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    }
+    growth
+}
+
+/// 月ごとの伸びのグラフ (累計の行数と、月ごとのAC数を縦に並べる)
+fn growth_chart_svg(growth: &[MonthlyGrowth]) -> String {
+    use ac_garden::chart::{line_chart_svg, stack_svg, Series};
+
+    // x軸は最初の月からの月数
+    let months: Vec<String> = growth.iter().map(|g| g.month.clone()).collect();
+    let x_label = |x: f64| months.get(x.round() as usize).cloned().unwrap_or_default();
+    let series = |name: String, value: fn(&MonthlyGrowth) -> usize| Series {
+        name,
+        points: growth.iter().enumerate().map(|(i, g)| (i as f64, value(g) as f64)).collect(),
+    };
+    let lines = series(tr!("Lines of code"), |g| g.total_lines);
+    let solved = series(tr!("Problems solved"), |g| g.solved);
+    stack_svg(&[
+        line_chart_svg(&tr!("Cumulative lines of code"), &[lines], x_label),
+        line_chart_svg(&tr!("Problems solved per month"), &[solved], x_label),
+    ])
+}
+
+fn growth_cmd(chart: Option<PathBuf>) -> Result<()> {
+    let config = load_config()?;
+    let growth = monthly_growth(&config.atcoder);
+    if growth.is_empty() {
+        say!("No archived problems");
+        return Ok(());
+    }
+
+    say!("Month     Solved  Problems  Lines of code");
+    for g in &growth {
+        println!("{:<9} {:>6}  {:>8}  {:>13}", g.month, g.solved, g.total_problems, g.total_lines);
+    }
+
+    let Some(chart) = chart else {
+        return Ok(());
+    };
+    let path = config.atcoder.work_dir().join(chart);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let svg = growth_chart_svg(&growth);
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")) {
+        // PNGへの変換はlibrsvgのrsvg-convertに任せる
+        let mut child = Command::new("rsvg-convert")
+            .arg("--format=png")
+            .arg("--output")
+            .arg(&path)
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .context("Failed to run `rsvg-convert` (install librsvg to write PNG charts)")?;
+        child.stdin.take().context("Failed to open stdin")?
+            .write_all(svg.as_bytes())
+            .context("Failed to pass the chart")?;
+        let status = child.wait().context("Failed to run `rsvg-convert`")?;
+        if !status.success() {
+            return Err(anyhow!("`{}` exited with {}", "rsvg-convert", status));
+        }
+    } else {
+        fs::write(&path, svg).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    say!("Wrote the chart to {}", path.display());
+    Ok(())
+}
+
+/// 履歴に残ったAC提出のコード長
+struct GolfAttempt {
+    submission_id: i64,
+    contest_id: String,
+    problem_id: String,
+    /// 括弧より前の言語名 (例: "C++ 20")
+    language: String,
+    length: i64,
+    epoch_second: i64,
+}
+
+/// 問題・言語ごとの最短の解答
+struct GolfRecord {
+    problem_id: String,
+    language: String,
+    best: GolfAttempt,
+    /// 最初のACのコード長。後のACの方が短ければ最短と違う
+    first_length: i64,
+}
+
+/// アーカイブのコミットのトレーラーと、今のメタデータからAC提出を集める
+///
+/// `policy`が`all`なら上書きされた過去のACもコミットの履歴に残っている
+fn golf_attempts(config: &Config) -> Vec<GolfAttempt> {
+    use ac_garden::archive::parse_trailers;
+
+    let mut attempts: HashMap<i64, GolfAttempt> = HashMap::new();
+    for service in config.atcoder.user_services() {
+        for (_, s) in scan_archived_submissions(&service) {
+            if s.result == "AC" {
+                let language = canonical_language(&s.language);
+                attempts.insert(s.id, GolfAttempt {
+                    submission_id: s.id,
+                    contest_id: s.contest_id,
+                    problem_id: s.problem_id,
+                    language,
+                    length: s.length,
+                    epoch_second: s.epoch_second,
+                });
+            }
+        }
+    }
+
+    let Ok(repo) = Repository::open(&config.atcoder.repository_path) else {
+        return attempts.into_values().collect();
+    };
+    let Ok(mut revwalk) = repo.revwalk() else {
+        return attempts.into_values().collect();
+    };
+    if revwalk.push_head().is_err() {
+        return attempts.into_values().collect();
+    }
+    for commit in revwalk.filter_map(|oid| repo.find_commit(oid.ok()?).ok()) {
+        let message = commit.message().unwrap_or_default();
+        let trailers: HashMap<&str, &str> = parse_trailers(message).into_iter().collect();
+        let field = |key: &str| trailers.get(key).copied();
+        if field("Result") != Some("AC") {
+            continue;
+        }
+        let (Some(submission_id), Some(contest_id), Some(problem_id), Some(language), Some(length)) = (
+            field("Submission-Id").and_then(|id| id.parse().ok()),
+            field("Contest"),
+            field("Problem"),
+            field("Language"),
+            field("Code-Length").and_then(|length| length.trim_end_matches(" B").parse().ok()),
+        ) else {
+            continue;
+        };
+        let epoch_second = field("Submitted-At")
+            .and_then(|date| chrono::DateTime::parse_from_rfc3339(date).ok())
+            .map_or(commit.time().seconds(), |dt| dt.timestamp());
+        attempts.entry(submission_id).or_insert_with(|| GolfAttempt {
+            submission_id,
+            contest_id: contest_id.to_string(),
+            problem_id: problem_id.to_string(),
+            language: canonical_language(language),
+            length,
+            epoch_second,
+        });
+    }
+    attempts.into_values().collect()
+}
+
+/// 問題・言語ごとに最短の解答を選ぶ。同じ長さなら先にACした方
+fn golf_records(attempts: Vec<GolfAttempt>) -> Vec<GolfRecord> {
+    let mut groups: BTreeMap<(String, String), Vec<GolfAttempt>> = BTreeMap::new();
+    for attempt in attempts {
+        groups.entry((attempt.problem_id.clone(), attempt.language.clone())).or_default().push(attempt);
+    }
+    groups.into_iter()
+        .filter_map(|((problem_id, language), mut attempts)| {
+            attempts.sort_by_key(|a| (a.epoch_second, a.submission_id));
+            let first_length = attempts.first()?.length;
+            let best_index = (0..attempts.len()).min_by_key(|&i| (attempts[i].length, i))?;
+            let best = attempts.swap_remove(best_index);
+            Some(GolfRecord { problem_id, language, best, first_length })
+        })
+        .collect()
+}
+
+fn golf_cmd(language: Option<&str>, markdown: Option<PathBuf>) -> Result<()> {
+    let config = load_config()?;
+    let attempts: Vec<GolfAttempt> = golf_attempts(&config)
+        .into_iter()
+        .filter(|a| language.is_none_or(|filter| language_matches(&a.language, filter)))
+        .collect();
+    let records = golf_records(attempts);
+    if records.is_empty() {
+        say!("No AC submissions found in the archive history");
+        return Ok(());
+    }
+
+    // 言語ごとの問題数・最短の合計・短縮した問題数
+    let mut by_language: BTreeMap<&str, (usize, i64, usize)> = BTreeMap::new();
+    for record in &records {
+        let entry = by_language.entry(record.language.as_str()).or_default();
+        entry.0 += 1;
+        entry.1 += record.best.length;
+        entry.2 += usize::from(record.best.length < record.first_length);
+    }
+
+    say!("Shortest solutions per problem (bytes):");
+    for record in &records {
+        let line = format!(
+            "  {:<16} {:<16} {:>6}  {}",
+            record.problem_id, record.language, record.best.length, record.best.submission_id
+        );
+        if record.best.length < record.first_length {
+            let was = tr!("was {} B", record.first_length);
+            anstream::println!("{}", paint(STYLE_ARCHIVED, format!("{}  ({})", line, was)));
+        } else {
+            println!("{}", line);
+        }
+    }
+    println!();
+    say!("Shortest solutions by language:");
+    for (language, (problems, total, improved)) in &by_language {
+        println!("  {:<16}{}", language, tr!("  {} problem(s), {} B in total, {} shortened", problems, total, improved));
+    }
+
+    let Some(markdown) = markdown else {
+        return Ok(());
+    };
+    let mut content = String::from("# Code golf\n\n| Problem | Language | Bytes | Submission | First AC |\n| --- | --- | ---: | --- | ---: |\n");
+    for record in &records {
+        let bytes = if record.best.length < record.first_length {
+            format!("**{}**", record.best.length)
+        } else {
+            record.best.length.to_string()
+        };
+        let url = format!("{}/contests/{}/submissions/{}", ATCODER_BASE_URL, record.best.contest_id, record.best.submission_id);
+        content.push_str(&format!(
+            "| {} | {} | {} | [{}]({}) | {} |\n",
+            record.problem_id, record.language, bytes, record.best.submission_id, url, record.first_length
+        ));
+    }
+    content.push_str("\n| Language | Problems | Total bytes | Shortened |\n| --- | ---: | ---: | ---: |\n");
+    for (language, (problems, total, improved)) in &by_language {
+        content.push_str(&format!("| {} | {} | {} | {} |\n", language, problems, total, improved));
+    }
+    let path = config.atcoder.work_dir().join(markdown);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    say!("Wrote the leaderboard to {}", path.display());
+    Ok(())
+}
+
+const REVIEWS_STATE: &str = "reviews.json";
+
+/// AtCoder Problemsの問題モデル (問題ID → モデル)。期限内ならキャッシュを使う
+async fn fetch_problem_models(client: &Client, rate_limiter: &RateLimiter) -> Result<HashMap<String, ProblemModel>> {
+    let models = ac_garden::contest::fetch_problem_models(client, false, rate_limiter).await?;
+    Ok(value_or_warn(models, tr!("the problem models")))
+}
+
+/// キャッシュの取り直しに失敗していれば警告を表示して、古いキャッシュの値を使う
+fn value_or_warn<T>(fetched: Fetched<T>, what: String) -> T {
+    match fetched {
+        Fetched::Fresh(value) => value,
+        Fetched::Stale { value, error } => {
+            say_err!("Failed to refresh {}, using the cached copy: {:#}", what, anyhow::Error::from(error));
+            value
+        },
+    }
+}
+
+/// 取り直しに失敗して古いキャッシュになったらエラーにする
+fn fresh_or_err<T>(fetched: Fetched<T>, what: String) -> Result<T> {
+    match fetched {
+        Fetched::Fresh(value) => Ok(value),
+        Fetched::Stale { error, .. } => Err(anyhow::Error::from(error).context(tr!("Failed to refresh {}; the cache was left as it was", what))),
+    }
+}
+
+/// コンテスト一覧と問題モデルのキャッシュを取り直す
+async fn refresh_metadata_cmd() -> Result<()> {
+    let config = load_config()?;
+    // オフラインでは古いキャッシュをそのまま返してしまうので、先に断る
+    if is_offline() {
+        return Err(Error::Offline { url: CONTESTS_URL.to_string() }.into());
+    }
+    let client = build_client(&config)?;
+    let rate_limiter = RateLimiter::from_config(&config.http);
+    let contests = fresh_or_err(fetch_contests(&client, true, &rate_limiter).await?, tr!("the contest list"))?;
+    let models: Fetched<HashMap<String, ProblemModel>> = ac_garden::contest::fetch_problem_models(&client, true, &rate_limiter).await?;
+    let models = fresh_or_err(models, tr!("the problem models"))?;
+    say!("Cached {} contests and {} problem models", contests.len(), models.len());
+    Ok(())
+}
+
+async fn review_cmd(count: usize, record: Option<Option<String>>, quality: Option<u8>) -> Result<()> {
+    use ac_garden::review::ReviewItem;
+
+    let mut config = load_config()?;
+    let now = chrono::Utc::now().timestamp();
+    let mut reviews: HashMap<String, ReviewItem> = load_state(REVIEWS_STATE)?;
+    let archived = filter_archived(&config.atcoder, None, None);
+
+    if let (Some(record), Some(quality)) = (record, quality) {
+        let problem_id = pick_problem(&config, record)?;
+        let problem_id = problem_id.as_str();
+        let submission = archived.iter()
+            .find(|s| s.problem_id == problem_id)
+            .ok_or_else(|| anyhow!("{} is not archived yet", problem_id))?;
+        let item = reviews.entry(problem_id.to_string())
+            .or_insert_with(|| ReviewItem::new(problem_id, submission.epoch_second, None));
+        item.record(quality, now);
+        let next = chrono::DateTime::from_timestamp(item.due, 0)
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        say!("Recorded {} (quality {}), next review on {}", problem_id, quality, next);
+        return save_state(REVIEWS_STATE, &reviews);
+    }
+
+    resolve_credentials(&mut config)?;
+    let client = build_client(&config)?;
+    let models = match fetch_problem_models(&client, &RateLimiter::from_config(&config.http)).await {
+        Ok(models) => models,
+        Err(e) => {
+            say_err!("Failed to fetch difficulties, scheduling without them: {:#}", e);
+            HashMap::new()
+        }
+    };
+    let difficulty = |problem_id: &str| models.get(problem_id).and_then(|m| m.clipped_difficulty());
+
+    // 期限を過ぎた日数に、難しい問題ほど大きくなる重みを掛けて並べる
+    let mut candidates: Vec<(f64, &Submission, ReviewItem)> = archived.iter()
+        .map(|s| {
+            let item = reviews.get(&s.problem_id).cloned()
+                .unwrap_or_else(|| ReviewItem::new(&s.problem_id, s.epoch_second, difficulty(&s.problem_id)));
+            let weight = 1.0 + difficulty(&s.problem_id).unwrap_or(0.0) / 1000.0;
+            (item.overdue_days(now) * weight, s, item)
+        })
+        .filter(|(_, _, item)| item.due <= now)
+        .collect();
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    if candidates.is_empty() {
+        say!("Nothing to review today");
+        return Ok(());
+    }
+
+    say!("Problems to re-attempt:");
+    for (_, submission, item) in candidates.iter().take(count) {
+        let difficulty = difficulty(&submission.problem_id)
+            .map(|d| format!("{:>5}", d))
+            .unwrap_or_else(|| "    -".to_string());
+        println!(
+            "  {:<16} difficulty {}  overdue {:>4.0} day(s)  {}",
+            submission.problem_id,
+            difficulty,
+            item.overdue_days(now).max(0.0),
+            task_url(submission)
+        );
+    }
+    println!();
+    say!("After solving one again, record it with `ac-garden review --record <problem_id> --quality <0-5>`");
+
+    Ok(())
+}
+
+/// ユーザーのAC提出をAPIから取得し、ACした問題IDを返す
+async fn fetch_solved(config: &Config, user: &str, rate_limiter: &RateLimiter) -> Result<HashSet<String>> {
+    let mut client = ArchiveClient::builder()
+        .config(config.clone())
+        .user(user)
+        .rate_limiter(rate_limiter.clone())
+        .build()?;
+    let solved = client.fetch_submissions().await
+        .with_context(|| format!("Failed to fetch submissions of {}", user))?
+        .into_iter()
+        .filter(|s| s.result == "AC")
+        .map(|s| s.problem_id)
+        .collect();
+    Ok(solved)
+}
+
+/// 問題IDを難易度の色ごとに、易しい順に表示する
+fn print_by_difficulty(problems: &[&String], models: &HashMap<String, ProblemModel>, limit: usize) {
+    let mut groups: BTreeMap<usize, Vec<(f64, &str)>> = BTreeMap::new();
+    for problem_id in problems {
+        let difficulty = models.get(problem_id.as_str()).and_then(|m| m.clipped_difficulty());
+        groups.entry(difficulty_color(difficulty))
+            .or_default()
+            .push((difficulty.unwrap_or(f64::INFINITY), problem_id));
+    }
+
+    for (color, mut group) in groups {
+        group.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(b.1)));
+        let shown: Vec<&str> = group.iter().take(limit).map(|(_, id)| *id).collect();
+        let more = if group.len() > limit { format!(" ... (+{})", group.len() - limit) } else { String::new() };
+        println!("  {:<8}{:>5}  {}{}", DIFFICULTY_COLORS[color], group.len(), shown.join(" "), more);
+    }
+}
+
+async fn compare_cmd(other: &str, limit: usize) -> Result<()> {
+    let mut config = load_config()?;
+    resolve_credentials(&mut config)?;
+    let me = config.atcoder.user_id.clone();
+
+    let rate_limiter = RateLimiter::from_config(&config.http);
+    let mine = fetch_solved(&config, &me, &rate_limiter).await?;
+    let theirs = fetch_solved(&config, other, &rate_limiter).await?;
+
+    let client = build_client(&config)?;
+    let models = match fetch_problem_models(&client, &rate_limiter).await {
+        Ok(models) => models,
+        Err(e) => {
+            say_err!("Failed to fetch difficulties: {:#}", e);
+            HashMap::new()
+        }
+    };
+
+    let mut only_theirs: Vec<&String> = theirs.difference(&mine).collect();
+    let mut only_mine: Vec<&String> = mine.difference(&theirs).collect();
+    only_theirs.sort();
+    only_mine.sort();
+
+    say!("{} solved {} problem(s) you haven't:", other, only_theirs.len());
+    print_by_difficulty(&only_theirs, &models, limit);
+    say!("You solved {} problem(s) {} hasn't:", only_mine.len(), other);
+    print_by_difficulty(&only_mine, &models, limit);
+    say!("Both solved {} problem(s)", mine.intersection(&theirs).count());
+
+    Ok(())
+}
+
+/// 今の実力の目安にする直近のACの数
+const RECENT_SOLVED: usize = 50;
+/// 推薦する難易度の幅 (実力の目安からの上限)
+const RECOMMEND_RANGE: f64 = 300.0;
+
+async fn recommend_cmd(count: usize, todo: bool) -> Result<()> {
+    let mut config = load_config()?;
+    resolve_credentials(&mut config)?;
+    let client = build_client(&config)?;
+    let models = fetch_problem_models(&client, &RateLimiter::from_config(&config.http)).await?;
+    let difficulty = |problem_id: &str| models.get(problem_id).and_then(|m| m.clipped_difficulty());
+
+    let mut archived = filter_archived(&config.atcoder, None, None);
+    let solved: HashSet<String> = archived.iter().map(|s| s.problem_id.clone()).collect();
+
+    // 直近のACの難易度の上位1/4あたりを今の実力の目安にする
+    archived.sort_by_key(|s| std::cmp::Reverse(s.epoch_second));
+    let mut recent: Vec<f64> = archived.iter()
+        .filter_map(|s| difficulty(&s.problem_id))
+        .take(RECENT_SOLVED)
+        .collect();
+    if recent.is_empty() {
+        return Err(anyhow!("No archived problems with a known difficulty yet"));
+    }
+    recent.sort_by(|a, b| a.total_cmp(b));
+    let level = recent[recent.len() * 3 / 4];
+
+    let mut candidates: Vec<(f64, &String)> = models.iter()
+        .filter(|(problem_id, _)| !solved.contains(*problem_id))
+        .filter_map(|(problem_id, model)| Some((model.clipped_difficulty()?, problem_id)))
+        .filter(|(d, _)| (level..=level + RECOMMEND_RANGE).contains(d))
+        .collect();
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(b.1)));
+    candidates.truncate(count);
+
+    if candidates.is_empty() {
+        say!("No unsolved problems around difficulty {}", level);
+        return Ok(());
+    }
+
+    say!("Your current level: around difficulty {}", level);
+    let mut todo_lines = vec!["# TODO".to_string(), String::new()];
+    for (d, problem_id) in &candidates {
+        let url = problem_url(problem_id);
+        println!("  {:<16} difficulty {:>5}  {}", problem_id, d, url);
+        todo_lines.push(format!("- [ ] [{}]({}) (difficulty {})", problem_id, url, d));
+    }
+
+    if todo {
+        let path = config.atcoder.work_dir().join("todo.md");
+        todo_lines.push(String::new());
+        fs::write(&path, todo_lines.join("\n"))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        say!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// 問題IDからの問題URL。コンテストIDは問題IDの最後の`_`より前とみなす
+fn problem_url(problem_id: &str) -> String {
+    let contest_id = problem_id.rsplit_once('_').map_or(problem_id, |(contest_id, _)| contest_id);
+    format!("{}/contests/{}/tasks/{}", ATCODER_BASE_URL, contest_id, problem_id)
+}
+
+/// 言語名がフィルタに一致するか ("C++ 20 (gcc 12.2)"は"C++"や"C++ 20"に、"Python3 (3.4.3)"は"Python"にも一致する)
+fn language_matches(language: &str, filter: &str) -> bool {
+    let base = language.split('(').next().unwrap_or(language).trim();
+    let name = base.split_whitespace().next().unwrap_or(base);
+    name.eq_ignore_ascii_case(filter) || base.eq_ignore_ascii_case(filter) || canonical_language(language).eq_ignore_ascii_case(filter)
+}
+
+fn search_cmd(query: &str, language: Option<&str>, context: usize) -> Result<()> {
+    let config = load_config()?;
+    let query = query.to_lowercase();
+    let mut match_count = 0;
+    let mut problem_count = 0;
+
+    let mut archived = scan_archived_submissions(&config.atcoder);
+    archived.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (metadata_path, submission) in archived {
+        if language.is_some_and(|filter| !language_matches(&submission.language, filter)) {
+            continue;
+        }
+        let dir = metadata_path.parent().unwrap_or(Path::new("."));
+        // git notesにだけある提出はディレクトリがないこともある
+        if !is_dir_exist(dir) {
+            continue;
+        }
+        // 問題文は保存しないので、コードのほかはメモと保存した公式解説だけ
+        let mut files = find_source_files(dir)?;
+        files.extend([NOTES_MD, EDITORIAL_MD].map(|name| dir.join(name)).into_iter().filter(|path| is_file_exist(path)));
+
+        let mut printed_header = false;
+        for file in files {
+            let content = match fs::read_to_string(&file) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let lines: Vec<&str> = content.lines().collect();
+            let hits: Vec<usize> = lines.iter()
+                .enumerate()
+                .filter(|(_, line)| line.to_lowercase().contains(&query))
+                .map(|(i, _)| i)
+                .collect();
+            if hits.is_empty() {
+                continue;
+            }
+
+            if !printed_header {
+                println!("{}/{} ({})", submission.contest_id, submission.problem_id, submission.language);
+                printed_header = true;
+                problem_count += 1;
+            }
+            println!("  {}", file.display());
+
+            // grep -Cと同じく、近いマッチは1つのまとまりとして表示する
+            let mut last_printed: Option<usize> = None;
+            for &hit in &hits {
+                let start = hit.saturating_sub(context);
+                let end = (hit + context).min(lines.len() - 1);
+                if let Some(last) = last_printed {
+                    if start > last + 1 {
+                        println!("  --");
+                    }
+                }
+                let from = last_printed.map_or(start, |last| start.max(last + 1));
+                for (i, line) in lines.iter().enumerate().take(end + 1).skip(from) {
+                    let marker = if hits.contains(&i) { ':' } else { '-' };
+                    println!("  {:>5}{} {}", i + 1, marker, line);
+                }
+                last_printed = Some(end);
+            }
+            match_count += hits.len();
+        }
+    }
+
+    println!();
+    say!("{} matching line(s) in {} problem(s)", match_count, problem_count);
+    Ok(())
+}
+
+async fn diff_cmd(problem_id: Option<String>) -> Result<()> {
+    let mut config = load_config()?;
+    resolve_credentials(&mut config)?;
+    let problem_id = pick_problem(&config, problem_id)?;
+    let problem_id = problem_id.as_str();
+
+    let (metadata_path, archived) = find_archived(&config.atcoder, problem_id)?;
+    let dir = metadata_path.parent().unwrap_or(Path::new("."));
+    let source = find_source_files(dir)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No source file found in {}", dir.display()))?;
+    let archived_code = fs::read_to_string(&source)
+        .with_context(|| format!("Failed to read {}", source.display()))?;
+
+    let header = config.atcoder.header;
+    let mut client = ArchiveClient::builder().config(config).build()?;
+
+    let latest = client.fetch_submissions().await?
+        .into_iter()
+        .filter(|s| s.result == "AC" && s.problem_id == problem_id)
+        .max_by_key(|s| s.epoch_second)
+        .ok_or_else(|| anyhow!("No AC submission found for {}", problem_id))?;
+
+    if latest.id == archived.id {
+        say!("The archived code is already your latest AC (submission {})", latest.id);
+        return Ok(());
+    }
+
+    // アーカイブ時と同じ加工をしてから比べる
+    let latest_code = client.fetch_code(&latest).await?;
+    let file_name = source.file_name().unwrap_or_default().to_string_lossy();
+    let latest_code = if header {
+        with_header(&latest_code, &file_name, &latest)
+    } else {
+        latest_code
+    };
+
+    let diff = similar::TextDiff::from_lines(&archived_code, &latest_code);
+    print!(
+        "{}",
+        diff.unified_diff().header(
+            &format!("{} (submission {})", source.display(), archived.id),
+            &format!("{} (submission {})", submission_url(&latest), latest.id),
+        )
+    );
+
+    Ok(())
+}
+
+fn dedupe_report_cmd(threshold: f64, template_ratio: f64) -> Result<()> {
+    use ac_garden::similarity::{hash_lines, jaccard, minhash, normalize_lines, shingles};
+
+    const SHINGLE_SIZE: usize = 5;
+    const BANDS: usize = 8;
+    const ROWS: usize = 4;
+
+    let config = load_config()?;
+
+    // 問題ごとのソースを正規化して読み込む
+    let mut sources: Vec<(String, Vec<String>)> = Vec::new();
+    for (metadata_path, submission) in scan_archived_submissions(&config.atcoder) {
+        let dir = metadata_path.parent().unwrap_or(Path::new("."));
+        for source in find_source_files(dir)? {
+            let code = fs::read_to_string(&source).unwrap_or_default();
+            let label = format!(
+                "{}/{} ({})",
+                submission.contest_id,
+                submission.problem_id,
+                source.file_name().unwrap_or_default().to_string_lossy()
+            );
+            sources.push((label, normalize_lines(&code)));
+        }
+    }
+
+    // 多くのファイルに出てくる行はテンプレートとみなして比較から外す
+    let mut line_counts: HashMap<&str, usize> = HashMap::new();
+    for (_, lines) in &sources {
+        let unique: std::collections::HashSet<&str> = lines.iter().map(|l| l.as_str()).collect();
+        for line in unique {
+            *line_counts.entry(line).or_default() += 1;
+        }
+    }
+    let template_count = ((sources.len() as f64 * template_ratio).ceil() as usize).max(3);
+    let bodies: Vec<(&str, Vec<String>)> = sources.iter()
+        .map(|(label, lines)| {
+            let body = lines.iter()
+                .filter(|line| line_counts.get(line.as_str()).copied().unwrap_or(0) < template_count)
+                .cloned()
+                .collect();
+            (label.as_str(), body)
+        })
+        .filter(|(_, body): &(&str, Vec<String>)| !body.is_empty())
+        .collect();
+
+    // 完全一致
+    let mut identical: HashMap<u64, Vec<&str>> = HashMap::new();
+    for (label, body) in &bodies {
+        identical.entry(hash_lines(body)).or_default().push(label);
+    }
+    let mut identical_groups: Vec<Vec<&str>> = identical.into_values()
+        .filter(|labels| labels.len() > 1)
+        .collect();
+    identical_groups.sort();
+
+    // MinHashのバンドで候補を絞ってから類似度を計算する
+    let shingle_sets: Vec<_> = bodies.iter().map(|(_, body)| shingles(&body.join("\n"), SHINGLE_SIZE)).collect();
+    let mut buckets: HashMap<(usize, Vec<u64>), Vec<usize>> = HashMap::new();
+    for (i, set) in shingle_sets.iter().enumerate() {
+        let signature = minhash(set, BANDS * ROWS);
+        for (band, rows) in signature.chunks(ROWS).enumerate() {
+            buckets.entry((band, rows.to_vec())).or_default().push(i);
+        }
+    }
+    let mut candidates = std::collections::BTreeSet::new();
+    for indices in buckets.values() {
+        for (n, &i) in indices.iter().enumerate() {
+            for &j in &indices[n + 1..] {
+                candidates.insert((i.min(j), i.max(j)));
+            }
+        }
+    }
+    let mut near_duplicates: Vec<(f64, &str, &str)> = candidates.into_iter()
+        .filter(|&(i, j)| hash_lines(&bodies[i].1) != hash_lines(&bodies[j].1))
+        .map(|(i, j)| (jaccard(&shingle_sets[i], &shingle_sets[j]), bodies[i].0, bodies[j].0))
+        .filter(|(similarity, _, _)| *similarity >= threshold)
+        .collect();
+    near_duplicates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    say!("Scanned {} source files (lines shared by {} or more files are treated as template)", sources.len(), template_count);
+
+    println!();
+    say!("Identical code: {} group(s)", identical_groups.len());
+    for labels in &identical_groups {
+        println!("  {}", labels.join(", "));
+    }
+
+    println!();
+    say!("Near-duplicates (similarity >= {:.2}): {} pair(s)", threshold, near_duplicates.len());
+    for (similarity, a, b) in &near_duplicates {
+        println!("  {:.2}  {}  ~  {}", similarity, a, b);
+    }
+
+    Ok(())
+}
+
+/// AtCoder Problemsのコンテストと問題の対応
+#[derive(Debug, Deserialize)]
+struct ContestProblem {
+    contest_id: String,
+    problem_id: String,
+    problem_index: String,
+}
+
+/// 一部だけ解いたコンテスト
+struct ContestGap {
+    contest_id: String,
+    solved: Vec<String>,
+    missing: Vec<String>,
+}
+
+impl ContestGap {
+    /// 解いた問題の割合
+    fn ratio(&self) -> f64 {
+        self.solved.len() as f64 / (self.solved.len() + self.missing.len()) as f64
+    }
+}
+
+/// 問題の記号を並べる。連続するアルファベットは"A–D"のようにまとめる
+fn format_indices(indices: &[String]) -> String {
+    let letter = |index: &str| match index.as_bytes() {
+        [c] if c.is_ascii_alphabetic() => Some(*c),
+        _ => None,
+    };
+
+    let mut runs: Vec<(&str, &str)> = Vec::new();
+    for index in indices {
+        match runs.last_mut() {
+            Some((_, end)) if matches!((letter(end), letter(index)), (Some(a), Some(b)) if a + 1 == b) => *end = index,
+            _ => runs.push((index, index)),
+        }
+    }
+    runs.iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}–{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// GitHubのトークン。環境変数になければ保存した値を使う
+fn github_token() -> Result<String> {
+    if let Ok(token) = std::env::var(GITHUB_TOKEN_ENV) {
+        if !token.trim().is_empty() {
+            return Ok(token.trim().to_string());
+        }
+    }
+    if !has_secret("github_token")? {
+        return Err(anyhow!("Set {} or run `ac-garden credentials set github_token` first", GITHUB_TOKEN_ENV));
+    }
+    Ok(load_secret("github_token", prompt_passphrase)?)
+}
+
+/// GitHubにリポジトリを作ってリモートに追加し、アーカイブをpushする
+async fn setup_remote_cmd(name: Option<String>, private: bool, branch: Option<String>, remote: &str, ssh: bool) -> Result<()> {
+    let config = load_config()?;
+    let branch = branch.or_else(|| config.atcoder.default_branch.clone()).unwrap_or_else(|| DEFAULT_BRANCH.to_string());
+    let branch = branch.as_str();
+    let repo_path = PathBuf::from(&config.atcoder.repository_path);
+    let repo = Repository::open(&repo_path)
+        .with_context(|| format!("{} is not a git repository", repo_path.display()))?;
+    if repo.find_remote(remote).is_ok() {
+        return Err(anyhow!("The remote {} already exists", remote));
+    }
+    let name = match name {
+        Some(name) => name,
+        None => repo_path
+            .canonicalize()
+            .ok()
+            .and_then(|path| path.file_name().map(|name| name.to_string_lossy().trim_end_matches(".git").to_string()))
+            .context("Failed to decide the repository name. Pass --name")?,
+    };
+
+    let token = github_token()?;
+    let http = build_client(&config)?;
+    let github = create_github_repository(&http, &token, &name, private).await?;
+    say!("Created {}", github.html_url);
+
+    let url = if ssh { &github.ssh_url } else { &github.clone_url };
+    repo.remote(remote, url)?;
+    say!("Added the remote {} ({})", remote, url);
+
+    // まだコミットがなければ、最初のコミットがそのブランチに入るようにしておく。
+    // コミットがあれば、ローカルのブランチの名前は変えずにHEADをリモートのそのブランチとしてpushする
+    if repo.head().is_err() {
+        repo.set_head(&format!("refs/heads/{}", branch))?;
+        say!("Nothing to push yet. The archive will be committed to {}; push it after `ac-garden archive`.", branch);
+        return Ok(());
+    }
+
+    let auth = if ssh {
+        push_auth(&config, &repo_path, remote, prompt_passphrase)?
+    } else {
+        PushAuth { username: GITHUB_USERNAME.to_string(), token: Some(token.clone()), ssh_key: None }
+    };
+    push(&repo_path, remote, Some(branch), &auth)?;
+    set_github_default_branch(&http, &token, &github.full_name, branch).await?;
+    say!("Pushed {} to {}", branch, github.html_url);
+    Ok(())
+}
+
+/// exportで作れる形式
+const EXPORT_FORMATS: &[&str] = &["tar.gz", "tar", "zip"];
+
+/// アーカイブした問題のディレクトリをまとめて1つのファイルにする
+///
+/// 外部の`tar`や`zip`コマンドは使わずに書き出す
+fn export_cmd(output: Option<PathBuf>, format: &str, filters: &[String], metadata: bool) -> Result<()> {
+    let config = load_config()?;
+    let work_dir = config.atcoder.work_dir();
+
+    let matches = |contest_id: &str, problem_id: &str| {
+        filters.is_empty() || filters.iter().any(|pattern| problem_matches(pattern, contest_id, problem_id))
+    };
+
+    let mut problem_dirs = std::collections::BTreeSet::new();
+    for service in config.atcoder.user_services() {
+        for (metadata_path, submission) in scan_archived_submissions(&service) {
+            if matches(&submission.contest_id, &submission.problem_id) {
+                problem_dirs.extend(metadata_path.parent().map(Path::to_path_buf));
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut problems = 0;
+    for dir in problem_dirs.iter().filter(|dir| is_dir_exist(dir)) {
+        let paths = if metadata {
+            let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+                .with_context(|| format!("Failed to read {}", dir.display()))?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+                .map(|entry| entry.path())
+                .collect();
+            paths.sort();
+            paths
+        } else {
+            find_source_files(dir)?
+        };
+        if !paths.is_empty() {
+            problems += 1;
+        }
+        files.extend(paths.into_iter().filter_map(|path| path.strip_prefix(&work_dir).ok().map(Path::to_path_buf)));
+    }
+    if files.is_empty() {
+        say!("No archived problems match the filter");
+        return Ok(());
+    }
+
+    let output = match output {
+        Some(output) => std::path::absolute(output).context("Invalid output path")?,
+        None => std::env::current_dir()?.join(format!("ac-garden-export-{}.{}", chrono::Local::now().format("%Y%m%d"), format)),
+    };
+    write_export(&work_dir, &files, format, &output)?;
+
+    say!("Exported {} file(s) from {} problem(s) to {}", files.len(), problems, output.display());
+    Ok(())
+}
+
+/// `scaffold`で作れるワークスペースの形式
+const SCAFFOLD_TEMPLATES: &[&str] = &["cargo-compete", "atcoder-cli"];
+
+/// cargo-competeのワークスペースに依存として足すクレート。(コードでの名前, クレート名, AtCoderのジャッジのバージョン)
+const ATCODER_CRATES: &[(&str, &str, &str)] = &[
+    ("proconio", "proconio", "=0.4.5"),
+    ("itertools", "itertools", "=0.11.0"),
+    ("ac_library", "ac-library-rs", "=0.1.1"),
+    ("num", "num", "=0.4.1"),
+    ("superslice", "superslice", "=1.0.0"),
+    ("rand", "rand", "=0.8.5"),
+    ("petgraph", "petgraph", "=0.6.3"),
+    ("im_rc", "im-rc", "=15.1.0"),
+];
+
+/// ワークスペースでの問題のラベル。記号がなければ問題IDの末尾 (abc300_a → a)
+fn problem_label(submission: &Submission) -> String {
+    let label = match &submission.problem_index {
+        Some(index) => index.as_str(),
+        None => submission.problem_id.rsplit_once('_').map_or(submission.problem_id.as_str(), |(_, label)| label),
+    };
+    label.to_lowercase()
+}
+
+/// ファイルを書き込む。`force`がなければ既存のファイルは上書きしない
+fn write_new_file(path: &Path, content: &[u8], force: bool) -> Result<()> {
+    if path.exists() && !force {
+        return Err(anyhow!("{} already exists (use --force to overwrite)", path.display()));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// cargo-competeのパッケージ (`<contest>/Cargo.toml`と`src/bin/<label>.rs`) に解答を置く
+fn scaffold_cargo_compete(contest_dir: &Path, submission: &Submission, source: &Path, code: &str, force: bool) -> Result<PathBuf> {
+    if source.extension().is_none_or(|ext| ext != "rs") {
+        return Err(anyhow!("cargo-compete workspaces are for Rust solutions, but {} is not a Rust file", source.display()));
+    }
+    let label = problem_label(submission);
+    let bin_name = format!("{}-{}", submission.contest_id, label);
+    let bin_path = contest_dir.join("src").join("bin").join(format!("{}.rs", label));
+    write_new_file(&bin_path, code.as_bytes(), force)?;
+
+    // すでにあるパッケージには問題を足す
+    let manifest_path = contest_dir.join("Cargo.toml");
+    let mut manifest: toml::Table = if manifest_path.is_file() {
+        let content = fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", manifest_path.display()))?
+    } else {
+        toml::toml! {
+            [package]
+            name = ""
+            version = "0.1.0"
+            edition = "2021"
+            publish = false
+        }
+    };
+    let package = manifest.entry("package").or_insert_with(|| toml::Table::new().into());
+    if let Some(package) = package.as_table_mut() {
+        package.insert("name".to_string(), submission.contest_id.clone().into());
+        let bins = ["metadata", "cargo-compete", "bin"].iter().try_fold(package, |table, key| {
+            table.entry(key.to_string()).or_insert_with(|| toml::Table::new().into()).as_table_mut()
+        });
+        if let Some(bins) = bins {
+            let mut bin = toml::Table::new();
+            bin.insert("alias".to_string(), label.clone().into());
+            bin.insert("problem".to_string(), task_url(submission).into());
+            bins.insert(bin_name.clone(), bin.into());
+        }
+    }
+    if let Some(targets) = manifest.entry("bin").or_insert_with(|| toml::value::Array::new().into()).as_array_mut() {
+        let exists = targets.iter().any(|target| target.get("name").and_then(|name| name.as_str()) == Some(&bin_name));
+        if !exists {
+            let mut target = toml::Table::new();
+            target.insert("name".to_string(), bin_name.into());
+            target.insert("path".to_string(), format!("src/bin/{}.rs", label).into());
+            targets.push(target.into());
+        }
+    }
+    if let Some(dependencies) = manifest.entry("dependencies").or_insert_with(|| toml::Table::new().into()).as_table_mut() {
+        for (path, name, version) in ATCODER_CRATES {
+            if code.contains(&format!("{}::", path)) && !dependencies.contains_key(*name) {
+                dependencies.insert(name.to_string(), version.to_string().into());
+            }
+        }
+    }
+    let manifest = toml::to_string(&manifest).context("Failed to serialize Cargo.toml")?;
+    fs::write(&manifest_path, manifest).with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+    Ok(bin_path)
+}
+
+/// atcoder-cliのコンテストのディレクトリ (`<contest>/<label>/main.<ext>`と`contest.acc.json`) に解答を置く
+fn scaffold_atcoder_cli(contest_dir: &Path, submission: &Submission, source: &Path, code: &str, force: bool) -> Result<PathBuf> {
+    let label = problem_label(submission);
+    let file_name = match source.extension() {
+        Some(ext) => format!("main.{}", ext.to_string_lossy()),
+        None => "main".to_string(),
+    };
+    let task_dir = contest_dir.join(&label);
+    let main_path = task_dir.join(&file_name);
+    write_new_file(&main_path, code.as_bytes(), force)?;
+    fs::create_dir_all(task_dir.join("tests")).with_context(|| format!("Failed to create {}", task_dir.display()))?;
+
+    let acc_path = contest_dir.join("contest.acc.json");
+    let mut acc: serde_json::Value = if acc_path.is_file() {
+        let content = fs::read_to_string(&acc_path).with_context(|| format!("Failed to read {}", acc_path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", acc_path.display()))?
+    } else {
+        serde_json::json!({
+            "contest": {
+                "id": submission.contest_id,
+                "title": submission.contest_title.as_deref().unwrap_or(&submission.contest_id),
+                "url": format!("{}/contests/{}", ATCODER_BASE_URL, submission.contest_id),
+            },
+            "tasks": [],
+        })
+    };
+    let task = serde_json::json!({
+        "id": submission.problem_id,
+        "label": label.to_uppercase(),
+        "url": task_url(submission),
+        "directory": { "path": label, "testdir": "tests", "submit": file_name },
+    });
+    if let Some(tasks) = acc.get_mut("tasks").and_then(|tasks| tasks.as_array_mut()) {
+        tasks.retain(|t| t.get("id").and_then(|id| id.as_str()) != Some(&submission.problem_id));
+        tasks.push(task);
+        tasks.sort_by_key(|t| t.get("label").and_then(|label| label.as_str()).unwrap_or_default().to_string());
+    }
+    let json = serde_json::to_string_pretty(&acc).context("Failed to serialize contest.acc.json")?;
+    fs::write(&acc_path, json).with_context(|| format!("Failed to write {}", acc_path.display()))?;
+    Ok(main_path)
+}
+
+fn scaffold_cmd(problem_id: Option<String>, template: &str, output: Option<PathBuf>, force: bool) -> Result<()> {
+    let config = load_config()?;
+    let problem_id = pick_problem(&config, problem_id)?;
+    let (metadata_path, submission) = find_archived(&config.atcoder, &problem_id)?;
+    let dir = metadata_path.parent().unwrap_or(Path::new("."));
+    let source = find_source_files(dir)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No source file found in {}", dir.display()))?;
+    let code = fs::read_to_string(&source).with_context(|| format!("Failed to read {}", source.display()))?;
+
+    let output = match output {
+        Some(output) => output,
+        None => std::env::current_dir()?,
+    };
+    let contest_dir = output.join(sanitize_path_component(&submission.contest_id));
+    let path = match template {
+        "cargo-compete" => scaffold_cargo_compete(&contest_dir, &submission, &source, &code, force)?,
+        _ => scaffold_atcoder_cli(&contest_dir, &submission, &source, &code, force)?,
+    };
+    say!("Scaffolded {} into {}", problem_id, path.display());
+    Ok(())
+}
+
+async fn gap_report_cmd(limit: usize) -> Result<()> {
+    let config = load_config()?;
+    let solved: HashSet<String> = filter_archived(&config.atcoder, None, None)
+        .into_iter()
+        .map(|s| s.problem_id)
+        .collect();
+
+    let client = build_client(&config)?;
+    let rate_limiter = RateLimiter::from_config(&config.http);
+    let contest_problems: Vec<ContestProblem> = send_get(&client, CONTEST_PROBLEMS_URL, &rate_limiter).await?
+        .error_for_status()?
+        .json().await
+        .context("Failed to decode contest problems")?;
+
+    let mut contests: BTreeMap<String, Vec<ContestProblem>> = BTreeMap::new();
+    for problem in contest_problems {
+        contests.entry(problem.contest_id.clone()).or_default().push(problem);
+    }
+
+    // 一部だけ解いたコンテストを、解いた割合の高い順に並べる
+    let mut gaps: Vec<ContestGap> = contests.into_iter()
+        .filter_map(|(contest_id, mut problems)| {
+            // "Ex"のような2文字の記号は1文字の記号の後に並べる
+            problems.sort_by(|a, b| {
+                (a.problem_index.len(), &a.problem_index).cmp(&(b.problem_index.len(), &b.problem_index))
+            });
+            let (solved, missing): (Vec<ContestProblem>, Vec<ContestProblem>) =
+                problems.into_iter().partition(|p| solved.contains(&p.problem_id));
+            if solved.is_empty() || missing.is_empty() {
+                return None;
+            }
+            let indices = |problems: Vec<ContestProblem>| problems.into_iter().map(|p| p.problem_index).collect();
+            Some(ContestGap { contest_id, solved: indices(solved), missing: indices(missing) })
+        })
+        .collect();
+    gaps.sort_by(|a, b| {
+        b.ratio().total_cmp(&a.ratio())
+            .then(a.missing.len().cmp(&b.missing.len()))
+            .then(a.contest_id.cmp(&b.contest_id))
+    });
+
+    if gaps.is_empty() {
+        say!("No partially solved contests");
+        return Ok(());
+    }
+
+    for gap in gaps.iter().take(limit) {
+        say!("{}: solved {}, missing {}", gap.contest_id, format_indices(&gap.solved), format_indices(&gap.missing));
+    }
+    if gaps.len() > limit {
+        say!("... and {} more contest(s)", gaps.len() - limit);
+    }
+
+    Ok(())
+}
+
+async fn contests_cmd(upcoming: bool, ics: Option<&Path>, limit: usize) -> Result<()> {
+    let config = load_config()?;
+    let client = build_client(&config)?;
+    let rate_limiter = RateLimiter::from_config(&config.http);
+    let now = chrono::Utc::now().timestamp();
+
+    let mut contests = if upcoming {
+        fetch_upcoming_contests(&client, &rate_limiter).await.context("Failed to fetch upcoming contests")?
+    } else {
+        // 終了したコンテストを新しい順に
+        let contests = fetch_contests(&client, false, &rate_limiter).await.context("Failed to fetch contests")?;
+        let mut contests: Vec<Contest> = value_or_warn(contests, tr!("the contest list"))
+            .into_iter()
+            .filter(|c| c.start_epoch_second + c.duration_second <= now)
+            .collect();
+        contests.sort_by_key(|c| std::cmp::Reverse(c.start_epoch_second));
+        contests
+    };
+    contests.truncate(limit);
+
+    if contests.is_empty() {
+        say!("No contests to show");
+        return Ok(());
+    }
+    for contest in &contests {
+        let start = chrono::DateTime::from_timestamp(contest.start_epoch_second, 0)
+            .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d (%a) %H:%M").to_string())
+            .unwrap_or_default();
+        let duration = format!("{}:{:02}", contest.duration_second / 3600, contest.duration_second % 3600 / 60);
+        println!("{}  {:>6}  {:<10} {}", start, duration, contest.id, contest.title);
+    }
+
+    if let Some(path) = ics {
+        fs::write(path, contests_to_ics(&contests))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        say!("Wrote {} contest(s) to {}", contests.len(), path.display());
+    }
+
+    Ok(())
+}
+
+/// watchで見ているコンテストの記録
+const WATCH_STATE: &str = "watch.json";
+/// これより前に終わったコンテストはwatchで扱わない
+const WATCH_LOOKBACK_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Default, Serialize, Deserialize)]
+struct WatchState {
+    /// 予定されていたコンテスト。始まるとatcoder.jpの予定一覧から消えるので覚えておく
+    scheduled: Vec<Contest>,
+    /// 参加したかを確認し終えたコンテスト
+    handled: Vec<String>,
+}
+
+async fn watch_cmd(interval: u64, delay: u64) -> Result<()> {
+    say!("Watching for contests to end, checking every {} minute(s). Press Ctrl-C to stop.", interval);
+    loop {
+        if let Err(e) = watch_once(delay).await {
+            print_error(&e);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(interval.max(1) * 60)).await;
+    }
+}
+
+/// 終わってからdelay分経ったコンテストに参加していれば、アーカイブする
+async fn watch_once(delay: u64) -> Result<()> {
+    let config = load_config()?;
+    let http = build_client(&config)?;
+    let rate_limiter = RateLimiter::from_config(&config.http);
+    let now = chrono::Utc::now().timestamp();
+    let mut state: WatchState = load_state(WATCH_STATE)?;
+
+    match fetch_upcoming_contests(&http, &rate_limiter).await {
+        Ok(upcoming) => {
+            for contest in upcoming {
+                if !state.scheduled.iter().any(|c| c.id == contest.id) {
+                    state.scheduled.push(contest);
+                }
+            }
+        },
+        Err(e) => say_err!("Failed to fetch upcoming contests: {:#}", anyhow::Error::from(e)),
+    }
+    let contests = fetch_contests(&http, false, &rate_limiter).await.context("Failed to fetch contests")?;
+    let mut contests = value_or_warn(contests, tr!("the contest list"));
+    contests.extend(state.scheduled.iter().cloned());
+
+    // 直近に終わったコンテストだけを見る
+    let end = |c: &Contest| c.start_epoch_second + c.duration_second;
+    contests.retain(|c| end(c) > now - WATCH_LOOKBACK_SECS);
+    state.scheduled.retain(|c| end(c) > now - WATCH_LOOKBACK_SECS);
+    state.handled.retain(|id| contests.iter().any(|c| &c.id == id));
+
+    let mut seen = HashSet::new();
+    let due: Vec<Contest> = contests.into_iter()
+        .filter(|c| end(c) + delay as i64 * 60 <= now && !state.handled.contains(&c.id))
+        .filter(|c| seen.insert(c.id.clone()))
+        .collect();
+
+    let mut participated = Vec::new();
+    if !due.is_empty() {
+        let mut client = ArchiveClient::builder().config(config.clone()).build()?;
+        for contest in &due {
+            for service in config.atcoder.user_services() {
+                if client.participated(&contest.id, &service.user_id).await? {
+                    participated.push(contest.title.clone());
+                    break;
+                }
+            }
+            state.handled.push(contest.id.clone());
+        }
+    }
+    save_state(WATCH_STATE, &state)?;
+
+    if !participated.is_empty() {
+        say!("{} ended, archiving new submissions", participated.join(", "));
+        archive_cmd(ArchiveOptions::default()).await?;
+    }
+
+    Ok(())
+}
+
+/// アーカイブが変わったかの目印 (HEAD・git notes・index.jsonの更新日時)
+type ArchiveState = (Option<String>, Option<String>, Option<std::time::SystemTime>);
+
+/// `serve`で読み込んだ索引。目印が変わるまで使い回す
+type ArchiveCache = Arc<Mutex<Option<(ArchiveState, Arc<Vec<IndexEntry>>)>>>;
+
+fn archive_state(service: &Service) -> ArchiveState {
+    let (head, notes) = repository_heads(Path::new(&service.repository_path));
+    let index_modified = fs::metadata(service.work_dir().join(INDEX_JSON)).and_then(|m| m.modified()).ok();
+    (head, notes, index_modified)
+}
+
+/// index.jsonを読む。archiveやtagでコミットされるか索引が書き直されたときだけ読み直す
+///
+/// 索引がまだなければ、アーカイブを走査して同じ形の項目を作る
+fn cached_archive(config: &Config, cache: &ArchiveCache) -> Arc<Vec<IndexEntry>> {
+    let state = archive_state(&config.atcoder);
+    if let Some((cached_state, entries)) = cache.lock().unwrap().as_ref() {
+        if *cached_state == state {
+            return entries.clone();
+        }
+    }
+    let entries = if state.2.is_some() {
+        read_index(&config.atcoder.work_dir())
+    } else {
+        index_entries(config, &HashMap::new(), &[])
+    };
+    let entries = Arc::new(entries);
+    *cache.lock().unwrap() = Some((state, entries.clone()));
+    entries
+}
+
+/// `serve --api`で索引を読み取り専用のJSON APIとして公開する
+///
+/// 読み込んだ索引はコミットか索引が変わるまで使い回すので、archiveの結果は次のリクエストから反映される。
+/// 読み込みはブロッキングなので、非同期のランタイムを止めないよう別のスレッドで行う
+async fn serve_cmd(bind: &str, port: u16) -> Result<()> {
+    use hyper::service::{make_service_fn, service_fn};
+    use std::convert::Infallible;
+
+    let config = Arc::new(load_config()?);
+    let cache: ArchiveCache = Arc::default();
+    let addr: std::net::SocketAddr = format!("{}:{}", bind, port).parse()
+        .with_context(|| format!("Invalid address {}:{}", bind, port))?;
+
+    let make_service = make_service_fn(move |_| {
+        let config = config.clone();
+        let cache = cache.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let config = config.clone();
+                let cache = cache.clone();
+                async move {
+                    let archived = tokio::task::spawn_blocking(move || cached_archive(&config, &cache)).await;
+                    let response = match archived {
+                        Ok(archived) => api_response(&archived, &request),
+                        Err(e) => json_response(
+                            hyper::StatusCode::INTERNAL_SERVER_ERROR,
+                            &serde_json::json!({ "error": format!("failed to read the archive: {}", e) }),
+                        ),
+                    };
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    let server = hyper::Server::try_bind(&addr)
+        .with_context(|| format!("Failed to listen on {}", addr))?
+        .serve(make_service);
+    say!("Serving the archive API on http://{}", addr);
+    server.await.context("API server failed")?;
+
+    Ok(())
+}
+
+fn json_response(status: hyper::StatusCode, value: &serde_json::Value) -> hyper::Response<hyper::Body> {
+    hyper::Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json; charset=utf-8")
+        // 手元のダッシュボードからfetchできるようにする
+        .header(hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(hyper::Body::from(serde_json::to_string_pretty(value).unwrap_or_default()))
+        .unwrap()
+}
+
+fn api_response(archived: &[IndexEntry], request: &hyper::Request<hyper::Body>) -> hyper::Response<hyper::Body> {
+    use hyper::StatusCode;
+
+    if request.method() != hyper::Method::GET {
+        return json_response(StatusCode::METHOD_NOT_ALLOWED, &serde_json::json!({ "error": "only GET is supported" }));
+    }
+    let query: HashMap<String, String> = request.uri().query()
+        .map(|query| url::form_urlencoded::parse(query.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    let param = |name: &str| query.get(name).map(String::as_str).filter(|value| !value.is_empty());
+
+    // 索引はコンテスト・問題順に並んでいる
+    let entries: Vec<&IndexEntry> = archived.iter()
+        .filter(|e| param("tag").is_none_or(|tag| e.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+        .filter(|e| param("language").is_none_or(|filter| language_matches(&e.language, filter)))
+        .filter(|e| param("contest").is_none_or(|contest| e.contest_id == contest))
+        .collect();
+    let value = match request.uri().path().trim_end_matches('/') {
+        "/submissions" => serde_json::json!(entries),
+        "/problems" => serde_json::json!(api_problems(&entries)),
+        "/stats" => api_stats(&entries),
+        "/heatmap" => serde_json::json!(api_heatmap(&entries)),
+        _ => {
+            let endpoints = ["/submissions", "/problems", "/stats", "/heatmap"];
+            return json_response(StatusCode::NOT_FOUND, &serde_json::json!({ "error": "not found", "endpoints": endpoints }));
+        },
+    };
+    json_response(StatusCode::OK, &value)
+}
+
+/// `/problems`の1件。同じ問題の提出をまとめたもの
+#[derive(Serialize)]
+struct ApiProblem {
+    contest_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contest_title: Option<String>,
+    problem_id: String,
+    difficulty: Option<f64>,
+    submissions: usize,
+    languages: Vec<String>,
+    tags: Vec<String>,
+    first_ac_epoch_second: i64,
+    last_ac_epoch_second: i64,
+}
+
+fn api_problems(entries: &[&IndexEntry]) -> Vec<ApiProblem> {
+    let mut problems: BTreeMap<(String, String), ApiProblem> = BTreeMap::new();
+    for entry in entries {
+        let problem = problems.entry((entry.contest_id.clone(), entry.problem_id.clone()))
+            .or_insert_with(|| ApiProblem {
+                contest_id: entry.contest_id.clone(),
+                contest_title: None,
+                problem_id: entry.problem_id.clone(),
+                difficulty: entry.difficulty,
+                submissions: 0,
+                languages: Vec::new(),
+                tags: Vec::new(),
+                first_ac_epoch_second: i64::MAX,
+                last_ac_epoch_second: i64::MIN,
+            });
+        problem.contest_title = problem.contest_title.take().or_else(|| entry.contest_title.clone());
+        problem.submissions += 1;
+        if !problem.languages.contains(&entry.language) {
+            problem.languages.push(entry.language.clone());
+        }
+        for tag in &entry.tags {
+            if !problem.tags.contains(tag) {
+                problem.tags.push(tag.clone());
+            }
+        }
+        problem.first_ac_epoch_second = problem.first_ac_epoch_second.min(entry.epoch_second());
+        problem.last_ac_epoch_second = problem.last_ac_epoch_second.max(entry.epoch_second());
+    }
+    problems.into_values().collect()
+}
+
+fn api_stats(entries: &[&IndexEntry]) -> serde_json::Value {
+    let streaks = archive_streaks(entries.iter().map(|e| e.epoch_second()));
+    let counts = |counts: Vec<(&str, usize)>| -> Vec<serde_json::Value> {
+        counts.into_iter().map(|(name, count)| serde_json::json!({ "name": name, "count": count })).collect()
+    };
+    let problems: HashSet<(&str, &str)> = entries.iter()
+        .map(|e| (e.contest_id.as_str(), e.problem_id.as_str()))
+        .collect();
+    let languages: Vec<String> = entries.iter().map(|e| canonical_language(&e.language)).collect();
+    serde_json::json!({
+        "archived_submissions": entries.len(),
+        "archived_problems": problems.len(),
+        "current_streak": streaks.current,
+        "longest_streak": streaks.longest,
+        "solved_today": streaks.solved_today,
+        "by_language": counts(count_by(languages.iter().map(String::as_str))),
+        "by_tag": counts(count_by(entries.iter().flat_map(|e| e.tags.iter().map(|t| t.as_str())))),
+    })
+}
+
+/// 日付 (ローカル時間) ごとのAC数
+fn api_heatmap(entries: &[&IndexEntry]) -> BTreeMap<String, usize> {
+    use chrono::TimeZone;
+
+    let mut days = BTreeMap::new();
+    for entry in entries {
+        if let Some(dt) = chrono::Local.timestamp_opt(entry.epoch_second(), 0).single() {
+            *days.entry(dt.format("%Y-%m-%d").to_string()).or_default() += 1;
+        }
+    }
+    days
+}
+
+/// 設定ファイルの問題点。JSONとして読めなければその位置を示す
+fn config_problems(config_file: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(config_file)
+        .context("Failed to read config file")?;
+
+    let config: Config = match serde_json::from_str(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            let mut problem = e.to_string();
+            if let Some(line) = content.lines().nth(e.line().saturating_sub(1)) {
+                problem.push_str(&format!(
+                    "\n    {:>4} | {}\n         | {}^",
+                    e.line(),
+                    line,
+                    " ".repeat(e.column().saturating_sub(1))
+                ));
+            }
+            return Ok(vec![problem]);
+        }
+    };
+
+    Ok(config.atcoder.missing_fields()
+        .into_iter()
+        .map(|key| format!("atcoder.{} is empty", key))
+        .collect())
+}
+
+/// y/nで答えてもらう。端末でなければfalse
+fn confirm(question: &str) -> Result<bool> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    print!("{} [Y/n] ", tr!("{}", question));
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim().to_lowercase();
+    Ok(answer.is_empty() || answer == "y" || answer == "yes")
+}
+
+/// 端末で使えるエディタ
+#[cfg(target_os = "windows")]
+const TERMINAL_EDITORS: &[&str] = &["notepad"];
+#[cfg(not(target_os = "windows"))]
+const TERMINAL_EDITORS: &[&str] = &["nano", "vim", "vi"];
+
+/// エディタの候補。`$VISUAL`、`$EDITOR`、設定の`editor`、端末のエディタの順
+fn editor_candidates(config_file: &Path) -> Vec<String> {
+    // 壊れた設定でも開けるように、editorだけを読む
+    let configured = fs::read_to_string(config_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|value| value.get("editor")?.as_str().map(str::to_string));
+
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .chain(configured)
+        .filter(|editor| !editor.trim().is_empty())
+        .chain(TERMINAL_EDITORS.iter().map(|editor| editor.to_string()))
+        .collect()
+}
+
+/// 候補のうち最初に起動できたエディタで開き、閉じるまで待つ。どれも見つからなければfalse
+fn open_in_editor(candidates: &[String], file: &Path) -> Result<bool> {
+    for editor in candidates {
+        // "code --wait"のような引数付きの指定も受け付ける
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else {
+            continue;
+        };
+        match Command::new(program).args(words).arg(file).status() {
+            Ok(_) => return Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to run editor {}", editor)),
+        }
+    }
+    Ok(false)
+}
+
+fn edit_cmd() -> Result<()> {
+    let config_file = get_config_file()?;
+    
+    // 設定ファイルが存在しない場合は初期化
+    if !is_file_exist(&config_file) {
+        init_config(true, prefill_service(None, None, None))?;
+    }
+    
+    // 閉じた後に設定を確かめ、問題があれば開き直す
+    while open_in_editor(&editor_candidates(&config_file), &config_file)? {
+        let problems = config_problems(&config_file)?;
+        if problems.is_empty() {
+            say!("Config looks good");
+            return Ok(());
+        }
+        say_err!("Problems in {}:", config_file.display());
+        for problem in &problems {
+            eprintln!("  {}", tr!("{}", problem));
+        }
+        if !confirm("Re-open the editor?")? {
+            say_err!("Leaving the config as is. Fix it before running archive.");
+            return Ok(());
+        }
+    }
+
+    // エディタが見つからなければOSの既定のアプリで開く
+    open_with_system(config_file.as_os_str())
+}
+
+/// ファイルやURLをOSの既定のアプリで開く (Windows/Mac/Linux)
+fn open_with_system(target: &std::ffi::OsStr) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("cmd")
+            .args(["/c", "start", ""])
+            .arg(target)
+            .spawn()?;
+    }
+    
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg(target)
+            .spawn()?;
+    }
+    
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("xdg-open")
+            .arg(target)
+            .spawn()?;
+    }
+    
+    Ok(())
+}
+
+/// index.jsonとアーカイブしたファイル・gitの履歴を突き合わせ、ずれを表示する
+///
+/// `repair`なら、消えたファイルをHEADから戻し、コミットしていない新しいアーカイブをコミットして索引を作り直す
+async fn fsck_cmd(repair: bool) -> Result<()> {
+    let _lock = if repair { Some(acquire_lock()?) } else { None };
+    let config = load_config()?;
+    fsck(&config, repair).await
+}
+
+async fn fsck(config: &Config, repair: bool) -> Result<()> {
+    let work_dir = config.atcoder.work_dir();
+    let repo_path = Path::new(&config.atcoder.repository_path);
+    let repo = Repository::open(repo_path).ok().filter(|repo| !repo.is_bare());
+    let head_tree = repo.as_ref().and_then(|repo| repo.head().ok()?.peel_to_tree().ok());
+    let rel = |path: &Path| path.strip_prefix(&work_dir).ok().map(|path| path.to_string_lossy().replace('\\', "/"));
+
+    // 問題ディレクトリ (リポジトリからの相対パス) ごとのメタデータ
+    let mut archived: BTreeMap<String, Submission> = BTreeMap::new();
+    for service in config.atcoder.user_services() {
+        for (metadata_path, submission) in scan_archived_submissions(&service) {
+            if let Some(dir) = metadata_path.parent().and_then(rel) {
+                archived.insert(dir, submission);
+            }
+        }
+    }
+
+    let mut issues = 0;
+    let mut index_stale = false;
+    let mut restore = Vec::new();
+    if !is_file_exist(work_dir.join(INDEX_JSON)) {
+        say!("{} does not exist", INDEX_JSON);
+        issues += 1;
+        index_stale = true;
+    }
+
+    let index = read_index(&work_dir);
+    let mut indexed = HashSet::new();
+    for entry in &index {
+        if entry.path.is_empty() {
+            // ソースファイルが見つからないまま索引に載った提出。索引を作り直しても直らない
+            issues += 1;
+            say!("No source file for {} (submission {})", entry.problem_id, entry.submission_id);
+            if let Some((dir, _)) = archived.iter().find(|(_, submission)| submission.id == entry.submission_id) {
+                indexed.insert(dir.clone());
+            }
+            continue;
+        }
+        let dir = Path::new(&entry.path).parent().map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default();
+        indexed.insert(dir.clone());
+        if !work_dir.join(&entry.path).is_file() {
+            issues += 1;
+            let in_head = head_tree.as_ref().is_some_and(|tree| tree.get_path(Path::new(&entry.path)).is_ok());
+            if in_head {
+                say!("Missing file: {} ({}, still in HEAD)", entry.path, entry.problem_id);
+                restore.push(entry.path.clone());
+            } else {
+                say!("Missing file: {} ({})", entry.path, entry.problem_id);
+                index_stale = true;
+            }
+            continue;
+        }
+        match archived.get(&dir) {
+            None => {
+                issues += 1;
+                index_stale = true;
+                say!("No metadata for the indexed directory {}", dir);
+            },
+            Some(submission) if submission.id != entry.submission_id => {
+                issues += 1;
+                index_stale = true;
+                say!("Submission id mismatch in {}: index.json has {}, the metadata has {}", dir, entry.submission_id, submission.id);
+            },
+            Some(_) => {},
+        }
+    }
+    for dir in archived.keys().filter(|dir| !indexed.contains(*dir)) {
+        issues += 1;
+        index_stale = true;
+        say!("Not in {}: {}", INDEX_JSON, dir);
+    }
+
+    // 作業ツリーにあってもコミットされていないアーカイブ。アーカイブが書き込むファイルだけを修復でコミットする
+    let mut untracked = Vec::new();
+    if let Some(repo) = &repo {
+        for (dir, submission) in &archived {
+            let Ok(files) = fs::read_dir(work_dir.join(dir)) else {
+                continue;
+            };
+            for file in files.filter_map(|entry| entry.ok()).filter(|entry| entry.path().is_file()) {
+                let Some(path) = rel(&file.path()) else {
+                    continue;
+                };
+                let Ok(status) = repo.status_file(Path::new(&path)) else {
+                    continue;
+                };
+                if status.contains(git2::Status::WT_NEW) {
+                    if !is_archive_file(&file.file_name().to_string_lossy(), submission) {
+                        // ユーザーが置いたファイルかもしれないので、修復でもコミットしない
+                        say!("Not committed and not written by ac-garden, left alone: {}", path);
+                        continue;
+                    }
+                    issues += 1;
+                    say!("Not committed: {}", path);
+                    untracked.push(PathBuf::from(path));
+                } else if status.intersects(git2::Status::WT_MODIFIED | git2::Status::WT_DELETED) {
+                    // 手で直したコードかもしれないので、修復でも触らない
+                    issues += 1;
+                    say!("Uncommitted changes: {}", path);
+                }
+            }
+        }
+    }
+
+    if issues == 0 {
+        say!("{} and the archive are consistent", INDEX_JSON);
+        return Ok(());
+    }
+    if !repair {
+        return Err(anyhow!("Found {} problem(s). Run `ac-garden fsck --repair` to fix the ones it can", issues));
+    }
+
+    if let (Some(repo), false) = (&repo, restore.is_empty()) {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.force();
+        for path in &restore {
+            checkout.path(path);
+        }
+        repo.checkout_head(Some(&mut checkout))?;
+        say!("Restored {} file(s) from HEAD", restore.len());
+    }
+    if !untracked.is_empty() {
+        say!("Committing {} archived file(s):", untracked.len());
+        for path in &untracked {
+            say!("  {}", path.display());
+        }
+        let (author, committer) = commit_signatures(&config.atcoder, &config.atcoder.user_id, chrono::Utc::now().timestamp())?;
+        let message = format!("[Fsck] Commit {} archived file(s)", untracked.len());
+        commit_files(config.atcoder.git_backend, repo_path, &untracked, &author, &committer, &message)?;
+        say!("Committed {} file(s)", untracked.len());
+    }
+    if index_stale || !restore.is_empty() {
+        update_index(config).await?;
+        say!("Rebuilt {}", INDEX_JSON);
+    }
+    Ok(())
+}
+
+/// index.jsonを読み込む。なければ空
+fn read_index(work_dir: &Path) -> Vec<IndexEntry> {
+    fs::read_to_string(work_dir.join(INDEX_JSON))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 問題IDを省略したとき、アーカイブした問題を絞り込んで選ばせる
+///
+/// 候補は索引から作り、索引がなければアーカイブを走査する。新しくACした問題から並べる
+fn pick_problem(config: &Config, problem_id: Option<String>) -> Result<String> {
+    use std::io::IsTerminal;
+
+    if let Some(problem_id) = problem_id {
+        return Ok(problem_id);
+    }
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Err(anyhow!("Specify a problem id (choosing one interactively needs a terminal)"));
+    }
+
+    let mut choices: Vec<(i64, String, String)> = read_index(&config.atcoder.work_dir())
+        .into_iter()
+        .map(|entry| {
+            let epoch_second = chrono::DateTime::parse_from_rfc3339(&entry.date).map_or(0, |dt| dt.timestamp());
+            (epoch_second, entry.problem_id, entry.language)
+        })
+        .collect();
+    if choices.is_empty() {
+        choices = filter_archived(&config.atcoder, None, None)
+            .into_iter()
+            .map(|s| (s.epoch_second, s.problem_id, s.language))
+            .collect();
+    }
+    choices.sort_by(|a, b| b.cmp(a));
+    let mut seen = HashSet::new();
+    choices.retain(|(_, problem_id, _)| seen.insert(problem_id.clone()));
+    if choices.is_empty() {
+        return Err(anyhow!("No archived problems"));
+    }
+
+    let items: Vec<String> = choices.iter()
+        .map(|(_, problem_id, language)| format!("{}  {}", problem_id, language))
+        .collect();
+    let selection = dialoguer::FuzzySelect::new()
+        .with_prompt(tr!("Problem"))
+        .items(&items)
+        .default(0)
+        .interact_opt()?;
+    match selection {
+        Some(index) => Ok(choices.swap_remove(index).1),
+        None => Err(anyhow!("No problem selected")),
+    }
+}
+
+/// 索引から問題のソースファイルとURLを探す。索引がないか古ければアーカイブを走査する
+fn resolve_archived(config: &Config, problem_id: &str) -> Result<(PathBuf, String, String)> {
+    let work_dir = config.atcoder.work_dir();
+    let indexed = read_index(&work_dir).into_iter()
+        .filter(|entry| entry.problem_id == problem_id && !entry.path.is_empty())
+        .filter(|entry| is_file_exist(work_dir.join(&entry.path)))
+        .max_by_key(|entry| entry.submission_id);
+    if let Some(entry) = indexed {
+        let task = format!("{}/contests/{}/tasks/{}", ATCODER_BASE_URL, entry.contest_id, entry.problem_id);
+        let submission = format!("{}/contests/{}/submissions/{}", ATCODER_BASE_URL, entry.contest_id, entry.submission_id);
+        return Ok((work_dir.join(&entry.path), task, submission));
+    }
+
+    let (metadata_path, submission) = find_archived(&config.atcoder, problem_id)?;
+    let dir = metadata_path.parent().unwrap_or(Path::new("."));
+    let source = find_source_files(dir)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No source file found in {}", dir.display()))?;
+    Ok((source, task_url(&submission), submission_url(&submission)))
+}
+
+fn open_cmd(problem_id: Option<String>, web: bool, submission: bool) -> Result<()> {
+    let config = load_config()?;
+    let problem_id = pick_problem(&config, problem_id)?;
+    let (source, task, submission_page) = resolve_archived(&config, &problem_id)?;
+
+    let mut urls = Vec::new();
+    if web {
+        urls.push(task);
+    }
+    if submission {
+        urls.push(submission_page);
+    }
+    if urls.is_empty() {
+        if !open_in_editor(&editor_candidates(&get_config_file()?), &source)? {
+            open_with_system(source.as_os_str())?;
+        }
+        return Ok(());
+    }
+    for url in urls {
+        say!("Opening {}", url);
+        open_with_system(url.as_ref())?;
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = localize_command(Cli::command()).get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if cli.no_color {
+        anstream::ColorChoice::Never.write_global();
+    }
+    set_offline(cli.offline);
+    // ライブラリの警告は訳した状態で届くのでそのまま表示する
+    warning::set_handler(|message| anstream::eprintln!("{}", message));
+    let code = match run(cli).await {
+        Ok(code) => code,
+        Err(e) if is_maintenance_error(&e) => {
+            say_err!("{}", e);
+            EXIT_MAINTENANCE
+        },
+        Err(e) => {
+            print_error(&e);
+            exit_code_of(&e)
+        },
+    };
+    std::process::exit(code);
+}
+
+/// ヘルプの説明を表示言語に訳す
+fn localize_command(command: clap::Command) -> clap::Command {
+    let command = match command.get_about().map(|about| tr!("{}", about)) {
+        Some(about) => command.about(about),
+        None => command,
+    };
+    let command = command.mut_args(|arg| match arg.get_help().map(|help| tr!("{}", help)) {
+        Some(help) => arg.help(help),
+        None => arg,
+    });
+    let names: Vec<String> = command.get_subcommands().map(|sub| sub.get_name().to_string()).collect();
+    names.iter().fold(command, |command, name| command.mut_subcommand(name, localize_command))
+}
+
+/// エラーを原因とともに表示言語で表示する
+fn print_error(error: &anyhow::Error) {
+    anstream::eprintln!("{}: {}", paint(STYLE_FAILED, tr!("Error")), tr!("{}", error));
+    let causes: Vec<_> = error.chain().skip(1).collect();
+    if !causes.is_empty() {
+        anstream::eprintln!();
+        anstream::eprintln!("{}", tr!("Caused by:"));
+        for (i, cause) in causes.iter().enumerate() {
+            anstream::eprintln!("    {}: {}", i, tr!("{}", cause));
+        }
+    }
+}
+
+/// コマンドを実行し、終了コードを返す
+async fn run(cli: Cli) -> Result<i32> {
+    if let Some(config) = cli.config {
+        let config = std::path::absolute(&config)
+            .with_context(|| format!("Invalid config path {}", config.display()))?;
+        CONFIG_FILE.set(config).ok();
+    }
+    if let Some(repository) = cli.repo {
+        let repository = std::path::absolute(&repository)
+            .with_context(|| format!("Invalid repository path {}", repository.display()))?;
+        REPOSITORY_PATH.set(repository).ok();
+    }
+    if let Some(profile) = cli.profile {
+        PROFILE.set(profile).ok();
+    }
+
+    // 設定を作る・戻すコマンド以外は設定ファイルが要る
+    let needs_config = !matches!(
+        cli.command,
+        Commands::Init { .. } | Commands::Edit | Commands::Restore { .. } | Commands::Credentials { .. } | Commands::Config { .. }
+    );
+    if needs_config {
+        ensure_config().await?;
+    }
+
+    match cli.command {
+        Commands::Archive { force, contests, retry_failed, interactive, limit, id, full } => {
+            let archived = archive_cmd(ArchiveOptions { force, contests, retry_failed, interactive, limit, id, full }).await?;
+            return Ok(if archived > 0 { EXIT_ARCHIVED } else { EXIT_NOTHING_NEW });
+        },
+        Commands::Init { force, user, email, repository } => {
+            init_cmd(force, user, email, repository).await?;
+        },
+        Commands::Edit => {
+            edit_cmd()?;
+        },
+        Commands::Undo => {
+            undo_cmd()?;
+        },
+        Commands::Diff { problem_id } => {
+            diff_cmd(problem_id).await?;
+        },
+        Commands::Tag { problem_id, tags, remove, import } => {
+            tag_cmd(problem_id.as_deref(), &tags, remove, import.as_deref())?;
+        },
+        Commands::List { tag, language } => {
+            list_cmd(tag.as_deref(), language.as_deref()).await?;
+        },
+        Commands::Stats { time, json, trends, growth, chart } => {
+            if growth {
+                growth_cmd(chart)?;
+            } else if trends {
+                trends_cmd().await?;
+            } else {
+                stats_cmd(time || json, json).await?;
+            }
+        },
+        Commands::Status => {
+            status_cmd()?;
+        },
+        Commands::Review { count, record, quality } => {
+            review_cmd(count, record, quality).await?;
+        },
+        Commands::Compare { user, limit } => {
+            compare_cmd(&user, limit).await?;
+        },
+        Commands::Recommend { count, todo } => {
+            recommend_cmd(count, todo).await?;
+        },
+        Commands::Search { query, language, context } => {
+            search_cmd(&query, language.as_deref(), context)?;
+        },
+        Commands::DedupeReport { threshold, template_ratio } => {
+            dedupe_report_cmd(threshold, template_ratio)?;
+        },
+        Commands::GapReport { limit } => {
+            gap_report_cmd(limit).await?;
+        },
+        Commands::Contests { upcoming, ics, limit } => {
+            contests_cmd(upcoming, ics.as_deref(), limit).await?;
+        },
+        Commands::Watch { interval, delay } => {
+            watch_cmd(interval, delay).await?;
+        },
+        Commands::Serve { api: _, bind, port } => {
+            serve_cmd(&bind, port).await?;
+        },
+        Commands::Backup { output, no_credentials } => {
+            backup_cmd(output, no_credentials)?;
+        },
+        Commands::Restore { input, force } => {
+            restore_cmd(&input, force)?;
+        },
+        Commands::SetupRemote { github: _, name, private, branch, remote, ssh } => {
+            setup_remote_cmd(name, private, branch, &remote, ssh).await?;
+        },
+        Commands::Export { output, archive_format, filter, metadata } => {
+            export_cmd(output, &archive_format, &filter, metadata)?;
+        },
+        Commands::Open { problem_id, web, submission } => {
+            open_cmd(problem_id, web, submission)?;
+        },
+        Commands::Golf { language, markdown } => {
+            golf_cmd(language.as_deref(), markdown)?;
+        },
+        Commands::Scaffold { problem_id, template, output, force } => {
+            scaffold_cmd(problem_id, &template, output, force)?;
+        },
+        Commands::RefreshMetadata => {
+            refresh_metadata_cmd().await?;
+        },
+        Commands::Fsck { repair } => {
+            fsck_cmd(repair).await?;
+        },
+        Commands::Credentials { action } => match action {
+            CredentialsAction::Set { name } => {
+                credentials_set_cmd(&name)?;
+            },
+            CredentialsAction::Delete { name } => {
+                credentials_delete_cmd(&name)?;
+            },
+        },
+        Commands::Config { action } => match action {
+            ConfigAction::Migrate { dry_run } => {
+                config_migrate_cmd(dry_run)?;
+            },
+        },
+    }
+
+    Ok(EXIT_NOTHING_NEW)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_each_error_category_to_its_exit_code() {
+        let code = |error: anyhow::Error| exit_code_of(&error);
+        assert_eq!(code(Error::config("missing user_id").into()), EXIT_CONFIG);
+        assert_eq!(code(Error::credential("session", "locked").into()), EXIT_CONFIG);
+        assert_eq!(code(Error::RateLimited { url: "https://atcoder.jp".to_string() }.into()), EXIT_NETWORK);
+        assert_eq!(code(Error::Offline { url: "https://atcoder.jp".to_string() }.into()), EXIT_NETWORK);
+        assert_eq!(code(Error::Maintenance { retry_after: None }.into()), EXIT_MAINTENANCE);
+        assert_eq!(code(Error::scrape("https://atcoder.jp", "no code").into()), EXIT_SCRAPE);
+        assert_eq!(code(Error::Git(git2::Error::from_str("bad ref")).into()), EXIT_GIT);
+        assert_eq!(code(git2::Error::from_str("bad ref").into()), EXIT_GIT);
+        assert_eq!(code(Error::HookFailed { event: "pre_commit".to_string() }.into()), EXIT_HOOK);
+        assert_eq!(code(LockHeld { holder: "pid 1".to_string(), path: PathBuf::from("archive.lock") }.into()), EXIT_LOCKED);
+        assert_eq!(code(Error::storage("index.json", "broken").into()), EXIT_FAILURE);
+        assert_eq!(code(anyhow!("something else")), EXIT_FAILURE);
+    }
+
+    fn fixture_index() -> Vec<IndexEntry> {
+        serde_json::from_str(include_str!("../tests/fixtures/index.json")).unwrap()
+    }
+
+    async fn get(entries: &[IndexEntry], uri: &str) -> (hyper::StatusCode, serde_json::Value) {
+        let request = hyper::Request::get(uri).body(hyper::Body::empty()).unwrap();
+        let response = api_response(entries, &request);
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn serves_index_entries_filtered_by_the_query() {
+        let entries = fixture_index();
+        let (status, submissions) = get(&entries, "/submissions").await;
+        assert_eq!(status, hyper::StatusCode::OK);
+        assert_eq!(submissions, serde_json::to_value(&entries).unwrap());
+
+        let (_, submissions) = get(&entries, "/submissions?tag=DP&language=rust").await;
+        let ids: Vec<&serde_json::Value> = submissions.as_array().unwrap().iter().map(|s| &s["submission_id"]).collect();
+        assert_eq!(ids, [2004]);
+        let (_, submissions) = get(&entries, "/submissions/?contest=abc300").await;
+        assert_eq!(submissions.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn groups_problems_and_counts_stats() {
+        let entries = fixture_index();
+        let (_, problems) = get(&entries, "/problems?contest=abc300&tag=dp").await;
+        assert_eq!(problems, serde_json::json!([{
+            "contest_id": "abc300",
+            "contest_title": "AtCoder Beginner Contest 300",
+            "problem_id": "abc300_c",
+            "difficulty": 800.0,
+            "submissions": 1,
+            "languages": ["C++ 20 (gcc 12.2)"],
+            "tags": ["dp", "grid"],
+            "first_ac_epoch_second": 1682772000,
+            "last_ac_epoch_second": 1682772000,
+        }]));
+
+        let (_, stats) = get(&entries, "/stats").await;
+        assert_eq!(stats["archived_submissions"], 3);
+        assert_eq!(stats["archived_problems"], 3);
+        assert_eq!(stats["solved_today"], false);
+        assert_eq!(stats["by_language"], serde_json::json!([
+            { "name": canonical_language("Rust (rustc 1.70.0)"), "count": 2 },
+            { "name": canonical_language("C++ 20 (gcc 12.2)"), "count": 1 },
+        ]));
+        assert_eq!(stats["by_tag"], serde_json::json!([
+            { "name": "dp", "count": 2 },
+            { "name": "grid", "count": 1 },
+            { "name": "implementation", "count": 1 },
+        ]));
+    }
+
+    #[tokio::test]
+    async fn counts_acs_per_local_day_and_rejects_unknown_requests() {
+        use chrono::TimeZone;
+
+        let entries = fixture_index();
+        let day = |epoch_second: i64| chrono::Local.timestamp_opt(epoch_second, 0).unwrap().format("%Y-%m-%d").to_string();
+        let mut expected = BTreeMap::new();
+        for entry in &entries {
+            *expected.entry(day(entry.epoch_second())).or_insert(0) += 1;
+        }
+        let (_, heatmap) = get(&entries, "/heatmap").await;
+        assert_eq!(heatmap, serde_json::json!(expected));
+
+        let (status, body) = get(&entries, "/nothing").await;
+        assert_eq!(status, hyper::StatusCode::NOT_FOUND);
+        assert_eq!(body["endpoints"], serde_json::json!(["/submissions", "/problems", "/stats", "/heatmap"]));
+        let request = hyper::Request::post("/stats").body(hyper::Body::empty()).unwrap();
+        assert_eq!(api_response(&entries, &request).status(), hyper::StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    /// 一時ディレクトリのリポジトリを指す設定
+    fn test_config(name: &str) -> Config {
+        let dir = std::env::temp_dir().join(format!("ac-garden-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Repository::init(&dir).unwrap();
+        let mut config = Config::default();
+        config.atcoder.repository_path = dir.to_string_lossy().to_string();
+        config.atcoder.user_id = "me".to_string();
+        config.atcoder.user_email = "me@example.com".to_string();
+        config
+    }
+
+    #[test]
+    fn rereads_the_index_only_when_it_or_the_repository_changes() {
+        let config = test_config("serve-cache");
+        let dir = config.atcoder.work_dir();
+        let index_path = dir.join(INDEX_JSON);
+        fs::write(&index_path, include_str!("../tests/fixtures/index.json")).unwrap();
+        let cache = ArchiveCache::default();
+
+        let first = cached_archive(&config, &cache);
+        assert_eq!(*first, fixture_index());
+        assert!(Arc::ptr_eq(&first, &cached_archive(&config, &cache)));
+
+        // 索引が書き直されたら読み直す
+        let mut entries = fixture_index();
+        entries.truncate(1);
+        fs::write(&index_path, serde_json::to_string(&entries).unwrap()).unwrap();
+        let modified = std::time::SystemTime::now() + Duration::from_secs(10);
+        File::options().write(true).open(&index_path).unwrap().set_modified(modified).unwrap();
+        let second = cached_archive(&config, &cache);
+        assert_eq!(*second, entries);
+
+        // コミットでHEADが変わっても読み直す
+        fs::write(dir.join("README.md"), "garden").unwrap();
+        let (author, committer) = commit_signatures(&config.atcoder, "me", 1_600_000_000).unwrap();
+        commit_files(config.atcoder.git_backend, &dir, &[PathBuf::from("README.md")], &author, &committer, "Add README").unwrap();
+        let third = cached_archive(&config, &cache);
+        assert!(!Arc::ptr_eq(&second, &third));
+        assert_eq!(*third, entries);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn scans_the_archive_when_there_is_no_index() {
+        let config = test_config("serve-scan");
+        let submission: Submission = serde_json::from_value(serde_json::json!({
+            "id": 1001, "epoch_second": 1682769900, "problem_id": "abc300_a", "contest_id": "abc300",
+            "user_id": "me", "language": "Rust (rustc 1.70.0)", "point": 100.0, "length": 12, "result": "AC",
+            "execution_time": 1, "tags": ["implementation"],
+        })).unwrap();
+        let problem_dir = config.atcoder.service_dir().join("abc300").join("abc300_a");
+        fs::create_dir_all(&problem_dir).unwrap();
+        fs::write(problem_dir.join("Main.rs"), "fn main() {}").unwrap();
+        write_metadata(&problem_dir, &submission, config.atcoder.metadata_format).unwrap();
+
+        let entries = cached_archive(&config, &ArchiveCache::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].submission_id, 1001);
+        assert_eq!(entries[0].path, "atcoder.jp/abc300/abc300_a/Main.rs");
+        assert_eq!(entries[0].tags, ["implementation"]);
+        assert_eq!(entries[0].epoch_second(), 1682769900);
+        fs::remove_dir_all(config.atcoder.work_dir()).unwrap();
+    }
+
+    /// 問題のディレクトリにコードとメタデータを書き、作業ツリーからの相対パスを返す
+    fn write_archived(config: &Config, problem_id: &str, id: i64) -> Vec<PathBuf> {
+        let submission: Submission = serde_json::from_value(serde_json::json!({
+            "id": id, "epoch_second": 1682769900, "problem_id": problem_id, "contest_id": "abc300",
+            "user_id": "me", "language": "Rust (rustc 1.70.0)", "point": 100.0, "length": 12, "result": "AC",
+            "execution_time": 1,
+        })).unwrap();
+        let problem_dir = config.atcoder.service_dir().join("abc300").join(problem_id);
+        fs::create_dir_all(&problem_dir).unwrap();
+        fs::write(problem_dir.join("Main.rs"), "fn main() {}").unwrap();
+        write_metadata(&problem_dir, &submission, config.atcoder.metadata_format).unwrap();
+        let work_dir = config.atcoder.work_dir();
+        ["Main.rs", "submission.json"].iter()
+            .map(|name| problem_dir.join(name).strip_prefix(&work_dir).unwrap().to_path_buf())
+            .collect()
+    }
+
+    fn commit_all(config: &Config, paths: &[PathBuf]) {
+        let (author, committer) = commit_signatures(&config.atcoder, "me", 1_600_000_000).unwrap();
+        commit_files(config.atcoder.git_backend, &config.atcoder.work_dir(), paths, &author, &committer, "Archive").unwrap();
+    }
+
+    #[tokio::test]
+    async fn repair_restores_missing_files_from_head() {
+        set_offline(true);
+        let config = test_config("fsck-missing");
+        let mut paths = write_archived(&config, "abc300_a", 1001);
+        write_index(&config, &HashMap::new()).unwrap();
+        paths.push(PathBuf::from(INDEX_JSON));
+        commit_all(&config, &paths);
+        fsck(&config, false).await.unwrap();
+
+        let source = config.atcoder.work_dir().join(&paths[0]);
+        fs::remove_file(&source).unwrap();
+        assert!(fsck(&config, false).await.is_err());
+        assert!(!source.exists());
+
+        fsck(&config, true).await.unwrap();
+        assert_eq!(fs::read_to_string(&source).unwrap(), "fn main() {}");
+        fsck(&config, false).await.unwrap();
+        fs::remove_dir_all(config.atcoder.work_dir()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn repair_commits_and_indexes_an_unindexed_directory() {
+        set_offline(true);
+        let config = test_config("fsck-unindexed");
+        let mut paths = write_archived(&config, "abc300_a", 1001);
+        write_index(&config, &HashMap::new()).unwrap();
+        paths.push(PathBuf::from(INDEX_JSON));
+        commit_all(&config, &paths);
+
+        // 書き込んだ後、コミットと索引の更新の前に止まったアーカイブ。ユーザーのメモはコミットしない
+        let unindexed = write_archived(&config, "abc300_b", 1002);
+        let memo = config.atcoder.service_dir().join("abc300").join("abc300_b").join("memo.txt");
+        fs::write(&memo, "todo").unwrap();
+        assert!(fsck(&config, false).await.is_err());
+
+        fsck(&config, true).await.unwrap();
+        let work_dir = config.atcoder.work_dir();
+        let indexed: Vec<i64> = read_index(&work_dir).iter().map(|entry| entry.submission_id).collect();
+        assert_eq!(indexed, [1001, 1002]);
+        let repo = Repository::open(&work_dir).unwrap();
+        for path in unindexed.iter().chain([&PathBuf::from(INDEX_JSON)]) {
+            assert!(repo.status_file(path).unwrap().is_empty(), "{}", path.display());
+        }
+        let memo = memo.strip_prefix(&work_dir).unwrap();
+        assert!(repo.status_file(memo).unwrap().contains(git2::Status::WT_NEW));
+
+        // メモは残るが、アーカイブのずれはなくなる
+        fsck(&config, false).await.unwrap();
+        fs::remove_dir_all(&work_dir).unwrap();
+    }
+
+    #[test]
+    fn restores_backup_files_only_inside_the_target_directory() {
+        let root = std::env::temp_dir().join(format!("ac-garden-restore-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let dir = root.join("state");
+        let outside = root.join("outside.json");
+        let files: BTreeMap<String, String> = [
+            "watch.json".to_string(),
+            "../outside.json".to_string(),
+            outside.to_string_lossy().to_string(),
+            "nested/watch.json".to_string(),
+            "..".to_string(),
+            ".".to_string(),
+            String::new(),
+        ].into_iter().map(|name| (name, "{}".to_string())).collect();
+
+        write_backup_dir(&dir, &files).unwrap();
+        let restored: Vec<String> = fs::read_dir(&root).unwrap()
+            .chain(fs::read_dir(&dir).unwrap())
+            .map(|entry| entry.unwrap().path().strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        assert_eq!(restored, ["state", "state/watch.json"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn seals_the_plain_session_out_of_the_backed_up_config() {
+        const SESSION: &str = "plain-revel-session-value";
+        let mut config = Config::default();
+        config.atcoder.session = SESSION.to_string();
+        let mut secrets = BTreeMap::new();
+        take_plain_session(&mut config, &mut secrets, false);
+        assert_eq!(config.atcoder.session, KEYRING_REFERENCE);
+        assert_eq!(secrets.get("session").map(String::as_str), Some(SESSION));
+
+        let backup = Backup {
+            version: BACKUP_VERSION,
+            created_at: String::new(),
+            config,
+            state: BTreeMap::new(),
+            cache: BTreeMap::new(),
+            credentials: Some(seal_secrets(&secrets, "correct horse").unwrap()),
+        };
+        let output = std::env::temp_dir().join(format!("ac-garden-backup-{}.json", std::process::id()));
+        // すでにあるファイルも上書きして権限を絞る
+        fs::write(&output, "old").unwrap();
+        write_backup(&output, &backup).unwrap();
+        let written = fs::read_to_string(&output).unwrap();
+        assert!(!written.contains(SESSION));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&output).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let restored: Backup = serde_json::from_str(&written).unwrap();
+        let sealed = restored.credentials.unwrap();
+        assert_eq!(unseal_secrets(&sealed, "correct horse").unwrap(), secrets);
+        assert!(unseal_secrets(&sealed, "wrong").is_err());
+        fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn drops_the_plain_session_without_credentials() {
+        let mut config = Config::default();
+        config.atcoder.session = "plain".to_string();
+        let mut secrets = BTreeMap::new();
+        take_plain_session(&mut config, &mut secrets, true);
+        assert!(config.atcoder.session.is_empty());
+        assert!(secrets.is_empty());
+
+        // キーチェーンを指す設定はそのまま残す
+        config.atcoder.session = KEYRING_REFERENCE.to_string();
+        take_plain_session(&mut config, &mut secrets, false);
+        assert_eq!(config.atcoder.session, KEYRING_REFERENCE);
+        assert!(secrets.is_empty());
+    }
+
+    #[test]
+    fn finds_the_category_under_added_context() {
+        let error = anyhow::Error::from(Error::config("missing user_id")).context("Failed to load the config");
+        assert_eq!(exit_code_of(&error), EXIT_CONFIG);
+    }
+}